@@ -1,8 +0,0 @@
-
-fn main() {
-    cc::Build::new()
-        .file("src/chunkmesh.c")
-        .include("lib/raylib/include/")
-        .compile("chunkmesh.a");
-    println!("compiled chunkmesh.c");
-}
\ No newline at end of file
@@ -0,0 +1,160 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::SpellcoderError;
+use crate::events::{GameEvent, Subscriber};
+
+// next to materials.json, missing means there are no achievements
+pub const ACHIEVEMENTS_PATH: &str = "achievements.json";
+// in the save directory. achievements are the player's, not the world's, so every world adds to them
+pub const UNLOCKED_PATH: &str = "achievements_unlocked.json";
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Achievement {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub trigger: Trigger,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "kind")]
+pub enum Trigger {
+    // the counter reaching count, across every session
+    Count { counter: Counter, count: u64 },
+    // landing alive after falling at least this many world pixels
+    Fall { distance: f32 },
+}
+
+// things counted from events for Count triggers
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Counter {
+    SpellCast,
+    PixelPlaced,
+    PixelDestroyed,
+    Kill,
+    Death,
+    Discovery,
+    QuestCompleted,
+}
+
+impl Counter {
+    fn counts(&self, event: &GameEvent) -> bool {
+        match self {
+            Counter::SpellCast => matches!(event, GameEvent::SpellCast { .. }),
+            Counter::PixelPlaced => matches!(event, GameEvent::PixelChanged { after: Some(_), .. }),
+            Counter::PixelDestroyed => matches!(event, GameEvent::PixelChanged { before: Some(_), after: None, .. }),
+            Counter::Kill => matches!(event, GameEvent::EntityDied { entity: Some(_), .. }),
+            Counter::Death => matches!(event, GameEvent::EntityDied { entity: None, .. }),
+            Counter::Discovery => matches!(event, GameEvent::Discovered { .. }),
+            Counter::QuestCompleted => matches!(event, GameEvent::QuestCompleted { .. }),
+        }
+    }
+}
+
+// what's in the unlocked file
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Unlocked {
+    // achievement id to when it was unlocked, seconds since 1970
+    pub unlocked: BTreeMap<String, u64>,
+    pub counters: BTreeMap<Counter, u64>,
+}
+
+// the achievements from achievements.json and which the player has. checks every event against
+// the ones still locked, newly unlocked ones wait in take_unlocked for the toast
+pub struct Achievements {
+    achievements: Vec<Achievement>,
+    pub progress: Unlocked,
+    path: String,
+    fresh: Vec<usize>,
+}
+
+impl Achievements {
+    // path is where the unlocked file goes. a missing definitions file means no achievements
+    pub fn load(definitions: &str, path: &str, errors: &mut Vec<SpellcoderError>) -> Self {
+        let achievements = read(definitions).unwrap_or_else(|e| {
+            errors.push(e);
+            None
+        });
+        let progress = read(path).unwrap_or_else(|e| {
+            errors.push(e);
+            None
+        });
+        Achievements {
+            achievements: achievements.unwrap_or_default(),
+            progress: progress.unwrap_or_default(),
+            path: path.to_string(),
+            fresh: Vec::new(),
+        }
+    }
+
+    pub fn save(&self) -> Result<(), SpellcoderError> {
+        let text = serde_json::to_string_pretty(&self.progress).map_err(|e| SpellcoderError::save(self.path.as_str(), e.into()))?;
+        fs::write(&self.path, text).map_err(|e| SpellcoderError::save(self.path.as_str(), e))
+    }
+
+    pub fn is_unlocked(&self, id: &str) -> bool {
+        self.progress.unlocked.contains_key(id)
+    }
+
+    // every achievement and whether it's unlocked, for listing them
+    pub fn all(&self) -> impl Iterator<Item = (&Achievement, bool)> {
+        self.achievements.iter().map(|a| (a, self.is_unlocked(&a.id)))
+    }
+
+    // achievements unlocked since last time
+    pub fn take_unlocked(&mut self) -> Vec<Achievement> {
+        std::mem::take(&mut self.fresh).into_iter().map(|i| self.achievements[i].clone()).collect()
+    }
+
+    fn unlock(&mut self, index: usize) {
+        let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        self.progress.unlocked.insert(self.achievements[index].id.clone(), seconds);
+        self.fresh.push(index);
+    }
+}
+
+// Ok(None) when the file isn't there
+fn read<T: for<'de> Deserialize<'de>>(path: &str) -> Result<Option<T>, SpellcoderError> {
+    if !Path::new(path).exists() {
+        return Ok(None);
+    }
+    let text = fs::read_to_string(path).map_err(|e| SpellcoderError::io(path, e))?;
+    serde_json::from_str(&text).map(Some).map_err(|e| SpellcoderError::parse(path, e))
+}
+
+impl Subscriber for Achievements {
+    fn on_event(&mut self, event: &GameEvent) {
+        // only counters something asks for, so the file doesn't fill up with unused ones
+        let mut counted = Vec::new();
+        for achievement in &self.achievements {
+            if let Trigger::Count { counter, .. } = achievement.trigger {
+                if counter.counts(event) && !counted.contains(&counter) {
+                    counted.push(counter);
+                }
+            }
+        }
+        for counter in counted {
+            *self.progress.counters.entry(counter).or_default() += 1;
+        }
+        for i in 0..self.achievements.len() {
+            if self.is_unlocked(&self.achievements[i].id) {
+                continue;
+            }
+            let done = match (&self.achievements[i].trigger, event) {
+                (Trigger::Count { counter, count }, _) => self.progress.counters.get(counter).copied().unwrap_or(0) >= *count,
+                (Trigger::Fall { distance }, GameEvent::Landed { distance: fallen, survived: true }) => fallen >= distance,
+                _ => false,
+            };
+            if done {
+                self.unlock(i);
+            }
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use raylib::prelude::*;
+
+use crate::events::{GameEvent, Subscriber};
+use crate::{scale, Player};
+
+// how long the one-shot states hold before velocity takes over again
+const CAST_TIME: f32 = 0.25;
+const HURT_TIME: f32 = 0.3;
+// drinking a potion or reading a scroll, nothing else can be used until it's done
+const USE_TIME: f32 = 0.5;
+// leg swings per second at full run speed
+const RUN_CYCLE_SPEED: f32 = 10.0;
+const IDLE_BREATH_SPEED: f32 = 2.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnimState {
+    Idle,
+    Run,
+    Jump,
+    Fall,
+    Cast,
+    Hurt,
+    // holding up an item, see Game::use_item
+    Use,
+}
+
+// picks the state from velocity every frame, events like casting or getting hit override it for a moment
+pub struct PlayerAnimation {
+    pub state: AnimState,
+    time: f32, // seconds in the current state, drives the cycles
+    facing: f32, // 1 right, -1 left
+    cast_timer: f32,
+    hurt_timer: f32,
+    use_timer: f32,
+    // of the item being used
+    use_color: Color,
+}
+
+impl PlayerAnimation {
+    pub fn new() -> Self {
+        PlayerAnimation {
+            state: AnimState::Idle,
+            time: 0.0,
+            facing: 1.0,
+            cast_timer: 0.0,
+            hurt_timer: 0.0,
+            use_timer: 0.0,
+            use_color: Color::WHITE,
+        }
+    }
+
+    pub fn cast(&mut self) {
+        self.cast_timer = CAST_TIME;
+    }
+
+    pub fn hurt(&mut self) {
+        self.hurt_timer = HURT_TIME;
+    }
+
+    pub fn use_item(&mut self, color: Color) {
+        self.use_timer = USE_TIME;
+        self.use_color = color;
+    }
+
+    pub fn is_using(&self) -> bool {
+        self.use_timer > 0.0
+    }
+
+    // velocity in world pixels per frame, like the main loop keeps it
+    pub fn update(&mut self, velocity: Vector2, grounded: bool, delta: f32) {
+        self.cast_timer = (self.cast_timer - delta).max(0.0);
+        self.hurt_timer = (self.hurt_timer - delta).max(0.0);
+        self.use_timer = (self.use_timer - delta).max(0.0);
+        if velocity.x != 0.0 {
+            self.facing = velocity.x.signum();
+        }
+        let state = if self.hurt_timer > 0.0 {
+            AnimState::Hurt
+        } else if self.cast_timer > 0.0 {
+            AnimState::Cast
+        } else if self.use_timer > 0.0 {
+            AnimState::Use
+        } else if !grounded && velocity.y < 0.0 {
+            AnimState::Jump
+        } else if !grounded {
+            AnimState::Fall
+        } else if velocity.x != 0.0 {
+            AnimState::Run
+        } else {
+            AnimState::Idle
+        };
+        if state != self.state {
+            self.state = state;
+            self.time = 0.0;
+        }
+        // running slower plays the cycle slower
+        let rate = if state == AnimState::Run { velocity.x.abs().clamp(0.3, 1.5) } else { 1.0 };
+        self.time += delta * rate;
+    }
+
+    // a stick figure filling the player's box, call inside the world's 2d mode
+    pub fn draw(&self, d: &mut impl RaylibDraw, player: &Player) {
+        let scale = scale() as f32;
+        let origin = player.position * scale;
+        let (w, h) = (player.size.x * scale, player.size.y * scale);
+        // everything below is in fractions of the box, x mirrored when facing left
+        let point = |x: f32, y: f32| origin + Vector2::new(w * (0.5 + (x - 0.5) * self.facing), h * y);
+        let color = if self.state == AnimState::Hurt { Color::new(255, 90, 90, 255) } else { Color::WHITE };
+        let thick = scale * 1.2;
+
+        let (bob, leg_swing, arm_swing, arm_raise) = match self.state {
+            AnimState::Idle => ((self.time * IDLE_BREATH_SPEED).sin() * 0.01, 0.0, 0.0, 0.0),
+            AnimState::Run => {
+                let cycle = (self.time * RUN_CYCLE_SPEED).sin();
+                (cycle.abs() * -0.02, cycle * 0.25, -cycle * 0.2, 0.0)
+            }
+            AnimState::Jump => (0.0, 0.15, 0.0, 0.5),
+            AnimState::Fall => (0.0, -0.1, 0.0, 0.8),
+            AnimState::Cast => (0.0, 0.0, 0.0, 1.0),
+            AnimState::Hurt => (0.03, 0.1, 0.2, 0.3),
+            // the front hand comes up to the face
+            AnimState::Use => (0.0, 0.0, -0.25, 0.8),
+        };
+
+        let head = point(0.5, 0.15 + bob);
+        let neck = point(0.5, 0.27 + bob);
+        let hip = point(0.5, 0.62 + bob);
+        // hurt leans back away from the hit
+        let hip = if self.state == AnimState::Hurt { hip - Vector2::new(w * 0.1 * self.facing, 0.0) } else { hip };
+        d.draw_circle_v(head, w * 0.28, color);
+        d.draw_line_ex(neck, hip, thick, color);
+        d.draw_line_ex(hip, point(0.5 + leg_swing, 1.0), thick, color);
+        d.draw_line_ex(hip, point(0.5 - leg_swing, 1.0), thick, color);
+
+        let shoulder = point(0.5, 0.33 + bob);
+        // the front arm points forward and up when casting, both go up when airborne
+        let front = point(0.5 + 0.4 + arm_swing, 0.55 - arm_raise * 0.35 + bob);
+        let back = point(0.5 - 0.35 - arm_swing, 0.55 - arm_raise * 0.2 + bob);
+        d.draw_line_ex(shoulder, front, thick, color);
+        d.draw_line_ex(shoulder, back, thick, color);
+        if self.state == AnimState::Cast {
+            d.draw_circle_v(front, scale * 0.9, Color::new(200, 170, 255, 220));
+        }
+        if self.state == AnimState::Use {
+            d.draw_rectangle_v(front - Vector2::new(scale, scale * 1.5), Vector2::new(scale * 2.0, scale * 2.5), self.use_color);
+        }
+    }
+}
+
+impl Subscriber for PlayerAnimation {
+    // the player is the only one who casts so far
+    fn on_event(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::DamageDealt { entity: None, .. } => self.hurt(),
+            GameEvent::SpellCast { .. } => self.cast(),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,540 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+
+use rand::Rng;
+use raylib::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+use crate::error::{Result, SpellcoderError};
+use crate::events::{GameEvent, Subscriber};
+
+pub const SFX_DIR: &str = "assets/sfx";
+pub const MUSIC_DIR: &str = "assets/music";
+pub const AMBIENT_DIR: &str = "assets/ambient";
+pub const STEM_DIR: &str = "assets/music/stems";
+pub const AUDIO_SETTINGS_PATH: &str = "audio.json";
+const SOUND_EXTENSIONS: &[&str] = &["wav", "ogg", "mp3"];
+// sfx get their pitch nudged by up to this much so repeated sounds don't get grating
+const PITCH_VARIATION: f32 = 0.08;
+const CROSSFADE_TIME: f32 = 2.0;
+// music stems and the intensity they start fading in at, over STEM_FADE_RANGE.
+// stems not listed here come in halfway
+const STEM_THRESHOLDS: &[(&str, f32)] = &[("percussion", 0.2), ("lead", 0.6)];
+const STEM_FADE_RANGE: f32 = 0.2;
+// ambience changes as you walk around, so it fades quicker than the music
+const AMBIENT_CROSSFADE_TIME: f32 = 1.0;
+// below this fraction of max hp the heartbeat starts and the music gets ducked to DUCK_VOLUME
+const LOW_HEALTH: f32 = 0.25;
+const DUCK_VOLUME: f32 = 0.35;
+const DUCK_SPEED: f32 = 1.5; // per second, both ways
+const HEARTBEAT_SOUND: &str = "heartbeat";
+// positional sounds, in world pixels from the listener. past HEARING_RANGE they're silent,
+// PAN_RANGE to either side pans them all the way over
+const HEARING_RANGE: f32 = 160.0;
+const PAN_RANGE: f32 = 80.0;
+
+// every sound plays through one of these, each with its own volume. master scales all of them
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Bus {
+    Master,
+    Sfx,
+    Music,
+    Ui,
+}
+
+// what audio.json holds
+#[derive(Serialize, Deserialize)]
+struct AudioSettings {
+    volumes: HashMap<Bus, f32>,
+    #[serde(default)]
+    muted: HashSet<Bus>,
+}
+
+// things that happen in game and have a sound, see SoundEvent::file_stem
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SoundEvent {
+    Cast,
+    Impact,
+    Jump,
+    Damage,
+    Pickup,
+    Death,
+}
+
+impl SoundEvent {
+    // the event plays whatever file in the sfx directory has this name, e.g. jump.wav
+    pub fn file_stem(&self) -> &'static str {
+        match self {
+            SoundEvent::Cast => "cast",
+            SoundEvent::Impact => "impact",
+            SoundEvent::Jump => "jump",
+            SoundEvent::Damage => "damage",
+            SoundEvent::Pickup => "pickup",
+            SoundEvent::Death => "death",
+        }
+    }
+}
+
+// menu and hud feedback, played on the ui bus by the widgets themselves
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UiSound {
+    Hover,
+    Click,
+    Confirm,
+    SpellSwitch,
+}
+
+impl UiSound {
+    pub fn file_stem(&self) -> &'static str {
+        match self {
+            UiSound::Hover => "ui_hover",
+            UiSound::Click => "ui_click",
+            UiSound::Confirm => "ui_confirm",
+            UiSound::SpellSwitch => "ui_spell_switch",
+        }
+    }
+}
+
+// what the game looks like to the audio, built fresh every frame from the player and world
+// so the audio decides for itself what to play instead of being told at each call site
+pub struct AudioState<'a> {
+    pub mood: &'a str,     // music mood, a biome name or "combat"
+    pub ambience: &'a str, // ambient bed, the biome name
+    pub health: f32,       // fraction of max hp
+    pub intensity: f32,    // combat intensity from the AudioDirector, 0 to 1
+}
+
+// a streamed music file, the part of the name before the first '_' is its mood,
+// so meadow_1.ogg and meadow_2.ogg both play while the mood is "meadow"
+struct Track<'aud> {
+    mood: String,
+    music: Music<'aud>,
+    gain: f32, // crossfade position, 0 to 1, multiplied with the bus volume
+}
+
+// a layer of combat music, all stems play in sync and only their volumes change
+struct Stem<'aud> {
+    music: Music<'aud>,
+    threshold: f32,
+    gain: f32,
+}
+
+// a set of looping streams where one mood plays at a time, crossfading on changes
+struct Playlist<'aud> {
+    tracks: Vec<Track<'aud>>,
+    current: Option<usize>,
+    mood: String,
+    crossfade: f32, // seconds
+}
+
+// sounds borrow the audio device, so the manager can't outlive it. without a device
+// (no sound card, init failed) everything here quietly does nothing
+pub struct AudioManager<'aud> {
+    device: Option<&'aud RaylibAudio>,
+    sounds: HashMap<String, Sound<'aud>>, // by file name, "jump.wav"
+    volumes: HashMap<Bus, f32>,
+    muted: HashSet<Bus>,
+    music: Playlist<'aud>,
+    ambience: Playlist<'aud>,
+    stems: Vec<Stem<'aud>>,
+    listener: Vector2, // world pixels, usually the middle of the screen
+    duck: f32,         // music volume factor, lowered while on low health
+    heartbeat: f32,    // heartbeat loop volume, fades with the duck
+    // widget hovered during the last drawn frame and the one being drawn, so hovering
+    // only makes a sound when the mouse moves onto something new
+    hovered: Option<[i32; 4]>,
+    hovered_now: Option<[i32; 4]>,
+}
+
+impl<'aud> AudioManager<'aud> {
+    pub fn new(device: Option<&'aud RaylibAudio>) -> Self {
+        let mut audio = AudioManager {
+            device,
+            sounds: HashMap::new(),
+            volumes: HashMap::from([(Bus::Master, 1.0), (Bus::Sfx, 1.0), (Bus::Music, 0.6), (Bus::Ui, 0.8)]),
+            muted: HashSet::new(),
+            music: Playlist::new(CROSSFADE_TIME),
+            ambience: Playlist::new(AMBIENT_CROSSFADE_TIME),
+            stems: Vec::new(),
+            listener: Vector2::zero(),
+            duck: 1.0,
+            heartbeat: 0.0,
+            hovered: None,
+            hovered_now: None,
+        };
+        audio.load_settings(&config::get().save_path(AUDIO_SETTINGS_PATH));
+        if let Some(device) = device {
+            audio.load_dir(SFX_DIR);
+            audio.music.load(device, MUSIC_DIR);
+            audio.ambience.load(device, AMBIENT_DIR);
+            audio.load_stems(device, STEM_DIR);
+        } else {
+            crate::log_warn!("no audio device, sound is disabled");
+        }
+        audio
+    }
+
+    pub fn load_dir(&mut self, dir: &str) {
+        let Some(device) = self.device else {
+            return;
+        };
+        for path in audio_files(dir) {
+            let Some(name) = path.file_name().map(|n| n.to_string_lossy().to_string()) else {
+                continue;
+            };
+            match device.new_sound(&path.to_string_lossy()) {
+                Ok(sound) => {
+                    self.sounds.insert(name, sound);
+                }
+                Err(e) => crate::log_warn!("could not load {}: {}", path.display(), e),
+            }
+        }
+    }
+
+    fn load_stems(&mut self, device: &'aud RaylibAudio, dir: &str) {
+        for path in audio_files(dir) {
+            let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+            let threshold = STEM_THRESHOLDS.iter().find(|(name, _)| *name == stem).map_or(0.5, |(_, t)| *t);
+            match device.new_music(&path.to_string_lossy()) {
+                Ok(music) => self.stems.push(Stem { music, threshold, gain: 0.0 }),
+                Err(e) => crate::log_warn!("could not load {}: {}", path.display(), e),
+            }
+        }
+    }
+
+    // only the bus volumes and mutes are stored, a missing file keeps the defaults
+    pub fn load_settings(&mut self, path: &str) {
+        let Ok(text) = fs::read_to_string(path) else {
+            return;
+        };
+        match serde_json::from_str::<AudioSettings>(&text) {
+            Ok(settings) => {
+                self.volumes.extend(settings.volumes);
+                self.muted = settings.muted;
+            }
+            Err(e) => crate::log_warn!("could not parse {}: {}", path, e),
+        }
+    }
+
+    pub fn save_settings(&self, path: &str) -> Result<()> {
+        let settings = AudioSettings {
+            volumes: self.volumes.clone(),
+            muted: self.muted.clone(),
+        };
+        let text = serde_json::to_string_pretty(&settings).map_err(|e| SpellcoderError::save(path, e.into()))?;
+        fs::write(path, text).map_err(|e| SpellcoderError::save(path, e))
+    }
+
+    // the slider value, mutes and master aren't applied
+    pub fn volume(&self, bus: Bus) -> f32 {
+        self.volumes.get(&bus).copied().unwrap_or(1.0)
+    }
+
+    pub fn set_volume(&mut self, bus: Bus, volume: f32) {
+        self.volumes.insert(bus, volume.clamp(0.0, 1.0));
+    }
+
+    pub fn is_muted(&self, bus: Bus) -> bool {
+        self.muted.contains(&bus)
+    }
+
+    pub fn set_muted(&mut self, bus: Bus, muted: bool) {
+        if muted {
+            self.muted.insert(bus);
+        } else {
+            self.muted.remove(&bus);
+        }
+    }
+
+    // what actually goes to raylib, read every time something plays or every frame for
+    // streams so changes apply right away
+    fn output_volume(&self, bus: Bus) -> f32 {
+        if self.is_muted(bus) || self.is_muted(Bus::Master) {
+            return 0.0;
+        }
+        self.volume(bus) * self.volume(Bus::Master)
+    }
+
+    // plays a sound by file name, returns false if it isn't loaded
+    pub fn play(&mut self, name: &str, bus: Bus) -> bool {
+        self.play_with(name, bus, 1.0, 0.5)
+    }
+
+    // like play but quieter the further position is from the listener, and panned towards it
+    pub fn play_at(&mut self, name: &str, position: Vector2) -> bool {
+        let (gain, pan) = self.spatialize(position);
+        if gain <= 0.0 {
+            // out of earshot still counts as played, callers shouldn't fall back to something else
+            return self.sounds.contains_key(name);
+        }
+        self.play_with(name, Bus::Sfx, gain, pan)
+    }
+
+    // picks one of the sounds whose name starts with prefix, for sample sets like step_grass_1.wav, step_grass_2.wav.
+    // sound never feeds back into the game, so it doesn't need to draw from the seeded streams
+    pub fn play_random(&mut self, prefix: &str, position: Vector2) -> bool {
+        let names = self.sounds.keys().filter(|name| name.starts_with(prefix)).collect::<Vec<_>>();
+        if names.is_empty() {
+            return false;
+        }
+        let name = names[rand::thread_rng().gen_range(0..names.len())].clone();
+        self.play_at(&name, position)
+    }
+
+    fn play_with(&mut self, name: &str, bus: Bus, gain: f32, pan: f32) -> bool {
+        let volume = self.output_volume(bus) * gain;
+        let Some(sound) = self.sounds.get_mut(name) else {
+            return false;
+        };
+        let pitch = match bus {
+            Bus::Sfx => 1.0 + rand::thread_rng().gen_range(-PITCH_VARIATION..=PITCH_VARIATION),
+            _ => 1.0,
+        };
+        sound.set_pitch(pitch);
+        sound.set_pan(pan);
+        sound.set_volume(volume);
+        sound.play();
+        true
+    }
+
+    // volume factor and raylib pan (0.5 is centered, higher goes left) for a sound at position
+    fn spatialize(&self, position: Vector2) -> (f32, f32) {
+        let offset = position - self.listener;
+        let falloff = (1.0 - offset.length() / HEARING_RANGE).clamp(0.0, 1.0);
+        let pan = 0.5 - 0.5 * (offset.x / PAN_RANGE).clamp(-1.0, 1.0);
+        (falloff * falloff, pan)
+    }
+
+    pub fn set_listener(&mut self, position: Vector2) {
+        self.listener = position;
+    }
+
+    // for sounds named in data files, anything missing falls back to the event's generic sound.
+    // with a position the sound is positional, see play_at
+    pub fn play_or_trigger(&mut self, name: Option<&str>, event: SoundEvent, position: Option<Vector2>) {
+        let played = name.is_some_and(|name| match position {
+            Some(position) => self.play_at(name, position),
+            None => self.play(name, Bus::Sfx),
+        });
+        if played {
+            return;
+        }
+        match position {
+            Some(position) => self.trigger_at(event, position),
+            None => self.trigger(event),
+        }
+    }
+
+    // call every frame, raylib only streams music while we keep feeding it
+    pub fn update(&mut self, delta: f32, state: &AudioState) {
+        self.hovered = self.hovered_now.take();
+        self.update_low_health(delta, state.health);
+        self.music.set_mood(state.mood);
+        self.music.update(delta, self.output_volume(Bus::Music) * self.duck);
+        self.ambience.set_mood(state.ambience);
+        self.ambience.update(delta, self.output_volume(Bus::Sfx));
+        self.update_stems(delta, state.intensity);
+    }
+
+    fn update_stems(&mut self, delta: f32, intensity: f32) {
+        let playing = self.stems.iter().any(|s| s.music.is_stream_playing());
+        if intensity > 0.0 && !playing {
+            // start them all at once so they stay in time with each other
+            for stem in &mut self.stems {
+                stem.music.seek_stream(0.0);
+                stem.music.play_stream();
+            }
+        }
+        let volume = self.output_volume(Bus::Music) * self.duck;
+        let step = delta / CROSSFADE_TIME;
+        for stem in &mut self.stems {
+            if !stem.music.is_stream_playing() {
+                continue;
+            }
+            stem.music.update_stream();
+            let target = ((intensity - stem.threshold) / STEM_FADE_RANGE).clamp(0.0, 1.0);
+            stem.gain += (target - stem.gain).clamp(-step, step);
+            stem.music.set_volume(stem.gain * volume);
+        }
+        if intensity <= 0.0 && self.stems.iter().all(|s| s.gain <= 0.0) {
+            for stem in &mut self.stems {
+                stem.music.stop_stream();
+            }
+        }
+    }
+
+    fn update_low_health(&mut self, delta: f32, health: f32) {
+        let low = health < LOW_HEALTH;
+        let target = if low { DUCK_VOLUME } else { 1.0 };
+        let step = DUCK_SPEED * delta;
+        self.duck += (target - self.duck).clamp(-step, step);
+        // heartbeat is loud exactly as much as the music is ducked
+        self.heartbeat = (1.0 - self.duck) / (1.0 - DUCK_VOLUME);
+
+        let Some(name) = self.sound_by_stem(HEARTBEAT_SOUND) else {
+            return;
+        };
+        let (heartbeat, volume) = (self.heartbeat, self.output_volume(Bus::Sfx) * self.heartbeat);
+        let Some(sound) = self.sounds.get_mut(&name) else {
+            return;
+        };
+        if heartbeat <= 0.0 {
+            sound.stop();
+            return;
+        }
+        // beats faster the closer to death, restarted by hand since raylib sounds don't loop
+        sound.set_pitch(1.0 + (LOW_HEALTH - health).max(0.0) * 2.0);
+        sound.set_volume(volume);
+        if low && !sound.is_playing() {
+            sound.play();
+        }
+    }
+
+    pub fn play_ui(&mut self, sound: UiSound) {
+        if let Some(name) = self.sound_by_stem(sound.file_stem()) {
+            self.play(&name, Bus::Ui);
+        }
+    }
+
+    // called by widgets every frame they are hovered, identified by their rectangle
+    pub fn ui_hover(&mut self, rect: Rectangle) {
+        let id = [rect.x as i32, rect.y as i32, rect.width as i32, rect.height as i32];
+        if self.hovered != Some(id) && self.hovered_now.is_none() {
+            self.play_ui(UiSound::Hover);
+        }
+        self.hovered_now = Some(id);
+    }
+
+    pub fn trigger(&mut self, event: SoundEvent) {
+        if let Some(name) = self.event_sound(event) {
+            self.play(&name, Bus::Sfx);
+        }
+    }
+
+    pub fn trigger_at(&mut self, event: SoundEvent, position: Vector2) {
+        if let Some(name) = self.event_sound(event) {
+            self.play_at(&name, position);
+        }
+    }
+
+    fn event_sound(&self, event: SoundEvent) -> Option<String> {
+        self.sound_by_stem(event.file_stem())
+    }
+
+    // owned, the sound is looked up again mutably to play it
+    fn sound_by_stem(&self, stem: &str) -> Option<String> {
+        self.sounds.keys().find(|name| name.rsplit_once('.').is_some_and(|(s, _)| s == stem)).cloned()
+    }
+}
+
+impl Subscriber for AudioManager<'_> {
+    fn on_event(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::DamageDealt { entity: None, .. } => self.trigger(SoundEvent::Damage),
+            GameEvent::SpellCast { target, touched, sound, touch_sound, .. } => {
+                self.play_or_trigger(sound.as_deref(), SoundEvent::Cast, None);
+                if *touched {
+                    self.play_or_trigger(touch_sound.as_deref(), SoundEvent::Impact, Some(*target));
+                }
+            }
+            GameEvent::EntityDied { entity: None, .. } => self.trigger(SoundEvent::Death),
+            _ => {}
+        }
+    }
+}
+
+impl<'aud> Playlist<'aud> {
+    fn new(crossfade: f32) -> Self {
+        Playlist {
+            tracks: Vec::new(),
+            current: None,
+            mood: String::new(),
+            crossfade,
+        }
+    }
+
+    fn load(&mut self, device: &'aud RaylibAudio, dir: &str) {
+        for path in audio_files(dir) {
+            let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+            let mood = stem.split('_').next().unwrap_or_default().to_string();
+            match device.new_music(&path.to_string_lossy()) {
+                Ok(music) => self.tracks.push(Track { mood, music, gain: 0.0 }),
+                Err(e) => crate::log_warn!("could not load {}: {}", path.display(), e),
+            }
+        }
+    }
+
+    // switches to a track of the given mood, crossfading from whatever played before.
+    // cheap to call every frame, nothing happens while the mood stays the same
+    fn set_mood(&mut self, mood: &str) {
+        if self.mood == mood {
+            return;
+        }
+        self.mood = mood.to_string();
+        self.start_next_track();
+    }
+
+    // the track after the current one with the same mood, wrapping around, so a mood
+    // with several tracks works through them in order
+    fn start_next_track(&mut self) {
+        let start = self.current.map_or(0, |i| i + 1);
+        let len = self.tracks.len();
+        let next = (0..len).map(|i| (start + i) % len).find(|i| self.tracks[*i].mood == self.mood);
+        if next == self.current {
+            return;
+        }
+        if let Some(i) = next {
+            let track = &mut self.tracks[i];
+            // a track faded out earlier may still be playing, only restart it if it went quiet
+            if !track.music.is_stream_playing() {
+                track.music.seek_stream(0.0);
+                track.music.play_stream();
+            }
+        }
+        self.current = next;
+    }
+
+    fn update(&mut self, delta: f32, volume: f32) {
+        let step = delta / self.crossfade;
+        for (i, track) in self.tracks.iter_mut().enumerate() {
+            if !track.music.is_stream_playing() {
+                continue;
+            }
+            track.music.update_stream();
+            if Some(i) == self.current {
+                track.gain = (track.gain + step).min(1.0);
+            } else {
+                track.gain = (track.gain - step).max(0.0);
+                if track.gain <= 0.0 {
+                    track.music.stop_stream();
+                }
+            }
+            track.music.set_volume(track.gain * volume);
+        }
+        // fade into the next track of the mood before this one loops
+        if let Some(i) = self.current {
+            let music = &self.tracks[i].music;
+            if music.get_time_played() + self.crossfade >= music.get_time_length() {
+                self.start_next_track();
+            }
+        }
+    }
+}
+
+fn audio_files(dir: &str) -> Vec<PathBuf> {
+    let mut paths = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|ext| SOUND_EXTENSIONS.iter().any(|s| ext == *s)))
+            .collect::<Vec<_>>(),
+        Err(e) => {
+            crate::log_warn!("could not read {}: {}", dir, e);
+            Vec::new()
+        }
+    };
+    paths.sort();
+    paths
+}
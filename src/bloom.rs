@@ -0,0 +1,56 @@
+use raylib::prelude::*;
+
+use crate::lighting::Lit;
+use crate::{scale, World};
+
+// world pixels a pixel with a glow of 1 blooms out to
+const GLOW_RADIUS: f32 = 3.0;
+// only every BLOOM_STEP-th pixel each way gets a glow of its own, they overlap anyway
+const BLOOM_STEP: u8 = 2;
+// how much of a pixel's color is added at the middle of its glow at full intensity
+const GLOW_STRENGTH: f32 = 0.35;
+// a light's bloom is this much of its radius, only the bright middle of it
+const LIGHT_CORE: f32 = 0.3;
+const LIGHT_STRENGTH: f32 = 0.5;
+// world pixels past the view that still bloom into it
+const MARGIN: f32 = 4.0;
+
+// the glow around bright materials and lights, added on top of the lit scene in the world's 2d
+// mode. view is in world pixels, see Game::view. intensity is from the video settings, 0 is off
+pub fn draw(d: &mut impl RaylibDraw, world: &World, view: Rectangle, lights: &[Lit], intensity: f32) {
+    if intensity <= 0.0 {
+        return;
+    }
+    crate::profile_scope!("bloom");
+    let s = scale() as f32;
+    let scaled = |color: Color, amount: f32| {
+        let amount = amount.clamp(0.0, 1.0);
+        Color::new((color.r as f32 * amount) as u8, (color.g as f32 * amount) as u8, (color.b as f32 * amount) as u8, 255)
+    };
+    let (left, right) = (view.x - MARGIN, view.x + view.width + MARGIN);
+    let (top, bottom) = (view.y - MARGIN, view.y + view.height + MARGIN);
+    let mut added = d.begin_blend_mode(BlendMode::BLEND_ADDITIVE);
+    let visible = world.chunks.iter().filter(|c| {
+        let (x, y) = (c.x as f32, c.y as f32);
+        x + 16.0 > left && x < right && y + 16.0 > top && y < bottom
+    });
+    for chunk in visible {
+        for pixel in chunk.pixels.iter().flatten() {
+            if pixel.x % BLOOM_STEP != 0 || pixel.y % BLOOM_STEP != 0 {
+                continue;
+            }
+            let glow = world.materials.get(pixel.material).glow;
+            if glow <= 0.0 {
+                continue;
+            }
+            let center = Vector2::new((chunk.x + pixel.x as i64) as f32 + 0.5, (chunk.y + pixel.y as i64) as f32 + 0.5) * s;
+            let color = scaled(pixel.color.into(), glow * GLOW_STRENGTH * intensity);
+            added.draw_circle_gradient(center.x as i32, center.y as i32, GLOW_RADIUS * glow * s, color, Color::new(0, 0, 0, 0));
+        }
+    }
+    for lit in lights {
+        let center = lit.position * s;
+        let color = scaled(lit.color, lit.strength * LIGHT_STRENGTH * intensity);
+        added.draw_circle_gradient(center.x as i32, center.y as i32, lit.radius * LIGHT_CORE * s, color, Color::new(0, 0, 0, 0));
+    }
+}
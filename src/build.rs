@@ -0,0 +1,73 @@
+use raylib::prelude::*;
+
+use crate::material::MaterialId;
+use crate::{Player, World};
+
+// world pixels from the player's center a pixel can be placed at
+pub const REACH: f32 = 24.0;
+// seconds between pixels while the button is held, so dragging draws a line
+const PLACE_INTERVAL: f32 = 0.08;
+
+// placing pixels by hand out of the inventory, next to casting rather than instead of it. the
+// item in the selected hotbar slot decides the material, see MaterialRegistry::placed_by
+pub struct BuildMode {
+    pub active: bool,
+    cooldown: f32,
+}
+
+// where a pixel would go and what of
+pub struct Placement {
+    pub x: i64,
+    pub y: i64,
+    pub material: MaterialId,
+    pub item: String,
+}
+
+impl BuildMode {
+    pub fn new() -> Self {
+        BuildMode { active: false, cooldown: 0.0 }
+    }
+
+    pub fn toggle(&mut self) {
+        self.active = !self.active;
+    }
+
+    // what placing at target would do, or why it can't happen
+    pub fn check(&self, world: &World, player: &Player, target: Vector2) -> Result<Placement, String> {
+        let item = player.inventory.held().ok_or("Nothing in hand to build with")?;
+        let material = world.materials.placed_by(&item.name).ok_or_else(|| format!("{} can't be placed", item.name))?;
+        let (x, y) = (target.x.floor() as i64, target.y.floor() as i64);
+        let cell = Vector2::new(x as f32 + 0.5, y as f32 + 0.5);
+        if cell.distance_to(player.center()) > REACH {
+            return Err("Out of reach".to_string());
+        }
+        if world.chunk_at(x, y).is_none() || world.pixel_at(x, y).is_some() {
+            return Err("Something is already there".to_string());
+        }
+        let body = Rectangle::new(player.position.x, player.position.y, player.size.x, player.size.y);
+        if body.check_collision_point_rec(cell) {
+            return Err("You are in the way".to_string());
+        }
+        Ok(Placement { x, y, material, item: item.name.clone() })
+    }
+
+    // places at target while held, taking the item out of the inventory. the reason it didn't
+    // is only given on the press itself, not every frame it's held
+    pub fn update(&mut self, world: &mut World, player: &mut Player, target: Vector2, pressed: bool, held: bool, delta: f32) -> Option<String> {
+        self.cooldown = (self.cooldown - delta).max(0.0);
+        if !self.active || !(pressed || held) || (self.cooldown > 0.0 && !pressed) {
+            return None;
+        }
+        let placement = match self.check(world, player, target) {
+            Ok(placement) => placement,
+            Err(reason) => return pressed.then_some(reason),
+        };
+        if !player.inventory.remove(&placement.item, 1) {
+            return None;
+        }
+        let color = world.materials.get(placement.material).color_at(placement.x, placement.y);
+        world.place_pixel(placement.x, placement.y, placement.material, color);
+        self.cooldown = PLACE_INTERVAL;
+        None
+    }
+}
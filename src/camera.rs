@@ -0,0 +1,65 @@
+use raylib::prelude::*;
+
+use crate::config;
+use crate::events::{GameEvent, Subscriber};
+
+// sits on top of player.camera, the player keeps owning the "real" camera and we only
+// perturb a copy of it right before drawing
+pub struct CameraEffects {
+    pub trauma: f32,
+    pub trauma_decay: f32, // trauma lost per second
+    pub max_offset: f32,   // in screen pixels at full trauma
+    pub max_rotation: f32, // in degrees at full trauma
+    pub frequency: f32,
+    time: f32,
+}
+
+impl CameraEffects {
+    pub fn new() -> Self {
+        CameraEffects {
+            trauma: 0.0,
+            trauma_decay: 1.5,
+            max_offset: 12.0,
+            max_rotation: 3.0,
+            frequency: 25.0,
+            time: 0.0,
+        }
+    }
+
+    pub fn add_trauma(&mut self, amount: f32) {
+        let amount = amount * config::get().screen_shake;
+        self.trauma = (self.trauma + amount).clamp(0.0, 1.0);
+    }
+
+    pub fn update(&mut self, delta: f32) {
+        self.time += delta;
+        self.trauma = (self.trauma - self.trauma_decay * delta).max(0.0);
+    }
+
+    pub fn apply(&self, camera: Camera2D) -> Camera2D {
+        // squaring makes small hits subtle and big hits violent
+        let shake = self.trauma * self.trauma;
+        if shake <= 0.0 {
+            return camera;
+        }
+        let t = self.time * self.frequency;
+        let mut shaken = camera;
+        shaken.offset.x += self.max_offset * shake * wobble(t, 0.0);
+        shaken.offset.y += self.max_offset * shake * wobble(t, 17.0);
+        shaken.rotation += self.max_rotation * shake * wobble(t, 41.0);
+        shaken
+    }
+}
+
+impl Subscriber for CameraEffects {
+    fn on_event(&mut self, event: &GameEvent) {
+        if let GameEvent::DamageDealt { amount, entity: None, .. } = event {
+            self.add_trauma(amount / 40.0);
+        }
+    }
+}
+
+// cheap smooth noise in [-1, 1], a few incommensurate sines are enough for a shake
+fn wobble(t: f32, seed: f32) -> f32 {
+    ((t + seed).sin() * 0.5 + (t * 1.7 + seed * 2.3).sin() * 0.3 + (t * 3.1 + seed * 0.7).sin() * 0.2).clamp(-1.0, 1.0)
+}
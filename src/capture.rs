@@ -0,0 +1,93 @@
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use raylib::prelude::*;
+
+use crate::error::SpellcoderError;
+
+// in the save directory, recordings get a folder of their own inside it
+pub const SCREENSHOT_DIR: &str = "screenshots";
+// a recording stops by itself after this many frames, 10 seconds
+const MAX_RECORD_FRAMES: u32 = 300;
+// only every this many frames is kept, 30 a second at 60 fps
+const RECORD_EVERY: u64 = 2;
+
+// screenshots and frame sequences. Game::draw asks wants_frame every frame and, when it says so,
+// reads the frame back and hands it to save
+pub struct Capture {
+    screenshot: bool,
+    recording: Option<Recording>,
+}
+
+struct Recording {
+    dir: String,
+    frames: u32,
+    counter: u64,
+    // this frame is one of the kept ones
+    keep: bool,
+}
+
+impl Capture {
+    pub fn new() -> Self {
+        Capture { screenshot: false, recording: None }
+    }
+
+    // the next frame drawn gets saved
+    pub fn request_screenshot(&mut self) {
+        self.screenshot = true;
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    // starts a new frame sequence under dir, or stops the one going and says where it went
+    pub fn toggle_recording(&mut self, dir: &str) -> Option<(String, u32)> {
+        if let Some(recording) = self.recording.take() {
+            return Some((recording.dir, recording.frames));
+        }
+        let dir = Path::new(dir).join(format!("recording_{}", timestamp())).to_string_lossy().to_string();
+        self.recording = Some(Recording { dir, frames: 0, counter: 0, keep: false });
+        None
+    }
+
+    // call once per frame
+    pub fn wants_frame(&mut self) -> bool {
+        if let Some(recording) = &mut self.recording {
+            recording.keep = recording.counter % RECORD_EVERY == 0;
+            recording.counter += 1;
+        }
+        self.screenshot || self.recording.as_ref().is_some_and(|r| r.keep)
+    }
+
+    // writes the frame wherever it was wanted, screenshots into dir. returns something to tell
+    // the player when a screenshot was taken or a recording ran out
+    pub fn save(&mut self, image: &Image, dir: &str) -> Result<Option<String>, SpellcoderError> {
+        let mut message = None;
+        if self.screenshot {
+            self.screenshot = false;
+            fs::create_dir_all(dir).map_err(|e| SpellcoderError::save(dir, e))?;
+            let path = Path::new(dir).join(format!("screenshot_{}.png", timestamp())).to_string_lossy().to_string();
+            image.export_image(&path);
+            message = Some(format!("Saved {}", path));
+        }
+        let Some(recording) = self.recording.as_mut().filter(|r| r.keep) else {
+            return Ok(message);
+        };
+        fs::create_dir_all(&recording.dir).map_err(|e| SpellcoderError::save(recording.dir.as_str(), e))?;
+        let path = Path::new(&recording.dir).join(format!("frame_{:04}.png", recording.frames));
+        image.export_image(&path.to_string_lossy());
+        recording.frames += 1;
+        if recording.frames >= MAX_RECORD_FRAMES {
+            let recording = self.recording.take().unwrap();
+            message = Some(format!("Saved {} frames to {}", recording.frames, recording.dir));
+        }
+        Ok(message)
+    }
+}
+
+// milliseconds, so two screenshots in the same second don't overwrite each other
+fn timestamp() -> u128 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis())
+}
@@ -0,0 +1,97 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::inventory::Inventory;
+use crate::loot::LootTables;
+use crate::rng::StreamRng;
+
+pub const CHEST_SLOTS: usize = 12;
+// what a ruin chest is filled from, see loot.json
+const CHEST_LOOT: &str = "ruin_chest";
+// used up opening a locked chest
+pub const KEY_ITEM: &str = "ruin key";
+// sealed chests open to one of these cast at them
+const SEALS: [&str; 3] = ["Arc", "Frost", "Fireball"];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Lock {
+    Open,
+    Key,
+    // the spell's name
+    Seal(&'static str),
+}
+
+impl Lock {
+    // the same chest of the same world always has the same lock
+    pub fn of(seed: u64, id: i64) -> Lock {
+        let hash = ((id as u64).wrapping_mul(0x1656_67B1) ^ seed).wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 40;
+        match hash % 4 {
+            0 | 1 => Lock::Open,
+            2 => Lock::Key,
+            _ => Lock::Seal(SEALS[(hash / 4) as usize % SEALS.len()]),
+        }
+    }
+}
+
+// a chest someone has opened or unlocked. the ones nobody has touched aren't in the save, their
+// contents come from the loot table the first time
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Chest {
+    pub unlocked: bool,
+    pub contents: Inventory,
+}
+
+// every chest in the world that's been touched, by the left edge of its ruin, see world::chest_at.
+// kept in the world save
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Chests {
+    pub chests: BTreeMap<i64, Chest>,
+}
+
+impl Chests {
+    // the chest, filled the first time it's asked for
+    pub fn get(&mut self, id: i64, seed: u64, loot: &LootTables) -> &mut Chest {
+        self.chests.entry(id).or_insert_with(|| {
+            let mut contents = Inventory::with_slots(CHEST_SLOTS);
+            let mut rng = StreamRng::new(seed ^ (id as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+            for drop in loot.roll(CHEST_LOOT, &mut rng) {
+                contents.add(&drop.item, drop.count);
+            }
+            Chest { unlocked: false, contents }
+        })
+    }
+
+    pub fn is_locked(&self, id: i64, seed: u64) -> bool {
+        Lock::of(seed, id) != Lock::Open && !self.chests.get(&id).is_some_and(|c| c.unlocked)
+    }
+
+    // takes a key out of the inventory for a locked chest, or says why it stays shut
+    pub fn open(&mut self, id: i64, seed: u64, loot: &LootTables, inventory: &mut Inventory) -> Result<(), String> {
+        if !self.is_locked(id, seed) {
+            return Ok(());
+        }
+        match Lock::of(seed, id) {
+            Lock::Key if inventory.remove(KEY_ITEM, 1) => {
+                self.get(id, seed, loot).unlocked = true;
+                Ok(())
+            }
+            Lock::Key => Err(format!("Locked, it needs a {}", KEY_ITEM)),
+            Lock::Seal(spell) => Err(format!("Sealed, {} might break it", spell)),
+            Lock::Open => Ok(()),
+        }
+    }
+
+    // true if the spell broke the chest's seal
+    pub fn cast_at(&mut self, id: i64, seed: u64, loot: &LootTables, spell: &str) -> bool {
+        let Lock::Seal(seal) = Lock::of(seed, id) else {
+            return false;
+        };
+        if seal != spell || !self.is_locked(id, seed) {
+            return false;
+        }
+        self.get(id, seed, loot).unlocked = true;
+        true
+    }
+}
@@ -0,0 +1,364 @@
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+use std::time::Instant;
+
+use raylib::prelude::*;
+
+use crate::chest::Chests;
+use crate::config::Config;
+use crate::equipment::Equipment;
+use crate::error::SpellcoderError;
+use crate::experience::Experience;
+use crate::exploration::Exploration;
+use crate::inventory::Inventory;
+use crate::material::{MaterialRegistry, MATERIALS_PATH};
+use crate::quest::QuestProgress;
+use crate::rules::WorldRules;
+use crate::save::{WorldSave, WORLD_SAVE_PATH};
+use crate::scenario::{self, SCENARIO_DIR};
+use crate::session::{self, Record, SESSION_DIR};
+use crate::skills::SkillTree;
+use crate::spell::{self, CastVariant, Spell, Spellbook};
+use crate::stats::Statistics;
+use crate::{dryrun, Player, World};
+
+const DEFAULT_RADIUS: i64 = 4;
+const DEFAULT_MAP_PATH: &str = "map.png";
+// a little to the right of the spawn and into the ground
+const DEFAULT_TARGET: Vector2 = Vector2::new(8.0, 8.0);
+const USAGE: &str = "usage:
+  spellcoder gen [--seed X] [--radius N] [--force]   set up a world save and report what it holds
+  spellcoder validate-spells [--spells DIR]          check every spell file, exits with 1 on problems
+  spellcoder export-map [--seed X] [--radius N] [--out FILE]   draw the world to a png
+  spellcoder analyze-sessions [--from FILE_OR_DIR]   sum up the session logs written with --session-log
+  spellcoder dry-run --spell NAME [--variant shift|ctrl] [--at X,Y] [--seed X] [--radius N]
+                                                     cast a spell on a fresh world and report what it did
+  spellcoder scenario [--from FILE_OR_DIR]           play out the scenarios, exits with 1 if any fail
+any other flags are the game's own, e.g. --saves DIR, --config FILE or --daily";
+
+// the subcommands run without a window. returns None when the first argument isn't one, then
+// the game starts as usual, otherwise the exit code
+pub fn run(args: &[String]) -> Option<i32> {
+    let command = args.first()?;
+    let command = match command.as_str() {
+        "gen" | "validate-spells" | "export-map" | "analyze-sessions" | "dry-run" | "scenario" => command.clone(),
+        "help" | "--help" => {
+            println!("{}", USAGE);
+            return Some(0);
+        }
+        _ => return None,
+    };
+    let result = Options::parse(&args[1..]).and_then(|options| match command.as_str() {
+        "gen" => gen(&options),
+        "validate-spells" => validate_spells(&options),
+        "analyze-sessions" => analyze_sessions(&options),
+        "dry-run" => dry_run(&options),
+        "scenario" => scenarios(&options),
+        _ => export_map(&options),
+    });
+    Some(match result {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    })
+}
+
+// the subcommand's own flags, everything else goes to the config like when starting the game
+struct Options {
+    config: Config,
+    radius: i64,
+    out: String,
+    force: bool,
+    from: Option<String>,
+    spell: Option<String>,
+    variant: CastVariant,
+    // world pixels the spell is aimed at, the caster stands at the spawn
+    at: Vector2,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Options, SpellcoderError> {
+        let mut rest = Vec::new();
+        let mut radius = DEFAULT_RADIUS;
+        let mut out = DEFAULT_MAP_PATH.to_string();
+        let mut force = false;
+        let mut from = None;
+        let mut spell = None;
+        let mut variant = CastVariant::Normal;
+        let mut at = DEFAULT_TARGET;
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--radius" => {
+                    let value = args.next().ok_or_else(|| SpellcoderError::invalid("command line", "--radius needs a value"))?;
+                    radius = value
+                        .parse()
+                        .map_err(|_| SpellcoderError::invalid("command line", format!("--radius expects a number, not {}", value)))?;
+                }
+                "--out" => out = args.next().ok_or_else(|| SpellcoderError::invalid("command line", "--out needs a value"))?.clone(),
+                "--force" => force = true,
+                "--from" => from = Some(args.next().ok_or_else(|| SpellcoderError::invalid("command line", "--from needs a value"))?.clone()),
+                "--spell" => spell = Some(args.next().ok_or_else(|| SpellcoderError::invalid("command line", "--spell needs a value"))?.clone()),
+                "--variant" => {
+                    let value = args.next().ok_or_else(|| SpellcoderError::invalid("command line", "--variant needs a value"))?;
+                    variant = CastVariant::parse(value)
+                        .ok_or_else(|| SpellcoderError::invalid("command line", format!("--variant is normal, shift or ctrl, not {}", value)))?;
+                }
+                "--at" => {
+                    let value = args.next().ok_or_else(|| SpellcoderError::invalid("command line", "--at needs a value"))?;
+                    let parsed = value.split_once(',').and_then(|(x, y)| Some(Vector2::new(x.trim().parse().ok()?, y.trim().parse().ok()?)));
+                    at = parsed.ok_or_else(|| SpellcoderError::invalid("command line", format!("--at expects X,Y, not {}", value)))?;
+                }
+                _ => rest.push(arg.clone()),
+            }
+        }
+        let mut errors = Vec::new();
+        let config = Config::load(&rest, &mut errors);
+        if let Some(e) = errors.into_iter().next() {
+            return Err(e);
+        }
+        Ok(Options { config, radius: radius.max(1), out, force, from, spell, variant, at })
+    }
+
+    fn materials(&self) -> Result<MaterialRegistry, SpellcoderError> {
+        if Path::new(MATERIALS_PATH).exists() {
+            MaterialRegistry::load(MATERIALS_PATH)
+        } else {
+            MaterialRegistry::builtin()
+        }
+    }
+
+    // radius chunks to either side of the spawn and as many down
+    fn world(&self) -> Result<World, SpellcoderError> {
+        let mut world = World::new(self.materials()?, self.config.seed);
+        for x in -self.radius..self.radius {
+            for y in 0..self.radius {
+                world.generate_chunk(x, y);
+            }
+        }
+        Ok(world)
+    }
+}
+
+fn gen(options: &Options) -> Result<i32, SpellcoderError> {
+    let path = options.config.save_path(WORLD_SAVE_PATH);
+    if !options.force && WorldSave::load(&path)?.is_some() {
+        eprintln!("{} already exists, --force to replace it", path);
+        return Ok(1);
+    }
+    let started = Instant::now();
+    let world = options.world()?;
+    println!("seed {}: {} chunks in {:.2}s", world.seed, world.chunks.len(), started.elapsed().as_secs_f32());
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for pixel in world.chunks.iter().flat_map(|c| c.pixels.iter().flatten()) {
+        *counts.entry(world.materials.get(pixel.material).name.as_str()).or_default() += 1;
+    }
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1));
+    for (name, count) in counts {
+        println!("  {:12} {}", name, count);
+    }
+    std::fs::create_dir_all(&options.config.save_dir).map_err(|e| SpellcoderError::save(options.config.save_dir.as_str(), e))?;
+    let save = WorldSave {
+        seed: world.seed,
+        rules: WorldRules::default(),
+        stats: Statistics::default(),
+        experience: Experience::default(),
+        skills: SkillTree::default(),
+        quests: QuestProgress::default(),
+        known_spells: Vec::new(),
+        used_altars: BTreeSet::new(),
+        survived: 0.0,
+        boss_defeated: false,
+        inventory: Inventory::default(),
+        equipment: Equipment::default(),
+        chests: Chests::default(),
+        coins: 0,
+        explored: Exploration::default(),
+    };
+    save.save(&path)?;
+    println!("saved {}", path);
+    Ok(0)
+}
+
+fn validate_spells(options: &Options) -> Result<i32, SpellcoderError> {
+    let materials = options.materials()?;
+    let dir = &options.config.spell_dir;
+    let mut problems = 0;
+    let mut names: HashMap<String, String> = HashMap::new();
+    for path in spell::spell_files(dir)? {
+        let file = path.display().to_string();
+        let spell: Spell = match spell::load_spell(&path) {
+            Ok(spell) => spell,
+            Err(e) => {
+                println!("{}", e);
+                problems += 1;
+                continue;
+            }
+        };
+        let mut found = spell.lint(&materials);
+        if let Some(other) = names.insert(spell.name.clone(), file.clone()) {
+            found.push(format!("{} is also the name of {}", spell.name, other));
+        }
+        for problem in &found {
+            println!("{}: {}", file, problem);
+        }
+        problems += found.len();
+    }
+    println!("{} spells, {} problems", names.len(), problems);
+    Ok(if problems == 0 { 0 } else { 1 })
+}
+
+// one png pixel per world pixel, air left transparent
+fn export_map(options: &Options) -> Result<i32, SpellcoderError> {
+    let world = options.world()?;
+    let left = -options.radius * 16;
+    let (width, height) = (options.radius as i32 * 32, options.radius as i32 * 16);
+    let mut image = Image::gen_image_color(width, height, Color::BLANK);
+    for chunk in &world.chunks {
+        for pixel in chunk.pixels.iter().flatten() {
+            let x = (chunk.x + pixel.x as i64 - left) as i32;
+            let y = (chunk.y + pixel.y as i64) as i32;
+            image.draw_pixel(x, y, pixel.color);
+        }
+    }
+    image.export_image(&options.out);
+    println!("{}x{} map of seed {} written to {}", width, height, world.seed, options.out);
+    Ok(0)
+}
+
+// a spell from the spell directory, known or not, on a freshly generated world
+fn dry_run(options: &Options) -> Result<i32, SpellcoderError> {
+    let Some(name) = &options.spell else {
+        return Err(SpellcoderError::invalid("command line", "dry-run needs --spell NAME"));
+    };
+    let world = options.world()?;
+    let mut errors = Vec::new();
+    let spellbook = Spellbook::load(&options.config.spell_dir, &world.materials, &mut errors);
+    for e in errors {
+        eprintln!("{}", e);
+    }
+    let Some(spell) = spellbook.find(name) else {
+        eprintln!("no spell called {} in {}", name, options.config.spell_dir);
+        return Ok(1);
+    };
+    let player = Player::new(Vector2::zero());
+    let run = dryrun::dry_run(spell, options.variant, 1.0, &world, &player, options.at);
+    println!("seed {}, aimed at {}, {}", world.seed, options.at.x, options.at.y);
+    for line in run.lines() {
+        println!("  {}", line);
+    }
+    Ok(0)
+}
+
+fn scenarios(options: &Options) -> Result<i32, SpellcoderError> {
+    let from = options.from.clone().unwrap_or_else(|| SCENARIO_DIR.to_string());
+    let materials = options.materials()?;
+    let mut errors = Vec::new();
+    let spellbook = Spellbook::load(&options.config.spell_dir, &materials, &mut errors);
+    for e in errors {
+        eprintln!("{}", e);
+    }
+    let paths = scenario::scenario_files(&from)?;
+    let mut failed = 0;
+    for path in &paths {
+        let outcome = match scenario::load(path) {
+            Ok(scenario) => scenario.run(materials.clone(), &spellbook),
+            Err(e) => scenario::Outcome { name: path.display().to_string(), failures: vec![e.to_string()] },
+        };
+        if outcome.passed() {
+            println!("ok    {}", outcome.name);
+            continue;
+        }
+        failed += 1;
+        println!("FAIL  {}", outcome.name);
+        for failure in &outcome.failures {
+            println!("      {}", failure);
+        }
+    }
+    println!("{} of {} scenarios passed", paths.len() - failed, paths.len());
+    Ok(if failed > 0 { 1 } else { 0 })
+}
+
+// per spell totals over every session, to see which spells are too cheap for what they do
+#[derive(Default)]
+struct SpellUse {
+    casts: u32,
+    cost: f32,
+    touched: u32,
+}
+
+fn analyze_sessions(options: &Options) -> Result<i32, SpellcoderError> {
+    let from = options.from.clone().unwrap_or_else(|| options.config.save_path(SESSION_DIR));
+    let paths = if Path::new(&from).is_dir() {
+        let mut paths: Vec<_> = std::fs::read_dir(&from)
+            .map_err(|e| SpellcoderError::io(from.as_str(), e))?
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|ext| ext == "jsonl"))
+            .collect();
+        paths.sort();
+        paths
+    } else {
+        vec![Path::new(&from).to_path_buf()]
+    };
+    let mut spells: HashMap<String, SpellUse> = HashMap::new();
+    let (mut length, mut dealt, mut taken, mut deaths, mut kills, mut skipped) = (0.0, 0.0, 0.0, 0, 0, 0);
+    for path in &paths {
+        let (records, bad) = session::read(path)?;
+        skipped += bad;
+        // the last thing logged, close enough to when the session ended
+        let mut file_length: f32 = 0.0;
+        for record in records {
+            match record {
+                Record::Start { .. } => {}
+                Record::Cast { time, spell, cost, touched } => {
+                    let entry = spells.entry(spell).or_default();
+                    entry.casts += 1;
+                    entry.cost += cost;
+                    entry.touched += touched as u32;
+                    file_length = f32::max(file_length, time);
+                }
+                Record::Damage { time, amount, to_player } => {
+                    if to_player {
+                        taken += amount;
+                    } else {
+                        dealt += amount;
+                    }
+                    file_length = f32::max(file_length, time);
+                }
+                Record::Death { time, player } => {
+                    if player {
+                        deaths += 1;
+                    } else {
+                        kills += 1;
+                    }
+                    file_length = f32::max(file_length, time);
+                }
+            }
+        }
+        length += file_length;
+    }
+    if paths.is_empty() {
+        println!("no session logs in {}, start the game with --session-log to write some", from);
+        return Ok(1);
+    }
+    let spent: f32 = spells.values().map(|s| s.cost).sum();
+    println!("{} sessions, {:.1} minutes{}", paths.len(), length / 60.0, if skipped > 0 { format!(", {} bad lines skipped", skipped) } else { String::new() });
+    println!("{:20} {:>6} {:>9} {:>10} {:>8}", "spell", "casts", "avg cost", "total cost", "touched");
+    let mut spells: Vec<_> = spells.into_iter().collect();
+    spells.sort_by(|a, b| b.1.cost.total_cmp(&a.1.cost));
+    for (name, used) in &spells {
+        let touched = used.touched as f32 / used.casts as f32 * 100.0;
+        println!("{:20} {:>6} {:>9.1} {:>10.0} {:>7.0}%", name, used.casts, used.cost / used.casts as f32, used.cost, touched);
+    }
+    if length > 0.0 {
+        println!("mp spent per minute: {:.1}", spent / length * 60.0);
+    }
+    let per_mp = if spent > 0.0 { dealt / spent * 100.0 } else { 0.0 };
+    println!("damage dealt: {:.0} ({:.1} per 100 mp), {} kills", dealt, per_mp, kills);
+    println!("damage taken: {:.0}, {} deaths", taken, deaths);
+    Ok(0)
+}
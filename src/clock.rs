@@ -0,0 +1,176 @@
+use crate::events::{GameEvent, Subscriber};
+
+// longer frames than this are cut short, so a hitch or a breakpoint doesn't teleport everything
+const MAX_DELTA: f32 = 0.1;
+// how quickly slow motion eases back to normal speed once it runs out, per second
+const SLOW_MOTION_RECOVERY: f32 = 4.0;
+// what the debug time scale goes round through, see cycle_time_scale
+const DEBUG_SCALES: [f32; 3] = [1.0, 0.25, 4.0];
+
+// the one place frame time comes from. the main loop ticks it with the real frame time and
+// everything that simulates reads delta() from it, so pausing, slow motion and fixed steps for
+// replays all happen here instead of in every system
+pub struct GameClock {
+    real_time: f64,
+    time: f64,
+    frame: u64,
+    real_delta: f32,
+    delta: f32,
+    paused: bool,
+    // set by hand, from the console or debug tools
+    time_scale: f32,
+    // factor and real seconds left, from spells
+    slow_motion: Option<(f32, f32)>,
+    // the part of slow motion still easing out
+    recovery: f32,
+    // seconds per frame regardless of how long the frame really took, 0 for real time
+    fixed_step: f32,
+    // held from the debug keys, time stands still until it's let go except for single steps.
+    // unlike paused the states don't touch it
+    held: bool,
+    // simulated seconds the next frame steps while held
+    step: Option<f32>,
+}
+
+impl GameClock {
+    pub fn new(fixed_step: f32) -> Self {
+        GameClock {
+            real_time: 0.0,
+            time: 0.0,
+            frame: 0,
+            real_delta: 0.0,
+            delta: 0.0,
+            paused: false,
+            time_scale: 1.0,
+            slow_motion: None,
+            recovery: 1.0,
+            fixed_step: fixed_step.max(0.0),
+            held: false,
+            step: None,
+        }
+    }
+
+    // call once at the start of every frame with how long the last one took
+    pub fn tick(&mut self, frame_time: f32) {
+        self.frame += 1;
+        self.real_delta = if self.fixed_step > 0.0 { self.fixed_step } else { frame_time.min(MAX_DELTA) };
+        self.real_time += self.real_delta as f64;
+        match &mut self.slow_motion {
+            Some((factor, remaining)) => {
+                *remaining -= self.real_delta;
+                self.recovery = *factor;
+                if *remaining <= 0.0 {
+                    self.slow_motion = None;
+                }
+            }
+            None => self.recovery = (self.recovery + SLOW_MOTION_RECOVERY * self.real_delta).min(1.0),
+        }
+        self.delta = self.real_delta * self.scale();
+        if self.held {
+            // a step while paused is dropped, the menu would swallow it anyway
+            self.delta = self.step.take().filter(|_| !self.paused).unwrap_or(0.0);
+        }
+        self.time += self.delta as f64;
+    }
+
+    // simulated seconds this frame: scaled, and 0 while paused
+    pub fn delta(&self) -> f32 {
+        self.delta
+    }
+
+    // seconds this frame ignoring pause and slow motion, for menus, input and music
+    pub fn real_delta(&self) -> f32 {
+        self.real_delta
+    }
+
+    // simulated seconds since the start, what animations should run off
+    pub fn time(&self) -> f32 {
+        self.time as f32
+    }
+
+    // what walking and casting run on. real time, so slow motion from spells leaves the player
+    // alone, but held and debug scaled time take the player with them so a frame can be looked
+    // at, and a step moves it on by one tick like everything else
+    pub fn player_delta(&self) -> f32 {
+        if self.held || self.time_scale != 1.0 {
+            self.delta
+        } else {
+            self.real_delta
+        }
+    }
+
+    pub fn real_time(&self) -> f32 {
+        self.real_time as f32
+    }
+
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    // how many simulated seconds pass per real one right now
+    pub fn scale(&self) -> f32 {
+        if self.paused {
+            return 0.0;
+        }
+        self.time_scale * self.recovery
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_time_scale(&mut self, scale: f32) {
+        self.time_scale = scale.max(0.0);
+    }
+
+    pub fn time_scale(&self) -> f32 {
+        self.time_scale
+    }
+
+    // stops simulated time, or lets it go again. real time and everything running off
+    // real_delta carries on, the player stands still with the world, see player_delta
+    pub fn toggle_held(&mut self) -> bool {
+        self.held = !self.held;
+        self.step = None;
+        self.held
+    }
+
+    pub fn is_held(&self) -> bool {
+        self.held
+    }
+
+    // while held, the next frame moves simulated time on by seconds and then it stands still again
+    pub fn step(&mut self, seconds: f32) {
+        if self.held {
+            self.step = Some(seconds);
+        }
+    }
+
+    // the next of DEBUG_SCALES after the current time scale, back to 1 after the last
+    pub fn cycle_time_scale(&mut self) -> f32 {
+        let next = DEBUG_SCALES.iter().position(|s| *s == self.time_scale).map_or(0, |i| (i + 1) % DEBUG_SCALES.len());
+        self.set_time_scale(DEBUG_SCALES[next]);
+        self.time_scale
+    }
+
+    // slows everything to factor for duration real seconds. the slowest one running wins
+    pub fn slow_motion(&mut self, factor: f32, duration: f32) {
+        let factor = factor.clamp(0.05, 1.0);
+        self.slow_motion = match self.slow_motion {
+            Some((current, remaining)) if current <= factor => Some((current, remaining.max(duration))),
+            _ => Some((factor, duration)),
+        };
+    }
+}
+
+impl Subscriber for GameClock {
+    fn on_event(&mut self, event: &GameEvent) {
+        if let GameEvent::SlowMotion { factor, duration } = event {
+            self.slow_motion(*factor, *duration);
+        }
+    }
+}
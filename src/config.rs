@@ -0,0 +1,202 @@
+use std::fs;
+use std::path::Path;
+use std::sync::{OnceLock, RwLock, RwLockReadGuard};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::SpellcoderError;
+use crate::input::PROFILES;
+use crate::scheduler::DEFAULT_BUDGET_MS;
+use crate::spell::SPELL_DIR;
+
+pub const CONFIG_PATH: &str = "config.toml";
+// what the flags are called when something about them is wrong
+const ARGS_NAME: &str = "command line";
+
+// the defaults are overridden by config.toml, which is overridden by command line flags, see
+// Config::load. most are read when needed so the settings menu can change them while running
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub window_width: i32,
+    pub window_height: i32,
+    pub vsync: bool,
+    pub fullscreen: bool,
+    pub seed: u64,
+    pub spell_dir: String,
+    // where controls, audio settings and anything else the game writes go
+    pub save_dir: String,
+    // screen pixels per world pixel
+    pub scale: i32,
+    // seconds every frame counts as no matter how long it took, 0 for real time. for replays
+    pub fixed_step: f32,
+    pub show_fps: bool,
+    // 0 turns camera shake off, 1 is full strength
+    pub screen_shake: f32,
+    // 0 turns the glow around bright things off, 1 is full strength
+    pub bloom: f32,
+    pub damage_flash: bool,
+    // screenshots and recordings include the hud, otherwise they're just the world
+    pub capture_overlays: bool,
+    // writes a local log of casts, damage and deaths for balancing, see session.rs
+    pub session_log: bool,
+    // milliseconds per frame for chunk generation, saving and other work that can wait
+    pub background_budget_ms: f32,
+    // megabytes the loaded chunks and their baked shading should stay under. nothing unloads
+    // chunks yet, past this the debug overlay and the log warn about it
+    pub chunk_budget_mb: f32,
+    // one of input::PROFILES, each has its own controls file
+    pub controls_profile: String,
+    // the file this was loaded from, where the settings menu writes changes back to
+    #[serde(skip)]
+    pub path: String,
+    // play today's daily challenge instead of the saved world, only ever from --daily
+    #[serde(skip)]
+    pub daily: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            window_width: 640,
+            window_height: 480,
+            vsync: true,
+            fullscreen: false,
+            seed: 69420,
+            spell_dir: SPELL_DIR.to_string(),
+            save_dir: ".".to_string(),
+            scale: 4,
+            fixed_step: 0.0,
+            show_fps: true,
+            screen_shake: 1.0,
+            bloom: 0.6,
+            damage_flash: true,
+            capture_overlays: true,
+            session_log: false,
+            background_budget_ms: DEFAULT_BUDGET_MS,
+            chunk_budget_mb: 64.0,
+            controls_profile: PROFILES[0].to_string(),
+            path: CONFIG_PATH.to_string(),
+            daily: false,
+        }
+    }
+}
+
+impl Config {
+    // args without the program name. problems with a layer are added to errors and that layer
+    // is skipped, so a typo in config.toml doesn't also throw away the flags
+    pub fn load(args: &[String], errors: &mut Vec<SpellcoderError>) -> Config {
+        let path = flag_value(args, "--config").unwrap_or(CONFIG_PATH);
+        let mut config = match Config::from_file(path) {
+            Ok(config) => config,
+            Err(e) => {
+                errors.push(e);
+                Config::default()
+            }
+        };
+        // the flags go on a copy so one bad flag doesn't leave the ones before it applied
+        let mut flagged = config.clone();
+        match flagged.apply_args(args) {
+            Ok(()) => config = flagged,
+            Err(e) => errors.push(e),
+        }
+        if config.scale < 1 {
+            let name = if flag_value(args, "--scale").is_some() { ARGS_NAME } else { path };
+            errors.push(SpellcoderError::invalid(name, format!("scale has to be at least 1, not {}", config.scale)));
+            config.scale = Config::default().scale;
+        }
+        config.path = path.to_string();
+        config
+    }
+
+    // a missing file is fine and gives the defaults
+    fn from_file(path: &str) -> Result<Config, SpellcoderError> {
+        if !Path::new(path).exists() {
+            return Ok(Config::default());
+        }
+        let text = fs::read_to_string(path).map_err(|e| SpellcoderError::io(path, e))?;
+        toml::from_str(&text).map_err(|e| SpellcoderError::invalid(path, e.to_string()))
+    }
+
+    fn apply_args(&mut self, args: &[String]) -> Result<(), SpellcoderError> {
+        let mut args = args.iter();
+        while let Some(flag) = args.next() {
+            let mut value = || args.next().ok_or_else(|| SpellcoderError::invalid(ARGS_NAME, format!("{} needs a value", flag)));
+            match flag.as_str() {
+                "--width" => self.window_width = number(flag, value()?)?,
+                "--height" => self.window_height = number(flag, value()?)?,
+                "--vsync" => self.vsync = true,
+                "--no-vsync" => self.vsync = false,
+                "--fullscreen" => self.fullscreen = true,
+                "--windowed" => self.fullscreen = false,
+                "--seed" => self.seed = number(flag, value()?)?,
+                "--scale" => self.scale = number(flag, value()?)?,
+                "--fixed-step" => self.fixed_step = number(flag, value()?)?,
+                "--spells" => self.spell_dir = value()?.clone(),
+                "--saves" => self.save_dir = value()?.clone(),
+                "--session-log" => self.session_log = true,
+                "--daily" => self.daily = true,
+                // already read in load
+                "--config" => {
+                    value()?;
+                }
+                _ => return Err(SpellcoderError::invalid(ARGS_NAME, format!("unknown flag {}", flag))),
+            }
+        }
+        Ok(())
+    }
+
+    fn save(&self, path: &str) -> Result<(), SpellcoderError> {
+        let text = toml::to_string_pretty(self).map_err(|e| SpellcoderError::invalid(path, e.to_string()))?;
+        fs::write(path, text).map_err(|e| SpellcoderError::save(path, e))
+    }
+
+    // name is a file name like controls.json, the result is where it goes in the save directory
+    pub fn save_path(&self, name: &str) -> String {
+        Path::new(&self.save_dir).join(name).to_string_lossy().to_string()
+    }
+}
+
+fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter().position(|a| a == flag).and_then(|i| args.get(i + 1)).map(|s| s.as_str())
+}
+
+fn number<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, SpellcoderError> {
+    value
+        .parse()
+        .map_err(|_| SpellcoderError::invalid(ARGS_NAME, format!("{} expects a number, not {}", flag, value)))
+}
+
+fn lock() -> &'static RwLock<Config> {
+    static CONFIG: OnceLock<RwLock<Config>> = OnceLock::new();
+    CONFIG.get_or_init(|| RwLock::new(Config::default()))
+}
+
+// call once at startup, before anything reads the config
+pub fn init(config: Config) {
+    *lock().write().unwrap_or_else(|e| e.into_inner()) = config;
+}
+
+// the config as it is right now, or the defaults if init hasn't been called.
+// don't hold on to it across a call to change
+pub fn get() -> RwLockReadGuard<'static, Config> {
+    lock().read().unwrap_or_else(|e| e.into_inner())
+}
+
+// applies the change without saving it, for values that change every frame while being dragged
+pub fn set(apply: impl Fn(&mut Config)) {
+    apply(&mut lock().write().unwrap_or_else(|e| e.into_inner()));
+}
+
+// applies the change right away and writes it to the config file. the file is read fresh and
+// only gets this change, so values that came from command line flags don't end up in it
+pub fn change(apply: impl Fn(&mut Config)) -> Result<(), SpellcoderError> {
+    let path = {
+        let mut config = lock().write().unwrap_or_else(|e| e.into_inner());
+        apply(&mut config);
+        config.path.clone()
+    };
+    let mut saved = Config::from_file(&path)?;
+    apply(&mut saved);
+    saved.save(&path)
+}
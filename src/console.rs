@@ -0,0 +1,330 @@
+use raylib::prelude::*;
+
+use crate::audio::{AudioManager, Bus, SoundEvent, AUDIO_SETTINGS_PATH};
+use crate::config;
+use crate::debug::DebugOverlay;
+use crate::logging;
+use crate::profiler::{self, TRACE_DIR};
+use crate::spell::{CastVariant, Spellbook, MIN_CAST_RATE};
+use crate::{dryrun, ecs, Player, World};
+
+const MAX_OUTPUT: usize = 100;
+const VISIBLE_LINES: usize = 12;
+const LINE_HEIGHT: i32 = 14;
+
+// name, usage, used for help and tab completion
+const COMMANDS: &[(&str, &str)] = &[
+    ("help", "help - list commands"),
+    ("clear", "clear - clear the console"),
+    ("tp", "tp <x> <y> - teleport to world pixel x, y"),
+    ("give_spell", "give_spell <name> - learn a spell from the spell directory"),
+    ("set", "set <hp|mp|sp|max_hp|max_mp|max_sp|cast_rate> <value> - set a player stat"),
+    ("volume", "volume <master|sfx|music|ui> <0-1> - set a sound bus volume"),
+    ("seed", "seed - print the world seed"),
+    ("hash", "hash - print a digest of the chunks and entities, the same state gives the same one"),
+    ("regen_chunk", "regen_chunk - regenerate the chunk you are standing in"),
+    ("dummy", "dummy - put a training dummy next to you"),
+    ("entities", "entities - count the entities in the world"),
+    ("log", "log <level|module=level>,... - change what gets logged, e.g. log debug,spellcoder::audio=trace"),
+    ("debug", "debug <chunks|collision|changes> - toggle a debug view"),
+    ("trace", "trace - start recording a chrome trace of every frame, again to stop and save it"),
+    ("dryrun", "dryrun <spell> [shift|ctrl] - cast a spell where you aim on a copy of the world and report what it would do"),
+];
+
+pub enum Command {
+    Help,
+    Clear,
+    Tp(f32, f32),
+    GiveSpell(String),
+    Set(String, f32),
+    Volume(String, f32),
+    Seed,
+    Hash,
+    RegenChunk,
+    Dummy,
+    Entities,
+    Log(String),
+    Debug(String),
+    DryRun(String, CastVariant),
+    Trace,
+}
+
+pub struct Console {
+    pub open: bool,
+    input: String,
+    output: Vec<String>,
+    history: Vec<String>,
+    history_pos: Option<usize>,
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Command, String> {
+        let args = line.split_whitespace().collect::<Vec<_>>();
+        let number = |i: usize| -> Result<f32, String> {
+            let arg = args.get(i).ok_or("missing argument")?;
+            arg.parse::<f32>().map_err(|_| format!("{} is not a number", arg))
+        };
+        match args.first().copied() {
+            Some("help") => Ok(Command::Help),
+            Some("clear") => Ok(Command::Clear),
+            Some("tp") => Ok(Command::Tp(number(1)?, number(2)?)),
+            Some("give_spell") if args.len() > 1 => Ok(Command::GiveSpell(args[1..].join(" "))),
+            Some("set") if args.len() > 1 => Ok(Command::Set(args[1].to_string(), number(2)?)),
+            Some("volume") if args.len() > 1 => Ok(Command::Volume(args[1].to_string(), number(2)?)),
+            Some("seed") => Ok(Command::Seed),
+            Some("hash") => Ok(Command::Hash),
+            Some("regen_chunk") => Ok(Command::RegenChunk),
+            Some("dummy") => Ok(Command::Dummy),
+            Some("entities") => Ok(Command::Entities),
+            Some("log") if args.len() > 1 => Ok(Command::Log(args[1..].join(""))),
+            Some("debug") if args.len() > 1 => Ok(Command::Debug(args[1].to_string())),
+            Some("trace") => Ok(Command::Trace),
+            Some("dryrun") if args.len() > 1 => {
+                // a variant at the end, as long as there's a spell name before it
+                match args.last().and_then(|v| CastVariant::parse(v)).filter(|_| args.len() > 2) {
+                    Some(variant) => Ok(Command::DryRun(args[1..args.len() - 1].join(" "), variant)),
+                    None => Ok(Command::DryRun(args[1..].join(" "), CastVariant::Normal)),
+                }
+            }
+            Some(name) => match COMMANDS.iter().find(|(n, _)| *n == name) {
+                Some((_, usage)) => Err(format!("usage: {}", usage)),
+                None => Err(format!("unknown command {}, try help", name)),
+            },
+            None => Err("empty command".to_string()),
+        }
+    }
+}
+
+impl Console {
+    pub fn new() -> Self {
+        Console {
+            open: false,
+            input: String::new(),
+            output: Vec::new(),
+            history: Vec::new(),
+            history_pos: None,
+        }
+    }
+
+    pub fn print(&mut self, line: &str) {
+        self.output.push(line.to_string());
+        if self.output.len() > MAX_OUTPUT {
+            self.output.remove(0);
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.history_pos = None;
+    }
+
+    // handles typing, returns a command when enter is pressed on a valid line
+    pub fn update(&mut self, rl: &mut RaylibHandle) -> Option<Command> {
+        while let Some(c) = rl.get_char_pressed() {
+            // the toggle key itself shouldn't end up in the input
+            if c != '`' && !c.is_control() {
+                self.input.push(c);
+            }
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_BACKSPACE) {
+            self.input.pop();
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_TAB) {
+            self.complete();
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_UP) {
+            self.browse_history(-1);
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_DOWN) {
+            self.browse_history(1);
+        }
+        if !rl.is_key_pressed(KeyboardKey::KEY_ENTER) {
+            return None;
+        }
+
+        let line = std::mem::take(&mut self.input);
+        let line = line.trim();
+        if line.is_empty() {
+            return None;
+        }
+        self.print(&format!("> {}", line));
+        if self.history.last().map(|h| h.as_str()) != Some(line) {
+            self.history.push(line.to_string());
+        }
+        self.history_pos = None;
+        match Command::parse(line) {
+            Ok(command) => Some(command),
+            Err(e) => {
+                self.print(&e);
+                None
+            }
+        }
+    }
+
+    fn browse_history(&mut self, step: i32) {
+        if self.history.is_empty() {
+            return;
+        }
+        let pos = match self.history_pos {
+            Some(pos) => pos as i32 + step,
+            None if step < 0 => self.history.len() as i32 - 1,
+            None => return,
+        };
+        if pos >= self.history.len() as i32 {
+            self.history_pos = None;
+            self.input.clear();
+            return;
+        }
+        let pos = pos.max(0) as usize;
+        self.history_pos = Some(pos);
+        self.input = self.history[pos].clone();
+    }
+
+    // completes the command name, or lists the options when it's ambiguous
+    fn complete(&mut self) {
+        if self.input.contains(' ') {
+            return;
+        }
+        let matches = COMMANDS
+            .iter()
+            .map(|(name, _)| *name)
+            .filter(|name| name.starts_with(self.input.as_str()))
+            .collect::<Vec<_>>();
+        match matches.as_slice() {
+            [] => {}
+            [name] => self.input = format!("{} ", name),
+            names => {
+                let common = names[1..].iter().fold(names[0].to_string(), |prefix, name| {
+                    prefix.chars().zip(name.chars()).take_while(|(a, b)| a == b).map(|(a, _)| a).collect()
+                });
+                self.input = common;
+                self.print(&names.join("  "));
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn run(
+        &mut self,
+        command: Command,
+        player: &mut Player,
+        world: &mut World,
+        spellbook: &mut Spellbook,
+        audio: &mut AudioManager,
+        debug: &mut DebugOverlay,
+        aim: Vector2,
+    ) {
+        match command {
+            Command::Help => {
+                for (_, usage) in COMMANDS {
+                    self.print(usage);
+                }
+            }
+            Command::Clear => self.output.clear(),
+            Command::Tp(x, y) => {
+                player.teleport(Vector2::new(x, y));
+                self.print(&format!("teleported to {}, {}", x, y));
+            }
+            Command::GiveSpell(name) => match spellbook.learn(&name) {
+                Ok(name) => {
+                    audio.trigger(SoundEvent::Pickup);
+                    self.print(&format!("learned {}", name));
+                }
+                Err(e) => self.print(&e),
+            },
+            Command::Set(stat, value) => {
+                let target = match stat.as_str() {
+                    "hp" => &mut player.hp,
+                    "mp" => &mut player.mp,
+                    "sp" => &mut player.sp,
+                    "max_hp" => &mut player.max_hp,
+                    "max_mp" => &mut player.max_mp,
+                    "max_sp" => &mut player.max_sp,
+                    "cast_rate" => &mut spellbook.cast_rate,
+                    _ => {
+                        self.print(&format!("unknown stat {}", stat));
+                        return;
+                    }
+                };
+                let value = if stat == "cast_rate" { value.max(MIN_CAST_RATE) } else { value };
+                *target = value;
+                self.print(&format!("{} = {}", stat, value));
+            }
+            Command::Volume(bus_name, value) => {
+                let bus = match bus_name.as_str() {
+                    "master" => Bus::Master,
+                    "sfx" => Bus::Sfx,
+                    "music" => Bus::Music,
+                    "ui" => Bus::Ui,
+                    _ => {
+                        self.print(&format!("unknown bus {}", bus_name));
+                        return;
+                    }
+                };
+                audio.set_volume(bus, value);
+                if let Err(e) = audio.save_settings(&config::get().save_path(AUDIO_SETTINGS_PATH)) {
+                    self.print(&e.to_string());
+                }
+                self.print(&format!("{} volume = {}", bus_name, audio.volume(bus)));
+            }
+            Command::Seed => self.print(&format!("seed: {}", world.seed)),
+            Command::Hash => self.print(&format!("state hash: {:016x}", world.state_hash())),
+            Command::RegenChunk => {
+                let center = player.center();
+                let chunk_x = (center.x.floor() as i64).div_euclid(16);
+                let chunk_y = (center.y.floor() as i64).div_euclid(16);
+                match world.regenerate_chunk(chunk_x, chunk_y) {
+                    Ok(()) => self.print(&format!("regenerated chunk {}, {}", chunk_x, chunk_y)),
+                    Err(e) => self.print(&e.to_string()),
+                }
+            }
+            Command::Dummy => {
+                ecs::spawn_dummy(&mut world.ecs, player.center() + Vector2::new(12.0, -4.0));
+                self.print("spawned a training dummy");
+            }
+            Command::Entities => self.print(&format!("{} entities", world.ecs.len())),
+            Command::Log(filters) => match logging::set_filters(&filters) {
+                Ok(()) => self.print(&format!("logging {}", filters)),
+                Err(e) => self.print(&e),
+            },
+            Command::DryRun(name, variant) => {
+                let Some(spell) = spellbook.find(&name) else {
+                    self.print(&format!("no spell called {}", name));
+                    return;
+                };
+                if variant != CastVariant::Normal && !spell.has_variant(variant) {
+                    self.print(&format!("{} has no {} variant, casting it normally", spell.name, variant.name()));
+                }
+                for line in dryrun::dry_run(spell, variant, 1.0, world, player, aim).lines() {
+                    self.print(&line);
+                }
+            }
+            Command::Trace if profiler::is_tracing() => match profiler::finish_trace(&config::get().save_path(TRACE_DIR)) {
+                Ok(path) => self.print(&format!("trace saved to {}, open it in chrome://tracing or ui.perfetto.dev", path)),
+                Err(e) => self.print(&e.to_string()),
+            },
+            Command::Trace => {
+                profiler::start_trace();
+                self.print("tracing, trace again to stop and save");
+            }
+            Command::Debug(view) => match debug.toggle_view(&view) {
+                Ok(on) => self.print(&format!("debug {} {}", view, if on { "on" } else { "off" })),
+                Err(e) => self.print(&e),
+            },
+        }
+    }
+
+    pub fn draw(&self, d: &mut RaylibDrawHandle) {
+        let width = d.get_screen_width();
+        let height = (VISIBLE_LINES as i32 + 1) * LINE_HEIGHT + 10;
+        d.draw_rectangle(0, 0, width, height, Color::new(0, 0, 0, 210));
+        d.draw_line(0, height, width, height, Color::new(90, 90, 140, 255));
+        let start = self.output.len().saturating_sub(VISIBLE_LINES);
+        for (i, line) in self.output[start..].iter().enumerate() {
+            d.draw_text(line, 6, 4 + i as i32 * LINE_HEIGHT, 10, Color::LIGHTGRAY);
+        }
+        // blinking cursor
+        let cursor = if (d.get_time() * 2.0) as i64 % 2 == 0 { "_" } else { "" };
+        d.draw_text(&format!("> {}{}", self.input, cursor), 6, height - LINE_HEIGHT - 2, 10, Color::WHITE);
+    }
+}
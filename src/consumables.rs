@@ -0,0 +1,79 @@
+use raylib::prelude::*;
+
+use crate::status::StatusKind;
+
+// what using an item does
+#[derive(Clone, Copy, Debug)]
+pub enum Use {
+    // a status effect on the player, magnitude like StatusEffect's
+    Status { kind: StatusKind, seconds: f32, magnitude: f32 },
+    // the spell goes off at the aim without costing any mp, known or not
+    Cast(&'static str),
+    // thrown down towards the aim, it lights up radius world pixels around it for seconds
+    Light { color: Color, radius: f32, seconds: f32 },
+}
+
+// an item used up from the hotbar, see Game::use_item
+pub struct Consumable {
+    pub item: &'static str,
+    pub effect: Use,
+    // what the hand holds up while it's used, see PlayerAnimation::use_item
+    pub color: Color,
+}
+
+pub const CONSUMABLES: &[Consumable] = &[
+    Consumable {
+        item: "health potion",
+        effect: Use::Status { kind: StatusKind::Regen, seconds: 8.0, magnitude: 5.0 },
+        color: Color::new(220, 50, 60, 255),
+    },
+    Consumable {
+        item: "mana potion",
+        effect: Use::Status { kind: StatusKind::ManaSurge, seconds: 8.0, magnitude: 6.0 },
+        color: Color::new(60, 110, 240, 255),
+    },
+    Consumable {
+        item: "stamina potion",
+        effect: Use::Status { kind: StatusKind::Vigor, seconds: 6.0, magnitude: 10.0 },
+        color: Color::new(230, 150, 40, 255),
+    },
+    Consumable {
+        item: "swiftness potion",
+        effect: Use::Status { kind: StatusKind::Haste, seconds: 12.0, magnitude: 0.4 },
+        color: Color::new(230, 200, 40, 255),
+    },
+    Consumable {
+        item: "fireball scroll",
+        effect: Use::Cast("Fireball"),
+        color: Color::new(240, 225, 180, 255),
+    },
+    Consumable {
+        item: "frost scroll",
+        effect: Use::Cast("Frost"),
+        color: Color::new(240, 225, 180, 255),
+    },
+    Consumable {
+        item: "blast scroll",
+        effect: Use::Cast("Blast"),
+        color: Color::new(240, 225, 180, 255),
+    },
+    Consumable {
+        item: "torch",
+        effect: Use::Light { color: Color::new(255, 170, 70, 255), radius: 28.0, seconds: 120.0 },
+        color: Color::new(255, 170, 70, 255),
+    },
+];
+
+pub fn consumable(item: &str) -> Option<&'static Consumable> {
+    CONSUMABLES.iter().find(|c| c.item == item)
+}
+
+impl Use {
+    pub fn describe(&self) -> String {
+        match self {
+            Use::Status { kind, seconds, .. } => format!("{} for {:.0} seconds", kind.description(), seconds),
+            Use::Cast(spell) => format!("Casts {} without using MP", spell),
+            Use::Light { seconds, .. } => format!("Keeps the dark away for {:.0} seconds", seconds),
+        }
+    }
+}
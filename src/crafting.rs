@@ -0,0 +1,138 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::error::SpellcoderError;
+use crate::inventory::Inventory;
+
+// next to materials.json, missing means nothing can be crafted
+pub const RECIPES_PATH: &str = "recipes.json";
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub enum Category {
+    // something to build with, usually placeable, see MaterialRegistry::placed_by
+    Material,
+    Rune,
+    // worn rather than used, see equipment::GEAR
+    Gear,
+    // used up from the hotbar, see consumables::CONSUMABLES
+    Consumable,
+}
+
+// in the order the crafting screen's tabs go
+pub const CATEGORIES: [Category; 4] = [Category::Material, Category::Rune, Category::Gear, Category::Consumable];
+
+impl Category {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Category::Material => "Materials",
+            Category::Rune => "Runes",
+            Category::Gear => "Gear",
+            Category::Consumable => "Potions",
+        }
+    }
+}
+
+// an item by its inventory name and how many of it
+#[derive(Clone, Debug, Deserialize)]
+pub struct Ingredient {
+    pub item: String,
+    pub count: u32,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Recipe {
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "kind")]
+    pub category: Category,
+    pub inputs: Vec<Ingredient>,
+    pub output: Ingredient,
+}
+
+impl Recipe {
+    // "2 stone, 1 Essence"
+    pub fn describe_inputs(&self) -> String {
+        let parts: Vec<_> = self.inputs.iter().map(|i| format!("{} {}", i.count, i.item)).collect();
+        parts.join(", ")
+    }
+}
+
+// everything recipes.json lets the player make, in file order
+pub struct Recipes {
+    pub recipes: Vec<Recipe>,
+}
+
+impl Recipes {
+    pub fn empty() -> Self {
+        Recipes { recipes: Vec::new() }
+    }
+
+    // a missing file means no recipes. recipes that repeat an id or take or make nothing are
+    // left out and added to errors
+    pub fn load(path: &str, errors: &mut Vec<SpellcoderError>) -> Self {
+        let mut loaded = Recipes::empty();
+        if !Path::new(path).exists() {
+            return loaded;
+        }
+        let recipes: Vec<Recipe> = match fs::read_to_string(path)
+            .map_err(|e| SpellcoderError::io(path, e))
+            .and_then(|text| serde_json::from_str(&text).map_err(|e| SpellcoderError::parse(path, e)))
+        {
+            Ok(recipes) => recipes,
+            Err(e) => {
+                errors.push(e);
+                return loaded;
+            }
+        };
+        let mut ids = BTreeSet::new();
+        for recipe in recipes {
+            if !ids.insert(recipe.id.clone()) {
+                errors.push(SpellcoderError::invalid(path, format!("{} is in there twice", recipe.id)));
+                continue;
+            }
+            if recipe.inputs.is_empty() || recipe.inputs.iter().any(|i| i.count == 0) || recipe.output.count == 0 {
+                errors.push(SpellcoderError::invalid(path, format!("{} has to take and make at least one of each item", recipe.id)));
+                continue;
+            }
+            loaded.recipes.push(recipe);
+        }
+        loaded
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Recipe> {
+        self.recipes.iter().find(|r| r.id == id)
+    }
+
+    // why the recipe can't be made from what's in the inventory, if it can't
+    pub fn check(&self, recipe: &Recipe, inventory: &Inventory) -> Result<(), String> {
+        let missing: Vec<_> = recipe
+            .inputs
+            .iter()
+            .filter(|i| inventory.count(&i.item) < i.count)
+            .map(|i| format!("{} {}", i.count - inventory.count(&i.item), i.item))
+            .collect();
+        if !missing.is_empty() {
+            return Err(format!("{} needs {} more", recipe.name, missing.join(", ")));
+        }
+        Ok(())
+    }
+
+    // takes the inputs and adds the output, or leaves the inventory as it was if either can't
+    // happen. worked out on a copy first, the inputs freeing a slot can make room for the output
+    pub fn craft(&self, id: &str, inventory: &mut Inventory) -> Result<String, String> {
+        let recipe = self.get(id).ok_or_else(|| format!("no recipe called {}", id))?;
+        self.check(recipe, inventory)?;
+        let mut after = inventory.clone();
+        for input in &recipe.inputs {
+            after.remove(&input.item, input.count);
+        }
+        if after.add(&recipe.output.item, recipe.output.count) > 0 {
+            return Err(format!("No room for {}", recipe.output.item));
+        }
+        *inventory = after;
+        Ok(format!("Crafted {} {}", recipe.output.count, recipe.output.item))
+    }
+}
@@ -0,0 +1,154 @@
+use std::backtrace::Backtrace;
+use std::fs;
+use std::panic;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use raylib::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::error::SpellcoderError;
+use crate::material::MaterialId;
+use crate::save::WorldSave;
+use crate::world::{Chunk, Pixel};
+use crate::{Player, World};
+
+// in the save directory, holds the crash report and whatever could be saved
+pub const RECOVERY_DIR: &str = "recovery";
+const RECOVERY_FILE: &str = "recovery.json";
+const CRASH_REPORT_FILE: &str = "crash_report.txt";
+
+// writes a crash report into dir when anything panics, on top of the usual message on stderr.
+// the emergency save itself happens in main once the panic has unwound back to it
+pub fn install(dir: String) {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        let report = format!(
+            "spellcoder {} crashed at {}\n\n{}\n\n{}\n",
+            env!("CARGO_PKG_VERSION"),
+            seconds,
+            info,
+            Backtrace::force_capture()
+        );
+        let path = Path::new(&dir).join(CRASH_REPORT_FILE);
+        // no logging here, the panic may have happened while holding the logger
+        match fs::create_dir_all(&dir).and_then(|_| fs::write(&path, report)) {
+            Ok(()) => eprintln!("crash report written to {}", path.display()),
+            Err(e) => eprintln!("could not write a crash report to {}: {}", path.display(), e),
+        }
+    }));
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct SavedPixel {
+    x: u8,
+    y: u8,
+    material: MaterialId,
+    color: [u8; 4],
+}
+
+// a chunk that no longer matches what its seed generates
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct SavedChunk {
+    x: i64,
+    y: i64,
+    pixels: Vec<SavedPixel>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct SavedPlayer {
+    position: (f32, f32),
+    hp: f32,
+    mp: f32,
+    sp: f32,
+}
+
+// what was going on when the game crashed, offered back on the next start, see the title screen
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Recovery {
+    pub save: WorldSave,
+    player: SavedPlayer,
+    chunks: Vec<SavedChunk>,
+}
+
+impl Recovery {
+    // only chunks that changed since they were generated, the rest come back from the seed
+    pub fn capture(save: WorldSave, world: &World, player: &Player) -> Self {
+        let chunks = world
+            .chunks
+            .iter()
+            .filter(|chunk| {
+                let generated = Chunk::generate(chunk.x.div_euclid(16), chunk.y.div_euclid(16), &world.noise, world.seed, world.rules.prestige, world.season, &world.materials);
+                !same_pixels(chunk, &generated)
+            })
+            .map(|chunk| SavedChunk {
+                x: chunk.x,
+                y: chunk.y,
+                pixels: chunk
+                    .pixels
+                    .iter()
+                    .flatten()
+                    .map(|p| SavedPixel { x: p.x, y: p.y, material: p.material, color: [p.color.r, p.color.g, p.color.b, p.color.a] })
+                    .collect(),
+            })
+            .collect();
+        let player = SavedPlayer {
+            position: (player.position.x, player.position.y),
+            hp: player.hp,
+            mp: player.mp,
+            sp: player.sp,
+        };
+        Recovery { save, player, chunks }
+    }
+
+    // Ok(None) when the last session ended normally
+    pub fn load(dir: &str) -> Result<Option<Recovery>, SpellcoderError> {
+        let path = Path::new(dir).join(RECOVERY_FILE).to_string_lossy().to_string();
+        if !Path::new(&path).exists() {
+            return Ok(None);
+        }
+        let text = fs::read_to_string(&path).map_err(|e| SpellcoderError::io(path.as_str(), e))?;
+        serde_json::from_str(&text).map(Some).map_err(|e| SpellcoderError::parse(path, e))
+    }
+
+    pub fn save(&self, dir: &str) -> Result<(), SpellcoderError> {
+        let path = Path::new(dir).join(RECOVERY_FILE).to_string_lossy().to_string();
+        fs::create_dir_all(dir).map_err(|e| SpellcoderError::save(dir, e))?;
+        let text = serde_json::to_string(self).map_err(|e| SpellcoderError::save(path.as_str(), e.into()))?;
+        fs::write(&path, text).map_err(|e| SpellcoderError::save(path, e))
+    }
+
+    // the crash report goes too, it's only there until the player has decided
+    pub fn discard(dir: &str) -> Result<(), SpellcoderError> {
+        match fs::remove_dir_all(dir) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(SpellcoderError::save(dir, e)),
+            _ => Ok(()),
+        }
+    }
+
+    // puts the changed chunks and the player back. the world has to be the same seed
+    pub fn apply(&self, world: &mut World, player: &mut Player) {
+        for saved in &self.chunks {
+            let mut chunk = Chunk::new(saved.x, saved.y);
+            chunk.temperature = crate::temperature::base_temperature(world.seed, saved.x, saved.y);
+            for p in &saved.pixels {
+                let [r, g, b, a] = p.color;
+                chunk.set_pixel(Pixel { x: p.x, y: p.y, material: p.material, color: Color::new(r, g, b, a).into(), damage: 0.0 });
+            }
+            match world.chunks.iter().position(|c| c.x == saved.x && c.y == saved.y) {
+                Some(i) => world.chunks[i] = chunk,
+                None => world.chunks.push(chunk),
+            }
+        }
+        player.teleport(Vector2::new(self.player.position.0, self.player.position.1));
+        player.hp = self.player.hp;
+        player.mp = self.player.mp;
+        player.sp = self.player.sp;
+    }
+}
+
+fn same_pixels(a: &Chunk, b: &Chunk) -> bool {
+    a.pixels.iter().zip(&b.pixels).all(|(a, b)| a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.y == b.y && a.material == b.material))
+}
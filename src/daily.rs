@@ -0,0 +1,142 @@
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use raylib::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::digest::Digest;
+use crate::error::SpellcoderError;
+use crate::events::{GameEvent, Subscriber};
+
+// in the save directory, every daily run that ended
+pub const LEADERBOARD_PATH: &str = "daily_leaderboard.json";
+
+// what each part of a run is worth
+const DEPTH_POINTS: f32 = 2.0; // per world pixel below the surface
+const KILL_POINTS: u32 = 50;
+const TIME_POINTS: f32 = 1.0; // per second
+
+// today's date as yyyy-mm-dd, in utc so everyone's day starts at the same moment
+pub fn today() -> String {
+    let days = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() / 86_400) as i64;
+    // days to a civil date, after Howard Hinnant's days_from_civil written backwards
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+// everyone playing on the same date gets the same world
+pub fn seed(date: &str) -> u64 {
+    Digest::default().bytes(date.as_bytes()).finish()
+}
+
+// one daily challenge from the start to the first death. nothing about it goes into the world
+// save, only the score ends up on the leaderboard
+pub struct DailyRun {
+    pub date: String,
+    // deepest the player got, world pixels below the surface
+    pub depth: f32,
+    pub kills: u32,
+    // seconds played
+    pub time: f32,
+    // the run ended and was scored, playing on doesn't count any more
+    pub finished: bool,
+}
+
+impl DailyRun {
+    pub fn new(date: String) -> Self {
+        DailyRun { date, depth: 0.0, kills: 0, time: 0.0, finished: false }
+    }
+
+    // every frame the player is in control
+    pub fn update(&mut self, position: Vector2, delta: f32) {
+        if self.finished {
+            return;
+        }
+        self.depth = self.depth.max(position.y);
+        self.time += delta;
+    }
+
+    pub fn score(&self) -> u32 {
+        (self.depth * DEPTH_POINTS) as u32 + self.kills * KILL_POINTS + (self.time * TIME_POINTS) as u32
+    }
+
+    // stops counting and makes the leaderboard entry, None if that already happened
+    pub fn finish(&mut self) -> Option<Entry> {
+        if self.finished {
+            return None;
+        }
+        self.finished = true;
+        Some(Entry {
+            date: self.date.clone(),
+            score: self.score(),
+            depth: self.depth.floor() as u32,
+            kills: self.kills,
+            time: self.time.floor() as u32,
+        })
+    }
+}
+
+impl Subscriber for DailyRun {
+    fn on_event(&mut self, event: &GameEvent) {
+        if let GameEvent::EntityDied { entity: Some(_), .. } = event {
+            if !self.finished {
+                self.kills += 1;
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Entry {
+    pub date: String,
+    pub score: u32,
+    pub depth: u32,
+    pub kills: u32,
+    // seconds
+    pub time: u32,
+}
+
+// every run on this machine, best first within each day
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Leaderboard {
+    pub entries: Vec<Entry>,
+}
+
+impl Leaderboard {
+    // a missing file is an empty leaderboard
+    pub fn load(path: &str) -> Result<Leaderboard, SpellcoderError> {
+        if !Path::new(path).exists() {
+            return Ok(Leaderboard::default());
+        }
+        let text = fs::read_to_string(path).map_err(|e| SpellcoderError::io(path, e))?;
+        serde_json::from_str(&text).map_err(|e| SpellcoderError::parse(path, e))
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), SpellcoderError> {
+        let text = serde_json::to_string_pretty(self).map_err(|e| SpellcoderError::save(path, e.into()))?;
+        fs::write(path, text).map_err(|e| SpellcoderError::save(path, e))
+    }
+
+    // adds the entry and returns where it placed among the runs of its day, 1 for the best
+    pub fn submit(&mut self, entry: Entry) -> usize {
+        let rank = 1 + self.entries.iter().filter(|e| e.date == entry.date && e.score > entry.score).count();
+        self.entries.push(entry);
+        self.entries.sort_by(|a, b| b.date.cmp(&a.date).then(b.score.cmp(&a.score)));
+        rank
+    }
+
+    // the runs of one day, best first
+    pub fn day(&self, date: &str) -> Vec<&Entry> {
+        self.entries.iter().filter(|e| e.date == date).collect()
+    }
+}
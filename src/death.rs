@@ -0,0 +1,131 @@
+use raylib::prelude::*;
+
+use crate::audio::{AudioManager, SoundEvent};
+use crate::events::{EventBus, GameEvent};
+use crate::{scale, Player, TextWidth};
+
+// seconds spent watching the body before the screen starts fading
+const DEATH_PAUSE: f32 = 1.0;
+const FADE_OUT: f32 = 1.5;
+const FADE_IN: f32 = 1.0;
+// what you come back with, as a fraction of max
+const RESPAWN_HP: f32 = 0.5;
+const RESPAWN_MP: f32 = 0.25;
+// world pixels, how close you need to get to pick your soul back up
+const SOUL_PICKUP_RANGE: f32 = 6.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum LifeState {
+    Alive,
+    Dying { elapsed: f32 },
+    Respawning { elapsed: f32 },
+    // hardcore, there is no coming back
+    Gone,
+}
+
+// left where you died, holding the mana you had. dying again before getting it back loses it
+pub struct Soul {
+    pub position: Vector2,
+    pub mp: f32,
+}
+
+pub struct DeathSequence {
+    state: LifeState,
+    pub soul: Option<Soul>,
+}
+
+impl DeathSequence {
+    pub fn new() -> Self {
+        DeathSequence {
+            state: LifeState::Alive,
+            soul: None,
+        }
+    }
+
+    // false while dead or respawning, nothing should move the player then
+    pub fn is_alive(&self) -> bool {
+        self.state == LifeState::Alive
+    }
+
+    pub fn player_visible(&self) -> bool {
+        !matches!(self.state, LifeState::Dying { .. } | LifeState::Gone)
+    }
+
+    // dead for good, see WorldRules::hardcore
+    pub fn is_gone(&self) -> bool {
+        self.state == LifeState::Gone
+    }
+
+    pub fn update(&mut self, player: &mut Player, events: &mut EventBus, audio: &mut AudioManager, delta: f32) {
+        self.state = match self.state {
+            LifeState::Alive if player.hp <= 0.0 => {
+                let center = player.center();
+                events.publish(GameEvent::EntityDied { position: center, entity: None });
+                // no soul to go back for when there is no coming back
+                if !player.rules.hardcore {
+                    self.soul = Some(Soul { position: center, mp: player.mp });
+                }
+                LifeState::Dying { elapsed: 0.0 }
+            }
+            LifeState::Alive => {
+                self.try_pickup_soul(player, audio);
+                LifeState::Alive
+            }
+            LifeState::Dying { elapsed } if elapsed + delta >= DEATH_PAUSE + FADE_OUT && player.rules.hardcore => LifeState::Gone,
+            LifeState::Dying { elapsed } if elapsed + delta >= DEATH_PAUSE + FADE_OUT => {
+                // the screen is black now, nobody sees the teleport
+                player.teleport(player.spawn_point);
+                player.hp = player.max_hp * RESPAWN_HP;
+                player.mp = player.max_mp * RESPAWN_MP;
+                player.sp = player.max_sp;
+                player.status_effects.clear();
+                player.recent_hits.clear();
+                LifeState::Respawning { elapsed: 0.0 }
+            }
+            LifeState::Dying { elapsed } => LifeState::Dying { elapsed: elapsed + delta },
+            LifeState::Respawning { elapsed } if elapsed + delta >= FADE_IN => LifeState::Alive,
+            LifeState::Respawning { elapsed } => LifeState::Respawning { elapsed: elapsed + delta },
+            LifeState::Gone => LifeState::Gone,
+        };
+    }
+
+    fn try_pickup_soul(&mut self, player: &mut Player, audio: &mut AudioManager) {
+        let Some(soul) = &self.soul else {
+            return;
+        };
+        if soul.position.distance_to(player.center()) > SOUL_PICKUP_RANGE {
+            return;
+        }
+        player.mp = (player.mp + soul.mp).min(player.max_mp);
+        audio.trigger(SoundEvent::Pickup);
+        self.soul = None;
+    }
+
+    // call inside the world's 2d mode
+    pub fn draw_soul(&self, d: &mut impl RaylibDraw, time: f32) {
+        let Some(soul) = &self.soul else {
+            return;
+        };
+        let scale = scale() as f32;
+        let bob = (time * 2.0).sin() * 1.5;
+        let center = (soul.position + Vector2::new(0.0, bob)) * scale;
+        d.draw_circle_v(center, 3.0 * scale, Color::new(150, 200, 255, 60));
+        d.draw_circle_v(center, 1.5 * scale, Color::new(210, 235, 255, 200));
+    }
+
+    // the fade to black and back, call outside 2d mode
+    pub fn draw_screen(&self, d: &mut (impl RaylibDraw + TextWidth), width: i32, height: i32) {
+        let alpha = match self.state {
+            LifeState::Alive => return,
+            LifeState::Dying { elapsed } => ((elapsed - DEATH_PAUSE) / FADE_OUT).clamp(0.0, 1.0),
+            LifeState::Respawning { elapsed } => 1.0 - (elapsed / FADE_IN).clamp(0.0, 1.0),
+            LifeState::Gone => 1.0,
+        };
+        d.draw_rectangle(0, 0, width, height, Color::new(0, 0, 0, (alpha * 255.0) as u8));
+        if let LifeState::Dying { .. } | LifeState::Gone = self.state {
+            let text = "You died";
+            let text_alpha = (alpha * 2.0).min(1.0);
+            d.draw_text(text, (width - d.text_width(text, 40)) / 2, height / 2 - 20, 40, Color::new(200, 30, 30, (text_alpha * 255.0) as u8));
+        }
+    }
+}
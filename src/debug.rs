@@ -0,0 +1,363 @@
+use std::mem::size_of;
+
+use raylib::prelude::*;
+
+use crate::config;
+use crate::ecs::Entity;
+use crate::hud::HudDraw;
+use crate::particles::ParticleSystem;
+use crate::profiler::Profiler;
+use crate::world::{Chunk, Pixel};
+use crate::{scale, Player, World};
+
+// top left under the position, over the quests while it's open
+const LEFT: i32 = 10;
+const TOP: i32 = 60;
+const WIDTH: i32 = 220;
+const LINE: i32 = 12;
+const TEXT_SIZE: i32 = 10;
+const BACKGROUND: Color = Color::new(0, 0, 0, 170);
+// frame times over this many milliseconds show up in red, a frame at 60fps
+const SLOW_MS: f32 = 16.7;
+// slow sections and memory over budget
+const WARNING: Color = Color::new(255, 110, 110, 255);
+// the views drawn over the world, toggled from the console with debug <view>
+pub const VIEWS: [&str; 3] = ["chunks", "collision", "changes"];
+// chunks that were simulated this step stand out from the ones asleep
+const CHUNK_ACTIVE: Color = Color::new(255, 220, 60, 200);
+const CHUNK_ASLEEP: Color = Color::new(120, 120, 140, 140);
+const AABB: Color = Color::new(60, 255, 120, 255);
+const PROBE_CLEAR: Color = Color::new(80, 200, 255, 220);
+const PROBE_BLOCKED: Color = Color::new(255, 70, 70, 255);
+// seconds of velocity a body's probe ray shows, the step it takes is only a frame's worth
+const PROBE_SECONDS: f32 = 0.25;
+// seconds a changed pixel stays highlighted, fading out
+const CHANGE_FADE: f32 = 1.0;
+// changes highlighted at once, the oldest go first, falling sand changes a lot
+const MAX_CHANGES: usize = 4096;
+// world pixels from the cursor an entity can be picked at, on top of its sprite's size
+const PICK_REACH: f32 = 2.0;
+// the inspector panel sits on the right, halfway down
+const INSPECTOR_WIDTH: i32 = 230;
+const INSPECTOR_RIGHT: i32 = 10;
+const INSPECTED: Color = Color::new(255, 255, 255, 255);
+
+// what the inspector has picked. it's shown as it is now, not as it was when it was picked
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Inspected {
+    Pixel(i64, i64),
+    Entity(Entity),
+}
+
+// the F3 overlay: where the frame goes, what's loaded and roughly how much memory each part takes.
+// the views draw chunk borders, collision boxes and probes, and what changed lately
+#[derive(Default)]
+pub struct DebugOverlay {
+    pub open: bool,
+    pub chunks: bool,
+    pub collision: bool,
+    pub changes: bool,
+    // pixels that changed and seconds since
+    recent: Vec<(i64, i64, f32)>,
+    inspected: Option<Inspected>,
+    // the loaded chunks are over config::chunk_budget_mb, warned about once each time they go over
+    over_budget: bool,
+}
+
+// rough bytes each part of the game holds, measured only while the overlay is open
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Memory {
+    // the pixels and the chunks themselves, without their shading
+    pub chunks: usize,
+    // the shading baked into every chunk, see World::bake_shading
+    pub shading: usize,
+    // the frame and the light map, on the gpu
+    pub textures: usize,
+    pub entities: usize,
+    pub particles: usize,
+    pub explored: usize,
+}
+
+impl Memory {
+    pub fn measure(world: &World, particles: &ParticleSystem, textures: usize) -> Self {
+        Memory {
+            chunks: chunk_bytes(world) - shading_bytes(world),
+            shading: shading_bytes(world),
+            textures,
+            entities: world.ecs.bytes(),
+            particles: particles.bytes(),
+            explored: world.explored.columns.len() * size_of::<(i64, [u64; 16])>(),
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.chunks + self.shading + self.textures + self.entities + self.particles + self.explored
+    }
+}
+
+// rough bytes the loaded chunks hold, the pixels and the chunks themselves with their shading
+fn chunk_bytes(world: &World) -> usize {
+    let columns: usize = world.chunks.iter().flat_map(|c| &c.pixels).map(|column| size_of::<Vec<Pixel>>() + column.len() * size_of::<Pixel>()).sum();
+    columns + world.chunks.len() * size_of::<Chunk>()
+}
+
+fn shading_bytes(world: &World) -> usize {
+    world.chunks.len() * size_of::<[[u8; 16]; 16]>()
+}
+
+fn chunk_budget() -> usize {
+    (config::get().chunk_budget_mb.max(0.0) * 1024.0 * 1024.0) as usize
+}
+
+fn kilobytes(bytes: usize) -> String {
+    if bytes >= 1024 * 1024 {
+        format!("{:.1} MB", bytes as f32 / (1024.0 * 1024.0))
+    } else {
+        format!("{:.1} KB", bytes as f32 / 1024.0)
+    }
+}
+
+impl DebugOverlay {
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    // switches one of VIEWS on or off, returns whether it's on now
+    pub fn toggle_view(&mut self, view: &str) -> Result<bool, String> {
+        let on = match view {
+            "chunks" => &mut self.chunks,
+            "collision" => &mut self.collision,
+            "changes" => &mut self.changes,
+            _ => return Err(format!("unknown debug view {}, one of {}", view, VIEWS.join(", "))),
+        };
+        *on = !*on;
+        Ok(*on)
+    }
+
+    // picks whatever is at the cursor, an entity over the pixel behind it. nothing there puts
+    // the inspector away
+    pub fn inspect(&mut self, world: &World, at: Vector2) {
+        let entity = world
+            .ecs
+            .positions
+            .iter()
+            .map(|(entity, position)| {
+                let size = world.ecs.sprites.get(entity).map_or(1.0, |s| s.size);
+                (entity, position.distance_to(at) - size * 0.5)
+            })
+            .filter(|(_, distance)| *distance <= PICK_REACH)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(entity, _)| entity);
+        let (x, y) = (at.x.floor() as i64, at.y.floor() as i64);
+        self.inspected = match entity {
+            Some(entity) => Some(Inspected::Entity(entity)),
+            None if world.chunk_at(x, y).is_some() => Some(Inspected::Pixel(x, y)),
+            None => None,
+        };
+    }
+
+    // picks up what changed in the world since the last update, the world only keeps a list
+    // while the changes view is on
+    pub fn update(&mut self, world: &mut World, delta: f32) {
+        let over = chunk_bytes(world) > chunk_budget();
+        if over && !self.over_budget {
+            crate::log_warn!("{} loaded chunks take ~{}, over the chunk budget of {}", world.chunks.len(), kilobytes(chunk_bytes(world)), kilobytes(chunk_budget()));
+        }
+        self.over_budget = over;
+        if !self.changes {
+            world.changed = None;
+            self.recent.clear();
+            return;
+        }
+        for change in &mut self.recent {
+            change.2 += delta;
+        }
+        self.recent.retain(|c| c.2 < CHANGE_FADE);
+        let changed = world.changed.replace(Vec::new()).unwrap_or_default();
+        self.recent.extend(changed.into_iter().map(|(x, y)| (x, y, 0.0)));
+        if self.recent.len() > MAX_CHANGES {
+            self.recent.drain(..self.recent.len() - MAX_CHANGES);
+        }
+    }
+
+    // whichever views are on, in the world's 2d mode. view is in world pixels, see Game::view,
+    // floor is where the player's feet land
+    pub fn draw_views(&self, d: &mut impl RaylibDraw, world: &World, player: &Player, view: Rectangle, floor: f32) {
+        let s = scale();
+        if self.chunks {
+            let visible = world.chunks.iter().filter(|c| {
+                let (x, y) = (c.x as f32, c.y as f32);
+                x + 16.0 > view.x && x < view.x + view.width && y + 16.0 > view.y && y < view.y + view.height
+            });
+            for chunk in visible {
+                let color = if chunk.active { CHUNK_ACTIVE } else { CHUNK_ASLEEP };
+                let (x, y) = (chunk.x as i32 * s, chunk.y as i32 * s);
+                d.draw_rectangle_lines(x, y, 16 * s, 16 * s, color);
+                d.draw_text(&format!("{},{}", chunk.x.div_euclid(16), chunk.y.div_euclid(16)), x + 2, y + 2, 10, color);
+            }
+        }
+        if self.changes {
+            for &(x, y, age) in &self.recent {
+                let color = Color::new(255, 60, 255, (180.0 * (1.0 - age / CHANGE_FADE)) as u8);
+                d.draw_rectangle(x as i32 * s, y as i32 * s, s, s, color);
+            }
+        }
+        match self.inspected {
+            Some(Inspected::Pixel(x, y)) => d.draw_rectangle_lines(x as i32 * s - 1, y as i32 * s - 1, s + 2, s + 2, INSPECTED),
+            Some(Inspected::Entity(entity)) => {
+                if let Some(&position) = world.ecs.positions.get(entity) {
+                    let size = world.ecs.sprites.get(entity).map_or(1.0, |s| s.size) * s as f32;
+                    let corner = position * s as f32 - Vector2::new(size, size) * 0.5;
+                    d.draw_rectangle_lines(corner.x as i32 - 1, corner.y as i32 - 1, size as i32 + 2, size as i32 + 2, INSPECTED);
+                }
+            }
+            None => {}
+        }
+        if !self.collision {
+            return;
+        }
+        // the player stands on a flat floor, see Game::move_player, and only looks at the pixel
+        // under their feet for the footstep sound
+        let (position, size) = (player.position * s as f32, player.size * s as f32);
+        d.draw_rectangle_lines(position.x as i32, position.y as i32, size.x as i32, size.y as i32, AABB);
+        let floor = floor * s as f32;
+        d.draw_line_v(Vector2::new(view.x * s as f32, floor), Vector2::new((view.x + view.width) * s as f32, floor), AABB.fade(0.4));
+        let feet = Vector2::new(player.center().x, player.position.y + player.size.y);
+        let under = if world.is_solid(feet.x.floor() as i64, feet.y.floor() as i64) { PROBE_BLOCKED } else { PROBE_CLEAR };
+        d.draw_rectangle_lines(feet.x.floor() as i32 * s, feet.y.floor() as i32 * s, s, s, under);
+        // bodies probe the pixel they would step into, one axis at a time, see ecs::physics
+        for (entity, body) in world.ecs.bodies.iter() {
+            let Some(&position) = world.ecs.positions.get(entity) else {
+                continue;
+            };
+            let reach = body.velocity * PROBE_SECONDS;
+            let color = |probe: Vector2| if world.is_solid(probe.x.floor() as i64, probe.y.floor() as i64) { PROBE_BLOCKED } else { PROBE_CLEAR };
+            let sideways = position + Vector2::new(reach.x, 0.0);
+            let down = position + Vector2::new(0.0, reach.y);
+            d.draw_line_v(position * s as f32, sideways * s as f32, color(sideways));
+            d.draw_line_v(position * s as f32, down * s as f32, color(down));
+            let dot = if body.resting { CHUNK_ASLEEP } else { AABB };
+            d.draw_rectangle(position.x.floor() as i32 * s, position.y.floor() as i32 * s, s, s, dot.fade(0.6));
+        }
+    }
+
+    // what the inspector shows for a pixel, air too as long as its chunk is loaded
+    fn pixel_lines(world: &World, x: i64, y: i64) -> Vec<String> {
+        let (local_x, local_y) = (x.rem_euclid(16) as usize, y.rem_euclid(16) as usize);
+        let mut lines = vec![format!("pixel {}, {}", x, y)];
+        let Some(chunk) = world.chunk_at(x, y) else {
+            lines.push("not loaded".to_string());
+            return lines;
+        };
+        lines.push(format!("chunk {}, {} at {}, {}", chunk.x.div_euclid(16), chunk.y.div_euclid(16), chunk.x, chunk.y));
+        match chunk.get_pixel(local_x, local_y) {
+            Ok(pixel) => {
+                let index = chunk.pixels[local_x].iter().position(|p| p.y == pixel.y).unwrap_or_default();
+                let material = world.materials.get(pixel.material);
+                lines.push(format!("local {}, {}, column index {}", local_x, local_y, index));
+                lines.push(format!("material {} ({:?})", material.name, material.behavior));
+                let c = pixel.color;
+                lines.push(format!("color {} {} {} {}", c.r, c.g, c.b, c.a));
+                lines.push(format!("damage {:.2} of {:.2}", pixel.damage, material.hardness));
+            }
+            Err(index) => {
+                lines.push(format!("local {}, {}, would go at column index {}", local_x, local_y, index));
+                lines.push("air".to_string());
+            }
+        }
+        lines.push(format!("temperature {:.1}, {}", chunk.temperature, if chunk.active { "active" } else { "asleep" }));
+        lines
+    }
+
+    // what the inspector shows for an entity, whichever components it has
+    fn entity_lines(world: &World, entity: Entity) -> Vec<String> {
+        let ecs = &world.ecs;
+        if !ecs.is_alive(entity) {
+            return vec![format!("entity {}", entity), "gone".to_string()];
+        }
+        let name = ecs
+            .enemies
+            .get(entity)
+            .map(|e| e.name.clone())
+            .or_else(|| ecs.npcs.get(entity).map(|n| n.name.clone()))
+            .or_else(|| ecs.pickups.get(entity).map(|p| format!("{} x{}", p.name, p.count)))
+            .or_else(|| ecs.projectiles.get(entity).map(|p| p.spell.clone()))
+            .or_else(|| ecs.pulses.get(entity).map(|p| p.spell.clone()))
+            .unwrap_or_default();
+        let mut lines = vec![format!("entity {} {}", entity, name)];
+        if let Some(position) = ecs.positions.get(entity) {
+            lines.push(format!("at {:.1}, {:.1}", position.x, position.y));
+        }
+        if let Some(health) = ecs.healths.get(entity) {
+            lines.push(format!("hp {:.1} / {:.1}", health.hp, health.max_hp));
+        }
+        // there's no more to what they're thinking than this, see ecs::wander and ecs::physics
+        let state = match (ecs.npcs.get(entity), ecs.bodies.get(entity)) {
+            (Some(npc), _) => format!("wandering {}, {:.1} from home", if npc.direction < 0.0 { "left" } else { "right" }, npc.home - ecs.positions.get(entity).map_or(npc.home, |p| p.x)),
+            (None, Some(body)) if body.resting => "resting".to_string(),
+            (None, Some(body)) => format!("moving {:.1}, {:.1}{}", body.velocity.x, body.velocity.y, if body.collided { ", hit something" } else { "" }),
+            (None, None) => "still".to_string(),
+        };
+        lines.push(state);
+        if let Some(statuses) = ecs.statuses.get(entity).filter(|s| !s.is_empty()) {
+            let names = statuses.iter().map(|s| format!("{} {:.1}s", s.kind.name(), s.remaining)).collect::<Vec<_>>();
+            lines.push(names.join(", "));
+        }
+        if let Some(lifetime) = ecs.lifetimes.get(entity) {
+            lines.push(format!("despawns in {:.1}s", lifetime));
+        }
+        if let Some(light) = ecs.lights.get(entity) {
+            lines.push(format!("light radius {:.1}", light.radius));
+        }
+        lines
+    }
+
+    // the panel for whatever the inspector has picked
+    pub fn draw_inspector(&self, d: &mut RaylibDrawHandle, world: &World) {
+        let lines = match self.inspected {
+            Some(Inspected::Pixel(x, y)) => Self::pixel_lines(world, x, y),
+            Some(Inspected::Entity(entity)) => Self::entity_lines(world, entity),
+            None => return,
+        };
+        let height = lines.len() as i32 * LINE + 6;
+        let left = d.get_screen_width() - INSPECTOR_WIDTH - INSPECTOR_RIGHT;
+        let top = (d.get_screen_height() - height) / 2;
+        d.draw_rectangle(left - 4, top - 4, INSPECTOR_WIDTH, height, BACKGROUND);
+        for (i, text) in lines.iter().enumerate() {
+            let color = if i == 0 { Color::WHITE } else { Color::LIGHTGRAY };
+            d.draw_hud_text(text, left, top + i as i32 * LINE, TEXT_SIZE, color);
+        }
+    }
+
+    // background is how many tasks are waiting in the scheduler
+    pub fn draw(&self, d: &mut RaylibDrawHandle, profiler: &Profiler, world: &World, memory: &Memory, background: usize) {
+        if !self.open {
+            return;
+        }
+        let mut lines = vec![(format!("frame {:.1} ms ({} fps)", d.get_frame_time() * 1000.0, d.get_fps()), Color::WHITE)];
+        for section in profiler.sections() {
+            let color = if section.peak > SLOW_MS { WARNING } else { Color::LIGHTGRAY };
+            lines.push((format!("{:<10} {:>5.2} ms  peak {:>5.2}", section.name, section.average, section.peak), color));
+        }
+        let pixels: usize = world.chunks.iter().flat_map(|c| &c.pixels).map(|column| column.len()).sum();
+        lines.push((format!("chunks {} loaded, {} pixels", world.chunks.len(), pixels), Color::WHITE));
+        lines.push((format!("background tasks {}", background), Color::WHITE));
+        lines.push((format!("entities {}, enemies {}, lights {}", world.ecs.len(), world.ecs.enemies.iter().count(), world.ecs.lights.iter().count()), Color::WHITE));
+        lines.push((format!("memory ~{}", kilobytes(memory.total())), Color::WHITE));
+        let loaded = memory.chunks + memory.shading;
+        let budget = chunk_budget();
+        let color = if loaded > budget { WARNING } else { Color::LIGHTGRAY };
+        lines.push((format!("  chunks {} of {}", kilobytes(loaded), kilobytes(budget)), color));
+        lines.push((format!("    pixels {}, shading {}", kilobytes(memory.chunks), kilobytes(memory.shading)), Color::LIGHTGRAY));
+        lines.push((format!("  textures {}", kilobytes(memory.textures)), Color::LIGHTGRAY));
+        lines.push((format!("  entities {}, particles {}", kilobytes(memory.entities), kilobytes(memory.particles)), Color::LIGHTGRAY));
+        lines.push((format!("  explored {}", kilobytes(memory.explored)), Color::LIGHTGRAY));
+        if loaded > budget {
+            lines.push(("  over the chunk budget".to_string(), WARNING));
+        }
+        d.draw_rectangle(LEFT - 4, TOP - 4, WIDTH, lines.len() as i32 * LINE + 6, BACKGROUND);
+        for (i, (text, color)) in lines.iter().enumerate() {
+            d.draw_hud_text(text, LEFT, TOP + i as i32 * LINE, TEXT_SIZE, *color);
+        }
+    }
+}
@@ -0,0 +1,283 @@
+use rand::Rng;
+use raylib::prelude::*;
+
+use crate::ecs::{self, Entity};
+use crate::events::{GameEvent, Subscriber};
+use crate::lighting::Light;
+use crate::rng::StreamRng;
+use crate::sky::NightEvent;
+use crate::World;
+
+// turns what is happening in a fight into a single 0 to 1 intensity the music follows.
+// anything that knows about combat feeds events in, the audio only ever reads intensity()
+
+// how much each thing adds to the intensity
+const DAMAGE_TAKEN_WEIGHT: f32 = 1.0 / 50.0; // per hp lost
+const SPELL_CAST_WEIGHT: f32 = 0.03;
+const ENEMY_WEIGHT: f32 = 0.15; // per enemy nearby, held as a floor while they stay
+const DECAY: f32 = 0.1; // per second, down towards the floor
+
+#[derive(Clone, Copy, Debug)]
+pub enum CombatEvent {
+    DamageTaken(f32),
+    SpellCast,
+    // replaces the previous count, send it whenever it changes, see DifficultyDirector::update
+    EnemiesNearby(usize),
+}
+
+pub struct AudioDirector {
+    intensity: f32,
+    nearby_enemies: usize,
+}
+
+impl AudioDirector {
+    pub fn new() -> Self {
+        AudioDirector {
+            intensity: 0.0,
+            nearby_enemies: 0,
+        }
+    }
+
+    pub fn feed(&mut self, event: CombatEvent) {
+        match event {
+            CombatEvent::DamageTaken(amount) => self.intensity += amount * DAMAGE_TAKEN_WEIGHT,
+            CombatEvent::SpellCast => self.intensity += SPELL_CAST_WEIGHT,
+            CombatEvent::EnemiesNearby(count) => self.nearby_enemies = count,
+        }
+        self.intensity = self.intensity.clamp(self.floor(), 1.0);
+    }
+
+    pub fn update(&mut self, delta: f32) {
+        self.intensity = (self.intensity - DECAY * delta).max(self.floor());
+    }
+
+    pub fn intensity(&self) -> f32 {
+        self.intensity
+    }
+
+    fn floor(&self) -> f32 {
+        (self.nearby_enemies as f32 * ENEMY_WEIGHT).min(1.0)
+    }
+}
+
+impl Subscriber for AudioDirector {
+    fn on_event(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::DamageDealt { amount, entity: None, .. } => self.feed(CombatEvent::DamageTaken(*amount)),
+            GameEvent::SpellCast { .. } => self.feed(CombatEvent::SpellCast),
+            _ => {}
+        }
+    }
+}
+
+// what the player has going for them adds up to power: the level, the spells they know and how
+// long they've lasted since dying. the world's Scaling turns power into threat, and threat
+// decides how often enemies turn up, how much they can take and how hard the hazards hit
+
+// power per level after the first
+const LEVEL_POWER: f32 = 0.15;
+const SPELL_POWER: f32 = 0.05; // per spell known
+const SURVIVAL_POWER: f32 = 0.05; // per minute since the last death
+const MAX_SURVIVAL_POWER: f32 = 1.0;
+// seconds between spawns without any threat, the more threat the shorter
+const SPAWN_INTERVAL: f32 = 40.0;
+const MIN_SPAWN_INTERVAL: f32 = 8.0;
+// enemies around the player at once
+const BASE_ENEMIES: f32 = 1.0;
+const ENEMIES_PER_THREAT: f32 = 2.0;
+// world pixels from the player. spawned out of sight to either side and dropped from above
+const SPAWN_DISTANCE: f32 = 90.0;
+const SPAWN_HEIGHT: f32 = 30.0;
+const NEARBY_DISTANCE: f32 = 120.0;
+// spawned enemies the player left this far behind are taken away again
+const DESPAWN_DISTANCE: f32 = 300.0;
+const ENEMY_HP: f32 = 60.0;
+const HP_PER_THREAT: f32 = 0.5; // of ENEMY_HP
+const HAZARD_PER_THREAT: f32 = 0.25;
+// power on top for every New Game+
+const PRESTIGE_POWER: f32 = 0.5;
+// the warden comes once the threat gets this high, beating it opens New Game+
+const BOSS_THREAT: f32 = 1.5;
+const BOSS_HP: f32 = 400.0;
+const BOSS_SIZE: f32 = 8.0;
+// world pixels the warden's glow reaches
+const BOSS_GLOW: f32 = 20.0;
+// under a blood moon shades come this many times as often, and this many times as many of them
+const BLOOD_MOON_SPAWNS: f32 = 2.0;
+// seconds in deep darkness between shades coming out of it, and how many enemies around stop it
+const DARK_SPAWN_INTERVAL: f32 = 15.0;
+const DARK_SPAWN_LIMIT: usize = 3;
+// world pixels from the player, somewhere open in the dark
+const DARK_SPAWN_DISTANCE: (f32, f32) = (16.0, 40.0);
+const DARK_SPAWN_TRIES: u32 = 8;
+
+pub struct DifficultyDirector {
+    // seconds since the player last died, kept in the world save
+    pub survived: f32,
+    threat: f32,
+    spawn_timer: f32,
+    // what it spawned itself, so enemies from anywhere else are left alone
+    spawned: Vec<Entity>,
+    nearby: usize,
+    boss: Option<Entity>,
+    // the warden is beaten for good in this world, kept in the world save
+    pub boss_defeated: bool,
+    // beaten since the last take_boss_defeated
+    defeated_now: bool,
+    // until sunrise, see Sky
+    blood_moon: bool,
+    // seconds towards the next shade out of the dark
+    dark_timer: f32,
+    rng: StreamRng,
+}
+
+impl DifficultyDirector {
+    // rng is the spawns stream, see RngService
+    pub fn new(rng: StreamRng, survived: f32, boss_defeated: bool) -> Self {
+        DifficultyDirector {
+            survived,
+            threat: 0.0,
+            spawn_timer: 0.0,
+            spawned: Vec::new(),
+            nearby: 0,
+            boss: None,
+            boss_defeated,
+            defeated_now: false,
+            blood_moon: false,
+            dark_timer: 0.0,
+            rng,
+        }
+    }
+
+    // whether the warden was beaten since last time, for the message
+    pub fn take_boss_defeated(&mut self) -> bool {
+        std::mem::take(&mut self.defeated_now)
+    }
+
+    // 0 while the world's scaling is off, no upper limit
+    pub fn threat(&self) -> f32 {
+        self.threat
+    }
+
+    // multiplies the damage from the world's hazards
+    pub fn hazards(&self) -> f32 {
+        1.0 + self.threat * HAZARD_PER_THREAT
+    }
+
+    // works out the threat and spawns or drops enemies around center. returns how many enemies
+    // are nearby when that changed, for the AudioDirector
+    pub fn update(&mut self, world: &mut World, center: Vector2, level: u32, spells_known: usize, delta: f32) -> Option<usize> {
+        self.survived += delta;
+        let survival = (self.survived / 60.0 * SURVIVAL_POWER).min(MAX_SURVIVAL_POWER);
+        let power = level.saturating_sub(1) as f32 * LEVEL_POWER
+            + spells_known as f32 * SPELL_POWER
+            + survival
+            + world.rules.prestige as f32 * PRESTIGE_POWER;
+        // creative worlds are for building, nothing comes for the player there
+        self.threat = if world.rules.creative { 0.0 } else { power * world.rules.scaling.factor() };
+
+        let entities = &mut world.ecs;
+        for entity in &self.spawned {
+            if entities.positions.get(*entity).is_none_or(|p| p.distance_to(center) > DESPAWN_DISTANCE) {
+                entities.despawn(*entity);
+            }
+        }
+        // killed ones are gone too
+        self.spawned.retain(|entity| entities.is_alive(*entity));
+        let nearby = entities
+            .enemies
+            .entities()
+            .into_iter()
+            .filter(|entity| entities.positions.get(*entity).is_some_and(|p| p.distance_to(center) <= NEARBY_DISTANCE))
+            .count();
+
+        // a warden left behind was despawned with the rest and comes back
+        let boss_alive = self.boss.is_some_and(|boss| world.ecs.is_alive(boss));
+        if self.threat >= BOSS_THREAT && !self.boss_defeated && !boss_alive {
+            let position = center + Vector2::new(SPAWN_DISTANCE, -SPAWN_HEIGHT);
+            if !world.is_solid(position.x.floor() as i64, position.y.floor() as i64) {
+                let hp = BOSS_HP * (1.0 + self.threat * HP_PER_THREAT);
+                let boss = ecs::spawn_enemy(&mut world.ecs, "Warden", position, hp, Color::new(150, 30, 60, 255), Some("warden"));
+                if let Some(sprite) = world.ecs.sprites.get_mut(boss) {
+                    sprite.size = BOSS_SIZE;
+                }
+                world.ecs.lights.insert(boss, Light::new(Color::new(220, 40, 70, 255), BOSS_GLOW, 0.7).flickering(0.2));
+                self.boss = Some(boss);
+                self.spawned.push(boss);
+                crate::log_info!("the warden spawned with {:.0} hp", hp);
+            }
+        }
+
+        if self.threat > 0.0 {
+            self.spawn_timer += delta;
+            let moon = if self.blood_moon { BLOOD_MOON_SPAWNS } else { 1.0 };
+            let interval = (SPAWN_INTERVAL / (1.0 + self.threat)).max(MIN_SPAWN_INTERVAL) / moon;
+            let room = ((BASE_ENEMIES + self.threat * ENEMIES_PER_THREAT) * moon).floor() as usize > nearby;
+            if self.spawn_timer >= interval && room {
+                let side = if self.rng.gen_bool(0.5) { 1.0 } else { -1.0 };
+                let position = center + Vector2::new(side * SPAWN_DISTANCE, -SPAWN_HEIGHT);
+                // inside the terrain it could never get out, try again next frame
+                if !world.is_solid(position.x.floor() as i64, position.y.floor() as i64) {
+                    self.spawn_timer = 0.0;
+                    let hp = ENEMY_HP * (1.0 + self.threat * HP_PER_THREAT);
+                    let entity = ecs::spawn_enemy(&mut world.ecs, "Shade", position, hp, Color::new(90, 60, 130, 255), Some("shade"));
+                    self.spawned.push(entity);
+                    crate::log_debug!("spawned a shade with {:.0} hp at threat {:.2}", hp, self.threat);
+                }
+            }
+        } else {
+            self.spawn_timer = 0.0;
+        }
+
+        if nearby != self.nearby {
+            self.nearby = nearby;
+            return Some(nearby);
+        }
+        None
+    }
+
+    // shades come out of deep darkness for whoever stays in it, whatever the threat. darkness is
+    // how long the player has been in it, see Player::endure_darkness
+    pub fn lurk(&mut self, world: &mut World, center: Vector2, darkness: f32, delta: f32) {
+        if darkness <= 0.0 || world.rules.creative {
+            self.dark_timer = 0.0;
+            return;
+        }
+        self.dark_timer += delta;
+        if self.dark_timer < DARK_SPAWN_INTERVAL || self.nearby >= DARK_SPAWN_LIMIT {
+            return;
+        }
+        // in a cave most of what's around is rock, look for a pocket of air
+        for _ in 0..DARK_SPAWN_TRIES {
+            let angle = self.rng.gen_range(0.0..std::f32::consts::TAU);
+            let distance = self.rng.gen_range(DARK_SPAWN_DISTANCE.0..DARK_SPAWN_DISTANCE.1);
+            let position = center + Vector2::new(angle.cos(), angle.sin()) * distance;
+            let (x, y) = (position.x.floor() as i64, position.y.floor() as i64);
+            if world.is_solid(x, y) || world.is_solid(x, y - 1) || position.y < 0.0 {
+                continue;
+            }
+            self.dark_timer = 0.0;
+            let hp = ENEMY_HP * (1.0 + self.threat * HP_PER_THREAT);
+            let entity = ecs::spawn_enemy(&mut world.ecs, "Shade", position, hp, Color::new(90, 60, 130, 255), Some("shade"));
+            self.spawned.push(entity);
+            crate::log_debug!("a shade came out of the dark with {:.0} hp", hp);
+            return;
+        }
+    }
+}
+
+impl Subscriber for DifficultyDirector {
+    fn on_event(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::EntityDied { entity: None, .. } => self.survived = 0.0,
+            GameEvent::EntityDied { entity: Some(entity), .. } if self.boss == Some(*entity) => {
+                self.boss = None;
+                self.boss_defeated = true;
+                self.defeated_now = true;
+            }
+            GameEvent::Nightfall { event, .. } => self.blood_moon = *event == Some(NightEvent::BloodMoon),
+            GameEvent::Daybreak => self.blood_moon = false,
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use raylib::prelude::*;
+
+use crate::effects::Effects;
+use crate::events::GameEvent;
+use crate::rng::StreamRng;
+use crate::simulation::STEP_TIME;
+use crate::spell::{self, CastVariant, Spell};
+use crate::{ecs, Player, World};
+
+// seconds the scratch world runs on after the cast, long enough for projectiles to land and
+// lingering spells to go off a few times
+const SETTLE_TIME: f32 = 5.0;
+
+// what a spell would do, worked out on a copy of the world that's thrown away after. only the
+// spell and the entities run on the copy, not the falling sand, so every pixel counted here is
+// the spell's doing
+#[derive(Clone, Debug)]
+pub struct DryRun {
+    pub spell: String,
+    pub variant: CastVariant,
+    // mp at the power it was cast with, with the caster's bonuses. creative worlds pay nothing
+    pub cost: f32,
+    pub placed: usize,
+    pub removed: usize,
+    // a different material there now
+    pub changed: usize,
+    // chipped but still standing
+    pub damaged: usize,
+    // to entities, the caster's own is separate
+    pub damage: f32,
+    pub caster_damage: f32,
+    pub healed: f32,
+    pub killed: usize,
+    // how many more entities there are once it's settled, fewer if negative
+    pub entities: i64,
+}
+
+// the caster is copied as far as spells care: where they stand, their stats, bonuses and rules
+fn stand_in(player: &Player) -> Player {
+    let mut caster = Player::new(player.position);
+    caster.hp = player.hp;
+    caster.max_hp = player.max_hp;
+    caster.mp = player.mp;
+    caster.max_mp = player.max_mp;
+    caster.sp = player.sp;
+    caster.max_sp = player.max_sp;
+    caster.status_effects = player.status_effects.clone();
+    caster.rules = player.rules;
+    caster.bonuses = player.bonuses;
+    caster
+}
+
+// casts the spell at target on a scratch copy of the world and reports what changed. neither
+// world nor player are touched
+pub fn dry_run(spell: &Spell, variant: CastVariant, power: f32, world: &World, player: &Player, target: Vector2) -> DryRun {
+    let mut scratch = world.scratch();
+    let mut caster = stand_in(player);
+    let mut effects = Effects::new(StreamRng::new(0));
+    spell::activate_spell(spell, variant, power, &mut scratch, &mut caster, &mut effects, target);
+    let mut events = scratch.events.drain();
+    let mut time = 0.0;
+    while time < SETTLE_TIME {
+        spell::update_entities(&mut scratch, &mut caster, &mut effects, STEP_TIME);
+        ecs::update(&mut scratch, STEP_TIME);
+        events.extend(scratch.events.drain());
+        time += STEP_TIME;
+    }
+    let caster_damage = caster.recent_hits.iter().map(|h| h.amount).sum::<f32>();
+    let mut run = DryRun {
+        spell: spell.name.clone(),
+        variant,
+        cost: if player.rules.creative { 0.0 } else { spell.cost_with(variant, power, &player.bonuses) },
+        placed: 0,
+        removed: 0,
+        changed: 0,
+        damaged: 0,
+        damage: 0.0,
+        caster_damage,
+        healed: (caster.hp - player.hp + caster_damage).max(0.0),
+        killed: 0,
+        entities: scratch.ecs.len() as i64 - world.ecs.len() as i64,
+    };
+    for event in &events {
+        match event {
+            GameEvent::DamageDealt { amount, entity: Some(_), .. } => run.damage += amount,
+            GameEvent::EntityDied { .. } => run.killed += 1,
+            _ => {}
+        }
+    }
+    // the scratch world never loads chunks of its own, everything in it has its original
+    for chunk in &scratch.chunks {
+        let before = world.chunk_at(chunk.x, chunk.y);
+        for x in 0..16 {
+            for y in 0..16 {
+                let was = before.and_then(|c| c.get_pixel(x, y).ok());
+                match (was, chunk.get_pixel(x, y).ok()) {
+                    (None, Some(_)) => run.placed += 1,
+                    (Some(_), None) => run.removed += 1,
+                    (Some(a), Some(b)) if a.material != b.material => run.changed += 1,
+                    (Some(a), Some(b)) if a.damage != b.damage => run.damaged += 1,
+                    _ => {}
+                }
+            }
+        }
+    }
+    run
+}
+
+impl DryRun {
+    // for the console and the command line
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = vec![
+            format!("{} ({}) costs {:.1} MP", self.spell, self.variant.name(), self.cost),
+            format!("pixels: {} placed, {} removed, {} changed, {} damaged", self.placed, self.removed, self.changed, self.damaged),
+            format!("damage: {:.1} dealt, {} killed, {:.1} to the caster", self.damage, self.killed, self.caster_damage),
+        ];
+        if self.healed > 0.0 {
+            lines.push(format!("heals {:.1}", self.healed));
+        }
+        if self.entities != 0 {
+            lines.push(format!("{:+} entities after {}s", self.entities, SETTLE_TIME));
+        }
+        lines
+    }
+}
@@ -0,0 +1,506 @@
+use std::fmt;
+
+use raylib::prelude::*;
+
+use crate::digest::Digest;
+use crate::events::GameEvent;
+use crate::lighting::Light;
+use crate::loot::Rarity;
+use crate::spell::SpellComponent;
+use crate::status::{self, StatusEffect};
+use crate::{scale, TextWidth, World};
+
+// world pixels per second squared, the same pull items feel
+const DUMMY_GRAVITY: f32 = 60.0;
+const DUMMY_HP: f32 = 100.0;
+const DUMMY_SIZE: f32 = 4.0;
+// world pixels per second, and how far from where they arrived npcs wander
+const NPC_SPEED: f32 = 6.0;
+const NPC_RANGE: f32 = 16.0;
+// world pixels per second, landing slower than this a body comes to rest
+const SETTLE_SPEED: f32 = 2.0;
+// world pixels, a torch lying on the ground
+const TORCH_SIZE: f32 = 1.5;
+
+// entities are just ids, everything about them lives in the component storages on Ecs.
+// the generation stops an old id from matching whatever gets spawned into its slot later
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Entity {
+    index: u32,
+    generation: u32,
+}
+
+// index and generation, how the debug tools show it
+impl fmt::Display for Entity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}.{}", self.index, self.generation)
+    }
+}
+
+// one slot per entity index, most of them empty for any one component
+#[derive(Clone)]
+pub struct Storage<T> {
+    slots: Vec<Option<(u32, T)>>,
+}
+
+impl<T> Default for Storage<T> {
+    fn default() -> Self {
+        Storage { slots: Vec::new() }
+    }
+}
+
+impl<T> Storage<T> {
+    pub fn insert(&mut self, entity: Entity, value: T) {
+        let index = entity.index as usize;
+        if self.slots.len() <= index {
+            self.slots.resize_with(index + 1, || None);
+        }
+        self.slots[index] = Some((entity.generation, value));
+    }
+
+    pub fn remove(&mut self, entity: Entity) -> Option<T> {
+        let slot = self.slots.get_mut(entity.index as usize)?;
+        match slot {
+            Some((generation, _)) if *generation == entity.generation => slot.take().map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn get(&self, entity: Entity) -> Option<&T> {
+        match self.slots.get(entity.index as usize)? {
+            Some((generation, value)) if *generation == entity.generation => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, entity: Entity) -> Option<&mut T> {
+        match self.slots.get_mut(entity.index as usize)? {
+            Some((generation, value)) if *generation == entity.generation => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.get(entity).is_some()
+    }
+
+    // roughly what the slots take up, empty ones included, for the debug overlay
+    pub fn bytes(&self) -> usize {
+        self.slots.len() * std::mem::size_of::<Option<(u32, T)>>()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, &T)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            slot.as_ref().map(|(generation, value)| (Entity { index: index as u32, generation: *generation }, value))
+        })
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Entity, &mut T)> {
+        self.slots.iter_mut().enumerate().filter_map(|(index, slot)| {
+            slot.as_mut().map(|(generation, value)| (Entity { index: index as u32, generation: *generation }, value))
+        })
+    }
+
+    // a copy of who has the component, for when the loop needs to touch other storages too
+    pub fn entities(&self) -> Vec<Entity> {
+        self.iter().map(|(entity, _)| entity).collect()
+    }
+}
+
+// moves and falls, bouncing off solid pixels. velocity in world pixels per second
+#[derive(Clone)]
+pub struct Body {
+    pub velocity: Vector2,
+    pub gravity: f32,
+    pub bounce: f32,
+    // ran into something this update, projectiles go off when it is set
+    pub collided: bool,
+    // settled on the ground, physics leaves it be until it's pushed or the ground goes
+    pub resting: bool,
+}
+
+#[derive(Clone)]
+pub struct Health {
+    pub hp: f32,
+    pub max_hp: f32,
+}
+
+// a square of color centered on the entity's position, size in world pixels
+#[derive(Clone)]
+pub struct Sprite {
+    pub color: Color,
+    pub size: f32,
+}
+
+// an item that goes into the player's inventory when they walk over it
+#[derive(Clone)]
+pub struct Pickup {
+    pub name: String,
+    // seconds before it can be picked up, so a fresh drop visibly pops out first
+    pub delay: f32,
+    // a scroll teaches this spell instead of going into the inventory
+    pub spell: Option<String>,
+    // better than common gets its name drawn over it, see draw
+    pub rarity: Rarity,
+    // how many of the item, identical pickups that touch add up, see item::update_items
+    pub count: u32,
+}
+
+// a spell in flight, its components go off where it lands
+#[derive(Clone)]
+pub struct Projectile {
+    pub spell: String,
+    pub components: Vec<SpellComponent>,
+}
+
+// a spell that stays put and goes off every interval seconds until its lifetime runs out
+#[derive(Clone)]
+pub struct Pulse {
+    pub spell: String,
+    pub components: Vec<SpellComponent>,
+    pub interval: f32,
+    pub timer: f32,
+}
+
+// a marker for things that fight back, see DifficultyDirector for the ones that spawn by themselves
+#[derive(Clone)]
+pub struct Enemy {
+    pub name: String,
+    // id of the table in loot.json rolled when it dies
+    pub loot: Option<String>,
+}
+
+// someone who walks back and forth and can't be hurt, see Merchant
+#[derive(Clone)]
+pub struct Npc {
+    pub name: String,
+    // the x they arrived at, they stay within NPC_RANGE of it
+    pub home: f32,
+    // -1 or 1
+    pub direction: f32,
+}
+
+// everything in the world that isn't a pixel or the player. positions are world pixels. the
+// player isn't an entity yet, it still moves, takes damage and ticks its statuses in player.rs.
+// only contact effects come from the same place for both, see World::contact_effect
+#[derive(Clone, Default)]
+pub struct Ecs {
+    generations: Vec<u32>,
+    alive: Vec<bool>,
+    free: Vec<u32>,
+    pub positions: Storage<Vector2>,
+    pub bodies: Storage<Body>,
+    pub healths: Storage<Health>,
+    pub statuses: Storage<Vec<StatusEffect>>,
+    pub sprites: Storage<Sprite>,
+    // seconds left before the entity despawns by itself
+    pub lifetimes: Storage<f32>,
+    pub pickups: Storage<Pickup>,
+    pub projectiles: Storage<Projectile>,
+    pub pulses: Storage<Pulse>,
+    pub enemies: Storage<Enemy>,
+    pub npcs: Storage<Npc>,
+    // glows that move with the entity, see LightSources
+    pub lights: Storage<Light>,
+}
+
+impl Ecs {
+    pub fn spawn(&mut self) -> Entity {
+        if let Some(index) = self.free.pop() {
+            self.alive[index as usize] = true;
+            return Entity { index, generation: self.generations[index as usize] };
+        }
+        self.generations.push(0);
+        self.alive.push(true);
+        Entity { index: self.generations.len() as u32 - 1, generation: 0 }
+    }
+
+    pub fn despawn(&mut self, entity: Entity) {
+        if !self.is_alive(entity) {
+            return;
+        }
+        let index = entity.index as usize;
+        self.alive[index] = false;
+        self.generations[index] += 1;
+        self.free.push(entity.index);
+        self.positions.remove(entity);
+        self.bodies.remove(entity);
+        self.healths.remove(entity);
+        self.statuses.remove(entity);
+        self.sprites.remove(entity);
+        self.lifetimes.remove(entity);
+        self.pickups.remove(entity);
+        self.projectiles.remove(entity);
+        self.pulses.remove(entity);
+        self.enemies.remove(entity);
+        self.npcs.remove(entity);
+        self.lights.remove(entity);
+    }
+
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.generations.get(entity.index as usize) == Some(&entity.generation) && self.alive[entity.index as usize]
+    }
+
+    pub fn len(&self) -> usize {
+        self.alive.iter().filter(|a| **a).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // everything that moves the game on, in index order. sprites, projectiles and pulses come
+    // from spells and are covered by what they do once they go off, see World::state_hash
+    pub fn digest(&self, digest: &mut Digest) {
+        for (index, (&generation, &alive)) in self.generations.iter().zip(&self.alive).enumerate() {
+            if !alive {
+                continue;
+            }
+            let entity = Entity { index: index as u32, generation };
+            digest.u32(entity.index).u32(generation);
+            if let Some(position) = self.positions.get(entity) {
+                digest.u8(1).f32(position.x).f32(position.y);
+            }
+            if let Some(body) = self.bodies.get(entity) {
+                digest.u8(2).f32(body.velocity.x).f32(body.velocity.y).u8(body.resting as u8);
+            }
+            if let Some(health) = self.healths.get(entity) {
+                digest.u8(3).f32(health.hp).f32(health.max_hp);
+            }
+            if let Some(statuses) = self.statuses.get(entity) {
+                digest.u8(4).u64(statuses.len() as u64);
+                for status in statuses {
+                    digest.str(status.kind.name()).f32(status.remaining).f32(status.magnitude);
+                }
+            }
+            if let Some(lifetime) = self.lifetimes.get(entity) {
+                digest.u8(5).f32(*lifetime);
+            }
+            if let Some(pickup) = self.pickups.get(entity) {
+                digest.u8(6).str(&pickup.name).u32(pickup.count);
+            }
+            if let Some(enemy) = self.enemies.get(entity) {
+                digest.u8(7).str(&enemy.name);
+            }
+            if let Some(npc) = self.npcs.get(entity) {
+                digest.u8(8).str(&npc.name).f32(npc.direction);
+            }
+        }
+    }
+
+    // roughly what every storage takes up, not counting what the components point to
+    pub fn bytes(&self) -> usize {
+        let ids = self.generations.len() * (std::mem::size_of::<u32>() + std::mem::size_of::<bool>());
+        ids + self.positions.bytes()
+            + self.bodies.bytes()
+            + self.healths.bytes()
+            + self.statuses.bytes()
+            + self.sprites.bytes()
+            + self.lifetimes.bytes()
+            + self.pickups.bytes()
+            + self.projectiles.bytes()
+            + self.pulses.bytes()
+            + self.enemies.bytes()
+            + self.npcs.bytes()
+            + self.lights.bytes()
+    }
+
+    // adds the status to the entity, if it can have any
+    pub fn add_status(&mut self, entity: Entity, effect: StatusEffect) {
+        if let Some(effects) = self.statuses.get_mut(entity) {
+            status::add(effects, effect);
+        }
+    }
+}
+
+// something to cast at, an enemy that just stands there
+pub fn spawn_dummy(ecs: &mut Ecs, position: Vector2) -> Entity {
+    spawn_enemy(ecs, "Training dummy", position, DUMMY_HP, Color::new(170, 120, 70, 255), Some("dummy"))
+}
+
+// an enemy that falls to the ground and takes hits until hp runs out
+pub fn spawn_enemy(ecs: &mut Ecs, name: &str, position: Vector2, hp: f32, color: Color, loot: Option<&str>) -> Entity {
+    let entity = ecs.spawn();
+    ecs.positions.insert(entity, position);
+    ecs.bodies.insert(entity, Body { velocity: Vector2::zero(), gravity: DUMMY_GRAVITY, bounce: 0.0, collided: false, resting: false });
+    ecs.healths.insert(entity, Health { hp, max_hp: hp });
+    ecs.statuses.insert(entity, Vec::new());
+    ecs.sprites.insert(entity, Sprite { color, size: DUMMY_SIZE });
+    ecs.enemies.insert(entity, Enemy { name: name.to_string(), loot: loot.map(str::to_string) });
+    entity
+}
+
+// an npc that falls to the ground and wanders about until it leaves after lifetime seconds
+pub fn spawn_npc(ecs: &mut Ecs, name: &str, position: Vector2, color: Color, lifetime: f32) -> Entity {
+    let entity = ecs.spawn();
+    ecs.positions.insert(entity, position);
+    ecs.bodies.insert(entity, Body { velocity: Vector2::zero(), gravity: DUMMY_GRAVITY, bounce: 0.0, collided: false, resting: false });
+    ecs.sprites.insert(entity, Sprite { color, size: DUMMY_SIZE });
+    ecs.lifetimes.insert(entity, lifetime);
+    ecs.npcs.insert(entity, Npc { name: name.to_string(), home: position.x, direction: 1.0 });
+    entity
+}
+
+// a light left where it is for lifetime seconds, a wisp say
+pub fn spawn_light(ecs: &mut Ecs, position: Vector2, light: Light, lifetime: f32) -> Entity {
+    let entity = ecs.spawn();
+    ecs.positions.insert(entity, position);
+    ecs.lights.insert(entity, light);
+    ecs.lifetimes.insert(entity, lifetime);
+    entity
+}
+
+// a light that's thrown down and burns where it lands until lifetime runs out
+pub fn spawn_torch(ecs: &mut Ecs, position: Vector2, velocity: Vector2, light: Light, lifetime: f32) -> Entity {
+    let entity = spawn_light(ecs, position, light, lifetime);
+    ecs.bodies.insert(entity, Body { velocity, gravity: DUMMY_GRAVITY, bounce: 0.2, collided: false, resting: false });
+    ecs.sprites.insert(entity, Sprite { color: light.color, size: TORCH_SIZE });
+    entity
+}
+
+// runs every entity system that doesn't need the player, in order
+pub fn update(world: &mut World, delta: f32) {
+    wander(&mut world.ecs);
+    physics(world, delta);
+    statuses(world, delta);
+    lifetimes(world, delta);
+}
+
+// gravity and collisions for everything with a body. one axis at a time, like the particles,
+// so things hitting a wall keep falling and things hitting the floor keep sliding
+fn physics(world: &mut World, delta: f32) {
+    let mut ecs = std::mem::take(&mut world.ecs);
+    for entity in ecs.bodies.entities() {
+        let (Some(body), Some(position)) = (ecs.bodies.get_mut(entity), ecs.positions.get_mut(entity)) else {
+            continue;
+        };
+        let solid = |p: Vector2| world.is_solid(p.x.floor() as i64, p.y.floor() as i64);
+        if body.resting {
+            if body.velocity == Vector2::zero() && solid(*position + Vector2::new(0.0, 1.0)) {
+                continue;
+            }
+            body.resting = false;
+        }
+        body.collided = false;
+        body.velocity.y += body.gravity * delta;
+        let step_x = Vector2::new(body.velocity.x * delta, 0.0);
+        if solid(*position + step_x) {
+            body.velocity.x *= -body.bounce;
+            body.collided = true;
+        } else {
+            *position += step_x;
+        }
+        let step_y = Vector2::new(0.0, body.velocity.y * delta);
+        if solid(*position + step_y) {
+            let landed = body.velocity.y > 0.0;
+            body.velocity = Vector2::new(body.velocity.x * 0.5, -body.velocity.y * body.bounce);
+            body.collided = true;
+            if landed && body.velocity.length() < SETTLE_SPEED {
+                body.velocity = Vector2::zero();
+                body.resting = true;
+            }
+        } else {
+            *position += step_y;
+        }
+    }
+    world.ecs = ecs;
+}
+
+// npcs turn around at a wall, which stopped them dead last update, or at the end of their range
+fn wander(ecs: &mut Ecs) {
+    for entity in ecs.npcs.entities() {
+        let (Some(npc), Some(body), Some(position)) = (ecs.npcs.get_mut(entity), ecs.bodies.get_mut(entity), ecs.positions.get(entity)) else {
+            continue;
+        };
+        let blocked = body.collided && body.velocity.x == 0.0;
+        let strayed = (position.x - npc.home) * npc.direction > NPC_RANGE;
+        if blocked || strayed {
+            npc.direction = -npc.direction;
+        }
+        body.velocity.x = npc.direction * NPC_SPEED;
+    }
+}
+
+// status effects tick on anything with health, and whatever it stands in applies its contact effect
+fn statuses(world: &mut World, delta: f32) {
+    for entity in world.ecs.statuses.entities() {
+        let Some(&position) = world.ecs.positions.get(entity) else {
+            continue;
+        };
+        if let Some(effect) = world.contact_effect(position.x.floor() as i64, position.y.floor() as i64) {
+            world.ecs.add_status(entity, effect);
+        }
+        let Some(effects) = world.ecs.statuses.get_mut(entity) else {
+            continue;
+        };
+        let tick = status::tick(effects, delta);
+        if let Some(health) = world.ecs.healths.get_mut(entity) {
+            health.hp = (health.hp + tick.hp).min(health.max_hp);
+        }
+    }
+    for entity in world.ecs.healths.entities() {
+        if world.ecs.healths.get(entity).is_some_and(|h| h.hp <= 0.0) {
+            let position = world.ecs.positions.get(entity).copied().unwrap_or_default();
+            world.events.publish(GameEvent::EntityDied { position, entity: Some(entity) });
+            if let Some(table) = world.ecs.enemies.get(entity).and_then(|e| e.loot.clone()) {
+                world.drop_loot(&table, position);
+            }
+            world.ecs.despawn(entity);
+        }
+    }
+}
+
+fn lifetimes(world: &mut World, delta: f32) {
+    let mut expired = Vec::new();
+    for (entity, lifetime) in world.ecs.lifetimes.iter_mut() {
+        *lifetime -= delta;
+        if *lifetime <= 0.0 {
+            expired.push(entity);
+        }
+    }
+    for entity in expired {
+        world.ecs.despawn(entity);
+    }
+}
+
+// hurts the entity if it has health, publishing the damage like the player's hits
+pub fn damage(world: &mut World, entity: Entity, amount: f32, source: Option<Vector2>) {
+    let Some(health) = world.ecs.healths.get_mut(entity) else {
+        return;
+    };
+    health.hp -= amount;
+    let target = world.ecs.positions.get(entity).copied().unwrap_or_default();
+    world.events.publish(GameEvent::DamageDealt { amount, target, source, entity: Some(entity) });
+}
+
+// every entity with a sprite, plus a health bar over the ones that have been hurt and the name
+// over npcs and pickups better than common
+pub fn draw(d: &mut (impl RaylibDraw + TextWidth), ecs: &Ecs) {
+    let scale = scale() as f32;
+    for (entity, sprite) in ecs.sprites.iter() {
+        let Some(&position) = ecs.positions.get(entity) else {
+            continue;
+        };
+        let corner = (position - Vector2::new(sprite.size, sprite.size) / 2.0) * scale;
+        let size = sprite.size * scale;
+        d.draw_rectangle_v(corner, Vector2::new(size, size), sprite.color);
+        d.draw_rectangle_lines(corner.x as i32, corner.y as i32, size as i32, size as i32, Color::new(0, 0, 0, 160));
+        if let Some(pickup) = ecs.pickups.get(entity).filter(|p| p.rarity > Rarity::Common) {
+            let width = d.text_width(&pickup.name, 10);
+            d.draw_text(&pickup.name, (corner.x + size / 2.0) as i32 - width / 2, corner.y as i32 - 12, 10, pickup.rarity.color());
+        }
+        if let Some(pickup) = ecs.pickups.get(entity).filter(|p| p.count > 1) {
+            d.draw_text(&pickup.count.to_string(), (corner.x + size) as i32 + 1, corner.y as i32, 10, Color::LIGHTGRAY);
+        }
+        if let Some(npc) = ecs.npcs.get(entity) {
+            let width = d.text_width(&npc.name, 10);
+            d.draw_text(&npc.name, (corner.x + size / 2.0) as i32 - width / 2, corner.y as i32 - 12, 10, Color::WHITE);
+        }
+        let Some(health) = ecs.healths.get(entity).filter(|h| h.hp < h.max_hp) else {
+            continue;
+        };
+        let fraction = (health.hp / health.max_hp).clamp(0.0, 1.0);
+        d.draw_rectangle_v(corner - Vector2::new(0.0, 2.0 * scale), Vector2::new(size, scale), Color::new(60, 0, 0, 200));
+        d.draw_rectangle_v(corner - Vector2::new(0.0, 2.0 * scale), Vector2::new(size * fraction, scale), Color::new(200, 30, 30, 230));
+    }
+}
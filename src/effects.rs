@@ -0,0 +1,411 @@
+use interpolation::{Ease, EaseFunction};
+use rand::Rng;
+use raylib::prelude::*;
+
+use crate::config;
+use crate::events::{GameEvent, Subscriber};
+use crate::particles::{Emitter, ParticleSystem, PARTICLE_BUDGET};
+use crate::rng::StreamRng;
+use crate::{scale, World};
+
+// seconds a trail point stays visible
+const TRAIL_LIFE: f32 = 0.35;
+// beams are split into segments about this long (world pixels), each wobbling on its own
+const BEAM_SEGMENT: f32 = 3.0;
+const BEAM_JITTER: f32 = 1.2; // world pixels at the middle of the beam
+const BEAM_SPEED: f32 = 30.0;
+// bolts get a new shape this often, which is what makes them flicker
+const BOLT_FLICKER: f32 = 0.05;
+// midpoint displacement stops splitting below this length (world pixels)
+const BOLT_DETAIL: f32 = 1.5;
+// how far the first midpoint can move, as a fraction of the bolt's length. halves every level
+const BOLT_ROUGHNESS: f32 = 0.25;
+const BOLT_BRANCH_LENGTH: f32 = 0.4; // fraction of the main bolt
+// all flashes together never cover more of the screen than this
+const MAX_FLASH_ALPHA: f32 = 0.6;
+// part of a flash spent fading in, the rest fades out
+const FLASH_ATTACK: f32 = 0.15;
+
+// things gameplay code wants to see on screen. they get queued instead of drawn right away
+// so spells and physics don't need to know about the renderer
+#[derive(Clone, Copy, Debug)]
+pub enum Effect {
+    Particles { emitter: Emitter, position: Vector2 },
+    // adds a point to the trail with this id, send one every frame while the thing moves
+    TrailPoint { id: u32, position: Vector2, color: Color, width: f32 },
+    Beam { from: Vector2, to: Vector2, color: Color, width: f32, duration: f32 },
+    // jagged lightning that reshapes itself every few frames, with this many forks
+    Bolt { from: Vector2, to: Vector2, color: Color, width: f32, duration: f32, branches: u32 },
+    // strength from 0 to 1 scales how opaque it gets
+    Flash { kind: FlashKind, strength: f32 },
+    // glow around a charging caster, send one every frame while charging
+    ChargeGlow { position: Vector2, fraction: f32, color: Color },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FlashKind {
+    Damage,
+    Heal,
+    ManaSurge,
+}
+
+// one per kind at most, a new flash of the same kind restarts it instead of stacking
+struct Flash {
+    kind: FlashKind,
+    strength: f32,
+    elapsed: f32,
+}
+
+// positions and widths in world pixels
+struct TrailPoint {
+    position: Vector2,
+    age: f32,
+}
+
+struct Trail {
+    id: u32,
+    color: Color,
+    width: f32,
+    points: Vec<TrailPoint>, // oldest first
+}
+
+struct Beam {
+    from: Vector2,
+    to: Vector2,
+    color: Color,
+    width: f32,
+    remaining: f32,
+    duration: f32,
+    seed: f32,
+}
+
+struct Bolt {
+    from: Vector2,
+    to: Vector2,
+    color: Color,
+    width: f32,
+    branches: u32,
+    remaining: f32,
+    duration: f32,
+    // rebuilt every BOLT_FLICKER seconds, the main path first
+    paths: Vec<Vec<Vector2>>,
+    brightness: f32,
+    reshape_timer: f32,
+}
+
+struct ChargeGlow {
+    position: Vector2,
+    fraction: f32,
+    color: Color,
+}
+
+// the effects layer, drawn on top of the world
+pub struct Effects {
+    queue: Vec<Effect>,
+    pub particles: ParticleSystem,
+    trails: Vec<Trail>,
+    beams: Vec<Beam>,
+    bolts: Vec<Bolt>,
+    flashes: Vec<Flash>,
+    charge_glow: Option<ChargeGlow>,
+    time: f32,
+    rng: StreamRng,
+}
+
+impl FlashKind {
+    fn color(&self) -> Color {
+        match self {
+            FlashKind::Damage => Color::new(220, 20, 20, 255),
+            FlashKind::Heal => Color::new(40, 220, 80, 255),
+            FlashKind::ManaSurge => Color::new(50, 100, 240, 255),
+        }
+    }
+
+    fn duration(&self) -> f32 {
+        match self {
+            FlashKind::Damage => 0.35,
+            FlashKind::Heal => 0.6,
+            FlashKind::ManaSurge => 0.6,
+        }
+    }
+}
+
+impl Flash {
+    // eased in quickly, eased out slowly
+    fn alpha(&self) -> f32 {
+        let t = (self.elapsed / self.kind.duration()).clamp(0.0, 1.0);
+        let curve = if t < FLASH_ATTACK {
+            (t / FLASH_ATTACK).calc(EaseFunction::QuadraticOut)
+        } else {
+            (1.0 - (t - FLASH_ATTACK) / (1.0 - FLASH_ATTACK)).calc(EaseFunction::CubicIn)
+        };
+        curve * self.strength
+    }
+}
+
+impl Effects {
+    // rng is the particle stream, see RngService
+    pub fn new(mut rng: StreamRng) -> Self {
+        Effects {
+            queue: Vec::new(),
+            particles: ParticleSystem::new(PARTICLE_BUDGET, rng.fork()),
+            trails: Vec::new(),
+            beams: Vec::new(),
+            bolts: Vec::new(),
+            flashes: Vec::new(),
+            charge_glow: None,
+            time: 0.0,
+            rng,
+        }
+    }
+
+    pub fn request(&mut self, effect: Effect) {
+        self.queue.push(effect);
+    }
+
+    // view is the visible area in world pixels, for ambient particles
+    pub fn update(&mut self, view: Rectangle, world: &World, delta: f32) {
+        self.time += delta;
+        for trail in &mut self.trails {
+            for point in &mut trail.points {
+                point.age += delta;
+            }
+            trail.points.retain(|p| p.age < TRAIL_LIFE);
+        }
+        self.trails.retain(|t| !t.points.is_empty());
+        for beam in &mut self.beams {
+            beam.remaining -= delta;
+        }
+        self.beams.retain(|b| b.remaining > 0.0);
+        for bolt in &mut self.bolts {
+            bolt.remaining -= delta;
+            bolt.reshape_timer -= delta;
+            if bolt.reshape_timer <= 0.0 {
+                bolt.reshape(&mut self.rng);
+            }
+        }
+        self.bolts.retain(|b| b.remaining > 0.0);
+        for flash in &mut self.flashes {
+            flash.elapsed += delta;
+        }
+        self.flashes.retain(|f| f.elapsed < f.kind.duration());
+        // only lasts as long as someone keeps requesting it
+        self.charge_glow = None;
+
+        for effect in std::mem::take(&mut self.queue) {
+            match effect {
+                Effect::Particles { emitter, position } => self.particles.emit(emitter, position),
+                Effect::TrailPoint { id, position, color, width } => self.add_trail_point(id, position, color, width),
+                Effect::Beam { from, to, color, width, duration } => self.beams.push(Beam {
+                    from,
+                    to,
+                    color,
+                    width,
+                    remaining: duration,
+                    duration,
+                    // beams fired on the same frame shouldn't wobble in lockstep
+                    seed: self.beams.len() as f32 * 13.7 + self.time,
+                }),
+                Effect::Bolt { from, to, color, width, duration, branches } => {
+                    let mut bolt = Bolt {
+                        from,
+                        to,
+                        color,
+                        width,
+                        branches,
+                        remaining: duration,
+                        duration,
+                        paths: Vec::new(),
+                        brightness: 1.0,
+                        reshape_timer: 0.0,
+                    };
+                    bolt.reshape(&mut self.rng);
+                    self.bolts.push(bolt);
+                }
+                Effect::Flash { kind, strength } => self.add_flash(kind, strength.clamp(0.0, 1.0)),
+                Effect::ChargeGlow { position, fraction, color } => self.charge_glow = Some(ChargeGlow { position, fraction, color }),
+            }
+        }
+        self.particles.emit_ambient(view, delta);
+        self.particles.update(world, delta);
+    }
+
+    fn add_flash(&mut self, kind: FlashKind, strength: f32) {
+        if kind == FlashKind::Damage && !config::get().damage_flash {
+            return;
+        }
+        match self.flashes.iter_mut().find(|f| f.kind == kind) {
+            // a weaker hit during a strong flash shouldn't make it dimmer
+            Some(flash) => {
+                flash.strength = flash.alpha().max(strength);
+                flash.elapsed = 0.0;
+            }
+            None => self.flashes.push(Flash { kind, strength, elapsed: 0.0 }),
+        }
+    }
+
+    fn add_trail_point(&mut self, id: u32, position: Vector2, color: Color, width: f32) {
+        let point = TrailPoint { position, age: 0.0 };
+        match self.trails.iter_mut().find(|t| t.id == id) {
+            Some(trail) => {
+                trail.color = color;
+                trail.width = width;
+                trail.points.push(point);
+            }
+            None => self.trails.push(Trail {
+                id,
+                color,
+                width,
+                points: vec![point],
+            }),
+        }
+    }
+
+    // call inside the world's 2d mode
+    pub fn draw(&self, d: &mut impl RaylibDraw) {
+        let scale = scale() as f32;
+        for trail in &self.trails {
+            // thinner and fainter towards the old end
+            for pair in trail.points.windows(2) {
+                let t = 1.0 - pair[0].age / TRAIL_LIFE;
+                let color = fade(trail.color, t);
+                d.draw_line_ex(pair[0].position * scale, pair[1].position * scale, trail.width * scale * t, color);
+            }
+        }
+        for beam in &self.beams {
+            let color = fade(beam.color, beam.remaining / beam.duration);
+            let points = beam_points(beam, self.time);
+            for pair in points.windows(2) {
+                d.draw_line_ex(pair[0] * scale, pair[1] * scale, beam.width * scale, color);
+            }
+            // bright core so it reads as energy rather than a rope
+            let core = fade(Color::WHITE, beam.remaining / beam.duration);
+            for pair in points.windows(2) {
+                d.draw_line_ex(pair[0] * scale, pair[1] * scale, (beam.width * scale * 0.35).max(1.0), core);
+            }
+        }
+        if !self.bolts.is_empty() {
+            // additive so overlapping forks and the core glow instead of just covering each other
+            let mut d = d.begin_blend_mode(BlendMode::BLEND_ADDITIVE);
+            for bolt in &self.bolts {
+                let life = bolt.remaining / bolt.duration * bolt.brightness;
+                for (i, path) in bolt.paths.iter().enumerate() {
+                    // forks are thinner than the main path
+                    let width = if i == 0 { bolt.width } else { bolt.width * 0.5 };
+                    for pair in path.windows(2) {
+                        d.draw_line_ex(pair[0] * scale, pair[1] * scale, width * scale * 2.0, fade(bolt.color, life * 0.35));
+                        d.draw_line_ex(pair[0] * scale, pair[1] * scale, width * scale, fade(bolt.color, life));
+                        d.draw_line_ex(pair[0] * scale, pair[1] * scale, (width * scale * 0.35).max(1.0), fade(Color::WHITE, life));
+                    }
+                }
+            }
+        }
+        if let Some(glow) = &self.charge_glow {
+            // grows with the charge and pulses faster once it is full
+            let pulse_speed = if glow.fraction >= 1.0 { 16.0 } else { 6.0 };
+            let pulse = 0.85 + (self.time * pulse_speed).sin() * 0.15;
+            let radius = (4.0 + glow.fraction * 8.0) * pulse * scale;
+            let center = glow.position * scale;
+            d.draw_circle_v(center, radius, fade(glow.color, 0.15 + glow.fraction * 0.2));
+            d.draw_circle_v(center, radius * 0.6, fade(glow.color, 0.1 + glow.fraction * 0.25));
+            d.draw_circle_lines(center.x as i32, center.y as i32, radius, fade(glow.color, 0.4 + glow.fraction * 0.6));
+        }
+        self.particles.draw(d);
+    }
+
+    // full screen overlays, call outside 2d mode before the hud
+    pub fn draw_screen(&self, d: &mut impl RaylibDraw, width: i32, height: i32) {
+        let total = self.flashes.iter().map(|f| f.alpha()).sum::<f32>();
+        // several flashes at once share the cap instead of blacking out the screen
+        let scale = if total > 1.0 { 1.0 / total } else { 1.0 };
+        for flash in &self.flashes {
+            let alpha = flash.alpha() * scale * MAX_FLASH_ALPHA;
+            d.draw_rectangle(0, 0, width, height, fade(flash.kind.color(), alpha));
+        }
+    }
+}
+
+impl Subscriber for Effects {
+    fn on_event(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::DamageDealt { amount, entity: None, .. } => {
+                self.request(Effect::Flash { kind: FlashKind::Damage, strength: (amount / 30.0).clamp(0.3, 1.0) })
+            }
+            GameEvent::PixelChanged { x, y, before: Some(color), after: None } => {
+                let position = Vector2::new(*x as f32 + 0.5, *y as f32 + 0.5);
+                self.request(Effect::Particles { emitter: Emitter::Debris(*color), position });
+            }
+            GameEvent::EntityDied { position, .. } => self.request(Effect::Particles { emitter: Emitter::DeathBurst, position: *position }),
+            _ => {}
+        }
+    }
+}
+
+fn fade(color: Color, amount: f32) -> Color {
+    Color::new(color.r, color.g, color.b, (color.a as f32 * amount.clamp(0.0, 1.0)) as u8)
+}
+
+impl Bolt {
+    fn reshape(&mut self, rng: &mut impl Rng) {
+        self.reshape_timer = BOLT_FLICKER;
+        self.brightness = rng.gen_range(0.6..1.0);
+        let length = self.from.distance_to(self.to);
+        let mut main = vec![self.from];
+        lightning_points(self.from, self.to, length * BOLT_ROUGHNESS, rng, &mut main);
+        self.paths.clear();
+        for _ in 0..self.branches {
+            if main.len() < 3 {
+                break;
+            }
+            // forks leave from somewhere along the middle and head off roughly the same way
+            let start = main[rng.gen_range(main.len() / 4..main.len() * 3 / 4)];
+            let angle = rng.gen_range(-0.8..0.8_f32);
+            let direction = (self.to - self.from).normalized();
+            let direction = Vector2::new(
+                direction.x * angle.cos() - direction.y * angle.sin(),
+                direction.x * angle.sin() + direction.y * angle.cos(),
+            );
+            let branch_length = length * BOLT_BRANCH_LENGTH * rng.gen_range(0.5..1.0);
+            let end = start + direction * branch_length;
+            let mut branch = vec![start];
+            lightning_points(start, end, branch_length * BOLT_ROUGHNESS, rng, &mut branch);
+            self.paths.push(branch);
+        }
+        self.paths.insert(0, main);
+    }
+}
+
+// recursive midpoint displacement, pushes everything after `from` up to and including `to`
+fn lightning_points(from: Vector2, to: Vector2, displacement: f32, rng: &mut impl Rng, out: &mut Vec<Vector2>) {
+    let along = to - from;
+    let length = along.length();
+    if length < BOLT_DETAIL || displacement < 0.1 {
+        out.push(to);
+        return;
+    }
+    let normal = Vector2::new(-along.y, along.x) / length;
+    let middle = from + along * 0.5 + normal * rng.gen_range(-displacement..displacement);
+    lightning_points(from, middle, displacement * 0.5, rng, out);
+    lightning_points(middle, to, displacement * 0.5, rng, out);
+}
+
+// the beam as a polyline, every joint pushed sideways by animated noise. the ends stay put
+// and the middle moves the most
+fn beam_points(beam: &Beam, time: f32) -> Vec<Vector2> {
+    let along = beam.to - beam.from;
+    let length = along.length();
+    if length <= 0.0 {
+        return vec![beam.from, beam.to];
+    }
+    let normal = Vector2::new(-along.y, along.x) / length;
+    let segments = (length / BEAM_SEGMENT).ceil().max(1.0) as usize;
+    (0..=segments)
+        .map(|i| {
+            let t = i as f32 / segments as f32;
+            let envelope = (t * std::f32::consts::PI).sin();
+            let phase = time * BEAM_SPEED + i as f32 * 1.9 + beam.seed;
+            let noise = phase.sin() * 0.6 + (phase * 2.3 + 1.1).sin() * 0.4;
+            beam.from + along * t + normal * noise * BEAM_JITTER * envelope
+        })
+        .collect()
+}
@@ -0,0 +1,125 @@
+use std::collections::{HashMap, VecDeque};
+
+use raylib::prelude::*;
+
+use crate::{scale, Player, World};
+
+// pixels the charge can spread into per frame, across every discharge at once
+const PROPAGATION_BUDGET: usize = 200;
+// strength lost every pixel travelled, on top of the material's own conductivity
+const CHARGE_LOSS: f32 = 0.02;
+// charge weaker than this stops spreading
+const MIN_CHARGE: f32 = 0.05;
+// how far from where it lands a shock looks for something to conduct it
+const GROUNDING_RADIUS: i64 = 2;
+// how long the charged pixels keep glowing once the charge stops spreading
+const DISCHARGE_TIME: f32 = 0.4;
+// hp taken from the player touching a pixel at full strength
+const SHOCK_DAMAGE: f32 = 25.0;
+
+// charge spreading out from one shock through conductive pixels
+pub struct Discharge {
+    frontier: VecDeque<(i64, i64)>,
+    charged: HashMap<(i64, i64), f32>, // world pixel to strength, 0 to power
+    age: f32,
+    hit_player: bool,
+}
+
+// starts a discharge at world pixel x, y. returns false when there's nothing to conduct it
+pub fn shock(world: &mut World, x: i64, y: i64, power: f32) -> bool {
+    let mut discharge = Discharge {
+        frontier: VecDeque::new(),
+        charged: HashMap::new(),
+        age: 0.0,
+        hit_player: false,
+    };
+    for dy in -GROUNDING_RADIUS..=GROUNDING_RADIUS {
+        for dx in -GROUNDING_RADIUS..=GROUNDING_RADIUS {
+            if conductivity(world, x + dx, y + dy) > 0.0 {
+                discharge.charged.insert((x + dx, y + dy), power);
+                discharge.frontier.push_back((x + dx, y + dy));
+            }
+        }
+    }
+    if discharge.frontier.is_empty() {
+        return false;
+    }
+    world.discharges.push(discharge);
+    true
+}
+
+// call once a frame
+pub fn update(world: &mut World, player: &mut Player, delta: f32) {
+    // taken out so the discharges can look at the world while they change
+    let mut discharges = std::mem::take(&mut world.discharges);
+    let mut budget = PROPAGATION_BUDGET;
+    for discharge in &mut discharges {
+        discharge.age += delta;
+        while budget > 0 {
+            let Some((x, y)) = discharge.frontier.pop_front() else {
+                break;
+            };
+            budget -= 1;
+            let strength = discharge.charged[&(x, y)];
+            for (nx, ny) in [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)] {
+                if discharge.charged.contains_key(&(nx, ny)) {
+                    continue;
+                }
+                let next = strength * conductivity(world, nx, ny) - CHARGE_LOSS;
+                if next > MIN_CHARGE {
+                    discharge.charged.insert((nx, ny), next);
+                    discharge.frontier.push_back((nx, ny));
+                }
+            }
+        }
+        if !discharge.hit_player {
+            if let Some((position, strength)) = touching(discharge, player) {
+                player.damage(strength * SHOCK_DAMAGE, Some(position));
+                discharge.hit_player = true;
+            }
+        }
+        // the glow only starts fading once the charge has gone as far as it will
+        if !discharge.frontier.is_empty() {
+            discharge.age = 0.0;
+        }
+    }
+    discharges.retain(|d| d.age < DISCHARGE_TIME);
+    world.discharges = discharges;
+}
+
+fn conductivity(world: &World, x: i64, y: i64) -> f32 {
+    world.pixel_at(x, y).map_or(0.0, |p| world.materials.get(p.material).conductivity)
+}
+
+// the strongest charged pixel in or right next to the player
+fn touching(discharge: &Discharge, player: &Player) -> Option<(Vector2, f32)> {
+    let start_x = player.position.x.floor() as i64 - 1;
+    let start_y = player.position.y.floor() as i64 - 1;
+    let end_x = (player.position.x + player.size.x).ceil() as i64 + 1;
+    let end_y = (player.position.y + player.size.y).ceil() as i64 + 1;
+    let mut strongest = None;
+    for y in start_y..end_y {
+        for x in start_x..end_x {
+            let Some(&strength) = discharge.charged.get(&(x, y)) else {
+                continue;
+            };
+            if strongest.map_or(true, |(_, s)| strength > s) {
+                strongest = Some((Vector2::new(x as f32 + 0.5, y as f32 + 0.5), strength));
+            }
+        }
+    }
+    strongest
+}
+
+// in world space, over the pixels
+pub fn draw(d: &mut impl RaylibDraw, world: &World, time: f32) {
+    for discharge in &world.discharges {
+        let fade = 1.0 - discharge.age / DISCHARGE_TIME;
+        for (&(x, y), &strength) in &discharge.charged {
+            // crackles, every pixel flickering out of step with the others
+            let flicker = ((x * 7 + y * 13) as f32 + time * 40.0).sin() * 0.3 + 0.7;
+            let alpha = (strength.min(1.0) * fade * flicker * 220.0) as u8;
+            d.draw_rectangle(x as i32 * scale(), y as i32 * scale(), scale(), scale(), Color::new(190, 220, 255, alpha));
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::inventory::Inventory;
+use crate::skills::{Bonuses, SkillTree};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Slot {
+    Robe,
+    Amulet,
+    Focus,
+}
+
+pub const SLOTS: [Slot; 3] = [Slot::Robe, Slot::Amulet, Slot::Focus];
+
+impl Slot {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Slot::Robe => "Robe",
+            Slot::Amulet => "Amulet",
+            Slot::Focus => "Focus",
+        }
+    }
+}
+
+// what wearing something does. like skill bonuses, cost and speed factors multiply and the rest
+// adds up, see Equipment::bonuses
+#[derive(Clone, Copy, Debug)]
+pub enum Modifier {
+    MaxMp(f32),
+    // every spell costs this much of what it did
+    Cost(f32),
+    // fraction on top of walking speed
+    MoveSpeed(f32),
+    // fraction on top of the usual regeneration, see RegenModel
+    MpRegen(f32),
+}
+
+pub struct Gear {
+    // the inventory item it's worn from
+    pub item: &'static str,
+    pub slot: Slot,
+    pub modifiers: &'static [Modifier],
+}
+
+pub const GEAR: &[Gear] = &[
+    Gear {
+        item: "apprentice robe",
+        slot: Slot::Robe,
+        modifiers: &[Modifier::MaxMp(20.0)],
+    },
+    Gear {
+        item: "runed robe",
+        slot: Slot::Robe,
+        modifiers: &[Modifier::MaxMp(40.0), Modifier::MpRegen(0.15)],
+    },
+    Gear {
+        item: "travel amulet",
+        slot: Slot::Amulet,
+        modifiers: &[Modifier::MoveSpeed(0.15)],
+    },
+    Gear {
+        item: "mana amulet",
+        slot: Slot::Amulet,
+        modifiers: &[Modifier::MpRegen(0.25)],
+    },
+    Gear {
+        item: "focus staff",
+        slot: Slot::Focus,
+        modifiers: &[Modifier::Cost(0.9)],
+    },
+    Gear {
+        item: "arcane staff",
+        slot: Slot::Focus,
+        modifiers: &[Modifier::Cost(0.8), Modifier::MaxMp(-20.0)],
+    },
+];
+
+pub fn gear(item: &str) -> Option<&'static Gear> {
+    GEAR.iter().find(|g| g.item == item)
+}
+
+// a piece of gear's modifiers added up, to compare two against each other
+#[derive(Clone, Copy, Debug)]
+pub struct Stats {
+    pub max_mp: f32,
+    pub cost: f32,
+    pub move_speed: f32,
+    pub mp_regen: f32,
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Stats { max_mp: 0.0, cost: 1.0, move_speed: 0.0, mp_regen: 0.0 }
+    }
+}
+
+impl Modifier {
+    pub fn describe(&self) -> String {
+        match *self {
+            Modifier::MaxMp(amount) => format!("{:+.0} max MP", amount),
+            Modifier::Cost(factor) if factor > 1.0 => format!("Spells cost {:.0}% more", (factor - 1.0) * 100.0),
+            Modifier::Cost(factor) => format!("Spells cost {:.0}% less", (1.0 - factor) * 100.0),
+            Modifier::MoveSpeed(amount) => format!("Walk {:.0}% faster", amount * 100.0),
+            Modifier::MpRegen(amount) => format!("Mana regenerates {:.0}% faster", amount * 100.0),
+        }
+    }
+}
+
+impl Gear {
+    pub fn stats(&self) -> Stats {
+        let mut stats = Stats::default();
+        for modifier in self.modifiers {
+            match *modifier {
+                Modifier::MaxMp(amount) => stats.max_mp += amount,
+                Modifier::Cost(factor) => stats.cost *= factor,
+                Modifier::MoveSpeed(amount) => stats.move_speed += amount,
+                Modifier::MpRegen(amount) => stats.mp_regen += amount,
+            }
+        }
+        stats
+    }
+}
+
+impl Stats {
+    // what changes going from other to this, and whether it's for the better. nothing for
+    // stats that stay the same
+    pub fn differences(&self, other: &Stats) -> Vec<(String, bool)> {
+        let mut changes = Vec::new();
+        let max_mp = self.max_mp - other.max_mp;
+        if max_mp != 0.0 {
+            changes.push((format!("{:+.0} max MP", max_mp), max_mp > 0.0));
+        }
+        let cost = (self.cost - other.cost) * 100.0;
+        if cost.abs() >= 0.5 {
+            changes.push((format!("{:+.0}% spell cost", cost), cost < 0.0));
+        }
+        let speed = (self.move_speed - other.move_speed) * 100.0;
+        if speed.abs() >= 0.5 {
+            changes.push((format!("{:+.0}% walking speed", speed), speed > 0.0));
+        }
+        let regen = (self.mp_regen - other.mp_regen) * 100.0;
+        if regen.abs() >= 0.5 {
+            changes.push((format!("{:+.0}% mana regeneration", regen), regen > 0.0));
+        }
+        changes
+    }
+}
+
+// what the player has on, kept in the world save. worn items are out of the inventory until
+// they're taken off again
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Equipment {
+    pub worn: BTreeMap<Slot, String>,
+}
+
+impl Equipment {
+    pub fn get(&self, slot: Slot) -> Option<&'static Gear> {
+        self.worn.get(&slot).and_then(|item| gear(item))
+    }
+
+    // moves the item out of the inventory into its slot, and what was there back in
+    pub fn equip(&mut self, item: &str, inventory: &mut Inventory) -> Result<(), String> {
+        let gear = gear(item).ok_or_else(|| format!("{} can't be worn", item))?;
+        if !inventory.remove(item, 1) {
+            return Err(format!("No {} to wear", item));
+        }
+        if let Some(old) = self.worn.insert(gear.slot, item.to_string()) {
+            // the slot the new item came out of is free now, so this always fits
+            inventory.add(&old, 1);
+        }
+        Ok(())
+    }
+
+    pub fn unequip(&mut self, slot: Slot, inventory: &mut Inventory) -> Result<(), String> {
+        let Some(item) = self.worn.get(&slot) else {
+            return Ok(());
+        };
+        if !inventory.has_room(item) {
+            return Err(format!("No room for the {}", item));
+        }
+        inventory.add(item, 1);
+        self.worn.remove(&slot);
+        Ok(())
+    }
+
+    // the skills' bonuses with the gear on top. this is the only place the two come together,
+    // base stats stay as they are and everything that depends on either reads the result
+    pub fn bonuses(&self, skills: &SkillTree) -> Bonuses {
+        let mut bonuses = skills.bonuses();
+        // items left over from an older version are ignored
+        for gear in SLOTS.iter().filter_map(|slot| self.get(*slot)) {
+            for modifier in gear.modifiers {
+                match *modifier {
+                    Modifier::MaxMp(amount) => bonuses.max_mp += amount,
+                    Modifier::Cost(factor) => bonuses.scale_costs(factor),
+                    Modifier::MoveSpeed(amount) => bonuses.move_speed += amount,
+                    Modifier::MpRegen(amount) => bonuses.mp_regen += amount,
+                }
+            }
+        }
+        bonuses
+    }
+}
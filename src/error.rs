@@ -0,0 +1,61 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+// everything that can go wrong outside of gameplay itself. these get shown to the player
+// instead of crashing, the game carries on with defaults wherever it can
+#[derive(Debug)]
+pub enum SpellcoderError {
+    // a file that couldn't be read
+    Io { path: String, source: io::Error },
+    // a file that was read but isn't valid json for what it should hold
+    Parse { path: String, source: serde_json::Error },
+    // valid json that still doesn't make sense, like a material reacting with one that doesn't exist
+    Invalid { path: String, reason: String },
+    // world generation asked for something it can't do
+    Worldgen(String),
+    // a file that couldn't be written
+    Save { path: String, source: io::Error },
+}
+
+pub type Result<T> = std::result::Result<T, SpellcoderError>;
+
+impl SpellcoderError {
+    pub fn io(path: impl Into<String>, source: io::Error) -> Self {
+        SpellcoderError::Io { path: path.into(), source }
+    }
+
+    pub fn parse(path: impl Into<String>, source: serde_json::Error) -> Self {
+        SpellcoderError::Parse { path: path.into(), source }
+    }
+
+    pub fn invalid(path: impl Into<String>, reason: impl Into<String>) -> Self {
+        SpellcoderError::Invalid { path: path.into(), reason: reason.into() }
+    }
+
+    pub fn save(path: impl Into<String>, source: io::Error) -> Self {
+        SpellcoderError::Save { path: path.into(), source }
+    }
+}
+
+impl fmt::Display for SpellcoderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpellcoderError::Io { path, source } => write!(f, "could not read {}: {}", path, source),
+            SpellcoderError::Parse { path, source } => write!(f, "could not parse {}: {}", path, source),
+            SpellcoderError::Invalid { path, reason } => write!(f, "{}: {}", path, reason),
+            SpellcoderError::Worldgen(reason) => write!(f, "world generation failed: {}", reason),
+            SpellcoderError::Save { path, source } => write!(f, "could not save {}: {}", path, source),
+        }
+    }
+}
+
+impl Error for SpellcoderError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SpellcoderError::Io { source, .. } | SpellcoderError::Save { source, .. } => Some(source),
+            SpellcoderError::Parse { source, .. } => Some(source),
+            SpellcoderError::Invalid { .. } | SpellcoderError::Worldgen(_) => None,
+        }
+    }
+}
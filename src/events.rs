@@ -0,0 +1,57 @@
+use raylib::prelude::*;
+
+use crate::ecs::Entity;
+use crate::material::MaterialId;
+use crate::sky::{MoonPhase, NightEvent};
+
+// things that happened this frame that other systems may want to react to. whoever causes one
+// publishes it and doesn't need to know who listens, see Game::dispatch_events for who does
+#[derive(Clone, Debug)]
+pub enum GameEvent {
+    // target is where the one who got hurt was, source where the damage came from if anywhere.
+    // entity is None when it was the player
+    DamageDealt { amount: f32, target: Vector2, source: Option<Vector2>, entity: Option<Entity> },
+    // a pixel placed or removed by a spell or the editor, the simulation moving things about doesn't count.
+    // before is the color of what was there, after the material now there
+    PixelChanged { x: i64, y: i64, before: Option<Color>, after: Option<MaterialId> },
+    // target is where the spell was aimed, touched whether it changed any pixels. cost is the mp the
+    // spell is worth at the power it was cast with, even in creative where nothing is paid
+    SpellCast { name: String, target: Vector2, touched: bool, cost: f32, sound: Option<String>, touch_sound: Option<String> },
+    EntityDied { position: Vector2, entity: Option<Entity> },
+    // the player touched the ground after falling distance world pixels from the highest point
+    // in the air. survived is false when the fall damage killed them
+    Landed { distance: f32, survived: bool },
+    // the player set foot somewhere for the first time, a biome so far
+    Discovered { name: String },
+    // after its rewards have been handed out
+    QuestCompleted { id: String },
+    // chunk coordinates, not pixels
+    ChunkGenerated { chunk_x: i64, chunk_y: i64 },
+    // everything runs at factor speed for duration real seconds, see GameClock::slow_motion
+    SlowMotion { factor: f32, duration: f32 },
+    // the sun went down, event is what's special about the night if anything. see Sky
+    Nightfall { phase: MoonPhase, event: Option<NightEvent> },
+    Daybreak,
+}
+
+// implemented by every system that reacts to events
+pub trait Subscriber {
+    fn on_event(&mut self, event: &GameEvent);
+}
+
+// events wait here until the end of the frame, so nothing reacts halfway through an update
+#[derive(Default)]
+pub struct EventBus {
+    queue: Vec<GameEvent>,
+}
+
+impl EventBus {
+    pub fn publish(&mut self, event: GameEvent) {
+        self.queue.push(event);
+    }
+
+    // everything published since the last drain, oldest first
+    pub fn drain(&mut self) -> Vec<GameEvent> {
+        std::mem::take(&mut self.queue)
+    }
+}
@@ -0,0 +1,97 @@
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::events::{GameEvent, Subscriber};
+use crate::tuning::{self, LevelingTuning};
+use crate::Player;
+
+// what a fresh player has before any levels
+const BASE_HP: f32 = 100.0;
+const BASE_MP: f32 = 100.0;
+const BASE_SP: f32 = 100.0;
+
+// xp from kills and discoveries, kept in the world save. the numbers are in tuning.toml
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Experience {
+    pub level: u32,
+    // towards the next level, starts over at every level up
+    pub xp: u32,
+    // biome names, each only gives xp the first time
+    pub discovered: BTreeSet<String>,
+    // max stats on top of what the level gives, from quest rewards
+    pub boosts: Boosts,
+    // levels gained since the last take_level_ups
+    #[serde(skip)]
+    gained: u32,
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Boosts {
+    pub hp: f32,
+    pub mp: f32,
+    pub sp: f32,
+}
+
+impl Default for Experience {
+    fn default() -> Self {
+        Experience { level: 1, xp: 0, discovered: BTreeSet::new(), boosts: Boosts::default(), gained: 0 }
+    }
+}
+
+impl Experience {
+    pub fn add(&mut self, xp: u32) {
+        self.xp += xp;
+        let leveling = tuning::get().leveling.clone();
+        while self.xp >= xp_to_next(&leveling, self.level) {
+            self.xp -= xp_to_next(&leveling, self.level);
+            self.level += 1;
+            self.gained += 1;
+        }
+    }
+
+    pub fn to_next(&self) -> u32 {
+        xp_to_next(&tuning::get().leveling, self.level)
+    }
+
+    pub fn has_discovered(&self, name: &str) -> bool {
+        self.discovered.contains(name)
+    }
+
+    // how many levels went up since last time, for the level up message
+    pub fn take_level_ups(&mut self) -> u32 {
+        std::mem::take(&mut self.gained)
+    }
+
+    // sets the player's max stats for the level and whatever the player.bonuses add. what they
+    // have now goes up by as much, so a level up also heals by what it adds
+    pub fn apply(&self, player: &mut Player) {
+        let leveling = tuning::get().leveling.clone();
+        let bonus = |per_level: f32| per_level * ((self.level - 1) as f32).powf(leveling.stat_curve);
+        let hp = BASE_HP + bonus(leveling.hp_per_level) + self.boosts.hp;
+        let mp = (BASE_MP + bonus(leveling.mp_per_level) + self.boosts.mp + player.bonuses.max_mp).max(1.0);
+        let sp = BASE_SP + bonus(leveling.sp_per_level) + self.boosts.sp;
+        player.hp += (hp - player.max_hp).max(0.0);
+        player.mp += (mp - player.max_mp).max(0.0);
+        player.sp += (sp - player.max_sp).max(0.0);
+        player.max_hp = hp;
+        player.max_mp = mp;
+        player.max_sp = sp;
+    }
+}
+
+fn xp_to_next(leveling: &LevelingTuning, level: u32) -> u32 {
+    (leveling.xp_base * leveling.xp_growth.powi(level as i32 - 1)).round().max(1.0) as u32
+}
+
+impl Subscriber for Experience {
+    fn on_event(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::EntityDied { entity: Some(_), .. } => self.add(tuning::get().leveling.kill_xp),
+            GameEvent::Discovered { name } if self.discovered.insert(name.clone()) => self.add(tuning::get().leveling.discovery_xp),
+            _ => {}
+        }
+    }
+}
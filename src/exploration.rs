@@ -0,0 +1,157 @@
+use std::collections::BTreeMap;
+
+use raylib::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::world::DEPTH_CHUNKS;
+use crate::{scale, World};
+
+// rows from the surface down that can be explored, one bit each so they have to fit in a u64.
+// the sky above the surface is always seen
+const ROWS: i64 = DEPTH_CHUNKS * 16;
+// world pixels around the player's center that count as seen
+const SIGHT_RADIUS: i64 = 20;
+// drawn over what hasn't been seen. not quite opaque, the shape of the ground still shows a little
+const FOG: Color = Color::new(6, 6, 12, 230);
+// world pixels past the view the fog goes on, the camera shakes past it now and then
+const FOG_MARGIN: f32 = 8.0;
+// the minimap sits bottom right above the prompts, one screen pixel per world pixel
+const MINIMAP_WIDTH: i32 = 128;
+const MINIMAP_SKY: i32 = 8;
+const MINIMAP_RIGHT: i32 = 10;
+const MINIMAP_BOTTOM: i32 = 30;
+const MINIMAP_AIR: Color = Color::new(40, 44, 60, 220);
+const MINIMAP_UNSEEN: Color = Color::new(12, 12, 18, 240);
+
+// a spell lifting the fog for a while, see SpellComponent::Reveal
+#[derive(Clone, Debug)]
+struct Reveal {
+    center: Vector2,
+    radius: f32,
+    left: f32,
+}
+
+// which pixels of the world the player has seen, by chunk column. one mask per pixel column in
+// it, bit y set once the pixel y below the surface has been seen. kept in the world save,
+// reveals aren't
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Exploration {
+    pub columns: BTreeMap<i64, [u64; 16]>,
+    #[serde(skip)]
+    reveals: Vec<Reveal>,
+    // the pixel the player was last seeing from, nothing new comes into sight standing still
+    #[serde(skip)]
+    seen_from: Option<(i64, i64)>,
+}
+
+impl Exploration {
+    pub fn clear(&mut self) {
+        *self = Exploration::default();
+    }
+
+    pub fn is_explored(&self, x: i64, y: i64) -> bool {
+        if !(0..ROWS).contains(&y) {
+            return true;
+        }
+        self.columns.get(&x.div_euclid(16)).is_some_and(|c| c[x.rem_euclid(16) as usize] & (1 << y) != 0)
+    }
+
+    // explored, or lit up by a spell right now
+    pub fn is_visible(&self, x: i64, y: i64) -> bool {
+        let center = Vector2::new(x as f32 + 0.5, y as f32 + 0.5);
+        self.is_explored(x, y) || self.reveals.iter().any(|r| r.center.distance_to(center) <= r.radius)
+    }
+
+    // marks everything in the radius around center as seen
+    pub fn explore(&mut self, center: Vector2, radius: i64) {
+        let (cx, cy) = (center.x.floor() as i64, center.y.floor() as i64);
+        for x in cx - radius..=cx + radius {
+            let reach = ((radius * radius - (x - cx) * (x - cx)) as f32).sqrt() as i64;
+            let (top, bottom) = ((cy - reach).max(0), (cy + reach).min(ROWS - 1));
+            if top > bottom {
+                continue;
+            }
+            let mask = (u64::MAX >> (63 - (bottom - top))) << top;
+            self.columns.entry(x.div_euclid(16)).or_insert([0; 16])[x.rem_euclid(16) as usize] |= mask;
+        }
+    }
+
+    // lifts the fog in the radius for seconds without exploring it, it comes back after
+    pub fn reveal(&mut self, center: Vector2, radius: f32, seconds: f32) {
+        self.reveals.push(Reveal { center, radius, left: seconds });
+    }
+
+    // explores around the player as they go and lets reveals run out
+    pub fn update(&mut self, center: Vector2, delta: f32) {
+        for reveal in &mut self.reveals {
+            reveal.left -= delta;
+        }
+        self.reveals.retain(|r| r.left > 0.0);
+        let from = (center.x.floor() as i64, center.y.floor() as i64);
+        if self.seen_from != Some(from) {
+            self.seen_from = Some(from);
+            self.explore(center, SIGHT_RADIUS);
+        }
+    }
+
+    // the runs of rows in column x between top and bottom that can't be seen, as (first, end)
+    fn hidden(&self, x: i64, top: i64, bottom: i64) -> Vec<(i64, i64)> {
+        let mut runs = Vec::new();
+        let mut y = top.max(0);
+        let bottom = bottom.min(ROWS);
+        while y < bottom {
+            if self.is_visible(x, y) {
+                y += 1;
+                continue;
+            }
+            let first = y;
+            while y < bottom && !self.is_visible(x, y) {
+                y += 1;
+            }
+            runs.push((first, y));
+        }
+        runs
+    }
+}
+
+// darkens what hasn't been seen, in the world's 2d mode. view is in world pixels, see Game::view
+pub fn draw_fog(d: &mut impl RaylibDraw, exploration: &Exploration, view: Rectangle) {
+    crate::profile_scope!("fog");
+    let s = scale();
+    let (left, right) = ((view.x - FOG_MARGIN).floor() as i64, (view.x + view.width + FOG_MARGIN).ceil() as i64);
+    let (top, bottom) = ((view.y - FOG_MARGIN).floor() as i64, (view.y + view.height + FOG_MARGIN).ceil() as i64);
+    for x in left..right {
+        for (first, end) in exploration.hidden(x, top, bottom) {
+            d.draw_rectangle(x as i32 * s, first as i32 * s, s, (end - first) as i32 * s, FOG);
+        }
+    }
+}
+
+// the ground around center as far as it's been seen, with the player in the middle
+pub fn draw_minimap(d: &mut RaylibDrawHandle, world: &World, center: Vector2) {
+    crate::profile_scope!("minimap");
+    let height = MINIMAP_SKY + ROWS as i32;
+    let left = d.get_screen_width() - MINIMAP_WIDTH - MINIMAP_RIGHT;
+    let top = d.get_screen_height() - MINIMAP_BOTTOM - height;
+    let first = center.x.floor() as i64 - MINIMAP_WIDTH as i64 / 2;
+    let surface = top + MINIMAP_SKY;
+    d.draw_rectangle(left, top, MINIMAP_WIDTH, height, MINIMAP_AIR);
+    for column in 0..MINIMAP_WIDTH {
+        for (start, end) in world.explored.hidden(first + column as i64, 0, ROWS) {
+            d.draw_rectangle(left + column, surface + start as i32, 1, (end - start) as i32, MINIMAP_UNSEEN);
+        }
+    }
+    let shown = first..first + MINIMAP_WIDTH as i64;
+    for chunk in world.chunks.iter().filter(|c| c.x + 16 > shown.start && c.x < shown.end) {
+        for pixel in chunk.pixels.iter().flatten() {
+            let (x, y) = (chunk.x + pixel.x as i64, chunk.y + pixel.y as i64);
+            if shown.contains(&x) && world.explored.is_visible(x, y) {
+                d.draw_pixel(left + (x - first) as i32, surface + y as i32, pixel.color);
+            }
+        }
+    }
+    let player = Vector2::new((left + MINIMAP_WIDTH / 2) as f32, surface as f32 + center.y.max(-MINIMAP_SKY as f32 + 2.0));
+    d.draw_rectangle(player.x as i32 - 1, player.y as i32 - 2, 2, 4, Color::WHITE);
+    d.draw_rectangle_lines(left - 1, top - 1, MINIMAP_WIDTH + 2, height + 2, Color::new(0, 0, 0, 255));
+}
@@ -0,0 +1,1287 @@
+use std::collections::{BTreeSet, HashSet};
+use std::time::Instant;
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+use raylib::prelude::*;
+
+use crate::achievements::{Achievements, ACHIEVEMENTS_PATH, UNLOCKED_PATH};
+use crate::animation::PlayerAnimation;
+use crate::bloom;
+use crate::build::{self, BuildMode};
+use crate::audio::{AudioManager, AudioState, SoundEvent};
+use crate::camera::CameraEffects;
+use crate::capture::{Capture, SCREENSHOT_DIR};
+use crate::chest::Chests;
+use crate::clock::GameClock;
+use crate::config;
+use crate::console::Console;
+use crate::consumables::{self, Use};
+use crate::crash::{Recovery, RECOVERY_DIR};
+use crate::crafting::{Recipes, RECIPES_PATH};
+use crate::daily::{self, DailyRun, Leaderboard, LEADERBOARD_PATH};
+use crate::death::DeathSequence;
+use crate::debug::{DebugOverlay, Memory};
+use crate::director::{AudioDirector, CombatEvent, DifficultyDirector};
+use crate::effects::{Effect, Effects};
+use crate::equipment::{Equipment, Slot};
+use crate::error::SpellcoderError;
+use crate::events::{GameEvent, Subscriber};
+use crate::experience::Experience;
+use crate::exploration::{self, Exploration};
+use crate::growth::Growth;
+use crate::hud::{Hud, HudDraw, Severity};
+use crate::input::{self, Action, InputMap, SPELL_SLOTS};
+use crate::inventory::Inventory;
+use crate::lighting::{Light, LightMap};
+use crate::logging;
+use crate::loot::{LootTables, Rarity, LOOT_PATH};
+use crate::material::MaterialRegistry;
+use crate::merchant::{Catalog, Merchant, CURRENCY, MERCHANT_PATH};
+use crate::particles::Emitter;
+use crate::profiler::Profiler;
+use crate::render::WorldDraw;
+use crate::quest::{Quest, QuestLog, QuestProgress, Reward, QUESTS_PATH};
+use crate::rng::Stream;
+use crate::rules::WorldRules;
+use crate::save::{WorldSave, WORLD_SAVE_PATH};
+use crate::session::{SessionLog, SESSION_DIR};
+use crate::scheduler::{Scheduler, Step};
+use crate::season::Season;
+use crate::settings::SettingsMenu;
+use crate::simulation::{self, Simulation};
+use crate::skills::SkillTree;
+use crate::sky::Sky;
+use crate::spell::{self, CastVariant, Spellbook};
+use crate::stats::Statistics;
+use crate::status::{self, StatusEffect, StatusKind};
+use crate::temperature::Temperature;
+use crate::tuning::{TuningWatcher, TUNING_PATH};
+use crate::weather::Weather;
+use crate::item::Collected;
+use crate::world::{self, DEPTH_CHUNKS};
+use crate::{ecs, electricity, item, scale, Player, TextWidth, World};
+
+const LANDING_SOUND_SPEED: f32 = 2.0;
+// effect ids, so every trail knows what it follows
+const PLAYER_TRAIL: u32 = 0;
+// horizontal world pixels walked per footstep
+const STEP_LENGTH: f32 = 6.0;
+// chunks to either side of the player's that get generated in the background
+const STREAM_RADIUS: i64 = 3;
+// seconds of play between saves
+const AUTOSAVE_INTERVAL: f32 = 120.0;
+// chance an enemy drops a scroll of a spell the player doesn't know
+const SCROLL_DROP_CHANCE: f32 = 0.3;
+// world pixels around a spell's target it looks for a sealed chest in
+const SEAL_RANGE: i64 = 3;
+// world pixels per second a torch is thrown down with, towards the aim
+const TORCH_THROW: f32 = 30.0;
+// seconds in deep darkness before the player is told about it
+const DARKNESS_WARNING: f32 = 2.0;
+
+// everything a running game is made of. the states in state.rs decide which parts of it run
+// each frame, these are the pieces they pick from
+pub struct Game<'aud> {
+    pub player: Player,
+    pub world: World,
+    pub hud: Hud,
+    pub camera_fx: CameraEffects,
+    pub controls: InputMap,
+    pub settings: SettingsMenu,
+    pub spellbook: Spellbook,
+    pub console: Console,
+    pub effects: Effects,
+    pub death: DeathSequence,
+    pub animation: PlayerAnimation,
+    pub weather: Weather,
+    // the day and night, and the moon, see sky.rs
+    pub sky: Sky,
+    pub simulation: Simulation,
+    pub temperature: Temperature,
+    pub growth: Growth,
+    pub audio: AudioManager<'aud>,
+    pub director: AudioDirector,
+    pub difficulty: DifficultyDirector,
+    pub clock: GameClock,
+    pub stats: Statistics,
+    pub experience: Experience,
+    pub skills: SkillTree,
+    pub quests: QuestLog,
+    pub recipes: Recipes,
+    pub used_altars: BTreeSet<i64>,
+    pub chests: Chests,
+    pub merchant: Merchant,
+    pub achievements: Achievements,
+    // started with --daily, the world save is left alone then
+    pub daily: Option<DailyRun>,
+    pub build: BuildMode,
+    pub capture: Capture,
+    // left behind by a crash, the title screen asks what to do with it
+    pub recovery: Option<Recovery>,
+    pub session: SessionLog,
+    pub tuning: TuningWatcher,
+    pub scheduler: Scheduler<Game<'aud>>,
+    // where the frame goes, for the debug overlay
+    pub profiler: Profiler,
+    pub debug: DebugOverlay,
+    // chunks waiting in the scheduler, so they're only asked for once
+    queued_chunks: HashSet<(i64, i64)>,
+    autosave_timer: f32,
+    // what the scene is drawn into, None if the graphics driver couldn't make one
+    frame: Option<RenderTexture2D>,
+    light_map: LightMap,
+    pub aim: Vector2, // world pixels
+    pub quit: bool,
+    // no save yet, the rules can still be picked in the menu
+    pub new_world: bool,
+    vel: Vector2,
+    // the highest point since leaving the ground, world pixels
+    fall_top: Option<f32>,
+    aim_screen: Vector2,
+    step_distance: f32,
+}
+
+impl<'aud> Game<'aud> {
+    // load_errors are shown once the hud is up
+    pub fn new(
+        rl: &mut RaylibHandle,
+        thread: &RaylibThread,
+        audio: AudioManager<'aud>,
+        materials: MaterialRegistry,
+        mut load_errors: Vec<SpellcoderError>,
+    ) -> Self {
+        // first, the player's stats depend on it
+        let mut tuning = TuningWatcher::new(TUNING_PATH);
+        if let Err(e) = tuning.load() {
+            load_errors.push(e);
+        }
+        let save_path = config::get().save_path(WORLD_SAVE_PATH);
+        let daily = config::get().daily.then(|| DailyRun::new(daily::today()));
+        // a daily challenge always starts from nothing with the starter spells
+        let save = if daily.is_some() {
+            None
+        } else {
+            WorldSave::load(&save_path).unwrap_or_else(|e| {
+                load_errors.push(e);
+                None
+            })
+        };
+        // the daily's rules are the defaults for everyone
+        let new_world = save.is_none() && daily.is_none();
+        let recovery = Recovery::load(&config::get().save_path(RECOVERY_DIR)).unwrap_or_else(|e| {
+            load_errors.push(e);
+            None
+        });
+        let save = save.unwrap_or(WorldSave {
+            seed: daily.as_ref().map_or(config::get().seed, |run| daily::seed(&run.date)),
+            rules: WorldRules::default(),
+            stats: Statistics::default(),
+            experience: Experience::default(),
+            skills: SkillTree::default(),
+            quests: QuestProgress::default(),
+            known_spells: Vec::new(),
+            used_altars: BTreeSet::new(),
+            survived: 0.0,
+            boss_defeated: false,
+            inventory: Inventory::default(),
+            equipment: Equipment::default(),
+            chests: Chests::default(),
+            coins: 0,
+            explored: Exploration::default(),
+        });
+        let mut player = Player::new(Vector2::zero());
+        let mut world = World::new(materials, save.seed);
+        world.rules = save.rules;
+        player.rules = save.rules;
+        player.inventory = save.inventory.clone();
+        player.inventory.fix_slots();
+        player.equipment = save.equipment.clone();
+        player.coins = save.coins;
+        world.explored = save.explored.clone();
+        world.season = Season::at(save.stats.playtime);
+        player.bonuses = player.equipment.bonuses(&save.skills);
+        save.experience.apply(&mut player);
+        // the rest is streamed in around the player, see queue_chunks
+        for x in 0..4 {
+            for z in 0..DEPTH_CHUNKS {
+                world.generate_chunk(x, z);
+            }
+        }
+        let mut hud = Hud::new(&player);
+        let profile = config::get().controls_profile.clone();
+        let controls = InputMap::load(&config::get().save_path(&input::profile_path(&profile)), &profile, &mut load_errors);
+        let mut spellbook = Spellbook::load(&config::get().spell_dir, &world.materials, &mut load_errors);
+        spellbook.restore(&save.known_spells);
+        let mut quests = QuestLog::load(QUESTS_PATH, &world.materials, &mut load_errors);
+        let recipes = Recipes::load(RECIPES_PATH, &mut load_errors);
+        world.loot = LootTables::load(LOOT_PATH, &world.materials, &mut load_errors);
+        let catalog = Catalog::load(MERCHANT_PATH, &mut load_errors);
+        let achievements = Achievements::load(ACHIEVEMENTS_PATH, &config::get().save_path(UNLOCKED_PATH), &mut load_errors);
+        quests.progress = save.quests;
+        crate::log_info!("seed {}, {} spells, {} materials", world.seed, spellbook.spells.len(), world.materials.count());
+        hud.notify("World generated", 2.0, Severity::Info);
+        for e in load_errors {
+            crate::log_error!("{}", e);
+            hud.notify(&e.to_string(), 6.0, Severity::Danger);
+        }
+        let frame = rl
+            .load_render_texture(thread, rl.get_screen_width() as u32, rl.get_screen_height() as u32)
+            .map_err(|e| crate::log_error!("could not make the frame texture, drawing straight to the screen: {}", e))
+            .ok();
+        let session = if config::get().session_log {
+            SessionLog::create(&config::get().save_path(SESSION_DIR), world.seed).unwrap_or_else(|e| {
+                hud.notify(&e.to_string(), 6.0, Severity::Danger);
+                SessionLog::disabled()
+            })
+        } else {
+            SessionLog::disabled()
+        };
+        let mut scheduler = Scheduler::new(config::get().background_budget_ms);
+        scheduler.push("growth", |game: &mut Game| {
+            game.growth.step(&mut game.world);
+            Step::Again
+        });
+        scheduler.push("tuning", |game: &mut Game| {
+            game.reload_tuning();
+            Step::Again
+        });
+        let effects = Effects::new(world.rng.stream(Stream::Particles));
+        let weather = Weather::new(world.rng.stream(Stream::Weather));
+        let difficulty = DifficultyDirector::new(world.rng.stream(Stream::Spawns), save.survived, save.boss_defeated);
+        let merchant = Merchant::new(catalog, world.rng.stream(Stream::Trade));
+        Game {
+            player,
+            world,
+            hud,
+            camera_fx: CameraEffects::new(),
+            controls,
+            settings: SettingsMenu::new(),
+            spellbook,
+            console: Console::new(),
+            effects,
+            death: DeathSequence::new(),
+            animation: PlayerAnimation::new(),
+            weather,
+            sky: Sky::default(),
+            simulation: Simulation::new(),
+            temperature: Temperature::new(),
+            growth: Growth::new(),
+            audio,
+            director: AudioDirector::new(),
+            difficulty,
+            clock: GameClock::new(config::get().fixed_step),
+            aim: Vector2::zero(),
+            quit: false,
+            new_world,
+            stats: save.stats,
+            experience: save.experience,
+            skills: save.skills,
+            quests,
+            recipes,
+            used_altars: save.used_altars,
+            chests: save.chests,
+            merchant,
+            achievements,
+            daily,
+            build: BuildMode::new(),
+            capture: Capture::new(),
+            recovery,
+            session,
+            tuning,
+            scheduler,
+            profiler: Profiler::default(),
+            debug: DebugOverlay::default(),
+            queued_chunks: HashSet::new(),
+            autosave_timer: 0.0,
+            frame,
+            light_map: LightMap::default(),
+            vel: Vector2::zero(),
+            fall_top: None,
+            aim_screen: Vector2::zero(),
+            step_distance: 0.0,
+        }
+    }
+
+    // only while the world is new, once it's saved the rules stay as they are
+    pub fn set_rules(&mut self, rules: WorldRules) {
+        if self.new_world {
+            self.world.rules = rules;
+            self.player.rules = rules;
+        }
+    }
+
+    // writes the save the first time the world is played
+    pub fn start(&mut self) {
+        if !self.new_world {
+            return;
+        }
+        self.new_world = false;
+        if let Err(e) = self.save() {
+            self.hud.notify(&e.to_string(), 4.0, Severity::Danger);
+        }
+    }
+
+    // nothing to save for a world that was never played or was lost in hardcore, and a daily
+    // challenge only leaves its score behind, see finish_daily
+    pub fn save(&self) -> Result<(), SpellcoderError> {
+        // the counters keep going whatever happens to the world
+        self.achievements.save()?;
+        if self.new_world || self.death.is_gone() || self.daily.is_some() {
+            return Ok(());
+        }
+        self.world_save().save(&config::get().save_path(WORLD_SAVE_PATH))
+    }
+
+    fn world_save(&self) -> WorldSave {
+        WorldSave {
+            seed: self.world.seed,
+            rules: self.world.rules,
+            stats: self.stats.clone(),
+            experience: self.experience.clone(),
+            skills: self.skills.clone(),
+            quests: self.quests.progress.clone(),
+            known_spells: self.spellbook.known(),
+            used_altars: self.used_altars.clone(),
+            survived: self.difficulty.survived,
+            boss_defeated: self.difficulty.boss_defeated,
+            inventory: self.player.inventory.clone(),
+            equipment: self.player.equipment.clone(),
+            chests: self.chests.clone(),
+            coins: self.player.coins,
+            explored: self.world.explored.clone(),
+        }
+    }
+
+    // after a crash, from main. the state may be half updated but it's better than nothing
+    pub fn emergency_save(&self) -> Result<(), SpellcoderError> {
+        if self.new_world || self.daily.is_some() {
+            return Ok(());
+        }
+        Recovery::capture(self.world_save(), &self.world, &self.player).save(&config::get().save_path(RECOVERY_DIR))
+    }
+
+    // takes the last session back up where it crashed
+    pub fn recover(&mut self) {
+        let Some(recovery) = self.recovery.take() else {
+            return;
+        };
+        if recovery.save.seed != self.world.seed {
+            self.hud.notify("The recovered session is from another world", 4.0, Severity::Danger);
+        } else {
+            recovery.apply(&mut self.world, &mut self.player);
+            self.stats = recovery.save.stats.clone();
+            self.skills = recovery.save.skills.clone();
+            self.player.equipment = recovery.save.equipment.clone();
+            self.player.bonuses = self.player.equipment.bonuses(&self.skills);
+            self.experience = recovery.save.experience.clone();
+            self.experience.apply(&mut self.player);
+            self.quests.progress = recovery.save.quests.clone();
+            self.spellbook.restore(&recovery.save.known_spells);
+            self.used_altars = recovery.save.used_altars.clone();
+            self.chests = recovery.save.chests.clone();
+            self.difficulty.survived = recovery.save.survived;
+            self.difficulty.boss_defeated = recovery.save.boss_defeated;
+            self.player.inventory = recovery.save.inventory.clone();
+            self.player.inventory.fix_slots();
+            self.player.coins = recovery.save.coins;
+            self.world.explored = recovery.save.explored.clone();
+            self.hud.notify("Recovered the crashed session", 3.0, Severity::Info);
+        }
+        self.discard_recovery();
+    }
+
+    pub fn discard_recovery(&mut self) {
+        self.recovery = None;
+        if let Err(e) = Recovery::discard(&config::get().save_path(RECOVERY_DIR)) {
+            self.hud.notify(&e.to_string(), 4.0, Severity::Danger);
+        }
+    }
+
+    // once the warden is beaten, a harsher world from a seed of its own. the spellbook, level,
+    // skills and quests come along, whatever belonged to the old world stays behind
+    pub fn new_game_plus(&mut self) {
+        if !self.difficulty.boss_defeated {
+            return;
+        }
+        let rules = WorldRules { prestige: self.world.rules.prestige + 1, ..self.world.rules };
+        let seed = prestige_seed(self.world.seed, rules.prestige);
+        self.world.reset(seed, rules);
+        for x in 0..4 {
+            for z in 0..DEPTH_CHUNKS {
+                self.world.generate_chunk(x, z);
+            }
+        }
+        self.queued_chunks.clear();
+        self.player.rules = rules;
+        self.player.teleport(self.player.spawn_point);
+        self.player.hp = self.player.max_hp;
+        self.player.mp = self.player.max_mp;
+        self.player.sp = self.player.max_sp;
+        self.effects = Effects::new(self.world.rng.stream(Stream::Particles));
+        self.weather = Weather::new(self.world.rng.stream(Stream::Weather));
+        self.difficulty = DifficultyDirector::new(self.world.rng.stream(Stream::Spawns), 0.0, false);
+        // says again what kind of night it is, the new director hasn't heard
+        self.sky = Sky::default();
+        self.simulation = Simulation::new();
+        self.temperature = Temperature::new();
+        self.growth = Growth::new();
+        self.death = DeathSequence::new();
+        self.used_altars.clear();
+        self.chests = Chests::default();
+        // the old merchant was in the old world's ecs
+        self.merchant = Merchant::new(std::mem::take(&mut self.merchant.catalog), self.world.rng.stream(Stream::Trade));
+        crate::log_info!("new game plus {}, seed {}", rules.prestige, seed);
+        self.hud.notify(&format!("New Game+ {}", rules.prestige), 3.0, Severity::Success);
+        if let Err(e) = self.save() {
+            self.hud.notify(&e.to_string(), 4.0, Severity::Danger);
+        }
+    }
+
+    // the first death ends a daily challenge, so does quitting. the score goes on the leaderboard
+    // and anything played after that doesn't count
+    pub fn finish_daily(&mut self) {
+        let Some(entry) = self.daily.as_mut().and_then(|run| run.finish()) else {
+            return;
+        };
+        let path = config::get().save_path(LEADERBOARD_PATH);
+        let result = Leaderboard::load(&path).and_then(|mut leaderboard| {
+            let rank = leaderboard.submit(entry.clone());
+            leaderboard.save(&path).map(|()| rank)
+        });
+        match result {
+            Ok(rank) => {
+                crate::log_info!("daily {} scored {}, #{} that day", entry.date, entry.score, rank);
+                self.hud.notify(&format!("Daily challenge over: {} points, #{} today", entry.score, rank), 6.0, Severity::Success);
+            }
+            Err(e) => {
+                crate::log_error!("{}", e);
+                self.hud.notify(&e.to_string(), 4.0, Severity::Danger);
+            }
+        }
+    }
+
+    // hardcore deaths take the world with them
+    pub fn delete_save(&mut self) {
+        if let Err(e) = WorldSave::delete(&config::get().save_path(WORLD_SAVE_PATH)) {
+            self.hud.notify(&e.to_string(), 4.0, Severity::Danger);
+        }
+    }
+
+    // works in every state, so menus can be captured and profiled too
+    pub fn update_capture(&mut self, rl: &RaylibHandle) {
+        if self.controls.is_pressed(rl, Action::Screenshot) {
+            self.capture.request_screenshot();
+        }
+        if self.controls.is_pressed(rl, Action::DebugOverlay) {
+            self.debug.toggle();
+        }
+        if self.controls.is_pressed(rl, Action::Inspect) {
+            self.debug.inspect(&self.world, self.aim);
+        }
+        self.update_time_controls(rl);
+        if !self.controls.is_pressed(rl, Action::Record) {
+            return;
+        }
+        match self.capture.toggle_recording(&config::get().save_path(SCREENSHOT_DIR)) {
+            Some((dir, frames)) => self.hud.notify(&format!("Saved {} frames to {}", frames, dir), 3.0, Severity::Info),
+            None => {
+                let text = format!("Recording, {} to stop", self.controls.prompt(Action::Record));
+                self.hud.notify(&text, 2.0, Severity::Info);
+            }
+        }
+    }
+
+    // the debug keys for simulated time, see GameClock::toggle_held
+    fn update_time_controls(&mut self, rl: &RaylibHandle) {
+        if self.controls.is_pressed(rl, Action::TimeHold) {
+            let text = if self.clock.toggle_held() {
+                format!("Time held, {} steps one tick", self.controls.prompt(Action::TimeStep))
+            } else {
+                "Time runs again".to_string()
+            };
+            self.hud.notify(&text, 2.0, Severity::Info);
+        }
+        if self.controls.is_pressed(rl, Action::TimeStep) {
+            self.clock.step(simulation::STEP_TIME);
+        }
+        if self.controls.is_pressed(rl, Action::TimeScale) {
+            let scale = self.clock.cycle_time_scale();
+            self.hud.notify(&format!("Time x{}", scale), 1.5, Severity::Info);
+        }
+    }
+
+    // for every state that isn't taking gameplay input
+    pub fn ignore_input(&mut self) {
+        // clicks in the menu shouldn't turn into casts once it closes
+        self.controls.clear_buffered();
+        self.spellbook.cancel_charge();
+    }
+
+    // walking, jumping and falling. with controls off the player still falls and gets knocked around
+    pub fn move_player(&mut self, rl: &RaylibHandle, delta: f32, controls_on: bool) {
+        crate::profile_scope!("move_player");
+        let controls = &mut self.controls;
+        let player = &mut self.player;
+        let mut inputs = Vector2::zero();
+        if controls_on {
+            if controls.is_down(rl, Action::MoveUp) {
+                inputs.y -= 1.0;
+            }
+            if controls.is_down(rl, Action::MoveDown) {
+                inputs.y += 1.0;
+            }
+            // sticks give partial strength, keys are always 1.0
+            inputs.x += controls.strength(rl, Action::MoveRight);
+            inputs.x -= controls.strength(rl, Action::MoveLeft);
+        }
+
+        let vel = &mut self.vel;
+        let floor = rl.get_screen_height() as f32 / scale() as f32;
+        let ground = floor - player.size.y;
+        if player.position.y < ground {
+            let top = self.fall_top.get_or_insert(player.position.y);
+            *top = top.min(player.position.y);
+        }
+        // a jump pressed just before landing still goes off when we touch the ground
+        let movement = player.step(vel, inputs.x, inputs.y < 0.0, || controls_on && controls.consume(Action::Jump), floor, delta);
+        let grounded = movement.grounded;
+        if movement.landed.is_some_and(|speed| speed > LANDING_SOUND_SPEED) {
+            let feet = Vector2::new(player.center().x, player.position.y + player.size.y);
+            self.audio.trigger_at(SoundEvent::Impact, feet);
+            self.effects.request(Effect::Particles { emitter: Emitter::LandingDust, position: feet });
+        }
+        if grounded {
+            if let Some(top) = self.fall_top.take() {
+                self.world.events.publish(GameEvent::Landed { distance: ground - top, survived: player.hp > 0.0 });
+            }
+        }
+        if movement.jumped {
+            self.audio.trigger(SoundEvent::Jump);
+        }
+        self.animation.update(*vel, grounded && !movement.jumped, delta);
+        // only haste leaves a trail, not gear
+        if status::speed_multiplier(&player.status_effects) > 1.0 && vel.x != 0.0 {
+            let color = StatusKind::Haste.color();
+            self.effects.request(Effect::TrailPoint { id: PLAYER_TRAIL, position: player.center(), color, width: 1.5 });
+        }
+        if grounded && vel.y >= 0.0 {
+            self.step_distance += vel.x.abs();
+            if self.step_distance >= STEP_LENGTH {
+                self.step_distance -= STEP_LENGTH;
+                // the ground under the screen edge has no pixels, it sounds like stone
+                let feet = Vector2::new(player.center().x, player.position.y + player.size.y);
+                let world = &self.world;
+                let material = world.pixel_at(feet.x.floor() as i64, feet.y.floor() as i64).map_or(world.materials.named("STONE"), |p| p.material);
+                if let Some(set) = &world.materials.get(material).footsteps {
+                    self.audio.play_random(set, feet);
+                }
+            }
+        } else {
+            self.step_distance = 0.0;
+        }
+    }
+
+    pub fn update_aim(&mut self, rl: &RaylibHandle) {
+        // aim with the undisturbed camera so screen shake doesn't move the target
+        self.aim_screen = self.controls.aim_position(rl, self.aim_screen);
+        self.aim = rl.get_screen_to_world2D(self.aim_screen, self.player.camera) / scale() as f32;
+    }
+
+    // building next to casting, from the held hotbar item
+    pub fn place_pixels(&mut self, rl: &RaylibHandle, delta: f32) {
+        if self.controls.is_pressed(rl, Action::Build) {
+            self.build.toggle();
+            let text = if self.build.active { "Build mode" } else { "Build mode off" };
+            self.hud.notify(text, 1.0, Severity::Info);
+        }
+        let pressed = self.controls.is_pressed(rl, Action::Place);
+        let held = self.controls.is_down(rl, Action::Place);
+        if let Some(reason) = self.build.update(&mut self.world, &mut self.player, self.aim, pressed, held, delta) {
+            self.hud.notify(&reason, 1.5, Severity::Warning);
+        }
+    }
+
+    // spell selection and casting
+    pub fn cast_spells(&mut self, rl: &RaylibHandle, delta: f32) {
+        let controls = &mut self.controls;
+        let spellbook = &mut self.spellbook;
+        if controls.is_pressed(rl, Action::CycleSpellUp) {
+            spellbook.cycle(-1);
+        }
+        if controls.is_pressed(rl, Action::CycleSpellDown) {
+            spellbook.cycle(1);
+        }
+        for (slot, action) in SPELL_SLOTS.iter().enumerate() {
+            if controls.is_pressed(rl, *action) {
+                spellbook.select(slot);
+            }
+        }
+        // casts pressed during the cooldown wait in the buffer until the spell is ready,
+        // holding the button keeps casting as fast as the cast rate allows.
+        // chargeable spells charge while held instead and go off on release
+        let pressed = controls.is_buffered(Action::Cast);
+        let held = pressed || controls.is_down(rl, Action::Cast);
+        let chargeable = spellbook.active_spell().is_some_and(|s| s.is_chargeable());
+        let power = if chargeable && held && spellbook.is_ready() {
+            controls.consume(Action::Cast);
+            spellbook.charge(delta);
+            None
+        } else if chargeable {
+            spellbook.release_charge()
+        } else if held && spellbook.is_ready() {
+            Some(1.0)
+        } else {
+            None
+        };
+        let Some(power) = power else {
+            return;
+        };
+        controls.consume(Action::Cast);
+        let variant = controls.cast_variant(rl);
+        match spellbook.cast(&mut self.world, &mut self.player, &mut self.effects, self.aim, variant, power) {
+            Ok(touched) => {
+                if let Some(spell) = spellbook.active_spell() {
+                    self.world.events.publish(GameEvent::SpellCast {
+                        name: spell.name.clone(),
+                        target: self.aim,
+                        touched,
+                        cost: spell.cost_at(variant, power),
+                        sound: spell.sound.clone(),
+                        touch_sound: spell.on_touch_sound.clone(),
+                    });
+                }
+            }
+            // only complain about the press itself, not every frame it is held
+            Err(e) if pressed || chargeable => self.hud.notify(&e, 1.5, Severity::Warning),
+            Err(_) => {}
+        }
+    }
+
+    // everything that carries on by itself: the world's systems, the player's stats, effects and the hud
+    pub fn update_world(&mut self, rl: &RaylibHandle, delta: f32) {
+        let started = Instant::now();
+        let view = self.view(rl);
+        // the weather's on top of skills and gear. it changes by itself, so it's folded in fresh
+        // every frame
+        self.player.bonuses = self.player.equipment.bonuses(&self.skills);
+        self.weather.modify(&mut self.player.bonuses);
+        let player = &mut self.player;
+        let world = &mut self.world;
+        let effects = &mut self.effects;
+        self.spellbook.update(delta);
+        let simulated = Instant::now();
+        self.simulation.update(world, delta);
+        self.temperature.update(world, player, self.difficulty.hazards(), delta);
+        self.profiler.record("simulation", simulated);
+        self.growth.update(delta);
+        electricity::update(world, player, delta);
+        spell::update_entities(world, player, effects, delta);
+        let physics = Instant::now();
+        ecs::update(world, delta);
+        self.profiler.record("physics", physics);
+        self.debug.update(world, delta);
+        world.lights.update(delta);
+        world.explored.update(player.center(), delta);
+        self.merchant.update(world, player.center(), delta);
+        if let Some(nearby) = self.difficulty.update(world, player.center(), self.experience.level, self.spellbook.spells.len(), delta) {
+            self.director.feed(CombatEvent::EnemiesNearby(nearby));
+        }
+        for collected in item::update_items(world, player, delta) {
+            self.audio.trigger(SoundEvent::Pickup);
+            match collected {
+                Collected::Item(name, count, rarity) => {
+                    let severity = if rarity >= Rarity::Rare { Severity::Success } else { Severity::Info };
+                    self.hud.notify(&format!("+{} {} ({})", count, name, player.inventory.count(&name)), 1.5, severity);
+                }
+                Collected::Full(name) => self.hud.notify(&format!("No room for {}", name), 1.5, Severity::Warning),
+                Collected::Coin(count) => self.hud.notify(&format!("+{} {} ({})", count, CURRENCY, player.coins), 1.5, Severity::Info),
+                Collected::Scroll(spell) => match self.spellbook.learn(&spell) {
+                    Ok(spell) => self.hud.notify(&format!("Learned {}", spell), 3.0, Severity::Success),
+                    Err(e) => self.hud.notify(&e, 1.5, Severity::Info),
+                },
+            }
+        }
+        if let Some(fraction) = self.spellbook.charge_fraction() {
+            effects.request(Effect::ChargeGlow { position: player.center(), fraction, color: Color::new(200, 170, 255, 255) });
+        }
+        simulation::apply_contact_effects(world, player);
+        let light = world.lights.level_at(&world.ecs, player.center());
+        player.endure_darkness(light, delta);
+        // once it's clearly not just a flicker
+        if player.darkness >= DARKNESS_WARNING && player.darkness - delta < DARKNESS_WARNING {
+            self.hud.notify("The darkness saps your strength, find some light", 3.0, Severity::Warning);
+        }
+        self.difficulty.lurk(world, player.center(), player.darkness, delta);
+        player.update_stats(delta);
+        self.death.update(player, &mut world.events, &mut self.audio, delta);
+        let center = player.center();
+        for hit in std::mem::take(&mut player.recent_hits) {
+            world.events.publish(GameEvent::DamageDealt { amount: hit.amount, target: center, source: hit.source, entity: None });
+        }
+        self.camera_fx.update(delta);
+        self.hud.update(player, &self.spellbook, &mut self.audio, delta);
+        self.director.update(delta);
+        if let Some(text) = self.weather.update(world, center, view, effects, delta).and_then(|kind| kind.announcement()) {
+            self.hud.notify(text, 3.0, Severity::Info);
+        }
+        // after the weather, the night darkens whatever sky it left
+        self.sky.update(self.stats.playtime, &mut world.events);
+        world.lights.sky = self.sky.light(self.stats.playtime, world.lights.sky);
+        let season = Season::at(self.stats.playtime);
+        if season != world.season {
+            crate::log_info!("the season turns to {}", season.name());
+            world.set_season(season);
+            self.hud.notify(season.announcement(), 4.0, Severity::Info);
+        }
+        {
+            crate::profile_scope!("effects");
+            effects.update(view, world, delta);
+        }
+        let baked = Instant::now();
+        world.bake_shading();
+        self.profiler.record("bake", baked);
+        #[cfg(feature = "invariants")]
+        crate::invariants::check(world, player, self.simulation.tick());
+        self.queue_chunks(center);
+        self.autosave_timer += delta;
+        if self.autosave_timer >= AUTOSAVE_INTERVAL {
+            self.autosave_timer = 0.0;
+            self.scheduler.push("autosave", |game: &mut Game| {
+                if let Err(e) = game.save() {
+                    game.hud.notify(&e.to_string(), 4.0, Severity::Danger);
+                }
+                Step::Done
+            });
+        }
+        self.profiler.record("update", started);
+    }
+
+    // asks the scheduler for any missing chunks around position
+    fn queue_chunks(&mut self, position: Vector2) {
+        let center = (position.x / 16.0).floor() as i64;
+        for chunk_x in center - STREAM_RADIUS..=center + STREAM_RADIUS {
+            for chunk_y in 0..DEPTH_CHUNKS {
+                if self.world.chunk_at(chunk_x * 16, chunk_y * 16).is_some() || !self.queued_chunks.insert((chunk_x, chunk_y)) {
+                    continue;
+                }
+                self.scheduler.push("chunk", move |game: &mut Game| {
+                    game.queued_chunks.remove(&(chunk_x, chunk_y));
+                    if game.world.chunk_at(chunk_x * 16, chunk_y * 16).is_none() {
+                        let started = Instant::now();
+                        game.world.generate_chunk(chunk_x, chunk_y);
+                        game.profiler.record("worldgen", started);
+                    }
+                    Step::Done
+                });
+            }
+        }
+    }
+
+    // whatever background work fits in this frame's budget
+    pub fn run_background(&mut self) {
+        crate::profile_scope!("background");
+        let mut scheduler = std::mem::take(&mut self.scheduler);
+        scheduler.run(self);
+        // anything the tasks queued while they ran
+        scheduler.append(&mut self.scheduler);
+        self.scheduler = scheduler;
+    }
+
+    // picks up edits to tuning.toml while the game runs
+    fn reload_tuning(&mut self) {
+        match self.tuning.poll() {
+            Some(Ok(())) => {
+                // the leveling curves may have changed
+                self.experience.apply(&mut self.player);
+                crate::log_info!("reloaded {}", self.tuning.path());
+                self.hud.notify(&format!("Reloaded {}", self.tuning.path()), 1.5, Severity::Info);
+            }
+            Some(Err(e)) => {
+                crate::log_error!("{}", e);
+                self.hud.notify(&e.to_string(), 4.0, Severity::Danger);
+            }
+            None => {}
+        }
+    }
+
+    // spends skill points, see the skill tree screen
+    pub fn unlock_skill(&mut self, id: &str) {
+        match self.skills.unlock(id, self.experience.level) {
+            Ok(()) => {
+                self.player.bonuses = self.player.equipment.bonuses(&self.skills);
+                crate::log_info!("unlocked {}", id);
+            }
+            Err(e) => self.hud.notify(&e, 2.0, Severity::Warning),
+        }
+    }
+
+    // uses up one of the held item if it's a consumable. a scroll whose spell can't be found
+    // isn't used up
+    pub fn use_item(&mut self) {
+        if self.animation.is_using() {
+            return;
+        }
+        let Some(name) = self.player.inventory.held().map(|s| s.name.clone()) else {
+            return;
+        };
+        let Some(consumable) = consumables::consumable(&name) else {
+            self.hud.notify(&format!("{} can't be used", name), 1.5, Severity::Warning);
+            return;
+        };
+        match consumable.effect {
+            Use::Status { kind, seconds, magnitude } => self.player.add_status(StatusEffect::new(kind, seconds, magnitude)),
+            Use::Cast(spell) => match self.spellbook.cast_scroll(spell, &mut self.world, &mut self.player, &mut self.effects, self.aim) {
+                Ok((spell, touched)) => self.world.events.publish(GameEvent::SpellCast {
+                    name: spell.name.clone(),
+                    target: self.aim,
+                    touched,
+                    cost: spell.cost_at(CastVariant::Normal, 1.0),
+                    sound: spell.sound.clone(),
+                    touch_sound: spell.on_touch_sound.clone(),
+                }),
+                Err(e) => {
+                    self.hud.notify(&e, 2.0, Severity::Warning);
+                    return;
+                }
+            },
+            Use::Light { color, radius, seconds } => {
+                let center = self.player.center();
+                let velocity = (self.aim - center).normalized() * TORCH_THROW;
+                let light = Light::new(color, radius, 0.9).flickering(0.35);
+                ecs::spawn_torch(&mut self.world.ecs, center, velocity, light, seconds);
+            }
+        }
+        self.player.inventory.remove(&name, 1);
+        self.animation.use_item(consumable.color);
+        self.audio.trigger(SoundEvent::Pickup);
+        crate::log_info!("used {}", name);
+    }
+
+    // puts on the item from the inventory, taking off whatever was in its slot
+    pub fn equip(&mut self, item: &str) {
+        let result = self.player.equipment.equip(item, &mut self.player.inventory);
+        self.refresh_gear(result);
+    }
+
+    pub fn unequip(&mut self, slot: Slot) {
+        let result = self.player.equipment.unequip(slot, &mut self.player.inventory);
+        self.refresh_gear(result);
+    }
+
+    // max mp follows the gear, but swapping gear back and forth mustn't fill it up
+    fn refresh_gear(&mut self, result: Result<(), String>) {
+        if let Err(e) = result {
+            self.hud.notify(&e, 2.0, Severity::Warning);
+            return;
+        }
+        let mp = self.player.mp;
+        self.player.bonuses = self.player.equipment.bonuses(&self.skills);
+        self.experience.apply(&mut self.player);
+        self.player.mp = mp.min(self.player.max_mp);
+    }
+
+    pub fn craft(&mut self, id: &str) {
+        match self.recipes.craft(id, &mut self.player.inventory) {
+            Ok(text) => {
+                crate::log_info!("crafted {}", id);
+                self.hud.notify(&text, 2.0, Severity::Info);
+            }
+            Err(e) => self.hud.notify(&e, 2.0, Severity::Warning),
+        }
+    }
+
+    fn reward_quest(&mut self, quest: &Quest) {
+        for reward in &quest.rewards {
+            match reward {
+                Reward::Spell { name } => {
+                    if let Err(e) = self.spellbook.learn(name) {
+                        crate::log_warn!("quest {} rewards {}: {}", quest.id, name, e);
+                    }
+                }
+                Reward::Item { name, count } => {
+                    // what doesn't fit lands at the player's feet, to be picked up once there's room
+                    let left = self.player.inventory.add(name, *count);
+                    if left > 0 {
+                        let entity = item::spawn_item(&mut self.world.ecs, name, self.player.center(), Vector2::zero(), item::PLAIN_COLOR);
+                        if let Some(pickup) = self.world.ecs.pickups.get_mut(entity) {
+                            pickup.count = left;
+                        }
+                    }
+                }
+                Reward::Boost { hp, mp, sp } => {
+                    let boosts = &mut self.experience.boosts;
+                    boosts.hp += hp;
+                    boosts.mp += mp;
+                    boosts.sp += sp;
+                    self.experience.apply(&mut self.player);
+                }
+                // a level up from this shows up with the others below
+                Reward::Xp { amount } => self.experience.add(*amount),
+            }
+        }
+        let rewards: Vec<_> = quest.rewards.iter().map(|r| r.describe()).collect();
+        let text = if rewards.is_empty() {
+            format!("{} complete", quest.name)
+        } else {
+            format!("{} complete: {}", quest.name, rewards.join(", "))
+        };
+        crate::log_info!("{}", text);
+        self.hud.notify(&text, 4.0, Severity::Success);
+        self.world.events.publish(GameEvent::QuestCompleted { id: quest.id.clone() });
+    }
+
+    // enemies sometimes drop a spell the player doesn't know yet
+    fn drop_scroll(&mut self, position: Vector2) {
+        if self.world.rng.loot.gen::<f32>() >= SCROLL_DROP_CHANCE {
+            return;
+        }
+        let unknown = self.spellbook.unknown();
+        let Some(spell) = unknown.choose(&mut self.world.rng.loot).map(|s| s.to_string()) else {
+            return;
+        };
+        let velocity = Vector2::new(self.world.rng.loot.gen_range(-10.0..10.0), -30.0);
+        item::spawn_scroll(&mut self.world.ecs, &spell, position, velocity);
+    }
+
+    // pointing at an altar that hasn't been used and interacting teaches a spell. every altar
+    // works once, the save remembers which
+    pub fn use_altar(&mut self) {
+        let (x, y) = (self.aim.x.floor() as i64, self.aim.y.floor() as i64);
+        let is_altar = self.world.pixel_at(x, y).is_some_and(|p| Some(p.material) == self.world.materials.id("ALTAR"));
+        let Some(altar) = world::altar_at(self.world.seed, x).filter(|_| is_altar) else {
+            return;
+        };
+        if self.used_altars.contains(&altar) {
+            self.hud.notify("The altar is silent", 1.5, Severity::Info);
+            return;
+        }
+        let unknown = self.spellbook.unknown();
+        let Some(spell) = unknown.choose(&mut self.world.rng.loot).map(|s| s.to_string()) else {
+            self.hud.notify("There is nothing left to learn here", 2.0, Severity::Info);
+            return;
+        };
+        self.used_altars.insert(altar);
+        if let Ok(spell) = self.spellbook.learn(&spell) {
+            self.audio.trigger(SoundEvent::Pickup);
+            self.effects.request(Effect::Particles { emitter: Emitter::SpellCast(Color::new(240, 210, 120, 255)), position: self.aim });
+            self.hud.notify(&format!("The altar teaches you {}", spell), 3.0, Severity::Success);
+        }
+    }
+
+    // pointing at a ruin's chest and interacting opens it, if it isn't locked or the player has
+    // the key. the chest to show, if it opened
+    pub fn open_chest(&mut self) -> Option<i64> {
+        let (x, y) = (self.aim.x.floor() as i64, self.aim.y.floor() as i64);
+        let is_chest = self.world.pixel_at(x, y).is_some_and(|p| Some(p.material) == self.world.materials.id("CHEST"));
+        let id = world::chest_at(self.world.seed, x, y).filter(|_| is_chest)?;
+        let was_locked = self.chests.is_locked(id, self.world.seed);
+        if let Err(reason) = self.chests.open(id, self.world.seed, &self.world.loot, &mut self.player.inventory) {
+            self.hud.notify(&reason, 2.0, Severity::Warning);
+            return None;
+        }
+        if was_locked {
+            self.hud.notify("The key turns", 1.5, Severity::Info);
+        }
+        self.audio.trigger(SoundEvent::Pickup);
+        Some(id)
+    }
+
+    // standing next to the merchant and interacting opens the trading screen
+    pub fn talk_to_merchant(&mut self) -> bool {
+        if !self.merchant.nearby(&self.world, self.player.center()) {
+            return false;
+        }
+        self.audio.trigger(SoundEvent::Pickup);
+        true
+    }
+
+    pub fn buy(&mut self, index: usize) {
+        match self.merchant.buy(index, &mut self.player.coins, &mut self.player.inventory) {
+            Ok(message) => {
+                self.audio.trigger(SoundEvent::Pickup);
+                self.hud.notify(&message, 1.5, Severity::Success);
+            }
+            Err(reason) => self.hud.notify(&reason, 1.5, Severity::Warning),
+        }
+    }
+
+    pub fn sell(&mut self, item: &str) {
+        match self.merchant.sell(item, &mut self.player.coins, &mut self.player.inventory) {
+            Ok(message) => {
+                self.audio.trigger(SoundEvent::Pickup);
+                self.hud.notify(&message, 1.5, Severity::Info);
+            }
+            Err(reason) => self.hud.notify(&reason, 1.5, Severity::Warning),
+        }
+    }
+
+    // moves the stack in the slot between the chest and the inventory, whichever side it's on.
+    // coins go straight into the purse
+    pub fn transfer(&mut self, id: i64, from_chest: bool, slot: usize) {
+        let chest = self.chests.get(id, self.world.seed, &self.world.loot);
+        let coins = chest.contents.slots().get(slot).and_then(|s| s.as_ref()).filter(|s| s.name == CURRENCY).map(|s| s.count);
+        if let Some(count) = coins.filter(|_| from_chest) {
+            chest.contents.remove(CURRENCY, count);
+            self.player.coins += count;
+        } else if from_chest {
+            chest.contents.transfer(slot, &mut self.player.inventory);
+        } else {
+            self.player.inventory.transfer(slot, &mut chest.contents);
+        }
+    }
+
+    // the spell breaks the seal of a chest it's cast at if it's the one the seal wants
+    fn break_seal(&mut self, spell: &str, target: Vector2) {
+        let (x, y) = (target.x.floor() as i64, target.y.floor() as i64);
+        let near = (-SEAL_RANGE..=SEAL_RANGE).flat_map(|dx| (-SEAL_RANGE..=SEAL_RANGE).map(move |dy| (x + dx, y + dy)));
+        let Some(id) = near.filter_map(|(x, y)| world::chest_at(self.world.seed, x, y)).next() else {
+            return;
+        };
+        if self.chests.cast_at(id, self.world.seed, &self.world.loot, spell) {
+            self.effects.request(Effect::Particles { emitter: Emitter::SpellCast(Color::new(240, 210, 120, 255)), position: target });
+            self.hud.notify(&format!("{} breaks the seal", spell), 2.0, Severity::Success);
+        }
+    }
+
+    // the first time the player is in a biome counts as discovering it
+    pub fn discover(&mut self) {
+        let biome = self.world.biome_at(self.player.center()).name();
+        if !self.experience.has_discovered(biome) {
+            self.world.events.publish(GameEvent::Discovered { name: biome.to_string() });
+        }
+    }
+
+    // hands everything published since last time to the systems that react to it
+    pub fn dispatch_events(&mut self) {
+        crate::profile_scope!("events");
+        let events = self.world.events.drain();
+        for event in &events {
+            match event {
+                GameEvent::EntityDied { position, entity: Some(_) } => self.drop_scroll(*position),
+                GameEvent::EntityDied { entity: None, .. } => self.finish_daily(),
+                GameEvent::SpellCast { name, target, .. } => self.break_seal(name, *target),
+                _ => {}
+            }
+        }
+        for event in events {
+            self.hud.on_event(&event);
+            self.audio.on_event(&event);
+            self.effects.on_event(&event);
+            self.director.on_event(&event);
+            self.difficulty.on_event(&event);
+            self.player.mana.on_event(&event);
+            self.clock.on_event(&event);
+            self.camera_fx.on_event(&event);
+            self.animation.on_event(&event);
+            self.stats.on_event(&event);
+            self.experience.on_event(&event);
+            self.quests.on_event(&event);
+            self.achievements.on_event(&event);
+            if let Some(run) = &mut self.daily {
+                run.on_event(&event);
+            }
+            self.session.on_event(&event);
+        }
+        if self.difficulty.take_boss_defeated() {
+            crate::log_info!("the warden is defeated");
+            self.hud.notify("The Warden is defeated! New Game+ is open from the menu", 5.0, Severity::Success);
+        }
+        for quest in self.quests.take_completed() {
+            self.reward_quest(&quest);
+        }
+        let unlocked = self.achievements.take_unlocked();
+        for achievement in &unlocked {
+            crate::log_info!("achievement unlocked: {}", achievement.name);
+            self.hud.notify(&format!("Achievement unlocked: {}", achievement.name), 4.0, Severity::Success);
+        }
+        // written straight away, unlike the world they don't wait for the next save
+        if !unlocked.is_empty() {
+            if let Err(e) = self.achievements.save() {
+                self.hud.notify(&e.to_string(), 4.0, Severity::Danger);
+            }
+        }
+        let levels = self.experience.take_level_ups();
+        if levels > 0 {
+            self.experience.apply(&mut self.player);
+            crate::log_info!("reached level {}", self.experience.level);
+            let text = format!("Level {}! {} for the skill tree", self.experience.level, self.controls.prompt(Action::Skills));
+            self.hud.notify(&text, 3.0, Severity::Info);
+        }
+        for line in logging::take_console_lines() {
+            self.console.print(&line);
+        }
+    }
+
+    // music and ambience keep streaming even while everything else is frozen
+    pub fn update_audio(&mut self, rl: &RaylibHandle, delta: f32) {
+        let biome = self.world.biome_at(self.player.center()).name();
+        let mood = if self.player.in_combat() { "combat" } else { biome };
+        // hear the world from the middle of the screen, in world pixels like everything else
+        let screen_center = Vector2::new(rl.get_screen_width() as f32 / 2.0, rl.get_screen_height() as f32 / 2.0);
+        self.audio.set_listener(rl.get_screen_to_world2D(screen_center, self.player.camera) / scale() as f32);
+        let audio_state = AudioState {
+            mood,
+            ambience: self.weather.ambience().unwrap_or(biome),
+            health: self.player.hp / self.player.max_hp,
+            intensity: self.director.intensity(),
+        };
+        self.audio.update(delta, &audio_state);
+    }
+
+    // the part of the world on screen, in world pixels
+    pub fn view(&self, rl: &RaylibHandle) -> Rectangle {
+        let view_start = rl.get_screen_to_world2D(Vector2::zero(), self.player.camera) / scale() as f32;
+        let view_size = Vector2::new(rl.get_screen_width() as f32, rl.get_screen_height() as f32) / scale() as f32;
+        Rectangle::new(view_start.x, view_start.y, view_size.x, view_size.y)
+    }
+
+    // the world, the player and the hud. menus and overlays go on top of this
+    // the scene goes into the frame texture and from there onto the screen, with the overlays
+    // drawn over it. captures read the texture back, or the whole screen to include the overlays
+    pub fn draw(&mut self, d: &mut RaylibDrawHandle, thread: &RaylibThread, show_reticle: bool) {
+        let started = Instant::now();
+        let (screen_w, screen_h) = (d.get_screen_width(), d.get_screen_height());
+        self.resize_frame(d, thread, screen_w, screen_h);
+        // its own texture, it can't be drawn while the frame's is being drawn into
+        let view = self.view(d);
+        let lit = self.world.lights.lit(&self.world.ecs);
+        self.light_map.render(d, thread, self.camera_fx.apply(self.player.camera), view, self.world.lights.sky, &lit);
+        let mut frame = self.frame.take();
+        match &mut frame {
+            Some(frame) => {
+                self.draw_scene(&mut d.begin_texture_mode(thread, frame), view, show_reticle, screen_w, screen_h);
+                // render textures come out upside down
+                let source = Rectangle::new(0.0, 0.0, screen_w as f32, -screen_h as f32);
+                d.draw_texture_rec(frame.texture(), source, Vector2::zero(), Color::WHITE);
+            }
+            None => self.draw_scene(d, view, show_reticle, screen_w, screen_h),
+        }
+        self.draw_overlays(d);
+        if self.capture.wants_frame() {
+            let image = match &frame {
+                Some(frame) if !config::get().capture_overlays => frame.texture().load_image().map(|mut image| {
+                    image.flip_vertical();
+                    image
+                }),
+                _ => Ok(d.load_image_from_screen(thread)),
+            };
+            let saved = image
+                .map_err(|e| SpellcoderError::invalid(SCREENSHOT_DIR, e))
+                .and_then(|image| self.capture.save(&image, &config::get().save_path(SCREENSHOT_DIR)));
+            match saved {
+                Ok(Some(message)) => self.hud.notify(&message, 3.0, Severity::Info),
+                Ok(None) => {}
+                Err(e) => self.hud.notify(&e.to_string(), 4.0, Severity::Danger),
+            }
+        }
+        self.frame = frame;
+        self.profiler.record("draw", started);
+    }
+
+    // a new frame texture whenever the window changes size
+    fn resize_frame(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, width: i32, height: i32) {
+        let Some(frame) = &self.frame else {
+            return;
+        };
+        if frame.texture.width == width && frame.texture.height == height {
+            return;
+        }
+        self.frame = match rl.load_render_texture(thread, width as u32, height as u32) {
+            Ok(frame) => Some(frame),
+            Err(e) => {
+                crate::log_error!("could not resize the frame texture, drawing straight to the screen: {}", e);
+                None
+            }
+        };
+    }
+
+    // what the frame and the light map take on the gpu, 4 bytes a pixel
+    fn texture_bytes(&self) -> usize {
+        let frame = self.frame.as_ref().map_or(0, |f| f.texture.width as usize * f.texture.height as usize * 4);
+        frame + self.light_map.bytes()
+    }
+
+    // the world and everything in it, what a capture without overlays shows
+    fn draw_scene(&self, d: &mut (impl RaylibDraw + TextWidth), view: Rectangle, show_reticle: bool, screen_w: i32, screen_h: i32) {
+        crate::profile_scope!("draw_scene");
+        let time = self.clock.time();
+        d.clear_background(Color::BLACK);
+        let camera = self.camera_fx.apply(self.player.camera);
+        {
+            let mut d2d = d.begin_mode2D(camera);
+            d2d.draw_world(&self.world, time);
+            ecs::draw(&mut d2d, &self.world.ecs);
+            if self.death.player_visible() {
+                d2d.draw_player(&self.player, &self.animation);
+            }
+            // whatever's in the unexplored dark stays hidden in it
+            exploration::draw_fog(&mut d2d, &self.world.explored, view);
+        }
+        // everything above is lit, what glows by itself goes on after
+        self.light_map.apply(d);
+        let mut d2d = d.begin_mode2D(camera);
+        bloom::draw(&mut d2d, &self.world, view, &self.world.lights.lit(&self.world.ecs), config::get().bloom);
+        electricity::draw(&mut d2d, &self.world, time);
+        self.death.draw_soul(&mut d2d, time);
+        self.effects.draw(&mut d2d);
+        if show_reticle {
+            if self.build.active {
+                let valid = self.build.check(&self.world, &self.player, self.aim).is_ok();
+                d2d.draw_build_preview(self.aim, self.player.center(), build::REACH, valid);
+            }
+            d2d.draw_reticle(self.aim);
+        }
+        drop(d2d);
+        self.effects.draw_screen(d, screen_w, screen_h);
+        self.death.draw_screen(d, screen_w, screen_h);
+    }
+
+    // the hud and debug text on top of the scene
+    fn draw_overlays(&self, d: &mut RaylibDrawHandle) {
+        crate::profile_scope!("draw_overlays");
+        {
+            // the same floor as in move_player
+            let floor = d.get_screen_height() as f32 / scale() as f32;
+            let view = self.view(d);
+            let mut d2d = d.begin_mode2D(self.camera_fx.apply(self.player.camera));
+            self.debug.draw_views(&mut d2d, &self.world, &self.player, view, floor);
+        }
+        if config::get().show_fps {
+            d.draw_fps(10, 10);
+        }
+        let position = format!("{}, {}", self.player.position.x, self.player.position.y);
+        d.draw_hud_text(&position, 10, 30, 20, Color::new(0, 179, 0, 255));
+        // so nobody forgets they left the debug time controls on
+        if self.clock.is_held() {
+            d.draw_hud_text("time held", 200, 10, 10, Color::new(255, 200, 80, 255));
+        } else if self.clock.time_scale() != 1.0 {
+            d.draw_hud_text(&format!("time x{}", self.clock.time_scale()), 200, 10, 10, Color::new(255, 200, 80, 255));
+        }
+        d.draw_hud(&self.hud, &self.player);
+        d.draw_experience(&self.experience);
+        if self.debug.open {
+            let memory = Memory::measure(&self.world, &self.effects.particles, self.texture_bytes());
+            self.debug.draw(d, &self.profiler, &self.world, &memory, self.scheduler.pending());
+        } else {
+            d.draw_quests(&self.quests);
+        }
+        d.draw_hotbar(&self.player.inventory);
+        exploration::draw_minimap(d, &self.world, self.player.center());
+        self.debug.draw_inspector(d, &self.world);
+        if let Some(run) = &self.daily {
+            d.draw_daily(run);
+        }
+        d.draw_active_spell(&self.spellbook, self.controls.cast_variant(d), &self.player.bonuses);
+        if self.controls.touch.active {
+            self.controls.touch.draw(d);
+        } else {
+            d.draw_prompts(&self.controls);
+        }
+    }
+}
+
+// every New Game+ gets a world of its own, the same one for the same seed and prestige
+fn prestige_seed(seed: u64, prestige: u32) -> u64 {
+    (seed ^ (prestige as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)).rotate_left(17)
+}
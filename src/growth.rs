@@ -0,0 +1,89 @@
+use rand::Rng;
+
+use crate::material::{MaterialId, AIR};
+use crate::World;
+
+// growth is meant to be noticed over minutes, not frames
+const TICK_TIME: f32 = 1.0;
+// chunks visited each tick, taking turns so the cost stays flat however many are loaded
+const CHUNKS_PER_TICK: usize = 2;
+// passes that can pile up while the background budget is busy with other things
+const MAX_PENDING: usize = 16;
+const NEIGHBOURS: [(i64, i64); 8] = [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)];
+
+// slowly spreads the materials with a growth rule, grass over bare dirt, crystal near mana ore.
+// nobody notices a pass landing a few frames late, so they run as background work, see Game::new
+pub struct Growth {
+    timer: f32,
+    next_chunk: usize,
+    pending: usize,
+}
+
+impl Growth {
+    pub fn new() -> Self {
+        Growth { timer: 0.0, next_chunk: 0, pending: 0 }
+    }
+
+    // counts up the chunk passes that are due, step runs them
+    pub fn update(&mut self, delta: f32) {
+        self.timer += delta;
+        while self.timer >= TICK_TIME {
+            self.timer -= TICK_TIME;
+            self.pending = (self.pending + CHUNKS_PER_TICK).min(MAX_PENDING);
+        }
+    }
+
+    // one chunk's pass, if any are due
+    pub fn step(&mut self, world: &mut World) {
+        if self.pending == 0 || world.chunks.is_empty() {
+            return;
+        }
+        self.pending -= 1;
+        self.next_chunk = (self.next_chunk + 1) % world.chunks.len();
+        grow_chunk(world, self.next_chunk);
+    }
+}
+
+fn grow_chunk(world: &mut World, index: usize) {
+    let mut rng = world.rng.take();
+    let chunk = &world.chunks[index];
+    let mut grown = Vec::new();
+    for pixel in chunk.pixels.iter().flatten() {
+        let material = world.materials.get(pixel.material);
+        let (Some(rule), Some((into, near))) = (&material.growth, material.resolved_growth) else {
+            continue;
+        };
+        if !rng.gen_bool(rule.chance.clamp(0.0, 1.0) as f64) {
+            continue;
+        }
+        let (dx, dy) = NEIGHBOURS[rng.gen_range(0..NEIGHBOURS.len())];
+        let (x, y) = (chunk.x + pixel.x as i64 + dx, chunk.y + pixel.y as i64 + dy);
+        if material_at(world, x, y) != Some(into) {
+            continue;
+        }
+        if rule.exposed && material_at(world, x, y - 1).map_or(true, |above| world.materials.get(above).is_solid()) {
+            continue;
+        }
+        if let Some(near) = near {
+            if !is_near(world, x, y, near, rule.radius) {
+                continue;
+            }
+        }
+        grown.push((x, y, pixel.material));
+    }
+    world.rng.restore(rng);
+    for (x, y, material) in grown {
+        let color = world.color_at(material, x, y);
+        world.set_pixel(x, y, material, color);
+    }
+}
+
+// None for unloaded space, AIR for empty space
+fn material_at(world: &World, x: i64, y: i64) -> Option<MaterialId> {
+    world.chunk_at(x, y)?;
+    Some(world.pixel_at(x, y).map_or(AIR, |p| p.material))
+}
+
+fn is_near(world: &World, x: i64, y: i64, material: MaterialId, radius: i64) -> bool {
+    (-radius..=radius).any(|dy| (-radius..=radius).any(|dx| material_at(world, x + dx, y + dy) == Some(material)))
+}
@@ -0,0 +1,430 @@
+use raylib::prelude::*;
+
+use crate::audio::{AudioManager, UiSound};
+use crate::daily::DailyRun;
+use crate::events::{GameEvent, Subscriber};
+use crate::experience::Experience;
+use crate::input::{Action, InputMap};
+use crate::inventory::Inventory;
+use crate::quest::QuestLog;
+use crate::skills::Bonuses;
+use crate::spell::{CastVariant, Spellbook};
+use crate::tuning;
+use crate::{Player, TextWidth};
+
+const ICON_SIZE: i32 = 20;
+// how much color the mana bar loses while exhausted, 1 is grey
+const EXHAUSTED_DESATURATION: f32 = 0.8;
+// screen pixels, the hotbar's slots along the bottom middle
+const HOTBAR_SLOT: i32 = 22;
+const HOTBAR_GAP: i32 = 3;
+// quests listed on screen at once
+const MAX_QUESTS_SHOWN: usize = 3;
+const ICON_SPACING: i32 = 4;
+const MAX_TOASTS: usize = 5;
+const TOAST_FADE: f32 = 0.5;
+const INDICATOR_TIME: f32 = 1.2;
+const INDICATOR_RADIUS: f32 = 90.0;
+const BAR_TICK: f32 = 25.0;
+const GHOST_DELAY: f32 = 0.5;
+const GHOST_DRAIN: f32 = 0.6; // fraction of the max drained per second
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Info,
+    Success,
+    Warning,
+    Danger,
+}
+
+struct Toast {
+    text: String,
+    severity: Severity,
+    remaining: f32,
+}
+
+// the fill follows the stat, the ghost lingers behind it after losses to show how much was taken
+pub struct StatBar {
+    fill: f32,
+    ghost: f32,
+    ghost_delay: f32,
+}
+
+struct DamageIndicator {
+    direction: Vector2,
+    remaining: f32,
+}
+
+pub struct Hud {
+    hp_bar: StatBar,
+    mp_bar: StatBar,
+    sp_bar: StatBar,
+    toasts: Vec<Toast>,
+    damage_indicators: Vec<DamageIndicator>,
+    low_health_warned: bool,
+    shown_spell: Option<String>, // name of the active spell on screen, to notice switches
+}
+
+pub trait HudDraw {
+    fn draw_hud(&mut self, hud: &Hud, player: &Player);
+    fn draw_bar(&mut self, y: i32, bar: &StatBar, value: f32, max: f32, color: Color);
+    fn draw_status_icons(&mut self, x: i32, y: i32, player: &Player);
+    fn draw_hud_text(&mut self, text: &str, x: i32, y: i32, size: i32, color: Color);
+    fn draw_toasts(&mut self, hud: &Hud);
+    fn draw_damage_indicators(&mut self, hud: &Hud);
+    fn draw_prompts(&mut self, controls: &InputMap);
+    fn draw_active_spell(&mut self, spellbook: &Spellbook, variant: CastVariant, bonuses: &Bonuses);
+    fn draw_experience(&mut self, experience: &Experience);
+    fn draw_quests(&mut self, quests: &QuestLog);
+    fn draw_daily(&mut self, run: &DailyRun);
+    fn draw_hotbar(&mut self, inventory: &Inventory);
+}
+
+impl StatBar {
+    fn new(value: f32) -> Self {
+        StatBar {
+            fill: value,
+            ghost: value,
+            ghost_delay: 0.0,
+        }
+    }
+
+    fn update(&mut self, value: f32, max: f32, delta: f32) {
+        if value < self.fill {
+            // losses show up immediately, the ghost keeps the old value for a moment. only when the
+            // drain starts, a stat ticking down every frame would otherwise hold the ghost forever
+            if self.ghost_delay <= 0.0 && self.ghost <= self.fill {
+                self.ghost_delay = GHOST_DELAY;
+            }
+            self.fill = value;
+        } else {
+            self.fill += (value - self.fill) * (8.0 * delta).min(1.0);
+        }
+        if self.ghost_delay > 0.0 {
+            self.ghost_delay -= delta;
+        } else {
+            self.ghost = (self.ghost - max * GHOST_DRAIN * delta).max(self.fill);
+        }
+        self.ghost = self.ghost.max(self.fill);
+    }
+}
+
+impl Severity {
+    fn color(&self) -> Color {
+        match self {
+            Severity::Info => Color::new(220, 220, 220, 255),
+            Severity::Success => Color::new(90, 220, 110, 255),
+            Severity::Warning => Color::new(240, 200, 60, 255),
+            Severity::Danger => Color::new(240, 70, 60, 255),
+        }
+    }
+}
+
+impl Hud {
+    pub fn new(player: &Player) -> Self {
+        Hud {
+            hp_bar: StatBar::new(player.hp),
+            mp_bar: StatBar::new(player.mp),
+            sp_bar: StatBar::new(player.sp),
+            toasts: Vec::new(),
+            damage_indicators: Vec::new(),
+            low_health_warned: false,
+            shown_spell: None,
+        }
+    }
+
+    pub fn notify(&mut self, text: &str, duration: f32, severity: Severity) {
+        if self.toasts.len() >= MAX_TOASTS {
+            self.toasts.remove(0);
+        }
+        self.toasts.push(Toast {
+            text: text.to_string(),
+            severity,
+            remaining: duration,
+        });
+    }
+
+    // direction is from the player towards whatever hurt them
+    pub fn add_damage_indicator(&mut self, direction: Vector2) {
+        if direction.length() <= 0.0 {
+            return;
+        }
+        self.damage_indicators.push(DamageIndicator {
+            direction: direction.normalized(),
+            remaining: INDICATOR_TIME,
+        });
+    }
+
+    pub fn update(&mut self, player: &Player, spellbook: &Spellbook, audio: &mut AudioManager, delta: f32) {
+        self.hp_bar.update(player.hp, player.max_hp, delta);
+        self.mp_bar.update(player.mp, player.max_mp, delta);
+        self.sp_bar.update(player.sp, player.max_sp, delta);
+
+        for toast in &mut self.toasts {
+            toast.remaining -= delta;
+        }
+        self.toasts.retain(|t| t.remaining > 0.0);
+        for indicator in &mut self.damage_indicators {
+            indicator.remaining -= delta;
+        }
+        self.damage_indicators.retain(|i| i.remaining > 0.0);
+
+        if player.hp < player.max_hp * 0.25 {
+            if !self.low_health_warned {
+                self.notify("Low health!", 3.0, Severity::Danger);
+                self.low_health_warned = true;
+            }
+        } else {
+            self.low_health_warned = false;
+        }
+
+        let active = spellbook.active_spell().map(|s| s.name.clone());
+        if active != self.shown_spell {
+            // learning the first spell isn't a switch
+            if self.shown_spell.is_some() {
+                audio.play_ui(UiSound::SpellSwitch);
+            }
+            self.shown_spell = active;
+        }
+    }
+}
+
+impl Subscriber for Hud {
+    fn on_event(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::DamageDealt { target, source: Some(source), entity: None, .. } => self.add_damage_indicator(*source - *target),
+            GameEvent::Nightfall { event: Some(night), .. } => self.notify(night.announcement(), 4.0, Severity::Warning),
+            _ => {}
+        }
+    }
+}
+
+impl HudDraw for RaylibDrawHandle<'_> {
+    fn draw_hud(&mut self, hud: &Hud, player: &Player) {
+        // bar positions come from tuning.toml and can change while running
+        let layout = tuning::get().hud.clone();
+        let top = self.get_screen_height() - 3 * layout.bar_spacing - layout.bar_bottom;
+        self.draw_bar(top, &hud.hp_bar, player.hp, player.max_hp, Color::new(200, 30, 30, 255));
+        // exhausted mana is drained of color until it has come back
+        let mp_color = Color::new(40, 80, 220, 255);
+        let mp_color = if player.mana.exhausted { desaturate(mp_color, EXHAUSTED_DESATURATION) } else { mp_color };
+        self.draw_bar(top + layout.bar_spacing, &hud.mp_bar, player.mp, player.max_mp, mp_color);
+        self.draw_bar(top + 2 * layout.bar_spacing, &hud.sp_bar, player.sp, player.max_sp, Color::new(40, 180, 60, 255));
+        self.draw_status_icons(layout.bar_x + layout.bar_width + 8, top - (ICON_SIZE - layout.bar_height) / 2, player);
+        self.draw_toasts(hud);
+        self.draw_damage_indicators(hud);
+    }
+
+    fn draw_bar(&mut self, y: i32, bar: &StatBar, value: f32, max: f32, color: Color) {
+        if max <= 0.0 {
+            return;
+        }
+        let layout = tuning::get().hud.clone();
+        let width = |v: f32| (layout.bar_width as f32 * (v / max).clamp(0.0, 1.0)) as i32;
+        self.draw_rectangle(layout.bar_x, y, layout.bar_width, layout.bar_height, Color::new(20, 20, 20, 200));
+        self.draw_rectangle(layout.bar_x, y, width(bar.ghost), layout.bar_height, Color::new(160, 20, 20, 220));
+        self.draw_rectangle(layout.bar_x, y, width(bar.fill), layout.bar_height, color);
+        let mut tick = BAR_TICK;
+        while tick < max {
+            let x = layout.bar_x + width(tick);
+            self.draw_line(x, y, x, y + layout.bar_height / 3, Color::new(0, 0, 0, 180));
+            tick += BAR_TICK;
+        }
+        self.draw_rectangle_lines(layout.bar_x, y, layout.bar_width, layout.bar_height, Color::new(0, 0, 0, 255));
+        let text = format!("{}/{}", value.ceil() as i32, max as i32);
+        self.draw_hud_text(&text, layout.bar_x + (layout.bar_width - self.text_width(&text, 10)) / 2, y + 1, 10, Color::WHITE);
+    }
+
+    fn draw_status_icons(&mut self, x: i32, y: i32, player: &Player) {
+        let mouse = self.get_mouse_position();
+        let mut hovered = None;
+        for (i, effect) in player.status_effects.iter().enumerate() {
+            let ix = x + i as i32 * (ICON_SIZE + ICON_SPACING);
+            let border = if effect.kind.is_debuff() {
+                Color::new(230, 40, 40, 255)
+            } else {
+                Color::new(230, 230, 230, 255)
+            };
+            self.draw_rectangle(ix, y, ICON_SIZE, ICON_SIZE, effect.kind.color());
+            let glyph = effect.kind.glyph();
+            self.draw_text(glyph, ix + (ICON_SIZE - self.text_width(glyph, 10)) / 2, y + (ICON_SIZE - 10) / 2, 10, Color::WHITE);
+            // darken the elapsed part of the duration like a clock
+            let elapsed = 1.0 - effect.fraction_left();
+            if elapsed > 0.0 {
+                let center = Vector2::new((ix + ICON_SIZE / 2) as f32, (y + ICON_SIZE / 2) as f32);
+                let mut s = self.begin_scissor_mode(ix, y, ICON_SIZE, ICON_SIZE);
+                s.draw_circle_sector(center, ICON_SIZE as f32, -90.0, -90.0 + 360.0 * elapsed, 24, Color::new(0, 0, 0, 140));
+            }
+            self.draw_rectangle_lines(ix, y, ICON_SIZE, ICON_SIZE, border);
+            if Rectangle::new(ix as f32, y as f32, ICON_SIZE as f32, ICON_SIZE as f32).check_collision_point_rec(mouse) {
+                hovered = Some(effect);
+            }
+        }
+
+        // tooltip goes last so it is drawn on top of the other icons
+        if let Some(effect) = hovered {
+            let title = format!("{} ({:.1}s)", effect.kind.name(), effect.remaining.max(0.0));
+            let desc = effect.kind.description();
+            let width = self.text_width(&title, 10).max(self.text_width(desc, 10)) + 8;
+            let tx = (mouse.x as i32 + 12).min(self.get_screen_width() - width);
+            let ty = mouse.y as i32 - 30;
+            self.draw_rectangle(tx, ty, width, 28, Color::new(10, 10, 10, 220));
+            self.draw_rectangle_lines(tx, ty, width, 28, effect.kind.color());
+            self.draw_text(&title, tx + 4, ty + 4, 10, Color::WHITE);
+            self.draw_text(desc, tx + 4, ty + 16, 10, Color::LIGHTGRAY);
+        }
+    }
+
+    fn draw_hud_text(&mut self, text: &str, x: i32, y: i32, size: i32, color: Color) {
+        let font = self.get_font_default();
+        draw_text_outlined(self, &font, text, Vector2::new(x as f32, y as f32), size as f32, color);
+    }
+
+    // newest toast on top, older ones pushed down and fading out
+    fn draw_toasts(&mut self, hud: &Hud) {
+        let right = self.get_screen_width() - 10;
+        let mut y = 10;
+        for toast in hud.toasts.iter().rev() {
+            let alpha = (toast.remaining / TOAST_FADE).clamp(0.0, 1.0);
+            let width = self.text_width(&toast.text, 10) + 12;
+            let color = toast.severity.color();
+            self.draw_rectangle(right - width, y, width, 20, Color::new(10, 10, 10, 200).fade(alpha * 0.8));
+            self.draw_rectangle(right - width, y, 3, 20, color.fade(alpha));
+            self.draw_hud_text(&toast.text, right - width + 7, y + 5, 10, color.fade(alpha));
+            y += 24;
+        }
+    }
+
+    fn draw_damage_indicators(&mut self, hud: &Hud) {
+        let center = Vector2::new(self.get_screen_width() as f32 / 2.0, self.get_screen_height() as f32 / 2.0);
+        for indicator in &hud.damage_indicators {
+            let alpha = (indicator.remaining / INDICATOR_TIME).clamp(0.0, 1.0);
+            let dir = indicator.direction;
+            let side = Vector2::new(-dir.y, dir.x);
+            let tip = center + dir * (INDICATOR_RADIUS + 18.0);
+            let base = center + dir * INDICATOR_RADIUS;
+            draw_triangle_any(self, tip, base + side * 14.0, base - side * 14.0, Color::new(220, 20, 20, 255).fade(alpha));
+        }
+    }
+
+    // switches between keyboard keys and pad glyphs depending on what was touched last
+    fn draw_prompts(&mut self, controls: &InputMap) {
+        let text = [(Action::Jump, "Jump"), (Action::Cast, "Cast"), (Action::Menu, "Menu")]
+            .iter()
+            .map(|(action, label)| format!("{} {}", controls.prompt(*action), label))
+            .collect::<Vec<_>>()
+            .join("  ");
+        let x = self.get_screen_width() - self.text_width(&text, 10) - 10;
+        let y = self.get_screen_height() - 20;
+        self.draw_hud_text(&text, x, y, 10, Color::LIGHTGRAY);
+    }
+
+    fn draw_active_spell(&mut self, spellbook: &Spellbook, variant: CastVariant, bonuses: &Bonuses) {
+        let layout = tuning::get().hud.clone();
+        let y = self.get_screen_height() - 3 * layout.bar_spacing - layout.bar_bottom - 24;
+        let text = match spellbook.active_spell() {
+            Some(spell) if spell.has_variant(variant) => {
+                format!("{} [{}] ({:.0} MP)", spell.name, variant.name(), spell.cost_with(variant, 1.0, bonuses))
+            }
+            Some(spell) => format!("{} ({:.0} MP)", spell.name, spell.cost_with(variant, 1.0, bonuses)),
+            None => "no spells".to_string(),
+        };
+        self.draw_hud_text(&text, layout.bar_x, y, 20, Color::new(200, 180, 255, 255));
+        if let Some(fraction) = spellbook.charge_fraction() {
+            // sits in the gap between the spell name and the stat bars
+            let bar_y = y + 22;
+            let color = if fraction >= 1.0 { Color::new(255, 240, 200, 255) } else { Color::new(180, 140, 255, 255) };
+            self.draw_rectangle(layout.bar_x, bar_y, layout.bar_width, 6, Color::new(20, 20, 20, 200));
+            self.draw_rectangle(layout.bar_x, bar_y, (layout.bar_width as f32 * fraction) as i32, 6, color);
+            self.draw_rectangle_lines(layout.bar_x, bar_y, layout.bar_width, 6, Color::new(0, 0, 0, 255));
+        }
+    }
+
+    // the level next to the stat bars and a thin xp bar under them
+    fn draw_experience(&mut self, experience: &Experience) {
+        let layout = tuning::get().hud.clone();
+        let y = self.get_screen_height() - layout.bar_bottom + 2;
+        let fraction = (experience.xp as f32 / experience.to_next() as f32).clamp(0.0, 1.0);
+        self.draw_rectangle(layout.bar_x, y, layout.bar_width, 4, Color::new(20, 20, 20, 200));
+        self.draw_rectangle(layout.bar_x, y, (layout.bar_width as f32 * fraction) as i32, 4, Color::new(230, 200, 60, 255));
+        self.draw_rectangle_lines(layout.bar_x, y, layout.bar_width, 4, Color::new(0, 0, 0, 255));
+        let text = format!("Lv {}", experience.level);
+        let x = layout.bar_x + layout.bar_width + 8;
+        self.draw_hud_text(&text, x, y - layout.bar_height, 10, Color::new(230, 200, 60, 255));
+    }
+
+    // under the position readout, the first few quests being worked on
+    fn draw_quests(&mut self, quests: &QuestLog) {
+        let active = quests.active();
+        if active.is_empty() {
+            return;
+        }
+        let mut y = 60;
+        self.draw_hud_text("Quests", 10, y, 10, Color::new(230, 200, 60, 255));
+        for (quest, count) in active.iter().take(MAX_QUESTS_SHOWN) {
+            y += 14;
+            let target = quest.objective.target();
+            let text = if target > 1 { format!("{} {}/{}", quest.name, count, target) } else { quest.name.clone() };
+            self.draw_hud_text(&text, 14, y, 10, Color::WHITE);
+        }
+        if active.len() > MAX_QUESTS_SHOWN {
+            self.draw_hud_text(&format!("and {} more", active.len() - MAX_QUESTS_SHOWN), 14, y + 14, 10, Color::LIGHTGRAY);
+        }
+    }
+
+    // bottom middle, the first letters of each item and how many, the selected slot outlined
+    fn draw_hotbar(&mut self, inventory: &Inventory) {
+        let slots = inventory.hotbar();
+        let width = slots.len() as i32 * (HOTBAR_SLOT + HOTBAR_GAP) - HOTBAR_GAP;
+        let left = (self.get_screen_width() - width) / 2;
+        let y = self.get_screen_height() - HOTBAR_SLOT - 8;
+        for (i, slot) in slots.iter().enumerate() {
+            let x = left + i as i32 * (HOTBAR_SLOT + HOTBAR_GAP);
+            self.draw_rectangle(x, y, HOTBAR_SLOT, HOTBAR_SLOT, Color::new(20, 20, 20, 180));
+            let outline = if i == inventory.selected { Color::new(230, 200, 60, 255) } else { Color::new(0, 0, 0, 255) };
+            self.draw_rectangle_lines(x, y, HOTBAR_SLOT, HOTBAR_SLOT, outline);
+            if let Some(stack) = slot {
+                let short: String = stack.name.chars().take(3).collect();
+                self.draw_hud_text(&short, x + 3, y + 2, 10, Color::WHITE);
+                let count = stack.count.to_string();
+                self.draw_hud_text(&count, x + HOTBAR_SLOT - 3 - self.text_width(&count, 10), y + HOTBAR_SLOT - 11, 10, Color::LIGHTGRAY);
+            }
+        }
+    }
+
+    // top middle, the score so far and what it's made of
+    fn draw_daily(&mut self, run: &DailyRun) {
+        let title = if run.finished { format!("Daily {} - over", run.date) } else { format!("Daily {}", run.date) };
+        let score = format!("{} points  depth {:.0}  kills {}  {:.0}s", run.score(), run.depth, run.kills, run.time);
+        let center = self.get_screen_width() / 2;
+        self.draw_hud_text(&title, center - self.text_width(&title, 10) / 2, 10, 10, Color::new(230, 200, 60, 255));
+        self.draw_hud_text(&score, center - self.text_width(&score, 10) / 2, 24, 10, Color::WHITE);
+    }
+}
+
+// towards the color's own grey by amount
+fn desaturate(color: Color, amount: f32) -> Color {
+    let grey = color.r as f32 * 0.3 + color.g as f32 * 0.59 + color.b as f32 * 0.11;
+    let mix = |c: u8| (c as f32 + (grey - c as f32) * amount) as u8;
+    Color::new(mix(color.r), mix(color.g), mix(color.b), color.a)
+}
+
+// text with a dark outline so it stays readable over bright terrain
+fn draw_text_outlined(d: &mut impl RaylibDraw, font: impl AsRef<ffi::Font> + Copy, text: &str, pos: Vector2, size: f32, color: Color) {
+    let spacing = size / 10.0;
+    let outline = Color::new(0, 0, 0, color.a);
+    for (dx, dy) in [(-1.0, -1.0), (0.0, -1.0), (1.0, -1.0), (-1.0, 0.0), (1.0, 0.0), (-1.0, 1.0), (0.0, 1.0), (1.0, 1.0)] {
+        d.draw_text_ex(font, text, pos + Vector2::new(dx, dy), size, spacing, outline);
+    }
+    d.draw_text_ex(font, text, pos, size, spacing, color);
+}
+
+// raylib only fills triangles with counter-clockwise winding, so sort the vertices first
+fn draw_triangle_any(d: &mut impl RaylibDraw, a: Vector2, b: Vector2, c: Vector2, color: Color) {
+    let cross = (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x);
+    if cross > 0.0 {
+        d.draw_triangle(a, c, b, color);
+    } else {
+        d.draw_triangle(a, b, c, color);
+    }
+}
@@ -0,0 +1,715 @@
+use std::collections::HashMap;
+use std::fs;
+
+use raylib::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::error::SpellcoderError;
+use crate::spell::CastVariant;
+use crate::touch::TouchControls;
+
+pub const CONTROLS_PATH: &str = "controls.json";
+// what Export writes and Import reads in the settings, to hand a layout to someone else
+pub const PROFILE_EXPORT_PATH: &str = "controls_export.json";
+// the layouts to start from, the first is the default. each keeps its own rebinds
+pub const PROFILES: [&str; 3] = ["QWERTY", "AZERTY", "Lefty"];
+pub const GAMEPAD: i32 = 0;
+const STICK_DEADZONE: f32 = 0.25;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+    Jump,
+    Cast,
+    ShiftVariant,
+    CtrlVariant,
+    CycleSpellUp,
+    CycleSpellDown,
+    SelectSpell1,
+    SelectSpell2,
+    SelectSpell3,
+    SelectSpell4,
+    SelectSpell5,
+    SelectSpell6,
+    SelectSpell7,
+    SelectSpell8,
+    SelectSpell9,
+    Menu,
+    Console,
+    Editor,
+    Screenshot,
+    Record,
+    // the frame timings and memory overlay
+    DebugOverlay,
+    // picks the pixel or entity under the cursor for the debug inspector
+    Inspect,
+    // debug time controls: stop simulated time, step it one simulation tick, go slower or faster
+    TimeHold,
+    TimeStep,
+    TimeScale,
+    Skills,
+    Interact,
+    Inventory,
+    // the next hotbar slot
+    Hotbar,
+    Build,
+    // puts down a pixel of the held item while building
+    Place,
+    Craft,
+    // the held consumable, see consumables.rs
+    UseItem,
+}
+
+pub const ACTIONS: [Action; 37] = [
+    Action::MoveLeft,
+    Action::MoveRight,
+    Action::MoveUp,
+    Action::MoveDown,
+    Action::Jump,
+    Action::Cast,
+    Action::ShiftVariant,
+    Action::CtrlVariant,
+    Action::CycleSpellUp,
+    Action::CycleSpellDown,
+    Action::SelectSpell1,
+    Action::SelectSpell2,
+    Action::SelectSpell3,
+    Action::SelectSpell4,
+    Action::SelectSpell5,
+    Action::SelectSpell6,
+    Action::SelectSpell7,
+    Action::SelectSpell8,
+    Action::SelectSpell9,
+    Action::Menu,
+    Action::Console,
+    Action::Editor,
+    Action::Screenshot,
+    Action::Record,
+    Action::DebugOverlay,
+    Action::Inspect,
+    Action::TimeHold,
+    Action::TimeStep,
+    Action::TimeScale,
+    Action::Skills,
+    Action::Interact,
+    Action::Inventory,
+    Action::Hotbar,
+    Action::Build,
+    Action::Place,
+    Action::Craft,
+    Action::UseItem,
+];
+
+// index in the spellbook each slot action selects
+pub const SPELL_SLOTS: [Action; 9] = [
+    Action::SelectSpell1,
+    Action::SelectSpell2,
+    Action::SelectSpell3,
+    Action::SelectSpell4,
+    Action::SelectSpell5,
+    Action::SelectSpell6,
+    Action::SelectSpell7,
+    Action::SelectSpell8,
+    Action::SelectSpell9,
+];
+
+// stored as plain strings in controls.json ("A", "SPACE", "MOUSE_LEFT", "PAD_A") so the file is easy to edit by hand
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Binding {
+    Key(KeyboardKey),
+    Mouse(MouseButton),
+    Pad(GamepadButton),
+    Axis(GamepadAxis, bool), // true for the positive direction of the stick
+    Wheel(bool),             // true for scrolling up
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Device {
+    Keyboard,
+    Gamepad,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct InputMap {
+    bindings: HashMap<Action, Vec<Binding>>,
+    // how long (in seconds) a press stays usable after it happened, for actions that get buffered
+    #[serde(default = "default_buffer_windows")]
+    buffer_windows: HashMap<Action, f32>,
+    #[serde(skip)]
+    buffered: HashMap<Action, f32>,
+    // sticks have no "pressed" event in raylib so we track them ourselves
+    #[serde(skip)]
+    axes_down: Vec<Binding>,
+    #[serde(skip)]
+    axes_down_before: Vec<Binding>,
+    #[serde(skip, default = "default_device")]
+    last_device: Device,
+    #[serde(skip)]
+    pub touch: TouchControls,
+}
+
+fn default_device() -> Device {
+    Device::Keyboard
+}
+
+fn default_buffer_windows() -> HashMap<Action, f32> {
+    HashMap::from([(Action::Jump, 0.12), (Action::Cast, 0.12)])
+}
+
+const KEY_NAMES: &[(&str, KeyboardKey)] = &[
+    ("A", KeyboardKey::KEY_A),
+    ("B", KeyboardKey::KEY_B),
+    ("C", KeyboardKey::KEY_C),
+    ("D", KeyboardKey::KEY_D),
+    ("E", KeyboardKey::KEY_E),
+    ("F", KeyboardKey::KEY_F),
+    ("G", KeyboardKey::KEY_G),
+    ("H", KeyboardKey::KEY_H),
+    ("I", KeyboardKey::KEY_I),
+    ("J", KeyboardKey::KEY_J),
+    ("K", KeyboardKey::KEY_K),
+    ("L", KeyboardKey::KEY_L),
+    ("M", KeyboardKey::KEY_M),
+    ("N", KeyboardKey::KEY_N),
+    ("O", KeyboardKey::KEY_O),
+    ("P", KeyboardKey::KEY_P),
+    ("Q", KeyboardKey::KEY_Q),
+    ("R", KeyboardKey::KEY_R),
+    ("S", KeyboardKey::KEY_S),
+    ("T", KeyboardKey::KEY_T),
+    ("U", KeyboardKey::KEY_U),
+    ("V", KeyboardKey::KEY_V),
+    ("W", KeyboardKey::KEY_W),
+    ("X", KeyboardKey::KEY_X),
+    ("Y", KeyboardKey::KEY_Y),
+    ("Z", KeyboardKey::KEY_Z),
+    ("0", KeyboardKey::KEY_ZERO),
+    ("1", KeyboardKey::KEY_ONE),
+    ("2", KeyboardKey::KEY_TWO),
+    ("3", KeyboardKey::KEY_THREE),
+    ("4", KeyboardKey::KEY_FOUR),
+    ("5", KeyboardKey::KEY_FIVE),
+    ("6", KeyboardKey::KEY_SIX),
+    ("7", KeyboardKey::KEY_SEVEN),
+    ("8", KeyboardKey::KEY_EIGHT),
+    ("9", KeyboardKey::KEY_NINE),
+    ("SPACE", KeyboardKey::KEY_SPACE),
+    ("ENTER", KeyboardKey::KEY_ENTER),
+    ("TAB", KeyboardKey::KEY_TAB),
+    ("BACKSPACE", KeyboardKey::KEY_BACKSPACE),
+    ("ESCAPE", KeyboardKey::KEY_ESCAPE),
+    ("UP", KeyboardKey::KEY_UP),
+    ("DOWN", KeyboardKey::KEY_DOWN),
+    ("LEFT", KeyboardKey::KEY_LEFT),
+    ("RIGHT", KeyboardKey::KEY_RIGHT),
+    ("LEFT_SHIFT", KeyboardKey::KEY_LEFT_SHIFT),
+    ("RIGHT_SHIFT", KeyboardKey::KEY_RIGHT_SHIFT),
+    ("LEFT_CONTROL", KeyboardKey::KEY_LEFT_CONTROL),
+    ("RIGHT_CONTROL", KeyboardKey::KEY_RIGHT_CONTROL),
+    ("LEFT_ALT", KeyboardKey::KEY_LEFT_ALT),
+    ("RIGHT_ALT", KeyboardKey::KEY_RIGHT_ALT),
+    ("GRAVE", KeyboardKey::KEY_GRAVE),
+    ("MINUS", KeyboardKey::KEY_MINUS),
+    ("EQUAL", KeyboardKey::KEY_EQUAL),
+    ("COMMA", KeyboardKey::KEY_COMMA),
+    ("PERIOD", KeyboardKey::KEY_PERIOD),
+    ("SLASH", KeyboardKey::KEY_SLASH),
+    ("SEMICOLON", KeyboardKey::KEY_SEMICOLON),
+    ("F1", KeyboardKey::KEY_F1),
+    ("F2", KeyboardKey::KEY_F2),
+    ("F3", KeyboardKey::KEY_F3),
+    ("F4", KeyboardKey::KEY_F4),
+    ("F5", KeyboardKey::KEY_F5),
+    ("F6", KeyboardKey::KEY_F6),
+    ("F7", KeyboardKey::KEY_F7),
+    ("F8", KeyboardKey::KEY_F8),
+    ("F9", KeyboardKey::KEY_F9),
+    ("F10", KeyboardKey::KEY_F10),
+    ("F11", KeyboardKey::KEY_F11),
+    ("F12", KeyboardKey::KEY_F12),
+];
+
+const MOUSE_NAMES: &[(&str, MouseButton)] = &[
+    ("MOUSE_LEFT", MouseButton::MOUSE_BUTTON_LEFT),
+    ("MOUSE_RIGHT", MouseButton::MOUSE_BUTTON_RIGHT),
+    ("MOUSE_MIDDLE", MouseButton::MOUSE_BUTTON_MIDDLE),
+    ("MOUSE_SIDE", MouseButton::MOUSE_BUTTON_SIDE),
+    ("MOUSE_EXTRA", MouseButton::MOUSE_BUTTON_EXTRA),
+];
+
+const WHEEL_NAMES: &[(&str, bool)] = &[("MOUSE_WHEEL_UP", true), ("MOUSE_WHEEL_DOWN", false)];
+
+const PAD_NAMES: &[(&str, GamepadButton)] = &[
+    ("PAD_A", GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_DOWN),
+    ("PAD_B", GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_RIGHT),
+    ("PAD_X", GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_LEFT),
+    ("PAD_Y", GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_UP),
+    ("PAD_UP", GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_UP),
+    ("PAD_DOWN", GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_DOWN),
+    ("PAD_LEFT", GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_LEFT),
+    ("PAD_RIGHT", GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_RIGHT),
+    ("PAD_LB", GamepadButton::GAMEPAD_BUTTON_LEFT_TRIGGER_1),
+    ("PAD_RB", GamepadButton::GAMEPAD_BUTTON_RIGHT_TRIGGER_1),
+    ("PAD_LT", GamepadButton::GAMEPAD_BUTTON_LEFT_TRIGGER_2),
+    ("PAD_RT", GamepadButton::GAMEPAD_BUTTON_RIGHT_TRIGGER_2),
+    ("PAD_SELECT", GamepadButton::GAMEPAD_BUTTON_MIDDLE_LEFT),
+    ("PAD_START", GamepadButton::GAMEPAD_BUTTON_MIDDLE_RIGHT),
+    ("PAD_LS", GamepadButton::GAMEPAD_BUTTON_LEFT_THUMB),
+    ("PAD_RS", GamepadButton::GAMEPAD_BUTTON_RIGHT_THUMB),
+];
+
+const AXIS_NAMES: &[(&str, GamepadAxis, bool)] = &[
+    ("PAD_LEFT_X-", GamepadAxis::GAMEPAD_AXIS_LEFT_X, false),
+    ("PAD_LEFT_X+", GamepadAxis::GAMEPAD_AXIS_LEFT_X, true),
+    ("PAD_LEFT_Y-", GamepadAxis::GAMEPAD_AXIS_LEFT_Y, false),
+    ("PAD_LEFT_Y+", GamepadAxis::GAMEPAD_AXIS_LEFT_Y, true),
+    ("PAD_RIGHT_X-", GamepadAxis::GAMEPAD_AXIS_RIGHT_X, false),
+    ("PAD_RIGHT_X+", GamepadAxis::GAMEPAD_AXIS_RIGHT_X, true),
+    ("PAD_RIGHT_Y-", GamepadAxis::GAMEPAD_AXIS_RIGHT_Y, false),
+    ("PAD_RIGHT_Y+", GamepadAxis::GAMEPAD_AXIS_RIGHT_Y, true),
+];
+
+impl Action {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::MoveLeft => "Move left",
+            Action::MoveRight => "Move right",
+            Action::MoveUp => "Move up",
+            Action::MoveDown => "Move down",
+            Action::Jump => "Jump",
+            Action::Cast => "Cast spell",
+            Action::ShiftVariant => "Shift variant",
+            Action::CtrlVariant => "Ctrl variant",
+            Action::CycleSpellUp => "Previous spell",
+            Action::CycleSpellDown => "Next spell",
+            Action::SelectSpell1 => "Spell 1",
+            Action::SelectSpell2 => "Spell 2",
+            Action::SelectSpell3 => "Spell 3",
+            Action::SelectSpell4 => "Spell 4",
+            Action::SelectSpell5 => "Spell 5",
+            Action::SelectSpell6 => "Spell 6",
+            Action::SelectSpell7 => "Spell 7",
+            Action::SelectSpell8 => "Spell 8",
+            Action::SelectSpell9 => "Spell 9",
+            Action::Menu => "Menu",
+            Action::Console => "Console",
+            Action::Editor => "World editor",
+            Action::Screenshot => "Screenshot",
+            Action::Record => "Record frames",
+            Action::DebugOverlay => "Debug overlay",
+            Action::Inspect => "Inspect",
+            Action::TimeHold => "Hold time",
+            Action::TimeStep => "Step time",
+            Action::TimeScale => "Time scale",
+            Action::Skills => "Skill tree",
+            Action::Interact => "Interact",
+            Action::Inventory => "Inventory",
+            Action::Hotbar => "Next hotbar slot",
+            Action::Build => "Build mode",
+            Action::Place => "Place pixel",
+            Action::Craft => "Crafting",
+            Action::UseItem => "Use held item",
+        }
+    }
+}
+
+impl Binding {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Binding::Key(key) => KEY_NAMES.iter().find(|(_, k)| k == key).map_or("?", |(n, _)| n),
+            Binding::Mouse(button) => MOUSE_NAMES.iter().find(|(_, b)| b == button).map_or("?", |(n, _)| n),
+            Binding::Wheel(up) => WHEEL_NAMES.iter().find(|(_, u)| u == up).map_or("?", |(n, _)| n),
+            Binding::Pad(button) => PAD_NAMES.iter().find(|(_, b)| b == button).map_or("?", |(n, _)| n),
+            Binding::Axis(axis, positive) => AXIS_NAMES
+                .iter()
+                .find(|(_, a, p)| a == axis && p == positive)
+                .map_or("?", |(n, _, _)| n),
+        }
+    }
+
+    // what to show on screen, pad buttons get wrapped like a button glyph
+    pub fn glyph(&self) -> String {
+        match self {
+            Binding::Pad(_) | Binding::Axis(..) => format!("({})", self.name().trim_start_matches("PAD_")),
+            _ => format!("[{}]", self.name()),
+        }
+    }
+
+    pub fn device(&self) -> Device {
+        match self {
+            Binding::Key(_) | Binding::Mouse(_) | Binding::Wheel(_) => Device::Keyboard,
+            Binding::Pad(_) | Binding::Axis(..) => Device::Gamepad,
+        }
+    }
+
+    // 0.0 to 1.0, only sticks give anything in between
+    pub fn strength(&self, rl: &RaylibHandle) -> f32 {
+        match *self {
+            Binding::Axis(axis, positive) => {
+                if !rl.is_gamepad_available(GAMEPAD) {
+                    return 0.0;
+                }
+                let value = rl.get_gamepad_axis_movement(GAMEPAD, axis);
+                let value = if positive { value } else { -value };
+                if value < STICK_DEADZONE {
+                    0.0
+                } else {
+                    ((value - STICK_DEADZONE) / (1.0 - STICK_DEADZONE)).min(1.0)
+                }
+            }
+            _ => {
+                if self.is_down(rl) {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+
+    pub fn is_down(&self, rl: &RaylibHandle) -> bool {
+        match *self {
+            Binding::Key(key) => rl.is_key_down(key),
+            Binding::Mouse(button) => rl.is_mouse_button_down(button),
+            Binding::Wheel(up) => wheel_moved(rl, up),
+            Binding::Pad(button) => rl.is_gamepad_available(GAMEPAD) && rl.is_gamepad_button_down(GAMEPAD, button),
+            Binding::Axis(..) => self.strength(rl) > 0.5,
+        }
+    }
+
+    fn is_pressed(&self, rl: &RaylibHandle) -> bool {
+        match *self {
+            Binding::Key(key) => rl.is_key_pressed(key),
+            Binding::Mouse(button) => rl.is_mouse_button_pressed(button),
+            // every wheel notch is its own press
+            Binding::Wheel(up) => wheel_moved(rl, up),
+            Binding::Pad(button) => rl.is_gamepad_available(GAMEPAD) && rl.is_gamepad_button_pressed(GAMEPAD, button),
+            // handled by InputMap since it needs last frame's state
+            Binding::Axis(..) => false,
+        }
+    }
+
+    // whatever the player pressed this frame, used by the rebinding screen
+    pub fn poll(rl: &mut RaylibHandle) -> Option<Binding> {
+        if let Some(key) = rl.get_key_pressed() {
+            if KEY_NAMES.iter().any(|(_, k)| *k == key) {
+                return Some(Binding::Key(key));
+            }
+        }
+        if let Some((_, b)) = MOUSE_NAMES.iter().find(|(_, b)| rl.is_mouse_button_pressed(*b)) {
+            return Some(Binding::Mouse(*b));
+        }
+        if let Some((_, up)) = WHEEL_NAMES.iter().find(|(_, up)| wheel_moved(rl, *up)) {
+            return Some(Binding::Wheel(*up));
+        }
+        if !rl.is_gamepad_available(GAMEPAD) {
+            return None;
+        }
+        if let Some((_, b)) = PAD_NAMES.iter().find(|(_, b)| rl.is_gamepad_button_pressed(GAMEPAD, *b)) {
+            return Some(Binding::Pad(*b));
+        }
+        AXIS_NAMES
+            .iter()
+            .map(|(_, a, p)| Binding::Axis(*a, *p))
+            .find(|b| b.strength(rl) > 0.8)
+    }
+}
+
+impl TryFrom<String> for Binding {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        if let Some((_, key)) = KEY_NAMES.iter().find(|(n, _)| *n == name) {
+            return Ok(Binding::Key(*key));
+        }
+        if let Some((_, button)) = MOUSE_NAMES.iter().find(|(n, _)| *n == name) {
+            return Ok(Binding::Mouse(*button));
+        }
+        if let Some((_, up)) = WHEEL_NAMES.iter().find(|(n, _)| *n == name) {
+            return Ok(Binding::Wheel(*up));
+        }
+        if let Some((_, button)) = PAD_NAMES.iter().find(|(n, _)| *n == name) {
+            return Ok(Binding::Pad(*button));
+        }
+        if let Some((_, axis, positive)) = AXIS_NAMES.iter().find(|(n, _, _)| *n == name) {
+            return Ok(Binding::Axis(*axis, *positive));
+        }
+        Err(format!("unknown binding {}", name))
+    }
+}
+
+fn wheel_moved(rl: &RaylibHandle, up: bool) -> bool {
+    let wheel = rl.get_mouse_wheel_move();
+    if up {
+        wheel > 0.0
+    } else {
+        wheel < 0.0
+    }
+}
+
+impl From<Binding> for String {
+    fn from(binding: Binding) -> String {
+        binding.name().to_string()
+    }
+}
+
+// the file a profile's rebinds are saved to. the default keeps the old name so existing
+// rebinds carry over
+pub fn profile_path(profile: &str) -> String {
+    if profile == PROFILES[0] {
+        CONTROLS_PATH.to_string()
+    } else {
+        format!("controls_{}.json", profile.to_lowercase())
+    }
+}
+
+impl InputMap {
+    pub fn defaults() -> Self {
+        use GamepadAxis::*;
+        use GamepadButton::*;
+        let mut bindings = HashMap::new();
+        bindings.insert(Action::MoveLeft, vec![Binding::Key(KeyboardKey::KEY_A), Binding::Axis(GAMEPAD_AXIS_LEFT_X, false)]);
+        bindings.insert(Action::MoveRight, vec![Binding::Key(KeyboardKey::KEY_D), Binding::Axis(GAMEPAD_AXIS_LEFT_X, true)]);
+        bindings.insert(Action::MoveUp, vec![Binding::Key(KeyboardKey::KEY_W)]);
+        bindings.insert(Action::MoveDown, vec![Binding::Key(KeyboardKey::KEY_S), Binding::Axis(GAMEPAD_AXIS_LEFT_Y, true)]);
+        bindings.insert(Action::Jump, vec![Binding::Key(KeyboardKey::KEY_SPACE), Binding::Pad(GAMEPAD_BUTTON_RIGHT_FACE_DOWN)]);
+        bindings.insert(Action::Cast, vec![Binding::Mouse(MouseButton::MOUSE_BUTTON_LEFT), Binding::Pad(GAMEPAD_BUTTON_RIGHT_TRIGGER_2)]);
+        bindings.insert(Action::ShiftVariant, vec![Binding::Key(KeyboardKey::KEY_LEFT_SHIFT), Binding::Pad(GAMEPAD_BUTTON_LEFT_TRIGGER_2)]);
+        bindings.insert(Action::CtrlVariant, vec![Binding::Key(KeyboardKey::KEY_LEFT_CONTROL), Binding::Pad(GAMEPAD_BUTTON_RIGHT_FACE_UP)]);
+        bindings.insert(
+            Action::CycleSpellUp,
+            vec![
+                Binding::Key(KeyboardKey::KEY_UP),
+                Binding::Wheel(true),
+                Binding::Pad(GAMEPAD_BUTTON_LEFT_TRIGGER_1),
+                Binding::Axis(GAMEPAD_AXIS_RIGHT_Y, false),
+            ],
+        );
+        bindings.insert(
+            Action::CycleSpellDown,
+            vec![
+                Binding::Key(KeyboardKey::KEY_DOWN),
+                Binding::Wheel(false),
+                Binding::Pad(GAMEPAD_BUTTON_RIGHT_TRIGGER_1),
+                Binding::Axis(GAMEPAD_AXIS_RIGHT_Y, true),
+            ],
+        );
+        let number_keys = [
+            KeyboardKey::KEY_ONE,
+            KeyboardKey::KEY_TWO,
+            KeyboardKey::KEY_THREE,
+            KeyboardKey::KEY_FOUR,
+            KeyboardKey::KEY_FIVE,
+            KeyboardKey::KEY_SIX,
+            KeyboardKey::KEY_SEVEN,
+            KeyboardKey::KEY_EIGHT,
+            KeyboardKey::KEY_NINE,
+        ];
+        for (action, key) in SPELL_SLOTS.iter().zip(number_keys) {
+            bindings.insert(*action, vec![Binding::Key(key)]);
+        }
+        bindings.insert(Action::Menu, vec![Binding::Key(KeyboardKey::KEY_ESCAPE), Binding::Pad(GAMEPAD_BUTTON_MIDDLE_RIGHT)]);
+        bindings.insert(Action::Console, vec![Binding::Key(KeyboardKey::KEY_GRAVE)]);
+        bindings.insert(Action::Editor, vec![Binding::Key(KeyboardKey::KEY_F2)]);
+        bindings.insert(Action::Screenshot, vec![Binding::Key(KeyboardKey::KEY_F12)]);
+        bindings.insert(Action::Record, vec![Binding::Key(KeyboardKey::KEY_F9)]);
+        bindings.insert(Action::DebugOverlay, vec![Binding::Key(KeyboardKey::KEY_F3)]);
+        bindings.insert(Action::Inspect, vec![Binding::Key(KeyboardKey::KEY_F4)]);
+        bindings.insert(Action::TimeHold, vec![Binding::Key(KeyboardKey::KEY_F5)]);
+        bindings.insert(Action::TimeStep, vec![Binding::Key(KeyboardKey::KEY_F6)]);
+        bindings.insert(Action::TimeScale, vec![Binding::Key(KeyboardKey::KEY_F7)]);
+        bindings.insert(Action::Interact, vec![Binding::Key(KeyboardKey::KEY_E), Binding::Pad(GAMEPAD_BUTTON_RIGHT_FACE_LEFT)]);
+        bindings.insert(Action::Skills, vec![Binding::Key(KeyboardKey::KEY_K), Binding::Pad(GAMEPAD_BUTTON_MIDDLE_LEFT)]);
+        bindings.insert(Action::Inventory, vec![Binding::Key(KeyboardKey::KEY_I), Binding::Pad(GAMEPAD_BUTTON_RIGHT_FACE_RIGHT)]);
+        bindings.insert(Action::Hotbar, vec![Binding::Key(KeyboardKey::KEY_TAB), Binding::Pad(GAMEPAD_BUTTON_LEFT_FACE_RIGHT)]);
+        bindings.insert(Action::Build, vec![Binding::Key(KeyboardKey::KEY_B), Binding::Pad(GAMEPAD_BUTTON_LEFT_FACE_UP)]);
+        bindings.insert(Action::Place, vec![Binding::Mouse(MouseButton::MOUSE_BUTTON_RIGHT), Binding::Pad(GAMEPAD_BUTTON_LEFT_FACE_DOWN)]);
+        bindings.insert(Action::Craft, vec![Binding::Key(KeyboardKey::KEY_C), Binding::Pad(GAMEPAD_BUTTON_LEFT_FACE_LEFT)]);
+        bindings.insert(Action::UseItem, vec![Binding::Key(KeyboardKey::KEY_F), Binding::Pad(GAMEPAD_BUTTON_RIGHT_THUMB)]);
+        InputMap {
+            bindings,
+            buffer_windows: default_buffer_windows(),
+            buffered: HashMap::new(),
+            axes_down: Vec::new(),
+            axes_down_before: Vec::new(),
+            last_device: Device::Keyboard,
+            touch: TouchControls::default(),
+        }
+    }
+
+    // the defaults with the keyboard moved around for the profile, unknown ones get the defaults.
+    // only keys change, mouse and gamepad stay where they are on every layout
+    pub fn for_profile(profile: &str) -> Self {
+        use KeyboardKey::*;
+        let mut map = InputMap::defaults();
+        match profile {
+            "AZERTY" => {
+                map.set_keys(Action::MoveLeft, &[KEY_Q]);
+                map.set_keys(Action::MoveUp, &[KEY_Z]);
+            }
+            // the right hand on the keyboard, the left on the mouse
+            "Lefty" => {
+                map.set_keys(Action::MoveLeft, &[KEY_LEFT]);
+                map.set_keys(Action::MoveRight, &[KEY_RIGHT]);
+                map.set_keys(Action::MoveUp, &[KEY_UP]);
+                map.set_keys(Action::MoveDown, &[KEY_DOWN]);
+                map.set_keys(Action::Jump, &[KEY_ENTER]);
+                map.set_keys(Action::ShiftVariant, &[KEY_RIGHT_SHIFT]);
+                map.set_keys(Action::CtrlVariant, &[KEY_RIGHT_CONTROL]);
+                map.set_keys(Action::CycleSpellUp, &[KEY_PERIOD]);
+                map.set_keys(Action::CycleSpellDown, &[KEY_COMMA]);
+                map.set_keys(Action::Interact, &[KEY_SLASH]);
+            }
+            _ => {}
+        }
+        map
+    }
+
+    fn set_keys(&mut self, action: Action, keys: &[KeyboardKey]) {
+        let bindings = self.bindings.entry(action).or_default();
+        bindings.retain(|b| !matches!(b, Binding::Key(_)));
+        bindings.extend(keys.iter().map(|k| Binding::Key(*k)));
+    }
+
+    // missing or broken files fall back to the profile's layout, actions missing from the file keep theirs
+    pub fn load(path: &str, profile: &str, errors: &mut Vec<SpellcoderError>) -> Self {
+        let mut map = InputMap::for_profile(profile);
+        if !std::path::Path::new(path).exists() {
+            return map;
+        }
+        if let Err(e) = map.import(path) {
+            errors.push(e);
+        }
+        map
+    }
+
+    // takes over every binding in the file, keeping the current one for actions it doesn't mention
+    pub fn import(&mut self, path: &str) -> Result<(), SpellcoderError> {
+        let text = fs::read_to_string(path).map_err(|e| SpellcoderError::io(path, e))?;
+        let loaded = serde_json::from_str::<InputMap>(&text).map_err(|e| SpellcoderError::parse(path, e))?;
+        self.bindings.extend(loaded.bindings);
+        self.buffer_windows.extend(loaded.buffer_windows);
+        Ok(())
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), SpellcoderError> {
+        let text = serde_json::to_string_pretty(self).map_err(|e| SpellcoderError::save(path, e.into()))?;
+        fs::write(path, text).map_err(|e| SpellcoderError::save(path, e))
+    }
+
+    pub fn bindings(&self, action: Action) -> &[Binding] {
+        self.bindings.get(&action).map_or(&[], |b| b.as_slice())
+    }
+
+    // replaces the action's binding for that device and unbinds it from anything else using it
+    pub fn rebind(&mut self, action: Action, binding: Binding) {
+        for bindings in self.bindings.values_mut() {
+            bindings.retain(|b| *b != binding);
+        }
+        let bindings = self.bindings.entry(action).or_default();
+        bindings.retain(|b| b.device() != binding.device());
+        bindings.push(binding);
+    }
+
+    // call once per frame before querying actions
+    pub fn update(&mut self, rl: &RaylibHandle, delta: f32) {
+        self.axes_down_before = std::mem::take(&mut self.axes_down);
+        self.axes_down = AXIS_NAMES
+            .iter()
+            .map(|(_, a, p)| Binding::Axis(*a, *p))
+            .filter(|b| b.is_down(rl))
+            .collect();
+
+        let pad_used = !self.axes_down.is_empty()
+            || (rl.is_gamepad_available(GAMEPAD) && PAD_NAMES.iter().any(|(_, b)| rl.is_gamepad_button_down(GAMEPAD, *b)));
+        let keyboard_used = KEY_NAMES.iter().any(|(_, k)| rl.is_key_down(*k))
+            || MOUSE_NAMES.iter().any(|(_, b)| rl.is_mouse_button_down(*b));
+        if pad_used {
+            self.last_device = Device::Gamepad;
+        } else if keyboard_used {
+            self.last_device = Device::Keyboard;
+        }
+        self.touch.update(rl);
+        if pad_used || KEY_NAMES.iter().any(|(_, k)| rl.is_key_down(*k)) {
+            self.touch.active = false;
+        }
+
+        for remaining in self.buffered.values_mut() {
+            *remaining -= delta;
+        }
+        self.buffered.retain(|_, remaining| *remaining > 0.0);
+        for (action, window) in &self.buffer_windows {
+            if self.is_pressed(rl, *action) {
+                self.buffered.insert(*action, *window);
+            }
+        }
+    }
+
+    // true while a recent press of a buffered action hasn't been used yet
+    pub fn is_buffered(&self, action: Action) -> bool {
+        self.buffered.contains_key(&action)
+    }
+
+    pub fn clear_buffered(&mut self) {
+        self.buffered.clear();
+    }
+
+    // uses up the buffered press so it only triggers once
+    pub fn consume(&mut self, action: Action) -> bool {
+        self.buffered.remove(&action).is_some()
+    }
+
+    // on-screen prompt for an action, following whatever device was used last
+    pub fn prompt(&self, action: Action) -> String {
+        let bindings = self.bindings(action);
+        bindings
+            .iter()
+            .find(|b| b.device() == self.last_device)
+            .or(bindings.first())
+            .map_or("[-]".to_string(), |b| b.glyph())
+    }
+
+    // with touch controls up, the mouse position is just wherever the last finger was
+    pub fn aim_position(&self, rl: &RaylibHandle, last_aim: Vector2) -> Vector2 {
+        if self.touch.active {
+            self.touch.aim().unwrap_or(last_aim)
+        } else {
+            rl.get_mouse_position()
+        }
+    }
+
+    // which of the spell's component sets a cast right now would use, ctrl wins if both are held
+    pub fn cast_variant(&self, rl: &RaylibHandle) -> CastVariant {
+        if self.is_down(rl, Action::CtrlVariant) {
+            CastVariant::Ctrl
+        } else if self.is_down(rl, Action::ShiftVariant) {
+            CastVariant::Shift
+        } else {
+            CastVariant::Normal
+        }
+    }
+
+    pub fn strength(&self, rl: &RaylibHandle, action: Action) -> f32 {
+        let bound = self.bindings(action).iter().map(|b| b.strength(rl)).fold(0.0, f32::max);
+        bound.max(self.touch.strength(action))
+    }
+
+    pub fn is_down(&self, rl: &RaylibHandle, action: Action) -> bool {
+        self.bindings(action).iter().any(|b| b.is_down(rl)) || self.touch.strength(action) > 0.5
+    }
+
+    pub fn is_pressed(&self, rl: &RaylibHandle, action: Action) -> bool {
+        // a tap on the on-screen controls also shows up as a mouse click, don't let it count twice
+        if self.touch.active && matches!(action, Action::Cast | Action::Jump) {
+            return self.touch.is_pressed(action);
+        }
+        self.bindings(action).iter().any(|b| match b {
+            Binding::Axis(..) => self.axes_down.contains(b) && !self.axes_down_before.contains(b),
+            _ => b.is_pressed(rl),
+        })
+    }
+}
@@ -0,0 +1,119 @@
+use std::collections::HashSet;
+
+use raylib::prelude::*;
+
+use crate::ecs::Entity;
+use crate::world::Chunk;
+use crate::{Player, World};
+
+// everything the rest of the game takes for granted about the world, checked every tick with
+// --features invariants. a broken one otherwise shows up much later as an index out of bounds or
+// a pixel gone missing, far from whatever broke it. this panics with all of them and where they
+// are instead, the crash report has the lot
+pub fn check(world: &World, player: &Player, tick: u64) {
+    let mut broken = Vec::new();
+    check_chunks(world, &mut broken);
+    check_entities(world, &mut broken);
+    check_player(player, &mut broken);
+    if broken.is_empty() {
+        return;
+    }
+    for violation in &broken {
+        crate::log_error!("invariant broken at tick {}: {}", tick, violation);
+    }
+    panic!("{} invariants broken at tick {}, the first: {}", broken.len(), tick, broken[0]);
+}
+
+fn finite(v: Vector2) -> bool {
+    v.x.is_finite() && v.y.is_finite()
+}
+
+fn check_chunks(world: &World, broken: &mut Vec<String>) {
+    let mut seen = HashSet::new();
+    for (i, chunk) in world.chunks.iter().enumerate() {
+        let at = format!("chunk {} at ({}, {})", i, chunk.x, chunk.y);
+        if chunk.x.rem_euclid(16) != 0 || chunk.y.rem_euclid(16) != 0 {
+            broken.push(format!("{} isn't on the 16 pixel grid", at));
+        }
+        // chunk_at finds the first, anything after it at the same place is never seen again
+        if !seen.insert((chunk.x, chunk.y)) {
+            broken.push(format!("{} is loaded twice", at));
+        }
+        if !chunk.temperature.is_finite() {
+            broken.push(format!("{} has a temperature of {}", at, chunk.temperature));
+        }
+        check_pixels(world, chunk, &at, broken);
+    }
+}
+
+fn check_pixels(world: &World, chunk: &Chunk, at: &str, broken: &mut Vec<String>) {
+    if chunk.pixels.len() != 16 {
+        broken.push(format!("{} has {} columns", at, chunk.pixels.len()));
+    }
+    let materials = world.materials.count();
+    for (x, column) in chunk.pixels.iter().enumerate() {
+        // sorted by y with no two in the same place, get_pixel and set_pixel binary search it
+        if let Some(pair) = column.windows(2).find(|pair| pair[0].y >= pair[1].y) {
+            broken.push(format!("{} column {} is out of order, y {} then {}", at, x, pair[0].y, pair[1].y));
+        }
+        for pixel in column {
+            let here = format!("{} pixel ({}, {}) in column {}", at, pixel.x, pixel.y, x);
+            if pixel.x as usize != x || pixel.y >= 16 {
+                broken.push(format!("{} is out of place", here));
+            }
+            if pixel.material.0 as usize >= materials {
+                broken.push(format!("{} is material {} of {}", here, pixel.material.0, materials));
+            }
+            if !pixel.damage.is_finite() {
+                broken.push(format!("{} has {} damage", here, pixel.damage));
+            }
+        }
+    }
+}
+
+fn check_entities(world: &World, broken: &mut Vec<String>) {
+    let ecs = &world.ecs;
+    let dead = |entity: Entity| !ecs.is_alive(entity);
+    // despawn takes every component away, one left behind belongs to whoever reuses the slot
+    let leftovers = [
+        ("position", ecs.positions.entities()),
+        ("body", ecs.bodies.entities()),
+        ("health", ecs.healths.entities()),
+        ("sprite", ecs.sprites.entities()),
+        ("projectile", ecs.projectiles.entities()),
+        ("enemy", ecs.enemies.entities()),
+        ("light", ecs.lights.entities()),
+    ];
+    for (component, entities) in leftovers {
+        for entity in entities.into_iter().filter(|e| dead(*e)) {
+            broken.push(format!("entity {} is dead but still has a {}", entity, component));
+        }
+    }
+    for (entity, position) in ecs.positions.iter() {
+        if !finite(*position) {
+            broken.push(format!("entity {} is at ({}, {})", entity, position.x, position.y));
+        }
+    }
+    for (entity, body) in ecs.bodies.iter() {
+        if !finite(body.velocity) {
+            let at = ecs.positions.get(entity).copied().unwrap_or_default();
+            broken.push(format!("entity {} at ({}, {}) moves at ({}, {})", entity, at.x, at.y, body.velocity.x, body.velocity.y));
+        }
+    }
+    for (entity, health) in ecs.healths.iter() {
+        if !health.hp.is_finite() || !health.max_hp.is_finite() {
+            broken.push(format!("entity {} has {} of {} hp", entity, health.hp, health.max_hp));
+        }
+    }
+}
+
+fn check_player(player: &Player, broken: &mut Vec<String>) {
+    if !finite(player.position) {
+        broken.push(format!("the player is at ({}, {})", player.position.x, player.position.y));
+    }
+    for (stat, value) in [("hp", player.hp), ("mp", player.mp), ("sp", player.sp)] {
+        if !value.is_finite() {
+            broken.push(format!("the player has {} {}", value, stat));
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use serde::{Deserialize, Serialize};
+
+// slots in all, the first HOTBAR_SLOTS of them are the hotbar
+pub const SLOTS: usize = 30;
+pub const HOTBAR_SLOTS: usize = 8;
+// most of one item a slot holds, the rest goes into the next free slot
+pub const STACK_LIMIT: u32 = 99;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Stack {
+    pub name: String,
+    pub count: u32,
+}
+
+// what the player carries, kept in the world save. items are only names, what they do is up
+// to whatever uses them
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Inventory {
+    slots: Vec<Option<Stack>>,
+    // the hotbar slot in hand
+    pub selected: usize,
+}
+
+impl Default for Inventory {
+    fn default() -> Self {
+        Inventory::with_slots(SLOTS)
+    }
+}
+
+impl Inventory {
+    // for anything other than the player's, a chest say. it has no hotbar to speak of
+    pub fn with_slots(count: usize) -> Self {
+        Inventory { slots: vec![None; count], selected: 0 }
+    }
+
+    // tops up stacks of the item before starting new ones. returns what didn't fit
+    pub fn add(&mut self, name: &str, count: u32) -> u32 {
+        let mut left = count;
+        for stack in self.slots.iter_mut().flatten().filter(|s| s.name == name) {
+            let moved = left.min(STACK_LIMIT.saturating_sub(stack.count));
+            stack.count += moved;
+            left -= moved;
+        }
+        for slot in self.slots.iter_mut().filter(|s| s.is_none()) {
+            if left == 0 {
+                break;
+            }
+            let moved = left.min(STACK_LIMIT);
+            *slot = Some(Stack { name: name.to_string(), count: moved });
+            left -= moved;
+        }
+        left
+    }
+
+    // whether a pickup of the item would fit
+    pub fn has_room(&self, name: &str) -> bool {
+        self.slots.iter().any(|slot| match slot {
+            Some(stack) => stack.name == name && stack.count < STACK_LIMIT,
+            None => true,
+        })
+    }
+
+    // takes count of the item from wherever it is, or nothing if there isn't that much
+    pub fn remove(&mut self, name: &str, count: u32) -> bool {
+        if self.count(name) < count {
+            return false;
+        }
+        let mut left = count;
+        // the last stacks first, so the hotbar is the last to run out
+        for slot in self.slots.iter_mut().rev() {
+            let Some(stack) = slot.as_mut().filter(|s| s.name == name) else {
+                continue;
+            };
+            let taken = left.min(stack.count);
+            stack.count -= taken;
+            left -= taken;
+            if stack.count == 0 {
+                *slot = None;
+            }
+            if left == 0 {
+                break;
+            }
+        }
+        true
+    }
+
+    pub fn count(&self, name: &str) -> u32 {
+        self.slots.iter().flatten().filter(|s| s.name == name).map(|s| s.count).sum()
+    }
+
+    pub fn slots(&self) -> &[Option<Stack>] {
+        &self.slots
+    }
+
+    pub fn hotbar(&self) -> &[Option<Stack>] {
+        &self.slots[..HOTBAR_SLOTS]
+    }
+
+    // what's in the selected hotbar slot
+    pub fn held(&self) -> Option<&Stack> {
+        self.slots[self.selected].as_ref()
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1) % HOTBAR_SLOTS;
+    }
+
+    // moving onto a stack of the same item merges as much as fits, anything else swaps places
+    pub fn move_stack(&mut self, from: usize, to: usize) {
+        if from == to || from >= SLOTS || to >= SLOTS {
+            return;
+        }
+        if let (Some(a), Some(b)) = (&self.slots[from], &self.slots[to]) {
+            if a.name == b.name {
+                let moved = a.count.min(STACK_LIMIT.saturating_sub(b.count));
+                if let Some(stack) = &mut self.slots[to] {
+                    stack.count += moved;
+                }
+                if let Some(stack) = &mut self.slots[from] {
+                    stack.count -= moved;
+                }
+                if self.slots[from].as_ref().is_some_and(|s| s.count == 0) {
+                    self.slots[from] = None;
+                }
+                return;
+            }
+        }
+        self.slots.swap(from, to);
+    }
+
+    // as much of the stack in the slot as fits into the other inventory, the rest stays
+    pub fn transfer(&mut self, slot: usize, to: &mut Inventory) {
+        let Some(stack) = self.slots.get_mut(slot).and_then(|s| s.as_mut()) else {
+            return;
+        };
+        stack.count = to.add(&stack.name, stack.count);
+        if stack.count == 0 {
+            self.slots[slot] = None;
+        }
+    }
+
+    // older saves or a different SLOTS can leave the wrong number of slots behind
+    pub fn fix_slots(&mut self) {
+        self.slots.resize(SLOTS, None);
+        self.selected = self.selected.min(HOTBAR_SLOTS - 1);
+    }
+}
@@ -0,0 +1,176 @@
+use raylib::prelude::*;
+
+use crate::ecs::{Body, Ecs, Entity, Pickup, Sprite};
+use crate::lighting::Light;
+use crate::loot::{Loot, Rarity};
+use crate::merchant::CURRENCY;
+use crate::{Player, World};
+
+// world pixels, how close the player has to get to pick something up
+const PICKUP_RANGE: f32 = 6.0;
+const ITEM_GRAVITY: f32 = 60.0;
+const ITEM_BOUNCE: f32 = 0.3;
+// seconds before a fresh drop can be picked up, so it visibly pops out first
+const PICKUP_DELAY: f32 = 0.4;
+// world pixels, how big an item is drawn
+const ITEM_SIZE: f32 = 2.0;
+const SCROLL_COLOR: Color = Color::new(240, 225, 180, 255);
+// for items that don't come out of a material
+pub const PLAIN_COLOR: Color = Color::new(200, 200, 210, 255);
+// seconds a pickup waits before trying again when the inventory was full
+const FULL_RETRY: f32 = 2.0;
+// world pixels, pickups the player has room for fly at them from this close
+const MAGNET_RANGE: f32 = 24.0;
+// world pixels per second, right at the edge of the range and right next to the player
+const MAGNET_MIN_SPEED: f32 = 20.0;
+const MAGNET_MAX_SPEED: f32 = 90.0;
+// world pixels, how far rare and better drops glow
+const RARE_GLOW: f32 = 6.0;
+// world pixels, identical pickups closer than this become one
+const MERGE_RANGE: f32 = 2.0;
+
+// something lying in the world waiting to be picked up, position in world pixels and velocity
+// in world pixels per second
+pub fn spawn_item(ecs: &mut Ecs, name: &str, position: Vector2, velocity: Vector2, color: Color) -> Entity {
+    let entity = ecs.spawn();
+    ecs.positions.insert(entity, position);
+    ecs.bodies.insert(
+        entity,
+        Body {
+            velocity,
+            gravity: ITEM_GRAVITY,
+            bounce: ITEM_BOUNCE,
+            collided: false,
+            resting: false,
+        },
+    );
+    ecs.sprites.insert(entity, Sprite { color, size: ITEM_SIZE });
+    ecs.pickups.insert(entity, Pickup { name: name.to_string(), delay: PICKUP_DELAY, spell: None, rarity: Rarity::Common, count: 1 });
+    entity
+}
+
+// one of what a loot table rolled. anything better than common looks like its rarity
+pub fn spawn_loot(ecs: &mut Ecs, loot: &Loot, position: Vector2, velocity: Vector2) -> Entity {
+    let color = if loot.rarity == Rarity::Common { PLAIN_COLOR } else { loot.rarity.color() };
+    let entity = spawn_item(ecs, &loot.item, position, velocity, color);
+    if let Some(pickup) = ecs.pickups.get_mut(entity) {
+        pickup.rarity = loot.rarity;
+    }
+    // the best of it glints, so it isn't lost in a dark cave
+    if loot.rarity >= Rarity::Rare {
+        ecs.lights.insert(entity, Light::new(loot.rarity.color(), RARE_GLOW, 0.6).flickering(0.4));
+    }
+    entity
+}
+
+// an item that teaches the spell when picked up, see Collected::Scroll
+pub fn spawn_scroll(ecs: &mut Ecs, spell: &str, position: Vector2, velocity: Vector2) -> Entity {
+    let entity = spawn_item(ecs, &format!("Scroll of {}", spell), position, velocity, SCROLL_COLOR);
+    if let Some(pickup) = ecs.pickups.get_mut(entity) {
+        pickup.spell = Some(spell.to_string());
+    }
+    entity
+}
+
+pub enum Collected {
+    // went into the player's inventory, how many of it
+    Item(String, u32, Rarity),
+    // left lying there, the inventory has no room for it
+    Full(String),
+    // the spell's name, for the game to teach
+    Scroll(String),
+    // went into the purse, how many
+    Coin(u32),
+}
+
+// merges, digs out and pulls in pickups, then hands the ones the player touches over and says
+// what they were. ecs::update moves them
+pub fn update_items(world: &mut World, player: &mut Player, delta: f32) -> Vec<Collected> {
+    merge(world);
+    let center = player.center();
+    let mut collected = Vec::new();
+    for entity in world.ecs.pickups.entities() {
+        let Some(&position) = world.ecs.positions.get(entity) else {
+            continue;
+        };
+        // buried by sand or the like, it works its way back up a pixel at a time
+        if world.is_solid(position.x.floor() as i64, position.y.floor() as i64) {
+            if let Some(position) = world.ecs.positions.get_mut(entity) {
+                position.y -= 1.0;
+            }
+            continue;
+        }
+        let Some(pickup) = world.ecs.pickups.get_mut(entity) else {
+            continue;
+        };
+        pickup.delay -= delta;
+        let distance = position.distance_to(center);
+        if pickup.delay > 0.0 || distance > PICKUP_RANGE {
+            let wanted = pickup.spell.is_some() || pickup.name == CURRENCY || player.inventory.has_room(&pickup.name);
+            if pickup.delay <= 0.0 && wanted && distance <= MAGNET_RANGE {
+                let closeness = 1.0 - distance / MAGNET_RANGE;
+                let speed = MAGNET_MIN_SPEED + (MAGNET_MAX_SPEED - MAGNET_MIN_SPEED) * closeness;
+                if let Some(body) = world.ecs.bodies.get_mut(entity) {
+                    body.velocity = (center - position).normalized() * speed;
+                }
+            }
+            continue;
+        }
+        match pickup.spell.take() {
+            Some(spell) => collected.push(Collected::Scroll(spell)),
+            None if pickup.name == CURRENCY => {
+                player.coins += pickup.count;
+                collected.push(Collected::Coin(pickup.count));
+            }
+            None if !player.inventory.has_room(&pickup.name) => {
+                pickup.delay = FULL_RETRY;
+                collected.push(Collected::Full(pickup.name.clone()));
+                continue;
+            }
+            None => {
+                let left = player.inventory.add(&pickup.name, pickup.count);
+                collected.push(Collected::Item(pickup.name.clone(), pickup.count - left, pickup.rarity));
+                // whatever didn't fit stays behind
+                if left > 0 {
+                    pickup.count = left;
+                    pickup.delay = FULL_RETRY;
+                    continue;
+                }
+            }
+        }
+        world.ecs.despawn(entity);
+    }
+    collected
+}
+
+// identical pickups lying on top of each other become one, the first one spawned keeps the lot.
+// scrolls each teach a spell of their own and never merge
+fn merge(world: &mut World) {
+    let ecs = &mut world.ecs;
+    let entities = ecs.pickups.entities();
+    for (i, &keep) in entities.iter().enumerate() {
+        for &other in &entities[i + 1..] {
+            if !ecs.is_alive(keep) {
+                break;
+            }
+            let (Some(a), Some(b)) = (ecs.positions.get(keep), ecs.positions.get(other)) else {
+                continue;
+            };
+            if a.distance_to(*b) > MERGE_RANGE {
+                continue;
+            }
+            let (Some(a), Some(b)) = (ecs.pickups.get(keep), ecs.pickups.get(other)) else {
+                continue;
+            };
+            if a.name != b.name || a.rarity != b.rarity || a.spell.is_some() || b.spell.is_some() {
+                continue;
+            }
+            let (count, delay) = (b.count, b.delay);
+            if let Some(a) = ecs.pickups.get_mut(keep) {
+                a.count += count;
+                a.delay = a.delay.max(delay);
+            }
+            ecs.despawn(other);
+        }
+    }
+}
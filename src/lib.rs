@@ -0,0 +1,111 @@
+// the game itself, main.rs is the raylib front-end that drives it
+pub mod achievements;
+pub mod animation;
+pub mod audio;
+pub mod bloom;
+pub mod build;
+pub mod camera;
+pub mod capture;
+pub mod chest;
+pub mod cli;
+pub mod clock;
+pub mod config;
+pub mod console;
+pub mod consumables;
+pub mod crafting;
+pub mod crash;
+pub mod daily;
+pub mod death;
+pub mod debug;
+pub mod digest;
+pub mod dryrun;
+pub mod director;
+pub mod ecs;
+pub mod effects;
+pub mod electricity;
+pub mod equipment;
+pub mod error;
+pub mod events;
+pub mod experience;
+pub mod exploration;
+pub mod game;
+pub mod growth;
+pub mod hud;
+pub mod input;
+#[cfg(feature = "invariants")]
+pub mod invariants;
+pub mod inventory;
+pub mod item;
+pub mod lighting;
+pub mod logging;
+pub mod loot;
+pub mod material;
+pub mod merchant;
+pub mod particles;
+pub mod player;
+pub mod profiler;
+pub mod quest;
+pub mod regen;
+pub mod render;
+pub mod rng;
+pub mod rules;
+pub mod save;
+pub mod scenario;
+pub mod scheduler;
+pub mod season;
+pub mod session;
+pub mod settings;
+pub mod simulation;
+pub mod skills;
+pub mod sky;
+pub mod spell;
+pub mod state;
+pub mod stats;
+pub mod status;
+pub mod temperature;
+pub mod touch;
+pub mod tuning;
+pub mod ui;
+pub mod weather;
+pub mod world;
+
+use raylib::prelude::{RaylibDrawHandle, RaylibHandle, RaylibMode2D, RaylibTextureMode};
+
+pub use error::SpellcoderError;
+pub use player::{Hit, Player};
+pub use world::{biome_at, Biome, Chunk, Pixel, World};
+
+// screen pixels per world pixel
+pub fn scale() -> i32 {
+    config::get().scale
+}
+
+// how wide draw_text draws text with raylib's default font, asked of whichever handle or draw
+// mode is at hand. text drawn with the hud's own font is measured with Hud::text_width
+pub trait TextWidth {
+    fn text_width(&self, text: &str, size: i32) -> i32;
+}
+
+impl TextWidth for RaylibHandle {
+    fn text_width(&self, text: &str, size: i32) -> i32 {
+        self.measure_text(text, size)
+    }
+}
+
+impl TextWidth for RaylibDrawHandle<'_> {
+    fn text_width(&self, text: &str, size: i32) -> i32 {
+        self.measure_text(text, size)
+    }
+}
+
+impl<T: TextWidth> TextWidth for RaylibMode2D<'_, T> {
+    fn text_width(&self, text: &str, size: i32) -> i32 {
+        (**self).text_width(text, size)
+    }
+}
+
+impl<T: TextWidth> TextWidth for RaylibTextureMode<'_, T> {
+    fn text_width(&self, text: &str, size: i32) -> i32 {
+        (**self).text_width(text, size)
+    }
+}
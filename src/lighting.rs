@@ -0,0 +1,201 @@
+use raylib::prelude::*;
+
+use crate::ecs::Ecs;
+use crate::scale;
+
+// the surface is lit by the sky. below it the ambient light fades down to DEEP_AMBIENT over this
+// many world pixels, see ambient
+const DARK_DEPTH: f32 = 40.0;
+const DEEP_AMBIENT: f32 = 0.15;
+// horizontal strips the ambient light is drawn in, a gradient each
+const AMBIENT_BANDS: i32 = 12;
+
+// something that glows. the color is added on top of the ambient light at the center and fades
+// out linearly to nothing at radius
+#[derive(Clone, Copy, Debug)]
+pub struct Light {
+    pub color: Color,
+    // world pixels
+    pub radius: f32,
+    // 0 to 1, how much of the color gets through at the center
+    pub intensity: f32,
+    // 0 for a steady glow, towards 1 for a torch's
+    pub flicker: f32,
+}
+
+impl Light {
+    pub fn new(color: Color, radius: f32, intensity: f32) -> Self {
+        Light { color, radius, intensity, flicker: 0.0 }
+    }
+
+    pub fn flickering(self, flicker: f32) -> Self {
+        Light { flicker, ..self }
+    }
+
+    // how strong it is at time, flicker wobbles it around a little with a phase of its own
+    fn strength(&self, phase: f32, time: f32) -> f32 {
+        let wobble = (time * 11.0 + phase).sin() * (time * 7.3 + phase * 1.7).sin();
+        self.intensity * (1.0 - self.flicker * 0.5 * (1.0 + wobble))
+    }
+}
+
+// a light that fades out by itself, a spell going off say
+struct Flash {
+    position: Vector2,
+    light: Light,
+    age: f32,
+    duration: f32,
+}
+
+// a light where it's shining this frame, strength with flicker and fading already applied
+#[derive(Clone, Copy, Debug)]
+pub struct Lit {
+    pub position: Vector2,
+    pub color: Color,
+    pub radius: f32,
+    pub strength: f32,
+}
+
+// how much of the sky's light reaches y, in world pixels. 1 at the surface and above
+pub fn ambient(y: f32) -> f32 {
+    let depth = (y / DARK_DEPTH).clamp(0.0, 1.0);
+    1.0 - depth * (1.0 - DEEP_AMBIENT)
+}
+
+// every light in the world. entities carry theirs in Ecs::lights and move them around with them,
+// flashes are loose and go by themselves. nothing here is saved
+pub struct LightSources {
+    // what the sky lights the surface with, white on a clear day, see Weather::sky
+    pub sky: Color,
+    flashes: Vec<Flash>,
+    time: f32,
+}
+
+impl Default for LightSources {
+    fn default() -> Self {
+        LightSources { sky: Color::WHITE, flashes: Vec::new(), time: 0.0 }
+    }
+}
+
+impl LightSources {
+    pub fn flash(&mut self, position: Vector2, light: Light, duration: f32) {
+        self.flashes.push(Flash { position, light, age: 0.0, duration: duration.max(0.01) });
+    }
+
+    pub fn update(&mut self, delta: f32) {
+        self.time += delta;
+        for flash in &mut self.flashes {
+            flash.age += delta;
+        }
+        self.flashes.retain(|f| f.age < f.duration);
+    }
+
+    pub fn clear(&mut self) {
+        self.flashes.clear();
+    }
+
+    // everything shining right now
+    pub fn lit(&self, ecs: &Ecs) -> Vec<Lit> {
+        let mut lit = Vec::new();
+        for (entity, light) in ecs.lights.iter() {
+            let Some(&position) = ecs.positions.get(entity) else {
+                continue;
+            };
+            let strength = light.strength(position.x * 0.37 + position.y * 0.11, self.time);
+            lit.push(Lit { position, color: light.color, radius: light.radius, strength });
+        }
+        for (i, flash) in self.flashes.iter().enumerate() {
+            let left = 1.0 - flash.age / flash.duration;
+            let strength = flash.light.strength(i as f32, self.time) * left;
+            lit.push(Lit { position: flash.position, color: flash.light.color, radius: flash.light.radius, strength });
+        }
+        lit
+    }
+
+    // the brightest channel of the light at position, ambient and everything shining added up.
+    // 0 is pitch black and 1 is daylight, bright lights can go over
+    pub fn level_at(&self, ecs: &Ecs, position: Vector2) -> f32 {
+        let lights: f32 = self
+            .lit(ecs)
+            .iter()
+            .map(|l| {
+                let falloff = (1.0 - l.position.distance_to(position) / l.radius).max(0.0);
+                let brightest = l.color.r.max(l.color.g).max(l.color.b) as f32 / 255.0;
+                brightest * l.strength * falloff
+            })
+            .sum();
+        let sky = self.sky.r.max(self.sky.g).max(self.sky.b) as f32 / 255.0;
+        ambient(position.y) * sky + lights
+    }
+}
+
+// the light map: the sky's light by depth with the lights added on, drawn into a texture the size
+// of the screen and multiplied over the lit part of the scene, see Game::draw_scene
+#[derive(Default)]
+pub struct LightMap {
+    texture: Option<RenderTexture2D>,
+}
+
+impl LightMap {
+    // draws the light map for what the camera sees. view is in world pixels, see Game::view.
+    // without a texture nothing is lit differently, the scene is drawn as it is
+    pub fn render(&mut self, d: &mut RaylibDrawHandle, thread: &RaylibThread, camera: Camera2D, view: Rectangle, sky: Color, lights: &[Lit]) {
+        crate::profile_scope!("light_map");
+        let (width, height) = (d.get_screen_width(), d.get_screen_height());
+        if self.texture.as_ref().is_none_or(|t| t.texture.width != width || t.texture.height != height) {
+            self.texture = match d.load_render_texture(thread, width as u32, height as u32) {
+                Ok(texture) => Some(texture),
+                Err(e) => {
+                    crate::log_error!("could not make the light map, the scene stays unlit: {}", e);
+                    None
+                }
+            };
+        }
+        let Some(texture) = &mut self.texture else {
+            return;
+        };
+        let s = scale() as f32;
+        let mut t = d.begin_texture_mode(thread, texture);
+        t.clear_background(Color::BLACK);
+        let mut world = t.begin_mode2D(camera);
+        // a margin on both sides, the camera shakes past the view now and then
+        let (left, right) = ((view.x - 8.0) * s, (view.x + view.width + 8.0) * s);
+        let band = (view.height + 16.0) / AMBIENT_BANDS as f32;
+        for i in 0..AMBIENT_BANDS {
+            let top = view.y - 8.0 + i as f32 * band;
+            let shade = |y: f32| {
+                let level = ambient(y);
+                Color::new((sky.r as f32 * level) as u8, (sky.g as f32 * level) as u8, (sky.b as f32 * level) as u8, 255)
+            };
+            world.draw_rectangle_gradient_v(left as i32, (top * s) as i32, (right - left) as i32, (band * s).ceil() as i32 + 1, shade(top), shade(top + band));
+        }
+        let mut added = world.begin_blend_mode(BlendMode::BLEND_ADDITIVE);
+        for lit in lights {
+            let strength = lit.strength.clamp(0.0, 1.0);
+            let color = Color::new(
+                (lit.color.r as f32 * strength) as u8,
+                (lit.color.g as f32 * strength) as u8,
+                (lit.color.b as f32 * strength) as u8,
+                255,
+            );
+            let center = lit.position * s;
+            added.draw_circle_gradient(center.x as i32, center.y as i32, lit.radius * s, color, Color::new(0, 0, 0, 0));
+        }
+    }
+
+    // on the gpu, 4 bytes a pixel
+    pub fn bytes(&self) -> usize {
+        self.texture.as_ref().map_or(0, |t| t.texture.width as usize * t.texture.height as usize * 4)
+    }
+
+    // multiplies whatever has been drawn so far by the light map, in screen space
+    pub fn apply(&self, d: &mut impl RaylibDraw) {
+        let Some(texture) = &self.texture else {
+            return;
+        };
+        let (width, height) = (texture.texture.width as f32, texture.texture.height as f32);
+        let mut multiplied = d.begin_blend_mode(BlendMode::BLEND_MULTIPLIED);
+        // render textures come out upside down
+        multiplied.draw_texture_rec(texture.texture(), Rectangle::new(0.0, 0.0, width, -height), Vector2::zero(), Color::WHITE);
+    }
+}
@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const LOG_PATH: &str = "spellcoder.log";
+// filters in the same form as the console's log command, e.g. "info,spellcoder::audio=debug"
+pub const LOG_ENV: &str = "SPELLCODER_LOG";
+// the file starts over past this many bytes, the old one moves to .1, .1 to .2 and so on
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+const KEEP_FILES: usize = 3;
+// lines waiting for the console, the oldest are dropped if nobody picks them up
+const MAX_PENDING: usize = 100;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Level::Error => "error",
+            Level::Warn => "warn",
+            Level::Info => "info",
+            Level::Debug => "debug",
+            Level::Trace => "trace",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Level> {
+        match name.to_lowercase().as_str() {
+            "error" => Some(Level::Error),
+            "warn" | "warning" => Some(Level::Warn),
+            "info" => Some(Level::Info),
+            "debug" => Some(Level::Debug),
+            "trace" => Some(Level::Trace),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+// one logger for the whole process, everything goes through the log_* macros
+pub struct Logger {
+    level: Level,
+    // module path prefix to the most detailed level logged for it, the longest match wins
+    filters: HashMap<String, Level>,
+    file: Option<File>,
+    // where file was opened, rotation moves this one along
+    path: Option<PathBuf>,
+    written: u64,
+    // mirrored to the in-game console, at this level or more severe
+    console_level: Level,
+    pending: Vec<String>,
+}
+
+impl Logger {
+    fn new() -> Self {
+        let mut logger = Logger {
+            level: Level::Info,
+            filters: HashMap::new(),
+            file: None,
+            path: None,
+            written: 0,
+            console_level: Level::Warn,
+            pending: Vec::new(),
+        };
+        if let Ok(filters) = std::env::var(LOG_ENV) {
+            if let Err(e) = logger.set_filters(&filters) {
+                eprintln!("{}: {}", LOG_ENV, e);
+            }
+        }
+        logger
+    }
+
+    fn enabled(&self, module: &str, level: Level) -> bool {
+        let limit = self
+            .filters
+            .iter()
+            .filter(|(prefix, _)| module.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or(self.level, |(_, level)| *level);
+        level <= limit
+    }
+
+    // a comma separated list of levels, bare for the default or module=level for one module
+    pub fn set_filters(&mut self, spec: &str) -> Result<(), String> {
+        for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            match part.split_once('=') {
+                Some((module, level)) => {
+                    let level = Level::parse(level).ok_or(format!("unknown level {}", level))?;
+                    self.filters.insert(module.to_string(), level);
+                }
+                None => self.level = Level::parse(part).ok_or(format!("unknown level {}", part))?,
+            }
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, module: &str, level: Level, message: &str) {
+        let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        let line = format!("{} {:5} {}: {}", seconds, level.name(), module, message);
+        eprintln!("{}", line);
+        if let Some(file) = &mut self.file {
+            if writeln!(file, "{}", line).is_ok() {
+                self.written += line.len() as u64 + 1;
+            }
+        }
+        if self.written > MAX_FILE_SIZE {
+            self.rotate();
+        }
+        if level <= self.console_level {
+            if self.pending.len() >= MAX_PENDING {
+                self.pending.remove(0);
+            }
+            self.pending.push(format!("[{}] {}", level, message));
+        }
+    }
+
+    fn rotate(&mut self) {
+        self.file = None;
+        let Some(path) = self.path.take() else {
+            return;
+        };
+        for i in (1..KEEP_FILES).rev() {
+            let _ = fs::rename(numbered(&path, i), numbered(&path, i + 1));
+        }
+        let _ = fs::rename(&path, numbered(&path, 1));
+        self.open(path);
+    }
+
+    fn open(&mut self, path: PathBuf) {
+        match OpenOptions::new().create(true).append(true).open(&path) {
+            Ok(file) => {
+                self.written = file.metadata().map_or(0, |m| m.len());
+                self.file = Some(file);
+                self.path = Some(path);
+            }
+            Err(e) => eprintln!("could not open {}: {}", path.display(), e),
+        }
+    }
+}
+
+// the log file's name with .n on the end, where rotation keeps the older ones
+fn numbered(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
+
+fn logger() -> &'static Mutex<Logger> {
+    static LOGGER: OnceLock<Mutex<Logger>> = OnceLock::new();
+    LOGGER.get_or_init(|| Mutex::new(Logger::new()))
+}
+
+// starts writing to the log file too, before this everything only goes to stderr
+pub fn init(path: impl Into<PathBuf>) {
+    if let Ok(mut logger) = logger().lock() {
+        logger.open(path.into());
+    }
+}
+
+pub fn set_filters(spec: &str) -> Result<(), String> {
+    logger().lock().map_err(|e| e.to_string())?.set_filters(spec)
+}
+
+pub fn set_console_level(level: Level) {
+    if let Ok(mut logger) = logger().lock() {
+        logger.console_level = level;
+    }
+}
+
+// what was logged for the console since last time, see Game::update_world
+pub fn take_console_lines() -> Vec<String> {
+    logger().lock().map(|mut l| std::mem::take(&mut l.pending)).unwrap_or_default()
+}
+
+// used by the macros, call those instead
+pub fn log(module: &str, level: Level, args: fmt::Arguments) {
+    let Ok(mut logger) = logger().lock() else {
+        return;
+    };
+    if logger.enabled(module, level) {
+        logger.write(module, level, &args.to_string());
+    }
+}
+
+#[macro_export]
+macro_rules! log_error {
+    ($($arg:tt)*) => { $crate::logging::log(module_path!(), $crate::logging::Level::Error, format_args!($($arg)*)) };
+}
+
+#[macro_export]
+macro_rules! log_warn {
+    ($($arg:tt)*) => { $crate::logging::log(module_path!(), $crate::logging::Level::Warn, format_args!($($arg)*)) };
+}
+
+#[macro_export]
+macro_rules! log_info {
+    ($($arg:tt)*) => { $crate::logging::log(module_path!(), $crate::logging::Level::Info, format_args!($($arg)*)) };
+}
+
+#[macro_export]
+macro_rules! log_debug {
+    ($($arg:tt)*) => { $crate::logging::log(module_path!(), $crate::logging::Level::Debug, format_args!($($arg)*)) };
+}
+
+#[macro_export]
+macro_rules! log_trace {
+    ($($arg:tt)*) => { $crate::logging::log(module_path!(), $crate::logging::Level::Trace, format_args!($($arg)*)) };
+}
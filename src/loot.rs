@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use rand::Rng;
+use raylib::prelude::*;
+use serde::Deserialize;
+
+use crate::error::SpellcoderError;
+use crate::material::{MaterialId, MaterialRegistry};
+
+// next to materials.json, missing means only the materials' own drops
+pub const LOOT_PATH: &str = "loot.json";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+pub enum Rarity {
+    #[default]
+    Common,
+    Uncommon,
+    Rare,
+    Epic,
+}
+
+impl Rarity {
+    // the pickup's name is drawn in it, common ones keep their own color and go unnamed
+    pub fn color(&self) -> Color {
+        match self {
+            Rarity::Common => Color::new(200, 200, 210, 255),
+            Rarity::Uncommon => Color::new(90, 200, 90, 255),
+            Rarity::Rare => Color::new(80, 140, 240, 255),
+            Rarity::Epic => Color::new(190, 90, 230, 255),
+        }
+    }
+}
+
+fn one() -> u32 {
+    1
+}
+
+fn always() -> f32 {
+    1.0
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct LootEntry {
+    pub item: String,
+    // how likely against the other entries of the table
+    #[serde(default = "one")]
+    pub weight: u32,
+    #[serde(default = "one")]
+    pub min: u32,
+    #[serde(default = "one")]
+    pub max: u32,
+    #[serde(default)]
+    pub rarity: Rarity,
+}
+
+// rolls times, each roll giving one entry picked by weight with the given chance. guaranteed
+// entries drop every time on top of that
+#[derive(Clone, Debug, Deserialize)]
+pub struct LootTable {
+    #[serde(default = "one")]
+    pub rolls: u32,
+    #[serde(default = "always")]
+    pub chance: f32,
+    #[serde(default)]
+    pub entries: Vec<LootEntry>,
+    #[serde(default)]
+    pub guaranteed: Vec<LootEntry>,
+}
+
+// what a roll came up with, one pickup per count
+#[derive(Clone, Debug)]
+pub struct Loot {
+    pub item: String,
+    pub count: u32,
+    pub rarity: Rarity,
+}
+
+// every table in loot.json by id. materials name theirs in materials.json, enemies get theirs
+// when they spawn, see ecs::spawn_enemy
+#[derive(Clone, Default)]
+pub struct LootTables {
+    tables: HashMap<String, LootTable>,
+}
+
+impl LootTables {
+    // a missing file means no tables. broken tables are left out, and materials naming a table
+    // that isn't there are added to errors
+    pub fn load(path: &str, materials: &MaterialRegistry, errors: &mut Vec<SpellcoderError>) -> Self {
+        let mut loaded = LootTables::default();
+        if Path::new(path).exists() {
+            let tables: HashMap<String, LootTable> = match fs::read_to_string(path)
+                .map_err(|e| SpellcoderError::io(path, e))
+                .and_then(|text| serde_json::from_str(&text).map_err(|e| SpellcoderError::parse(path, e)))
+            {
+                Ok(tables) => tables,
+                Err(e) => {
+                    errors.push(e);
+                    HashMap::new()
+                }
+            };
+            for (id, table) in tables {
+                let broken = table.entries.iter().chain(&table.guaranteed).find(|e| e.min > e.max || e.max == 0);
+                if let Some(entry) = broken {
+                    errors.push(SpellcoderError::invalid(path, format!("{} drops between {} and {} {}", id, entry.min, entry.max, entry.item)));
+                    continue;
+                }
+                if table.entries.iter().map(|e| e.weight).sum::<u32>() == 0 && table.guaranteed.is_empty() {
+                    errors.push(SpellcoderError::invalid(path, format!("{} never drops anything", id)));
+                    continue;
+                }
+                loaded.tables.insert(id, table);
+            }
+        }
+        for i in 0..materials.count() {
+            let material = materials.get(MaterialId(i as u16));
+            if let Some(id) = material.loot.as_ref().filter(|id| !loaded.tables.contains_key(*id)) {
+                errors.push(SpellcoderError::invalid(path, format!("{} drops from {}, which isn't a loot table", material.name, id)));
+            }
+        }
+        loaded
+    }
+
+    pub fn get(&self, id: &str) -> Option<&LootTable> {
+        self.tables.get(id)
+    }
+
+    // the best any table gives the item out as, so it looks the same in the inventory as it
+    // did lying on the ground
+    pub fn rarity(&self, item: &str) -> Rarity {
+        let entries = self.tables.values().flat_map(|t| t.entries.iter().chain(&t.guaranteed));
+        entries.filter(|e| e.item == item).map(|e| e.rarity).max().unwrap_or_default()
+    }
+
+    // nothing for a table that doesn't exist
+    pub fn roll(&self, id: &str, rng: &mut impl Rng) -> Vec<Loot> {
+        let Some(table) = self.get(id) else {
+            return Vec::new();
+        };
+        let mut loot: Vec<Loot> = table.guaranteed.iter().map(|e| roll_count(e, rng)).collect();
+        let total: u32 = table.entries.iter().map(|e| e.weight).sum();
+        for _ in 0..table.rolls {
+            if total == 0 || rng.gen::<f32>() >= table.chance {
+                continue;
+            }
+            let mut pick = rng.gen_range(0..total);
+            for entry in &table.entries {
+                if pick < entry.weight {
+                    loot.push(roll_count(entry, rng));
+                    break;
+                }
+                pick -= entry.weight;
+            }
+        }
+        loot
+    }
+}
+
+fn roll_count(entry: &LootEntry, rng: &mut impl Rng) -> Loot {
+    Loot { item: entry.item.clone(), count: rng.gen_range(entry.min..=entry.max), rarity: entry.rarity }
+}
@@ -0,0 +1,305 @@
+use std::collections::HashMap;
+use std::fs;
+
+use raylib::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Result, SpellcoderError};
+use crate::status::StatusKind;
+
+pub const MATERIALS_PATH: &str = "materials.json";
+// used when materials.json is missing or broken, so the world still has something to be made of
+const BUILTIN_MATERIALS: &str = include_str!("../materials.json");
+// the game refers to these by name, a registry without them is rejected
+const REQUIRED: &[&str] = &["AIR", "BLOCK", "STONE", "GRASS", "CRYSTAL", "BEDROCK", "SMOKE", "FIRE", "DIRT", "MANA_ORE", "METAL"];
+
+// index into the registry, AIR is always 0
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MaterialId(pub u16);
+
+pub const AIR: MaterialId = MaterialId(0);
+
+// how a material moves on its own
+#[derive(Clone, Copy, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub enum Behavior {
+    #[default]
+    Static,
+    // falls, slides off slopes and piles up
+    Powder,
+    // falls, then looks up to this many pixels sideways for somewhere lower and flows toward it
+    Liquid { spread: i64 },
+    // drifts upwards and sideways, loses this much alpha a second and is gone at zero
+    Gas { fade: f32 },
+}
+
+// colors that change over time, applied on top of the pixel's own color when drawing
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum MaterialAnimation {
+    // brightness pulses, out of step from pixel to pixel so it glitters
+    Shimmer { speed: f32, amount: f32 },
+    // brightness waves travelling along x
+    Flow { speed: f32, wavelength: f32, amount: f32 },
+}
+
+// generated pixels get the base color, each channel nudged by up to variation so surfaces aren't flat
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct ColorRule {
+    pub base: [u8; 4],
+    #[serde(default)]
+    pub variation: u8,
+}
+
+// standing in the material keeps the status going
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct ContactEffect {
+    pub status: StatusKind,
+    pub magnitude: f32,
+}
+
+// what breaking a pixel of the material can leave behind
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Drop {
+    pub item: String,
+    #[serde(default = "always")]
+    pub chance: f32, // 0 to 1
+}
+
+// turns into `becomes` when the chunk's temperature is below or above the limit
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Transition {
+    #[serde(default)]
+    pub below: Option<f32>,
+    #[serde(default)]
+    pub above: Option<f32>,
+    pub becomes: String,
+}
+
+// now and then spreads into a neighbouring `into` pixel, AIR meaning empty space.
+// exposed growth only reaches pixels with open space above, and with `near` set there has to
+// be some of that material within radius of where it grows
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GrowthRule {
+    pub into: String,
+    pub chance: f32, // 0 to 1, each time the scheduler visits the chunk
+    #[serde(default)]
+    pub exposed: bool,
+    #[serde(default)]
+    pub near: Option<String>,
+    #[serde(default = "default_radius")]
+    pub radius: i64,
+}
+
+// touching `with` turns this material into `becomes` and uses the other one up
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Reaction {
+    pub with: String,
+    pub becomes: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Material {
+    pub name: String,
+    #[serde(default)]
+    pub behavior: Behavior,
+    // heavier materials sink through lighter ones that can move
+    #[serde(default = "default_density")]
+    pub density: f32,
+    #[serde(default)]
+    pub flammability: f32, // 0 never burns, 1 catches at once
+    // degrees each pixel of it adds to its chunk's temperature, negative cools
+    #[serde(default)]
+    pub heat: f32,
+    #[serde(default)]
+    pub transitions: Vec<Transition>,
+    // 0 insulates, 1 carries a shock as far as it can go, see electricity.rs
+    #[serde(default)]
+    pub conductivity: f32,
+    // damage a pixel takes before breaking, see SpellComponent::Break. 0 breaks on any hit
+    #[serde(default)]
+    pub hardness: f32,
+    // nothing breaks it, no matter the damage
+    #[serde(default)]
+    pub unbreakable: bool,
+    #[serde(default)]
+    pub drop: Option<Drop>,
+    // id of a table in loot.json rolled on top of the drop, see LootTables
+    #[serde(default)]
+    pub loot: Option<String>,
+    #[serde(default = "default_color")]
+    pub color: ColorRule,
+    #[serde(default)]
+    pub animation: Option<MaterialAnimation>,
+    // 0 for none, up to 1 for something bright that blooms into its surroundings, see bloom.rs
+    #[serde(default)]
+    pub glow: f32,
+    // footstep sounds are picked at random from files starting with this, step_grass_1.wav etc
+    #[serde(default)]
+    pub footsteps: Option<String>,
+    #[serde(default)]
+    pub contact: Option<ContactEffect>,
+    #[serde(default)]
+    pub reactions: Vec<Reaction>,
+    #[serde(default)]
+    pub growth: Option<GrowthRule>,
+    // the reactions, transitions and growth with names looked up, filled in when loading
+    #[serde(skip)]
+    pub resolved_reactions: Vec<(MaterialId, MaterialId)>,
+    #[serde(skip)]
+    pub resolved_transitions: Vec<(Option<f32>, Option<f32>, MaterialId)>,
+    #[serde(skip)]
+    pub resolved_growth: Option<(MaterialId, Option<MaterialId>)>, // into, near
+}
+
+#[derive(Clone)]
+pub struct MaterialRegistry {
+    materials: Vec<Material>,
+    ids: HashMap<String, MaterialId>,
+}
+
+fn always() -> f32 {
+    1.0
+}
+
+fn default_density() -> f32 {
+    10.0
+}
+
+fn default_radius() -> i64 {
+    3
+}
+
+fn default_color() -> ColorRule {
+    ColorRule {
+        base: [255, 0, 255, 255],
+        variation: 0,
+    }
+}
+
+impl Material {
+    // whether things collide with it, you can fall through liquids and gases. AIR is static but
+    // placed AIR pixels are still empty space
+    pub fn is_solid(&self) -> bool {
+        matches!(self.behavior, Behavior::Static | Behavior::Powder) && self.name != "AIR"
+    }
+
+    // x and y are world pixel coordinates, the same pixel always gets the same shade
+    pub fn color_at(&self, x: i64, y: i64) -> ffi::Color {
+        let [r, g, b, a] = self.color.base;
+        let variation = self.color.variation as f32;
+        let hash = ((x as f32 * 12.9898 + y as f32 * 78.233).sin() * 43758.547).fract();
+        let offset = (hash * 2.0 - 1.0) * variation;
+        let channel = |c: u8| (c as f32 + offset).clamp(0.0, 255.0) as u8;
+        ffi::Color {
+            r: channel(r),
+            g: channel(g),
+            b: channel(b),
+            a,
+        }
+    }
+}
+
+impl MaterialAnimation {
+    // x and y are world pixel coordinates, time in seconds
+    pub fn apply(&self, color: ffi::Color, x: i64, y: i64, time: f32) -> ffi::Color {
+        let (x, y) = (x as f32, y as f32);
+        let brightness = match *self {
+            MaterialAnimation::Shimmer { speed, amount } => {
+                // a cheap hash of the position as the phase, neighbours shouldn't pulse together
+                let phase = (x * 12.9898 + y * 78.233).sin() * 43758.547;
+                1.0 + amount * (time * speed + phase).sin()
+            }
+            MaterialAnimation::Flow { speed, wavelength, amount } => {
+                1.0 + amount * ((x + y * 0.5) / wavelength - time * speed).sin()
+            }
+        };
+        let channel = |c: u8| (c as f32 * brightness).clamp(0.0, 255.0) as u8;
+        ffi::Color {
+            r: channel(color.r),
+            g: channel(color.g),
+            b: channel(color.b),
+            a: color.a,
+        }
+    }
+}
+
+impl MaterialRegistry {
+    pub fn load(path: &str) -> Result<Self> {
+        let text = fs::read_to_string(path).map_err(|e| SpellcoderError::io(path, e))?;
+        MaterialRegistry::parse(path, &text)
+    }
+
+    // the copy compiled into the game, for when materials.json is missing or broken
+    pub fn builtin() -> Result<Self> {
+        MaterialRegistry::parse("built in materials", BUILTIN_MATERIALS)
+    }
+
+    fn parse(path: &str, text: &str) -> Result<Self> {
+        let materials = serde_json::from_str::<Vec<Material>>(text).map_err(|e| SpellcoderError::parse(path, e))?;
+        MaterialRegistry::resolve(materials).map_err(|reason| SpellcoderError::invalid(path, reason))
+    }
+
+    // checks the materials make sense together and looks up the names they refer to each other by
+    fn resolve(mut materials: Vec<Material>) -> std::result::Result<Self, String> {
+        if materials.first().map(|m| m.name.as_str()) != Some("AIR") {
+            return Err("the first material has to be AIR".to_string());
+        }
+        let mut ids = HashMap::new();
+        for (i, material) in materials.iter().enumerate() {
+            if ids.insert(material.name.clone(), MaterialId(i as u16)).is_some() {
+                return Err(format!("{} is defined twice", material.name));
+            }
+        }
+        if let Some(missing) = REQUIRED.iter().find(|name| !ids.contains_key(**name)) {
+            return Err(format!("missing {}", missing));
+        }
+        for material in &mut materials {
+            let mut resolved = Vec::new();
+            for reaction in &material.reactions {
+                let with = ids.get(&reaction.with).ok_or(format!("{} reacts with unknown {}", material.name, reaction.with))?;
+                let becomes = ids.get(&reaction.becomes).ok_or(format!("{} becomes unknown {}", material.name, reaction.becomes))?;
+                resolved.push((*with, *becomes));
+            }
+            material.resolved_reactions = resolved;
+            let mut resolved = Vec::new();
+            for transition in &material.transitions {
+                let becomes = ids.get(&transition.becomes).ok_or(format!("{} becomes unknown {}", material.name, transition.becomes))?;
+                resolved.push((transition.below, transition.above, *becomes));
+            }
+            material.resolved_transitions = resolved;
+            if let Some(growth) = &material.growth {
+                let into = ids.get(&growth.into).ok_or(format!("{} grows into unknown {}", material.name, growth.into))?;
+                let near = match &growth.near {
+                    Some(near) => Some(*ids.get(near).ok_or(format!("{} grows near unknown {}", material.name, near))?),
+                    None => None,
+                };
+                material.resolved_growth = Some((*into, near));
+            }
+        }
+        Ok(MaterialRegistry { materials, ids })
+    }
+
+    // how many materials there are, ids run from 0 to this
+    pub fn count(&self) -> usize {
+        self.materials.len()
+    }
+
+    // ids only come from this registry, so they are always in range
+    pub fn get(&self, id: MaterialId) -> &Material {
+        &self.materials[id.0 as usize]
+    }
+
+    pub fn id(&self, name: &str) -> Option<MaterialId> {
+        self.ids.get(name).copied()
+    }
+
+    // for the materials the game itself uses, see REQUIRED
+    pub fn named(&self, name: &str) -> MaterialId {
+        self.id(name).unwrap_or(AIR)
+    }
+
+    // what building with the item makes, the first material that drops it
+    pub fn placed_by(&self, item: &str) -> Option<MaterialId> {
+        let i = self.materials.iter().position(|m| m.drop.as_ref().is_some_and(|d| d.item == item))?;
+        Some(MaterialId(i as u16))
+    }
+}
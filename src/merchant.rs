@@ -0,0 +1,212 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use rand::Rng;
+use raylib::prelude::*;
+use serde::Deserialize;
+
+use crate::ecs::{self, Entity};
+use crate::error::SpellcoderError;
+use crate::inventory::Inventory;
+use crate::lighting::Light;
+use crate::rng::StreamRng;
+use crate::World;
+
+// next to materials.json, missing means the merchant never comes
+pub const MERCHANT_PATH: &str = "merchant.json";
+// picked up into the purse instead of the inventory, see item::update_items
+pub const CURRENCY: &str = "coin";
+// world pixels between the merchant and the player's center to trade
+pub const TRADE_RANGE: f32 = 14.0;
+// seconds between one visit ending and the next starting
+const VISIT_INTERVAL: f32 = 150.0;
+// seconds the merchant sticks around for
+const VISIT_LENGTH: f32 = 120.0;
+// world pixels to the side of and above the player the merchant turns up at
+const ARRIVE_DISTANCE: f32 = 20.0;
+const ARRIVE_HEIGHT: f32 = 8.0;
+const LANTERN_COLOR: Color = Color::new(255, 190, 110, 255);
+// world pixels
+const LANTERN_RADIUS: f32 = 18.0;
+
+fn one() -> u32 {
+    1
+}
+
+fn always() -> f32 {
+    1.0
+}
+
+fn every_few_minutes() -> f32 {
+    180.0
+}
+
+// something the merchant may have for sale, count picked between min and max on every restock
+#[derive(Clone, Debug, Deserialize)]
+pub struct Offer {
+    pub item: String,
+    pub price: u32,
+    #[serde(default = "one")]
+    pub min: u32,
+    #[serde(default = "one")]
+    pub max: u32,
+    // how likely it's there at all after a restock
+    #[serde(default = "always")]
+    pub chance: f32,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct Catalog {
+    // seconds between restocks
+    #[serde(default = "every_few_minutes")]
+    pub refresh: f32,
+    #[serde(default)]
+    pub stock: Vec<Offer>,
+    // what the merchant pays for one of each item, anything else can't be sold
+    #[serde(default)]
+    pub buys: BTreeMap<String, u32>,
+}
+
+impl Catalog {
+    // a missing file means an empty catalog. offers that could never be bought are left out and
+    // added to errors
+    pub fn load(path: &str, errors: &mut Vec<SpellcoderError>) -> Self {
+        if !Path::new(path).exists() {
+            return Catalog::default();
+        }
+        let mut catalog: Catalog = match fs::read_to_string(path)
+            .map_err(|e| SpellcoderError::io(path, e))
+            .and_then(|text| serde_json::from_str(&text).map_err(|e| SpellcoderError::parse(path, e)))
+        {
+            Ok(catalog) => catalog,
+            Err(e) => {
+                errors.push(e);
+                return Catalog::default();
+            }
+        };
+        catalog.stock.retain(|offer| {
+            let broken = offer.min > offer.max || offer.max == 0 || offer.item == CURRENCY;
+            if broken {
+                errors.push(SpellcoderError::invalid(path, format!("{} can't be sold between {} and {}", offer.item, offer.min, offer.max)));
+            }
+            !broken
+        });
+        if catalog.refresh <= 0.0 {
+            errors.push(SpellcoderError::invalid(path, "refresh has to be more than 0 seconds"));
+            catalog.refresh = every_few_minutes();
+        }
+        catalog
+    }
+}
+
+// one line of what's for sale right now
+#[derive(Clone, Debug)]
+pub struct Listing {
+    pub item: String,
+    pub price: u32,
+    pub count: u32,
+}
+
+// the wandering merchant. comes and goes by itself and restocks on a timer whether it's around
+// or not. the stock isn't saved, a loaded world gets a fresh one
+pub struct Merchant {
+    pub catalog: Catalog,
+    pub stock: Vec<Listing>,
+    // the npc while it's visiting
+    pub entity: Option<Entity>,
+    restock_timer: f32,
+    visit_timer: f32,
+    rng: StreamRng,
+}
+
+impl Merchant {
+    // rng is the trade stream, see RngService
+    pub fn new(catalog: Catalog, rng: StreamRng) -> Self {
+        let mut merchant = Merchant { catalog, stock: Vec::new(), entity: None, restock_timer: 0.0, visit_timer: 0.0, rng };
+        merchant.restock();
+        merchant
+    }
+
+    pub fn restock(&mut self) {
+        self.restock_timer = 0.0;
+        self.stock.clear();
+        for offer in &self.catalog.stock {
+            if self.rng.gen::<f32>() >= offer.chance {
+                continue;
+            }
+            let count = self.rng.gen_range(offer.min..=offer.max);
+            self.stock.push(Listing { item: offer.item.clone(), price: offer.price, count });
+        }
+    }
+
+    // restocks, and sends the merchant in or lets them leave around center
+    pub fn update(&mut self, world: &mut World, center: Vector2, delta: f32) {
+        self.restock_timer += delta;
+        if self.restock_timer >= self.catalog.refresh {
+            self.restock();
+        }
+        if let Some(entity) = self.entity.filter(|e| !world.ecs.is_alive(*e)) {
+            crate::log_debug!("the merchant {:?} left", entity);
+            self.entity = None;
+        }
+        if self.entity.is_some() || self.catalog.stock.is_empty() {
+            return;
+        }
+        self.visit_timer += delta;
+        if self.visit_timer < VISIT_INTERVAL {
+            return;
+        }
+        let side = if self.rng.gen_bool(0.5) { 1.0 } else { -1.0 };
+        let position = center + Vector2::new(side * ARRIVE_DISTANCE, -ARRIVE_HEIGHT);
+        // inside the terrain, try again next frame
+        if world.is_solid(position.x.floor() as i64, position.y.floor() as i64) {
+            return;
+        }
+        self.visit_timer = 0.0;
+        let entity = ecs::spawn_npc(&mut world.ecs, "Merchant", position, Color::new(200, 160, 60, 255), VISIT_LENGTH);
+        // a lantern, so they can be found in the dark
+        world.ecs.lights.insert(entity, Light::new(LANTERN_COLOR, LANTERN_RADIUS, 0.8).flickering(0.25));
+        self.entity = Some(entity);
+    }
+
+    // close enough to talk to
+    pub fn nearby(&self, world: &World, center: Vector2) -> bool {
+        let position = self.entity.and_then(|e| world.ecs.positions.get(e));
+        position.is_some_and(|p| p.distance_to(center) <= TRADE_RANGE)
+    }
+
+    // what the merchant pays for one, if they want it at all
+    pub fn offer_for(&self, item: &str) -> Option<u32> {
+        self.catalog.buys.get(item).copied()
+    }
+
+    // one of the listing, if there's the money and the room for it
+    pub fn buy(&mut self, index: usize, coins: &mut u32, inventory: &mut Inventory) -> Result<String, String> {
+        let listing = self.stock.get(index).ok_or("Sold out")?;
+        if *coins < listing.price {
+            return Err(format!("{} costs {} {}s, {} short", listing.item, listing.price, CURRENCY, listing.price - *coins));
+        }
+        if !inventory.has_room(&listing.item) {
+            return Err(format!("No room for {}", listing.item));
+        }
+        inventory.add(&listing.item, 1);
+        *coins -= listing.price;
+        let message = format!("Bought {} for {}", listing.item, listing.price);
+        self.stock[index].count -= 1;
+        if self.stock[index].count == 0 {
+            self.stock.remove(index);
+        }
+        Ok(message)
+    }
+
+    // one of the item out of the inventory, for whatever the merchant pays
+    pub fn sell(&self, item: &str, coins: &mut u32, inventory: &mut Inventory) -> Result<String, String> {
+        let price = self.offer_for(item).ok_or_else(|| format!("The merchant doesn't want {}", item))?;
+        if !inventory.remove(item, 1) {
+            return Err(format!("No {} to sell", item));
+        }
+        *coins += price;
+        Ok(format!("Sold {} for {}", item, price))
+    }
+}
@@ -0,0 +1,314 @@
+use rand::Rng;
+use raylib::prelude::*;
+
+use crate::rng::StreamRng;
+use crate::{scale, World};
+
+// past this many live particles new ones are simply not spawned
+pub const PARTICLE_BUDGET: usize = 2000;
+// ambient motes spawned per second across the visible area
+const MOTE_RATE: f32 = 6.0;
+// settled snow and ash stay around this long at most
+const SETTLE_LIFE: f32 = 1.5;
+// velocity kept after hitting terrain, debris sliding along the ground keeps this much sideways
+const DEBRIS_BOUNCE: f32 = 0.45;
+const DEBRIS_FRICTION: f32 = 0.7;
+
+// positions and sizes are in world pixels, velocities in world pixels per second
+#[derive(Clone, Copy, Debug)]
+pub struct Particle {
+    pub position: Vector2,
+    pub velocity: Vector2,
+    pub color: Color,
+    pub size: f32,
+    pub gravity: f32,
+    pub drag: f32, // fraction of velocity lost per second
+    pub life: f32,
+    pub max_life: f32,
+    pub fade_in: bool, // motes fade in as well as out so they don't pop
+    pub collision: Collision,
+    pub wind: f32, // how quickly it picks up the wind, 0 ignores it
+    pub streak: f32, // drawn as a line this many seconds of velocity long instead of a square
+}
+
+// what happens when a particle runs into a solid pixel
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Collision {
+    None, // flies straight through the terrain
+    Bounce(f32), // keeps this much velocity
+    Splash, // dies in a small splash
+    Settle, // stops where it landed and fades
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Emitter {
+    SpellCast(Color),
+    Debris(Color), // colored like the pixel that broke
+    LandingDust,
+    Mote,
+    DeathBurst,
+    Rain,
+    Splash,
+    Snow,
+    Ash,
+    // glowing specks drifting down in a mana surge
+    Mana,
+}
+
+pub struct ParticleSystem {
+    particles: Vec<Particle>,
+    budget: usize,
+    mote_timer: f32,
+    pub wind: f32, // world pixels per second, set by the weather
+    rng: StreamRng,
+}
+
+impl Emitter {
+    // how many particles one emit spawns
+    fn count(&self) -> usize {
+        match self {
+            Emitter::SpellCast(_) => 12,
+            Emitter::Debris(_) => 3,
+            Emitter::LandingDust => 8,
+            Emitter::Mote => 1,
+            Emitter::DeathBurst => 40,
+            Emitter::Rain | Emitter::Snow | Emitter::Ash | Emitter::Mana => 1,
+            Emitter::Splash => 3,
+        }
+    }
+
+    fn particle(&self, position: Vector2, rng: &mut impl Rng) -> Particle {
+        let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+        let direction = Vector2::new(angle.cos(), angle.sin());
+        let base = Particle {
+            position,
+            velocity: Vector2::zero(),
+            color: Color::WHITE,
+            size: 1.0,
+            gravity: 0.0,
+            drag: 0.0,
+            life: 1.0,
+            max_life: 1.0,
+            fade_in: false,
+            collision: Collision::None,
+            wind: 0.0,
+            streak: 0.0,
+        };
+        let particle = match *self {
+            Emitter::SpellCast(color) => Particle {
+                velocity: direction * rng.gen_range(20.0..50.0),
+                color,
+                size: 0.5,
+                drag: 3.0,
+                life: rng.gen_range(0.4..0.8),
+                ..base
+            },
+            Emitter::Debris(color) => Particle {
+                velocity: Vector2::new(rng.gen_range(-15.0..15.0), rng.gen_range(-30.0..-10.0)),
+                color,
+                size: rng.gen_range(0.5..1.0),
+                gravity: 60.0,
+                drag: 0.5,
+                life: rng.gen_range(1.5..2.5),
+                collision: Collision::Bounce(DEBRIS_BOUNCE),
+                ..base
+            },
+            Emitter::LandingDust => Particle {
+                velocity: Vector2::new(rng.gen_range(-25.0..25.0), rng.gen_range(-6.0..0.0)),
+                color: Color::new(170, 160, 140, 200),
+                size: rng.gen_range(0.5..1.0),
+                gravity: 10.0,
+                drag: 4.0,
+                life: rng.gen_range(0.3..0.6),
+                ..base
+            },
+            Emitter::Mote => Particle {
+                velocity: direction * rng.gen_range(1.0..4.0),
+                color: Color::new(255, 250, 200, 120),
+                size: 0.4,
+                gravity: -0.5,
+                life: rng.gen_range(3.0..5.0),
+                fade_in: true,
+                ..base
+            },
+            Emitter::DeathBurst => Particle {
+                velocity: direction * rng.gen_range(15.0..60.0),
+                color: if rng.gen_bool(0.5) { Color::new(200, 30, 30, 255) } else { Color::WHITE },
+                size: rng.gen_range(0.5..1.2),
+                gravity: 40.0,
+                drag: 1.5,
+                life: rng.gen_range(0.8..1.5),
+                ..base
+            },
+            Emitter::Rain => Particle {
+                velocity: Vector2::new(0.0, rng.gen_range(90.0..120.0)),
+                color: Color::new(140, 170, 230, 170),
+                size: 0.3,
+                gravity: 30.0,
+                life: 3.0,
+                collision: Collision::Splash,
+                wind: 2.0,
+                streak: 0.02,
+                ..base
+            },
+            Emitter::Splash => Particle {
+                velocity: Vector2::new(rng.gen_range(-12.0..12.0), rng.gen_range(-18.0..-6.0)),
+                color: Color::new(160, 190, 240, 160),
+                size: 0.3,
+                gravity: 80.0,
+                life: rng.gen_range(0.15..0.3),
+                ..base
+            },
+            Emitter::Snow => Particle {
+                velocity: Vector2::new(rng.gen_range(-3.0..3.0), rng.gen_range(8.0..14.0)),
+                color: Color::new(240, 245, 255, 220),
+                size: rng.gen_range(0.4..0.8),
+                life: 12.0,
+                collision: Collision::Settle,
+                wind: 0.8,
+                ..base
+            },
+            Emitter::Ash => Particle {
+                velocity: Vector2::new(rng.gen_range(-2.0..2.0), rng.gen_range(3.0..7.0)),
+                color: if rng.gen_bool(0.8) { Color::new(90, 85, 80, 200) } else { Color::new(255, 120, 40, 220) },
+                size: rng.gen_range(0.3..0.6),
+                life: 15.0,
+                collision: Collision::Settle,
+                wind: 0.5,
+                ..base
+            },
+            Emitter::Mana => Particle {
+                velocity: Vector2::new(rng.gen_range(-4.0..4.0), rng.gen_range(5.0..10.0)),
+                color: if rng.gen_bool(0.7) { Color::new(190, 140, 255, 220) } else { Color::new(140, 220, 255, 220) },
+                size: rng.gen_range(0.3..0.6),
+                life: 10.0,
+                fade_in: true,
+                wind: 0.6,
+                ..base
+            },
+        };
+        Particle { max_life: particle.life, ..particle }
+    }
+}
+
+impl ParticleSystem {
+    pub fn new(budget: usize, rng: StreamRng) -> Self {
+        ParticleSystem {
+            particles: Vec::new(),
+            budget,
+            mote_timer: 0.0,
+            wind: 0.0,
+            rng,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.particles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.particles.is_empty()
+    }
+
+    // what the live particles take, and the room kept for more
+    pub fn bytes(&self) -> usize {
+        self.particles.capacity() * std::mem::size_of::<Particle>()
+    }
+
+    pub fn spawn(&mut self, particle: Particle) {
+        if self.particles.len() < self.budget {
+            self.particles.push(particle);
+        }
+    }
+
+    pub fn emit(&mut self, emitter: Emitter, position: Vector2) {
+        for _ in 0..emitter.count() {
+            let particle = emitter.particle(position, &mut self.rng);
+            self.spawn(particle);
+        }
+    }
+
+    // sprinkles motes over view (world pixels) at a steady rate
+    pub fn emit_ambient(&mut self, view: Rectangle, delta: f32) {
+        self.mote_timer += delta * MOTE_RATE;
+        while self.mote_timer >= 1.0 {
+            self.mote_timer -= 1.0;
+            let position = Vector2::new(
+                self.rng.gen_range(view.x..view.x + view.width),
+                self.rng.gen_range(view.y..view.y + view.height),
+            );
+            self.emit(Emitter::Mote, position);
+        }
+    }
+
+    pub fn update(&mut self, world: &World, delta: f32) {
+        let mut splashes = Vec::new();
+        for p in &mut self.particles {
+            p.velocity.y += p.gravity * delta;
+            p.velocity.x += (self.wind - p.velocity.x) * (p.wind * delta).min(1.0);
+            p.velocity *= (1.0 - p.drag * delta).max(0.0);
+            match p.collision {
+                Collision::None => p.position += p.velocity * delta,
+                Collision::Bounce(bounce) => move_colliding(p, world, bounce, delta),
+                Collision::Splash | Collision::Settle => {
+                    let next = p.position + p.velocity * delta;
+                    if !world.is_solid(next.x.floor() as i64, next.y.floor() as i64) {
+                        p.position = next;
+                    } else if p.collision == Collision::Splash {
+                        splashes.push(p.position);
+                        p.life = 0.0;
+                    } else {
+                        *p = Particle {
+                            velocity: Vector2::zero(),
+                            gravity: 0.0,
+                            wind: 0.0,
+                            collision: Collision::None,
+                            life: p.life.min(SETTLE_LIFE),
+                            max_life: p.life.min(SETTLE_LIFE),
+                            ..*p
+                        };
+                    }
+                }
+            }
+            p.life -= delta;
+        }
+        self.particles.retain(|p| p.life > 0.0);
+        for position in splashes {
+            self.emit(Emitter::Splash, position);
+        }
+    }
+
+    // call inside the world's 2d mode
+    pub fn draw(&self, d: &mut impl RaylibDraw) {
+        let scale = scale() as f32;
+        for p in &self.particles {
+            let t = p.life / p.max_life;
+            let alpha = if p.fade_in { (t * (1.0 - t) * 4.0).min(1.0) } else { t };
+            let color = Color::new(p.color.r, p.color.g, p.color.b, (p.color.a as f32 * alpha) as u8);
+            let size = p.size * scale;
+            if p.streak > 0.0 {
+                d.draw_line_ex(p.position * scale, (p.position - p.velocity * p.streak) * scale, size, color);
+                continue;
+            }
+            d.draw_rectangle_v(p.position * scale - Vector2::new(size, size) * 0.5, Vector2::new(size, size), color);
+        }
+    }
+}
+
+// one axis at a time so debris hitting a wall keeps falling and debris hitting the floor keeps sliding
+fn move_colliding(p: &mut Particle, world: &World, bounce: f32, delta: f32) {
+    let solid = |position: Vector2| world.is_solid(position.x.floor() as i64, position.y.floor() as i64);
+    let step_x = Vector2::new(p.velocity.x * delta, 0.0);
+    if solid(p.position + step_x) {
+        p.velocity.x *= -bounce;
+    } else {
+        p.position += step_x;
+    }
+    let step_y = Vector2::new(0.0, p.velocity.y * delta);
+    if solid(p.position + step_y) {
+        p.velocity.y *= -bounce;
+        p.velocity.x *= DEBRIS_FRICTION;
+    } else {
+        p.position += step_y;
+    }
+}
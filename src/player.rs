@@ -0,0 +1,206 @@
+use raylib::prelude::*;
+
+use crate::equipment::Equipment;
+use crate::inventory::Inventory;
+use crate::regen::{ManaState, RegenModel};
+use crate::rules::WorldRules;
+use crate::skills::Bonuses;
+use crate::status::{self, StatusEffect};
+use crate::tuning;
+
+// seconds after the last hit that we still count as being in a fight
+const COMBAT_TIME: f32 = 6.0;
+// below this light level it's deep darkness, see LightSources::level_at
+pub const DARKNESS: f32 = 0.2;
+// sp per second deep darkness takes at its darkest
+const DARKNESS_DRAIN: f32 = 6.0;
+// fraction of sideways knockback lost per second
+const KNOCKBACK_DRAG: f32 = 4.0;
+// world pixels per frame a landing can come in at before it hurts
+const FALL_DAMAGE_SPEED: f32 = 6.0;
+
+pub struct Hit {
+    pub amount: f32,
+    pub source: Option<Vector2>,
+}
+
+// what one frame of movement did, for the sounds and effects that go with it
+pub struct Movement {
+    // on the floor when the frame started
+    pub grounded: bool,
+    pub jumped: bool,
+    // how fast they were falling when they hit the floor this frame
+    pub landed: Option<f32>,
+}
+
+pub struct Player {
+    pub position: Vector2,
+    pub size: Vector2,
+    pub camera: Camera2D,
+    pub hp: f32,
+    pub max_hp: f32,
+    pub mp: f32,
+    pub max_mp: f32,
+    pub sp: f32,
+    pub max_sp: f32,
+    pub status_effects: Vec<StatusEffect>,
+    pub recent_hits: Vec<Hit>,
+    pub combat_timer: f32,
+    pub spawn_point: Vector2,
+    pub inventory: Inventory,
+    pub equipment: Equipment,
+    // the purse, coins don't take up inventory slots, see merchant::CURRENCY
+    pub coins: u32,
+    pub knockback: Vector2, // world pixels per frame, added on top of walking
+    pub rules: WorldRules, // the world's, copied over whenever they are set
+    pub bonuses: Bonuses,  // from the skill tree and gear
+    pub mana: ManaState,
+    // seconds spent in deep darkness without a break, 0 in the light
+    pub darkness: f32,
+}
+
+impl Player {
+    pub fn new(position: Vector2) -> Self {
+        let player = Player {
+            position,
+            size: Vector2 {
+                x: 8.0,
+                y: 16.0
+            }, 
+            camera: Camera2D {
+                offset: position,
+                target: position,
+                rotation: 0.0,
+                zoom: 1.0
+            },
+            hp: 100.0,
+            max_hp: 100.0,
+            mp: 100.0,
+            max_mp: 100.0,
+            sp: 100.0,
+            max_sp: 100.0,
+            status_effects: Vec::new(),
+            recent_hits: Vec::new(),
+            combat_timer: 0.0,
+            spawn_point: position,
+            inventory: Inventory::default(),
+            equipment: Equipment::default(),
+            coins: 0,
+            knockback: Vector2::zero(),
+            rules: WorldRules::default(),
+            bonuses: Bonuses::default(),
+            mana: ManaState::default(),
+            darkness: 0.0,
+        };
+        // player.set_look_direction_vec2(Vector2 {
+        //     x: 0.0,
+        //     y: -PI / 2.0,
+        // });
+        player
+    }
+    // move camera without changing yaw & pitch
+    pub fn move_self(&mut self, delta: Vector2) {
+        self.position += delta;
+        self.camera.offset += delta;
+        self.camera.target += delta;
+    }
+
+    // one frame of walking, jumping and falling, nothing here needs a window. walk is -1 to 1,
+    // rising pushes up every frame it's held. jump is only asked on the floor, so a jump pressed
+    // in the air isn't used up. floor is the world pixel row the feet land on, vel is in world
+    // pixels per frame and carries over between frames
+    pub fn step(&mut self, vel: &mut Vector2, walk: f32, rising: bool, jump: impl FnOnce() -> bool, floor: f32, delta: f32) -> Movement {
+        let tuning = tuning::get().player.clone();
+        vel.x = walk * tuning.walk_speed * self.speed_multiplier() + self.knockback.x;
+        self.knockback.x *= (1.0 - KNOCKBACK_DRAG * delta).max(0.0);
+        let ground = floor - self.size.y;
+        let grounded = self.position.y >= ground;
+        let mut landed = None;
+        if !grounded {
+            vel.y += tuning.gravity * delta;
+        } else {
+            if vel.y > 0.0 {
+                landed = Some(vel.y);
+            }
+            if vel.y > FALL_DAMAGE_SPEED {
+                let feet = Vector2 { x: self.center().x, y: self.position.y + self.size.y + 1.0 };
+                self.damage((vel.y - FALL_DAMAGE_SPEED) * 10.0, Some(feet));
+            }
+            vel.y = 0.0;
+            self.move_self(Vector2 { x: 0.0, y: ground - self.position.y });
+        }
+        let jumped = grounded && jump();
+        if jumped || rising {
+            vel.y -= tuning.jump_speed;
+        }
+        // upward knockback lifts us off the ground, so it goes in after landing is handled
+        vel.y += std::mem::take(&mut self.knockback.y);
+        self.move_self(*vel);
+        Movement { grounded, jumped, landed }
+    }
+
+    pub fn center(&self) -> Vector2 {
+        self.position + self.size * 0.5
+    }
+
+    pub fn teleport(&mut self, position: Vector2) {
+        self.move_self(position - self.position);
+    }
+
+    // source is the world position the damage came from, if it has one
+    pub fn damage(&mut self, amount: f32, source: Option<Vector2>) {
+        let amount = amount * self.rules.difficulty.damage_taken();
+        self.hp = (self.hp - amount).max(0.0);
+        self.recent_hits.push(Hit { amount, source });
+        self.combat_timer = COMBAT_TIME;
+    }
+
+    pub fn knock(&mut self, impulse: Vector2) {
+        self.knockback += impulse;
+    }
+
+    pub fn in_combat(&self) -> bool {
+        self.combat_timer > 0.0
+    }
+
+    pub fn add_status(&mut self, effect: StatusEffect) {
+        status::add(&mut self.status_effects, effect);
+    }
+
+    // light is the level where the player is. deep darkness wears them out, the darker the faster
+    pub fn endure_darkness(&mut self, light: f32, delta: f32) {
+        if light >= DARKNESS || self.rules.creative {
+            self.darkness = 0.0;
+            return;
+        }
+        self.darkness += delta;
+        let depth = 1.0 - light.max(0.0) / DARKNESS;
+        self.sp = (self.sp - DARKNESS_DRAIN * depth * delta).max(0.0);
+    }
+
+    pub fn update_stats(&mut self, delta: f32) {
+        self.combat_timer = (self.combat_timer - delta).max(0.0);
+        let regen = self.rules.difficulty.regen();
+        let tuning = tuning::get().player.clone();
+        let model = RegenModel::new(&tuning, &self.bonuses);
+        self.mp += self.mana.regen(&model, self.mp, self.max_mp, delta) * regen;
+        self.sp += tuning.sp_regen * self.bonuses.sp_regen * regen * delta;
+        let tick = status::tick(&mut self.status_effects, delta);
+        // healing over time scales like regeneration, poison and burning like any other damage
+        let scaled = |amount: f32| if amount > 0.0 { amount * regen } else { amount * self.rules.difficulty.damage_taken() };
+        self.hp += scaled(tick.hp);
+        self.mp += scaled(tick.mp);
+        self.sp += scaled(tick.sp);
+        if self.rules.creative {
+            self.mp = self.max_mp;
+        }
+        self.hp = self.hp.clamp(0.0, self.max_hp);
+        self.mp = self.mp.clamp(0.0, self.max_mp);
+        self.sp = self.sp.clamp(0.0, self.max_sp);
+    }
+
+    // statuses and gear together
+    pub fn speed_multiplier(&self) -> f32 {
+        status::speed_multiplier(&self.status_effects) * self.bonuses.move_speed
+    }
+}
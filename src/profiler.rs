@@ -0,0 +1,188 @@
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::error::SpellcoderError;
+
+pub const TRACE_DIR: &str = "traces";
+// how much of each new frame goes into the averages, the rest is what they were
+const SMOOTHING: f32 = 0.1;
+// per frame, how fast a peak sinks back towards the average once nothing tops it
+const PEAK_DECAY: f32 = 0.98;
+// spans kept in one trace, a few minutes of play. past this the trace stops by itself so one
+// left running doesn't eat all the memory
+const MAX_SPANS: usize = 1_000_000;
+
+// checked before anything else on every span, so scopes cost next to nothing while no trace runs
+static TRACING: AtomicBool = AtomicBool::new(false);
+
+// one finished span, microseconds since the trace started
+struct Span {
+    name: &'static str,
+    start: f64,
+    duration: f64,
+}
+
+// the spans recorded since start_trace, for finish_trace to write out
+struct Trace {
+    started: Instant,
+    spans: Vec<Span>,
+}
+
+// a complete event in the chrome tracing format, what chrome://tracing and ui.perfetto.dev open
+#[derive(Serialize)]
+struct TraceEvent {
+    name: &'static str,
+    ph: &'static str,
+    ts: f64,
+    dur: f64,
+    pid: u32,
+    tid: u32,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TraceFile {
+    trace_events: Vec<TraceEvent>,
+    display_time_unit: &'static str,
+}
+
+fn trace() -> &'static Mutex<Option<Trace>> {
+    static TRACE: OnceLock<Mutex<Option<Trace>>> = OnceLock::new();
+    TRACE.get_or_init(|| Mutex::new(None))
+}
+
+pub fn is_tracing() -> bool {
+    TRACING.load(Ordering::Relaxed)
+}
+
+// starts recording every span, whatever was recorded before is thrown away
+pub fn start_trace() {
+    if let Ok(mut trace) = trace().lock() {
+        *trace = Some(Trace { started: Instant::now(), spans: Vec::new() });
+        TRACING.store(true, Ordering::Relaxed);
+    }
+}
+
+// stops recording and writes what was recorded into dir, returns the file's path
+pub fn finish_trace(dir: &str) -> Result<String, SpellcoderError> {
+    TRACING.store(false, Ordering::Relaxed);
+    let trace = trace().lock().ok().and_then(|mut t| t.take()).ok_or_else(|| SpellcoderError::invalid(TRACE_DIR, "no trace is running"))?;
+    let trace_events = trace
+        .spans
+        .iter()
+        .map(|s| TraceEvent { name: s.name, ph: "X", ts: s.start, dur: s.duration, pid: 1, tid: 1 })
+        .collect();
+    let file = TraceFile { trace_events, display_time_unit: "ms" };
+    fs::create_dir_all(dir).map_err(|e| SpellcoderError::save(dir, e))?;
+    let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let path = Path::new(dir).join(format!("trace_{}.json", seconds)).to_string_lossy().to_string();
+    let text = serde_json::to_string(&file).map_err(|e| SpellcoderError::invalid(path.as_str(), e.to_string()))?;
+    fs::write(&path, text).map_err(|e| SpellcoderError::save(path.as_str(), e))?;
+    crate::log_info!("wrote {} spans to {}", trace.spans.len(), path);
+    Ok(path)
+}
+
+// records a span from started until now into the trace, if one is running
+pub fn span(name: &'static str, started: Instant) {
+    if !is_tracing() {
+        return;
+    }
+    let duration = started.elapsed();
+    let Ok(mut trace) = trace().lock() else {
+        return;
+    };
+    let Some(trace) = trace.as_mut() else {
+        return;
+    };
+    if trace.spans.len() >= MAX_SPANS {
+        TRACING.store(false, Ordering::Relaxed);
+        crate::log_warn!("the trace is full at {} spans, it stopped recording", MAX_SPANS);
+        return;
+    }
+    let start = started.saturating_duration_since(trace.started).as_secs_f64() * 1_000_000.0;
+    trace.spans.push(Span { name, start, duration: duration.as_secs_f64() * 1_000_000.0 });
+}
+
+// times the scope it's made in until it's dropped, see profile_scope
+pub struct Scope {
+    name: &'static str,
+    // None while no trace runs, then dropping it does nothing either
+    started: Option<Instant>,
+}
+
+impl Scope {
+    pub fn new(name: &'static str) -> Self {
+        Scope { name, started: is_tracing().then(Instant::now) }
+    }
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        if let Some(started) = self.started {
+            span(self.name, started);
+        }
+    }
+}
+
+// times the rest of the enclosing block under name for the trace, see profiler::start_trace.
+// the sections on the debug overlay go into the trace too, see Profiler::record
+#[macro_export]
+macro_rules! profile_scope {
+    ($name:expr) => {
+        let _profile_scope = $crate::profiler::Scope::new($name);
+    };
+}
+
+// one named part of the frame, every record under the name this frame added up
+#[derive(Clone, Debug)]
+pub struct Section {
+    pub name: &'static str,
+    spent: Duration,
+    // milliseconds per frame, smoothed over the last few
+    pub average: f32,
+    // the longest frame lately, in milliseconds
+    pub peak: f32,
+}
+
+// times the systems each frame for the debug overlay. cheap enough to leave running: a system
+// takes Instant::now() before it starts and hands it to record when it's done, end_frame folds
+// the frame into the averages. sections show up in the order they were first recorded
+#[derive(Default)]
+pub struct Profiler {
+    sections: Vec<Section>,
+}
+
+impl Profiler {
+    // adds the time since started to the section, however many times it runs a frame
+    pub fn record(&mut self, name: &'static str, started: Instant) {
+        span(name, started);
+        let spent = started.elapsed();
+        match self.sections.iter_mut().find(|s| s.name == name) {
+            Some(section) => section.spent += spent,
+            None => self.sections.push(Section { name, spent, average: 0.0, peak: 0.0 }),
+        }
+    }
+
+    // once at the end of every frame. a section that didn't run this frame counts as 0
+    pub fn end_frame(&mut self) {
+        for section in &mut self.sections {
+            let ms = section.spent.as_secs_f32() * 1000.0;
+            section.average += (ms - section.average) * SMOOTHING;
+            section.peak = (section.peak * PEAK_DECAY).max(ms);
+            section.spent = Duration::ZERO;
+        }
+    }
+
+    pub fn sections(&self) -> &[Section] {
+        &self.sections
+    }
+
+    pub fn section(&self, name: &str) -> Option<&Section> {
+        self.sections.iter().find(|s| s.name == name)
+    }
+}
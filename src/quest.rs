@@ -0,0 +1,208 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+use raylib::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::error::SpellcoderError;
+use crate::events::{GameEvent, Subscriber};
+use crate::material::{MaterialId, MaterialRegistry};
+
+// next to materials.json, missing means there are no quests
+pub const QUESTS_PATH: &str = "quests.json";
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Quest {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub objective: Objective,
+    #[serde(default)]
+    pub rewards: Vec<Reward>,
+    // ids of quests that have to be done before this one shows up
+    #[serde(default)]
+    pub requires: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "kind")]
+pub enum Objective {
+    Kill { count: u32 },
+    // world pixels
+    Reach { x: f32, y: f32, radius: f32 },
+    // pixels of the material placed by spells or the editor
+    Place { material: String, count: u32 },
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "kind")]
+pub enum Reward {
+    // by spell name or file name in the spell directory, see Spellbook::learn
+    Spell { name: String },
+    // goes into the player's inventory
+    Item { name: String, count: u32 },
+    // max stats on top of what levels give, see Experience::apply
+    Boost {
+        #[serde(default)]
+        hp: f32,
+        #[serde(default)]
+        mp: f32,
+        #[serde(default)]
+        sp: f32,
+    },
+    Xp { amount: u32 },
+}
+
+impl Objective {
+    // how many of something it takes, 1 for reaching a place
+    pub fn target(&self) -> u32 {
+        match self {
+            Objective::Kill { count } | Objective::Place { count, .. } => *count,
+            Objective::Reach { .. } => 1,
+        }
+    }
+}
+
+impl Reward {
+    pub fn describe(&self) -> String {
+        match self {
+            Reward::Spell { name } => format!("the {} spell", name),
+            Reward::Item { name, count } => format!("{} {}", count, name),
+            Reward::Boost { hp, mp, sp } => {
+                let parts: Vec<_> = [("HP", hp), ("MP", mp), ("SP", sp)]
+                    .iter()
+                    .filter(|(_, v)| **v != 0.0)
+                    .map(|(name, v)| format!("+{:.0} max {}", v, name))
+                    .collect();
+                parts.join(", ")
+            }
+            Reward::Xp { amount } => format!("{} XP", amount),
+        }
+    }
+}
+
+// how far along every quest is, kept in the world save
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QuestProgress {
+    // by quest id, only quests that have started counting
+    pub counts: BTreeMap<String, u32>,
+    pub done: BTreeSet<String>,
+}
+
+// the quests from quests.json and the player's progress on them. counts kills and placed
+// pixels from events, the player's position comes in through update. finished quests wait in
+// take_completed until the game hands out their rewards
+pub struct QuestLog {
+    quests: Vec<Quest>,
+    // the material of each Place objective, by quest index
+    materials: Vec<Option<MaterialId>>,
+    pub progress: QuestProgress,
+    completed: Vec<usize>,
+}
+
+impl QuestLog {
+    pub fn empty() -> Self {
+        QuestLog { quests: Vec::new(), materials: Vec::new(), progress: QuestProgress::default(), completed: Vec::new() }
+    }
+
+    // a missing file means no quests. quests naming a material that doesn't exist are left out
+    // and added to errors
+    pub fn load(path: &str, materials: &MaterialRegistry, errors: &mut Vec<SpellcoderError>) -> Self {
+        let mut log = QuestLog::empty();
+        if !Path::new(path).exists() {
+            return log;
+        }
+        let quests: Vec<Quest> = match fs::read_to_string(path)
+            .map_err(|e| SpellcoderError::io(path, e))
+            .and_then(|text| serde_json::from_str(&text).map_err(|e| SpellcoderError::parse(path, e)))
+        {
+            Ok(quests) => quests,
+            Err(e) => {
+                errors.push(e);
+                return log;
+            }
+        };
+        for quest in quests {
+            let material = match &quest.objective {
+                Objective::Place { material, .. } => match materials.id(material) {
+                    Some(id) => Some(id),
+                    None => {
+                        errors.push(SpellcoderError::invalid(path, format!("{} needs {}, which isn't a material", quest.id, material)));
+                        continue;
+                    }
+                },
+                _ => None,
+            };
+            log.quests.push(quest);
+            log.materials.push(material);
+        }
+        log
+    }
+
+    pub fn quest(&self, id: &str) -> Option<&Quest> {
+        self.quests.iter().find(|q| q.id == id)
+    }
+
+    fn is_active(&self, quest: &Quest) -> bool {
+        !self.progress.done.contains(&quest.id) && quest.requires.iter().all(|id| self.progress.done.contains(id))
+    }
+
+    // the quests being worked on, with how far along each is
+    pub fn active(&self) -> Vec<(&Quest, u32)> {
+        self.quests
+            .iter()
+            .filter(|q| self.is_active(q))
+            .map(|q| (q, self.progress.counts.get(&q.id).copied().unwrap_or(0)))
+            .collect()
+    }
+
+    // for Reach objectives, call every frame the player is in control
+    pub fn update(&mut self, position: Vector2) {
+        let reached: Vec<usize> = (0..self.quests.len())
+            .filter(|i| self.is_active(&self.quests[*i]))
+            .filter(|i| match self.quests[*i].objective {
+                Objective::Reach { x, y, radius } => position.distance_to(Vector2::new(x, y)) <= radius,
+                _ => false,
+            })
+            .collect();
+        for i in reached {
+            self.count(i, 1);
+        }
+    }
+
+    fn count(&mut self, index: usize, amount: u32) {
+        let quest = &self.quests[index];
+        let count = self.progress.counts.entry(quest.id.clone()).or_default();
+        *count += amount;
+        if *count >= quest.objective.target() {
+            self.progress.counts.remove(&quest.id);
+            self.progress.done.insert(quest.id.clone());
+            self.completed.push(index);
+        }
+    }
+
+    // quests finished since last time, for Game::dispatch_events to reward
+    pub fn take_completed(&mut self) -> Vec<Quest> {
+        std::mem::take(&mut self.completed).into_iter().map(|i| self.quests[i].clone()).collect()
+    }
+}
+
+impl Subscriber for QuestLog {
+    fn on_event(&mut self, event: &GameEvent) {
+        // decided up front, a quest finished by this event doesn't count it for the next one
+        let active: Vec<usize> = (0..self.quests.len()).filter(|i| self.is_active(&self.quests[*i])).collect();
+        for i in active {
+            let counts = match (&self.quests[i].objective, event) {
+                (Objective::Kill { .. }, GameEvent::EntityDied { entity: Some(_), .. }) => true,
+                (Objective::Place { .. }, GameEvent::PixelChanged { after: Some(material), .. }) => self.materials[i] == Some(*material),
+                _ => false,
+            };
+            if counts {
+                self.count(i, 1);
+            }
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use crate::events::{GameEvent, Subscriber};
+use crate::skills::Bonuses;
+use crate::sky::NightEvent;
+use crate::tuning::PlayerTuning;
+
+// how much faster mana comes back under a mana moon
+const MANA_MOON_REGEN: f32 = 1.5;
+
+// how mana comes back. built fresh every frame from tuning.toml and whatever the player has
+// that changes it, so skills and gear only have to add to multiplier
+#[derive(Clone, Copy, Debug)]
+pub struct RegenModel {
+    // mp per second once it's going, before difficulty
+    pub rate: f32,
+    // seconds after a cast before any comes back
+    pub delay: f32,
+    // fraction of the rate while exhausted
+    pub exhausted_rate: f32,
+    // fraction of max mp that ends exhaustion
+    pub recover_at: f32,
+    // skills and gear, 1 for none
+    pub multiplier: f32,
+}
+
+impl RegenModel {
+    pub fn new(tuning: &PlayerTuning, bonuses: &Bonuses) -> Self {
+        RegenModel {
+            rate: tuning.mp_regen,
+            delay: tuning.mp_regen_delay,
+            exhausted_rate: tuning.exhausted_regen,
+            recover_at: tuning.exhaustion_ends,
+            multiplier: bonuses.mp_regen,
+        }
+    }
+}
+
+// where the player's mana is at between frames
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ManaState {
+    // seconds since the last cast
+    pub since_cast: f32,
+    // ran dry and hasn't come back up to recover_at yet
+    pub exhausted: bool,
+    // until sunrise, see Sky
+    pub mana_moon: bool,
+}
+
+impl ManaState {
+    // call after paying for a cast with what's left
+    pub fn spent(&mut self, mp: f32) {
+        self.since_cast = 0.0;
+        if mp <= 0.0 {
+            self.exhausted = true;
+        }
+    }
+
+    // mp gained this frame
+    pub fn regen(&mut self, model: &RegenModel, mp: f32, max_mp: f32, delta: f32) -> f32 {
+        self.since_cast += delta;
+        if mp <= 0.0 {
+            self.exhausted = true;
+        } else if self.exhausted && mp >= max_mp * model.recover_at {
+            self.exhausted = false;
+        }
+        if self.since_cast < model.delay {
+            return 0.0;
+        }
+        let rate = if self.exhausted { model.rate * model.exhausted_rate } else { model.rate };
+        let moon = if self.mana_moon { MANA_MOON_REGEN } else { 1.0 };
+        rate * model.multiplier * moon * delta
+    }
+}
+
+impl Subscriber for ManaState {
+    fn on_event(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::Nightfall { event, .. } => self.mana_moon = *event == Some(NightEvent::ManaMoon),
+            GameEvent::Daybreak => self.mana_moon = false,
+            _ => {}
+        }
+    }
+}
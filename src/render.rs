@@ -0,0 +1,87 @@
+use raylib::prelude::*;
+
+use crate::animation::PlayerAnimation;
+use crate::material::MaterialRegistry;
+use crate::world::{Chunk, World};
+use crate::{scale, Player};
+
+pub trait WorldDraw {
+    fn draw_chunk(&mut self, chunk: &Chunk, materials: &MaterialRegistry, time: f32);
+    fn draw_world(&mut self, world: &World, time: f32);
+    fn draw_player(&mut self, player: &Player, animation: &PlayerAnimation);
+    fn draw_reticle(&mut self, target: Vector2);
+    fn draw_build_preview(&mut self, target: Vector2, center: Vector2, reach: f32, valid: bool);
+}
+
+// for anything in the world's 2d mode, on screen or into a render texture
+impl<D: RaylibDraw> WorldDraw for D {
+    fn draw_chunk(&mut self, chunk: &Chunk, materials: &MaterialRegistry, time: f32) {
+        crate::profile_scope!("draw_chunk");
+        for row in &chunk.pixels {
+            for vox in row {
+                let material = materials.get(vox.material);
+                let mut color = match material.animation {
+                    Some(animation) => animation.apply(vox.color, vox.x as i64 + chunk.x, vox.y as i64 + chunk.y, time),
+                    None => vox.color,
+                };
+                let mut shade = chunk.shading[vox.x as usize][vox.y as usize] as f32 / 255.0;
+                // cracked pixels get darker the closer they are to breaking
+                if vox.damage > 0.0 && material.hardness > 0.0 {
+                    shade *= 1.0 - (vox.damage / material.hardness).min(1.0) * 0.6;
+                }
+                if shade < 1.0 {
+                    color = ffi::Color {
+                        r: (color.r as f32 * shade) as u8,
+                        g: (color.g as f32 * shade) as u8,
+                        b: (color.b as f32 * shade) as u8,
+                        a: color.a,
+                    };
+                }
+                self.draw_rectangle((vox.x as i32 + chunk.x as i32) * scale(), (vox.y as i32 + chunk.y as i32) * scale(), scale(), scale(), color);
+            }
+        }
+    }
+
+    fn draw_player(&mut self, player: &Player, animation: &PlayerAnimation) {
+        animation.draw(self, player);
+    }
+
+    fn draw_world(&mut self, world: &World, time: f32) {
+        crate::profile_scope!("draw_world");
+        for chunk in &world.chunks {
+            self.draw_chunk(chunk, &world.materials, time);
+        }
+    }
+
+    // target is in world pixels, the cell it falls in gets outlined
+    fn draw_reticle(&mut self, target: Vector2) {
+        let cell_x = target.x.floor() as i32 * scale();
+        let cell_y = target.y.floor() as i32 * scale();
+        self.draw_rectangle_lines(cell_x - 1, cell_y - 1, scale() + 2, scale() + 2, prelude::Color::WHITE);
+        let center = target * scale() as f32;
+        let color = prelude::Color::new(255, 255, 255, 180);
+        self.draw_line_v(center - Vector2::new(10.0, 0.0), center - Vector2::new(4.0, 0.0), color);
+        self.draw_line_v(center + Vector2::new(4.0, 0.0), center + Vector2::new(10.0, 0.0), color);
+        self.draw_line_v(center - Vector2::new(0.0, 10.0), center - Vector2::new(0.0, 4.0), color);
+        self.draw_line_v(center + Vector2::new(0.0, 4.0), center + Vector2::new(0.0, 10.0), color);
+    }
+
+    // the pixel grid around the target fading out, the target cell filled green where a pixel
+    // can go and red where it can't, and how far the player reaches. all in world pixels
+    fn draw_build_preview(&mut self, target: Vector2, center: Vector2, reach: f32, valid: bool) {
+        const GRID_RADIUS: i32 = 3;
+        let s = scale();
+        let (cell_x, cell_y) = (target.x.floor() as i32, target.y.floor() as i32);
+        for i in -GRID_RADIUS..=GRID_RADIUS + 1 {
+            let alpha = (60 - i.abs().min(GRID_RADIUS) * 15) as u8;
+            let line = prelude::Color::new(255, 255, 255, alpha);
+            let (top, bottom) = ((cell_y - GRID_RADIUS) * s, (cell_y + GRID_RADIUS + 1) * s);
+            let (left, right) = ((cell_x - GRID_RADIUS) * s, (cell_x + GRID_RADIUS + 1) * s);
+            self.draw_line((cell_x + i) * s, top, (cell_x + i) * s, bottom, line);
+            self.draw_line(left, (cell_y + i) * s, right, (cell_y + i) * s, line);
+        }
+        let fill = if valid { prelude::Color::new(80, 220, 100, 110) } else { prelude::Color::new(230, 70, 60, 110) };
+        self.draw_rectangle(cell_x * s, cell_y * s, s, s, fill);
+        self.draw_circle_lines((center.x * s as f32) as i32, (center.y * s as f32) as i32, reach * s as f32, prelude::Color::new(255, 255, 255, 40));
+    }
+}
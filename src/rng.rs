@@ -0,0 +1,122 @@
+use rand::{Error, RngCore};
+
+use crate::digest::Digest;
+
+// independent streams split off the world seed. each kind of randomness draws from its own, so
+// spawning an extra particle never changes what a pixel does or what an ore drops
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stream {
+    // the simulation, temperature, growth and anything else that changes pixels
+    World,
+    // item drops and how they pop out
+    Loot,
+    // particles and other effects that are only for show
+    Particles,
+    Weather,
+    // where and when the DifficultyDirector sends enemies
+    Spawns,
+    // the merchant's visits and stock
+    Trade,
+}
+
+impl Stream {
+    // mixed into the seed so every stream starts somewhere different
+    fn salt(&self) -> u64 {
+        match self {
+            Stream::World => 0x5752_4c44,
+            Stream::Loot => 0x4c4f_4f54,
+            Stream::Particles => 0x5041_5254,
+            Stream::Weather => 0x5745_4154,
+            Stream::Spawns => 0x5350_574e,
+            Stream::Trade => 0x5452_4144,
+        }
+    }
+}
+
+// splitmix64. tiny, fast and fully determined by the seed, unlike rand's StdRng whose
+// algorithm may change between versions and break old replays
+#[derive(Clone, Debug)]
+pub struct StreamRng {
+    state: u64,
+}
+
+impl StreamRng {
+    pub fn new(seed: u64) -> Self {
+        StreamRng { state: seed }
+    }
+
+    // a new generator seeded from this one, for a system that wants its own copy of a stream
+    pub fn fork(&mut self) -> StreamRng {
+        StreamRng::new(self.next_u64())
+    }
+}
+
+impl RngCore for StreamRng {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+// every stream for one world seed. the world and loot streams live here on World, the show-only
+// ones are split off once for the systems that own them, see Game::new
+#[derive(Clone)]
+pub struct RngService {
+    seed: u64,
+    pub world: StreamRng,
+    pub loot: StreamRng,
+}
+
+impl RngService {
+    pub fn new(seed: u64) -> Self {
+        RngService {
+            seed,
+            world: RngService::split(seed, Stream::World),
+            loot: RngService::split(seed, Stream::Loot),
+        }
+    }
+
+    // a fresh generator for the stream, the same every time for the same seed
+    fn split(seed: u64, stream: Stream) -> StreamRng {
+        let mut mixer = StreamRng::new(seed ^ stream.salt().wrapping_mul(0x9E37_79B9_7F4A_7C15));
+        StreamRng::new(mixer.next_u64())
+    }
+
+    pub fn stream(&self, stream: Stream) -> StreamRng {
+        RngService::split(self.seed, stream)
+    }
+
+    // systems that need the world stream while also changing the world borrow it out with
+    // take and hand it back with restore when they are done
+    pub fn take(&mut self) -> StreamRng {
+        std::mem::replace(&mut self.world, StreamRng::new(0))
+    }
+
+    pub fn restore(&mut self, rng: StreamRng) {
+        self.world = rng;
+    }
+
+    // where the world's streams are, two worlds that drew differently part ways from here on
+    pub fn digest(&self, digest: &mut Digest) {
+        digest.u64(self.seed).u64(self.world.state).u64(self.loot.state);
+    }
+}
@@ -0,0 +1,120 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+pub const DIFFICULTIES: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+
+impl Difficulty {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Normal => "Normal",
+            Difficulty::Hard => "Hard",
+        }
+    }
+
+    // how much of the damage that comes in actually lands
+    pub fn damage_taken(&self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.5,
+            Difficulty::Normal => 1.0,
+            Difficulty::Hard => 1.5,
+        }
+    }
+
+    // scales hp, mp and sp regeneration
+    pub fn regen(&self) -> f32 {
+        match self {
+            Difficulty::Easy => 1.5,
+            Difficulty::Normal => 1.0,
+            Difficulty::Hard => 0.75,
+        }
+    }
+
+    pub fn next(&self) -> Difficulty {
+        let i = DIFFICULTIES.iter().position(|d| d == self).unwrap_or(0);
+        DIFFICULTIES[(i + 1) % DIFFICULTIES.len()]
+    }
+}
+
+// how much the DifficultyDirector lets the player's progress push back
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Scaling {
+    // nothing spawns and the hazards stay as they are
+    Off,
+    Gentle,
+    #[default]
+    Normal,
+    Steep,
+}
+
+pub const SCALINGS: [Scaling; 4] = [Scaling::Off, Scaling::Gentle, Scaling::Normal, Scaling::Steep];
+
+impl Scaling {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Scaling::Off => "Off",
+            Scaling::Gentle => "Gentle",
+            Scaling::Normal => "Normal",
+            Scaling::Steep => "Steep",
+        }
+    }
+
+    // turns the player's power into threat
+    pub fn factor(&self) -> f32 {
+        match self {
+            Scaling::Off => 0.0,
+            Scaling::Gentle => 0.5,
+            Scaling::Normal => 1.0,
+            Scaling::Steep => 1.75,
+        }
+    }
+
+    pub fn next(&self) -> Scaling {
+        let i = SCALINGS.iter().position(|s| s == self).unwrap_or(0);
+        SCALINGS[(i + 1) % SCALINGS.len()]
+    }
+}
+
+// picked when the world is made and saved with it, they can't change afterwards
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorldRules {
+    pub difficulty: Difficulty,
+    // dying deletes the save, there is no respawn
+    pub hardcore: bool,
+    // spells cost nothing and break whatever they touch at once
+    pub creative: bool,
+    pub scaling: Scaling,
+    // how many times the player has gone on to New Game+, see Game::new_game_plus
+    pub prestige: u32,
+}
+
+impl WorldRules {
+    pub fn describe(&self) -> String {
+        let prestige = format!("prestige {}", self.prestige);
+        let mut parts = vec![self.difficulty.name()];
+        if self.hardcore {
+            parts.push("hardcore");
+        }
+        if self.creative {
+            parts.push("creative");
+        }
+        match self.scaling {
+            Scaling::Off => parts.push("no scaling"),
+            Scaling::Gentle => parts.push("gentle scaling"),
+            Scaling::Normal => {}
+            Scaling::Steep => parts.push("steep scaling"),
+        }
+        if self.prestige > 0 {
+            parts.push(&prestige);
+        }
+        parts.join(", ")
+    }
+}
@@ -0,0 +1,81 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::chest::Chests;
+use crate::equipment::Equipment;
+use crate::error::SpellcoderError;
+use crate::experience::Experience;
+use crate::exploration::Exploration;
+use crate::inventory::Inventory;
+use crate::quest::QuestProgress;
+use crate::rules::WorldRules;
+use crate::skills::SkillTree;
+use crate::stats::Statistics;
+
+// in the save directory, see Config::save_path
+pub const WORLD_SAVE_PATH: &str = "world.json";
+
+// what makes a world this world. the terrain comes back from the seed, so that, what's been
+// counted about playing it and how far the player has come is all there is
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WorldSave {
+    pub seed: u64,
+    #[serde(default)]
+    pub rules: WorldRules,
+    #[serde(default)]
+    pub stats: Statistics,
+    #[serde(default)]
+    pub experience: Experience,
+    #[serde(default)]
+    pub skills: SkillTree,
+    #[serde(default)]
+    pub quests: QuestProgress,
+    // spell names on top of the starters, in the order they were learned
+    #[serde(default)]
+    pub known_spells: Vec<String>,
+    // by their left edge, see world::altar_at
+    #[serde(default)]
+    pub used_altars: BTreeSet<i64>,
+    // seconds since the player last died, see DifficultyDirector
+    #[serde(default)]
+    pub survived: f32,
+    // New Game+ is open once it is
+    #[serde(default)]
+    pub boss_defeated: bool,
+    #[serde(default)]
+    pub inventory: Inventory,
+    #[serde(default)]
+    pub equipment: Equipment,
+    #[serde(default)]
+    pub chests: Chests,
+    #[serde(default)]
+    pub coins: u32,
+    #[serde(default)]
+    pub explored: Exploration,
+}
+
+impl WorldSave {
+    // Ok(None) when there's no save yet
+    pub fn load(path: &str) -> Result<Option<WorldSave>, SpellcoderError> {
+        if !Path::new(path).exists() {
+            return Ok(None);
+        }
+        let text = fs::read_to_string(path).map_err(|e| SpellcoderError::io(path, e))?;
+        serde_json::from_str(&text).map(Some).map_err(|e| SpellcoderError::parse(path, e))
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), SpellcoderError> {
+        let text = serde_json::to_string_pretty(self).map_err(|e| SpellcoderError::save(path, e.into()))?;
+        fs::write(path, text).map_err(|e| SpellcoderError::save(path, e))
+    }
+
+    pub fn delete(path: &str) -> Result<(), SpellcoderError> {
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(SpellcoderError::save(path, e)),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,263 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use raylib::prelude::*;
+use serde::Deserialize;
+
+use crate::effects::Effects;
+use crate::error::SpellcoderError;
+use crate::material::MaterialRegistry;
+use crate::rng::StreamRng;
+use crate::simulation::{self, Simulation, STEP_TIME};
+use crate::spell::{self, CastVariant, Spellbook};
+use crate::world::Chunk;
+use crate::{ecs, Player, World};
+
+pub const SCENARIO_DIR: &str = "scenarios";
+// world pixels a position may be off by and still match, unless the scenario says otherwise
+const DEFAULT_TOLERANCE: f32 = 0.5;
+
+// a small world set up by hand, things done in it at set times and what it should look like
+// after, see scenarios/ for examples. runs without a window a frame of STEP_TIME at a time, so
+// the same file always plays out the same
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    #[serde(default)]
+    pub name: String,
+    // seconds it runs for
+    pub duration: f32,
+    #[serde(default)]
+    pub seed: u64,
+    // world pixels, corners of the area loaded as empty chunks before the pixels go in. the
+    // pixels outside of it load the chunks they're in
+    #[serde(default)]
+    pub area: Option<[[i64; 2]; 2]>,
+    // the top left of the player
+    #[serde(default)]
+    pub player: [f32; 2],
+    // the world pixel row the player's feet land on, the game has it at the bottom of the screen
+    #[serde(default = "default_floor")]
+    pub floor: f32,
+    #[serde(default)]
+    pub pixels: Vec<Fill>,
+    #[serde(default)]
+    pub inputs: Vec<Input>,
+    #[serde(default)]
+    pub expect: Expect,
+}
+
+fn default_floor() -> f32 {
+    120.0
+}
+
+// a rectangle of one material, both corners included
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Fill {
+    pub material: String,
+    pub from: [i64; 2],
+    #[serde(default)]
+    pub to: Option<[i64; 2]>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Input {
+    // seconds in
+    pub at: f32,
+    #[serde(flatten)]
+    pub action: Action,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Action {
+    // held for seconds, -1 is left and 1 is right
+    Walk { direction: f32, seconds: f32 },
+    // pressed once, goes off the next frame the player is on the floor
+    Jump,
+    Cast {
+        spell: String,
+        target: [f32; 2],
+        #[serde(default)]
+        variant: Option<String>,
+    },
+}
+
+// left out is not checked
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Expect {
+    pub position: Option<[f32; 2]>,
+    pub tolerance: Option<f32>,
+    pub min_hp: Option<f32>,
+    pub max_hp: Option<f32>,
+    pub entities: Option<usize>,
+    #[serde(default)]
+    pub pixels: Vec<ExpectPixel>,
+}
+
+// the material at a world pixel, AIR for nothing there
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExpectPixel {
+    pub at: [i64; 2],
+    pub material: String,
+}
+
+// what didn't match, empty when everything did
+pub struct Outcome {
+    pub name: String,
+    pub failures: Vec<String>,
+}
+
+impl Outcome {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+pub fn load(path: &Path) -> Result<Scenario, SpellcoderError> {
+    let name = path.display().to_string();
+    let text = fs::read_to_string(path).map_err(|e| SpellcoderError::io(&name, e))?;
+    let mut scenario: Scenario = toml::from_str(&text).map_err(|e| SpellcoderError::invalid(&name, e.to_string()))?;
+    if scenario.name.is_empty() {
+        scenario.name = path.file_stem().map_or(name, |s| s.to_string_lossy().to_string());
+    }
+    Ok(scenario)
+}
+
+// every .toml in dir, or just the file
+pub fn scenario_files(from: &str) -> Result<Vec<PathBuf>, SpellcoderError> {
+    if !Path::new(from).is_dir() {
+        return Ok(vec![PathBuf::from(from)]);
+    }
+    let mut paths = fs::read_dir(from)
+        .map_err(|e| SpellcoderError::io(from, e))?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "toml"))
+        .collect::<Vec<_>>();
+    paths.sort();
+    Ok(paths)
+}
+
+impl Scenario {
+    // everything in the file that can't work is reported before anything runs
+    fn build(&self, materials: MaterialRegistry, spellbook: &Spellbook) -> Result<World, String> {
+        for input in &self.inputs {
+            if let Action::Cast { spell, variant, .. } = &input.action {
+                if spellbook.find(spell).is_none() {
+                    return Err(format!("no spell called {}", spell));
+                }
+                if variant.as_deref().is_some_and(|v| CastVariant::parse(v).is_none()) {
+                    return Err(format!("{} has no variant {}", spell, variant.as_deref().unwrap_or_default()));
+                }
+            }
+        }
+        let unknown = self.pixels.iter().map(|f| &f.material).chain(self.expect.pixels.iter().map(|p| &p.material)).find(|m| materials.id(m).is_none());
+        if let Some(material) = unknown {
+            return Err(format!("no material called {}", material));
+        }
+        let mut world = World::new(materials, self.seed);
+        if let Some([from, to]) = self.area {
+            for x in (from[0].min(to[0]).div_euclid(16)..=from[0].max(to[0]).div_euclid(16)).map(|x| x * 16) {
+                for y in (from[1].min(to[1]).div_euclid(16)..=from[1].max(to[1]).div_euclid(16)).map(|y| y * 16) {
+                    load_empty(&mut world, x, y);
+                }
+            }
+        }
+        for fill in &self.pixels {
+            let material = world.materials.named(&fill.material);
+            let to = fill.to.unwrap_or(fill.from);
+            for x in fill.from[0].min(to[0])..=fill.from[0].max(to[0]) {
+                for y in fill.from[1].min(to[1])..=fill.from[1].max(to[1]) {
+                    load_empty(&mut world, x, y);
+                    let color = world.color_at(material, x, y);
+                    world.set_pixel(x, y, material, color);
+                }
+            }
+        }
+        Ok(world)
+    }
+
+    // plays it out and checks the expectations
+    pub fn run(&self, materials: MaterialRegistry, spellbook: &Spellbook) -> Outcome {
+        let mut outcome = Outcome { name: self.name.clone(), failures: Vec::new() };
+        let mut world = match self.build(materials, spellbook) {
+            Ok(world) => world,
+            Err(e) => {
+                outcome.failures.push(e);
+                return outcome;
+            }
+        };
+        let mut player = Player::new(Vector2::new(self.player[0], self.player[1]));
+        let mut effects = Effects::new(StreamRng::new(self.seed));
+        let mut simulation = Simulation::new();
+        let mut vel = Vector2::zero();
+        let mut jump = false;
+        let frames = (self.duration / STEP_TIME).round() as u32;
+        for frame in 0..frames {
+            let (time, next) = (frame as f32 * STEP_TIME, (frame + 1) as f32 * STEP_TIME);
+            let mut walk = 0.0;
+            for input in &self.inputs {
+                match &input.action {
+                    Action::Walk { direction, seconds } if input.at <= time && time < input.at + seconds => walk += direction,
+                    Action::Jump if input.at >= time && input.at < next => jump = true,
+                    Action::Cast { spell, target, variant } if input.at >= time && input.at < next => {
+                        let variant = variant.as_deref().and_then(CastVariant::parse).unwrap_or(CastVariant::Normal);
+                        if let Some(spell) = spellbook.find(spell) {
+                            spell::activate_spell(spell, variant, 1.0, &mut world, &mut player, &mut effects, Vector2::new(target[0], target[1]));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            player.step(&mut vel, walk.clamp(-1.0, 1.0), false, || std::mem::take(&mut jump), self.floor, STEP_TIME);
+            simulation.update(&mut world, STEP_TIME);
+            spell::update_entities(&mut world, &mut player, &mut effects, STEP_TIME);
+            ecs::update(&mut world, STEP_TIME);
+            simulation::apply_contact_effects(&world, &mut player);
+            player.update_stats(STEP_TIME);
+            // nothing listens here, they'd only pile up
+            world.events.drain();
+            #[cfg(feature = "invariants")]
+            crate::invariants::check(&world, &player, simulation.tick());
+        }
+        self.check(&world, &player, &mut outcome.failures);
+        outcome
+    }
+
+    fn check(&self, world: &World, player: &Player, failures: &mut Vec<String>) {
+        let expect = &self.expect;
+        if let Some([x, y]) = expect.position {
+            let tolerance = expect.tolerance.unwrap_or(DEFAULT_TOLERANCE);
+            let at = player.position;
+            if (at.x - x).abs() > tolerance || (at.y - y).abs() > tolerance {
+                failures.push(format!("the player is at ({:.2}, {:.2}), expected ({}, {})", at.x, at.y, x, y));
+            }
+        }
+        if expect.min_hp.is_some_and(|hp| player.hp < hp) || expect.max_hp.is_some_and(|hp| player.hp > hp) {
+            failures.push(format!("the player has {:.1} hp, expected {:?} to {:?}", player.hp, expect.min_hp, expect.max_hp));
+        }
+        if let Some(entities) = expect.entities {
+            if world.ecs.len() != entities {
+                failures.push(format!("{} entities, expected {}", world.ecs.len(), entities));
+            }
+        }
+        for expected in &expect.pixels {
+            let [x, y] = expected.at;
+            let found = world.pixel_at(x, y).map_or("AIR", |p| world.materials.get(p.material).name.as_str());
+            if found != expected.material {
+                failures.push(format!("({}, {}) is {}, expected {}", x, y, found, expected.material));
+            }
+        }
+    }
+}
+
+// an empty chunk around the world pixel unless one's there already
+fn load_empty(world: &mut World, x: i64, y: i64) {
+    if world.chunk_at(x, y).is_none() {
+        world.chunks.push(Chunk::new(x.div_euclid(16) * 16, y.div_euclid(16) * 16));
+    }
+}
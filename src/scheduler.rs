@@ -0,0 +1,98 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+// milliseconds of background work per frame unless the config says otherwise
+pub const DEFAULT_BUDGET_MS: f32 = 2.0;
+
+// what a task wants after running once
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Step {
+    Done,
+    // more to do, it goes to the back of the queue and runs again when there is time
+    Again,
+}
+
+struct Task<C> {
+    name: &'static str,
+    run: Box<dyn FnMut(&mut C) -> Step>,
+}
+
+// deferred work that doesn't have to happen this frame, chunk generation, saving and slow
+// simulation passes. every frame runs tasks in the order they were queued until the budget is
+// used up, so a burst of work spreads over several frames instead of making one of them long.
+// C is whatever the tasks work on, the Game for everything so far
+pub struct Scheduler<C> {
+    queue: VecDeque<Task<C>>,
+    budget: Duration,
+    // for the debug tools
+    last_used: Duration,
+    last_ran: usize,
+}
+
+impl<C> Default for Scheduler<C> {
+    fn default() -> Self {
+        Scheduler::new(DEFAULT_BUDGET_MS)
+    }
+}
+
+impl<C> Scheduler<C> {
+    pub fn new(budget_ms: f32) -> Self {
+        Scheduler {
+            queue: VecDeque::new(),
+            budget: Duration::from_secs_f32(budget_ms.max(0.0) / 1000.0),
+            last_used: Duration::ZERO,
+            last_ran: 0,
+        }
+    }
+
+    pub fn push(&mut self, name: &'static str, run: impl FnMut(&mut C) -> Step + 'static) {
+        self.queue.push_back(Task { name, run: Box::new(run) });
+    }
+
+    // tasks queued on other while this one was running, see Game::run_background
+    pub fn append(&mut self, other: &mut Scheduler<C>) {
+        self.queue.append(&mut other.queue);
+    }
+
+    // at least one task runs every frame however small the budget, so nothing waits forever.
+    // a task that alone takes longer than the budget still finishes, tasks should keep their
+    // steps small and return Again
+    pub fn run(&mut self, context: &mut C) {
+        let started = Instant::now();
+        let mut ran = 0;
+        // each task gets at most one turn a frame, even if there's time left
+        let mut turns = self.queue.len();
+        while turns > 0 && (ran == 0 || started.elapsed() < self.budget) {
+            let Some(mut task) = self.queue.pop_front() else {
+                break;
+            };
+            turns -= 1;
+            ran += 1;
+            if (task.run)(context) == Step::Again {
+                self.queue.push_back(task);
+            }
+        }
+        self.last_used = started.elapsed();
+        self.last_ran = ran;
+        if self.last_used > self.budget * 4 {
+            crate::log_debug!("background work took {:.1}ms, budget {:.1}ms", self.last_used.as_secs_f32() * 1000.0, self.budget.as_secs_f32() * 1000.0);
+        }
+    }
+
+    pub fn pending(&self) -> usize {
+        self.queue.len()
+    }
+
+    // names of everything waiting, oldest first
+    pub fn names(&self) -> Vec<&'static str> {
+        self.queue.iter().map(|t| t.name).collect()
+    }
+
+    pub fn last_used_ms(&self) -> f32 {
+        self.last_used.as_secs_f32() * 1000.0
+    }
+
+    pub fn last_ran(&self) -> usize {
+        self.last_ran
+    }
+}
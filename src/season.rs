@@ -0,0 +1,84 @@
+use raylib::prelude::*;
+
+// seconds of play each season lasts, a whole year is four of them
+const SEASON_LENGTH: f32 = 1200.0;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Season {
+    #[default]
+    Spring,
+    Summer,
+    Autumn,
+    Winter,
+}
+
+// how a season shifts a material's colors, a multiplier for each channel
+struct Tint {
+    season: Season,
+    material: &'static str,
+    multiply: [f32; 3],
+}
+
+// anything not in here keeps its colors the whole year
+const TINTS: &[Tint] = &[
+    Tint { season: Season::Spring, material: "GRASS", multiply: [0.9, 1.1, 0.9] },
+    Tint { season: Season::Summer, material: "GRASS", multiply: [1.25, 1.05, 0.7] },
+    Tint { season: Season::Autumn, material: "GRASS", multiply: [2.2, 0.85, 0.5] },
+    Tint { season: Season::Autumn, material: "DIRT", multiply: [1.05, 0.95, 0.9] },
+    Tint { season: Season::Winter, material: "GRASS", multiply: [1.1, 0.85, 1.2] },
+    Tint { season: Season::Winter, material: "DIRT", multiply: [0.9, 0.9, 1.0] },
+];
+
+impl Season {
+    // the season the world is in after seconds of play, see Statistics::playtime
+    pub fn at(seconds: f32) -> Season {
+        match (seconds / SEASON_LENGTH) as u64 % 4 {
+            0 => Season::Spring,
+            1 => Season::Summer,
+            2 => Season::Autumn,
+            _ => Season::Winter,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Season::Spring => "Spring",
+            Season::Summer => "Summer",
+            Season::Autumn => "Autumn",
+            Season::Winter => "Winter",
+        }
+    }
+
+    pub fn announcement(&self) -> &'static str {
+        match self {
+            Season::Spring => "Spring is here, the grass comes back",
+            Season::Summer => "Summer, the days are long and dry",
+            Season::Autumn => "Autumn turns the meadows",
+            Season::Winter => "Winter sets in, new ground lies under snow",
+        }
+    }
+
+    // the grass under a layer of snow in newly generated ground, see Chunk::generate
+    pub fn snow_cover(&self) -> bool {
+        *self == Season::Winter
+    }
+
+    // color as the material looks this season
+    pub fn tint(&self, material: &str, color: ffi::Color) -> ffi::Color {
+        let Some(tint) = TINTS.iter().find(|t| t.season == *self && t.material == material) else {
+            return color;
+        };
+        let channel = |c: u8, m: f32| (c as f32 * m).clamp(0.0, 255.0) as u8;
+        ffi::Color {
+            r: channel(color.r, tint.multiply[0]),
+            g: channel(color.g, tint.multiply[1]),
+            b: channel(color.b, tint.multiply[2]),
+            a: color.a,
+        }
+    }
+
+    // whether the season changes how the material looks at all
+    pub fn tints(material: &str) -> bool {
+        TINTS.iter().any(|t| t.material == material)
+    }
+}
@@ -0,0 +1,95 @@
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::SpellcoderError;
+use crate::events::{GameEvent, Subscriber};
+
+// in the save directory, one file per session
+pub const SESSION_DIR: &str = "sessions";
+
+// one line of a session log. nothing in here ever leaves the machine, it's for balancing spell
+// costs, see the analyze-sessions subcommand
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Record {
+    Start { seed: u64 },
+    Cast { time: f32, spell: String, cost: f32, touched: bool },
+    // to_player is false for damage the player's spells did to entities
+    Damage { time: f32, amount: f32, to_player: bool },
+    Death { time: f32, player: bool },
+}
+
+// writes a line per record as they happen, so a crash loses nothing
+pub struct SessionLog {
+    file: Option<File>,
+    started: Instant,
+}
+
+impl SessionLog {
+    // a log that doesn't write anything, for when it's turned off in the config
+    pub fn disabled() -> Self {
+        SessionLog { file: None, started: Instant::now() }
+    }
+
+    pub fn create(dir: &str, seed: u64) -> Result<Self, SpellcoderError> {
+        fs::create_dir_all(dir).map_err(|e| SpellcoderError::save(dir, e))?;
+        let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        let path = Path::new(dir).join(format!("session_{}.jsonl", seconds)).to_string_lossy().to_string();
+        let file = File::create(&path).map_err(|e| SpellcoderError::save(path.as_str(), e))?;
+        let mut log = SessionLog { file: Some(file), started: Instant::now() };
+        log.write(&Record::Start { seed });
+        Ok(log)
+    }
+
+    fn write(&mut self, record: &Record) {
+        let Some(file) = &mut self.file else {
+            return;
+        };
+        let Ok(line) = serde_json::to_string(record) else {
+            return;
+        };
+        if let Err(e) = writeln!(file, "{}", line) {
+            crate::log_warn!("session log stopped: {}", e);
+            self.file = None;
+        }
+    }
+
+    fn time(&self) -> f32 {
+        self.started.elapsed().as_secs_f32()
+    }
+}
+
+impl Subscriber for SessionLog {
+    fn on_event(&mut self, event: &GameEvent) {
+        if self.file.is_none() {
+            return;
+        }
+        let time = self.time();
+        let record = match event {
+            GameEvent::SpellCast { name, cost, touched, .. } => Record::Cast { time, spell: name.clone(), cost: *cost, touched: *touched },
+            GameEvent::DamageDealt { amount, entity, .. } => Record::Damage { time, amount: *amount, to_player: entity.is_none() },
+            GameEvent::EntityDied { entity, .. } => Record::Death { time, player: entity.is_none() },
+            _ => return,
+        };
+        self.write(&record);
+    }
+}
+
+// every record in the file, lines that don't parse are skipped and counted
+pub fn read(path: &Path) -> Result<(Vec<Record>, usize), SpellcoderError> {
+    let file = File::open(path).map_err(|e| SpellcoderError::io(path.display().to_string(), e))?;
+    let mut records = Vec::new();
+    let mut skipped = 0;
+    for line in BufReader::new(file).lines() {
+        let line = line.map_err(|e| SpellcoderError::io(path.display().to_string(), e))?;
+        match serde_json::from_str(&line) {
+            Ok(record) => records.push(record),
+            Err(_) => skipped += 1,
+        }
+    }
+    Ok((records, skipped))
+}
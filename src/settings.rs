@@ -0,0 +1,328 @@
+use raylib::prelude::*;
+
+use crate::audio::{AudioManager, Bus, UiSound, AUDIO_SETTINGS_PATH};
+use crate::config::{self, Config};
+use crate::error::SpellcoderError;
+use crate::input::{self, Action, Binding, InputMap, ACTIONS, PROFILES, PROFILE_EXPORT_PATH};
+use crate::{ui, TextWidth};
+
+const AUDIO_ROWS: [(&str, Bus); 3] = [("Master volume", Bus::Master), ("Music volume", Bus::Music), ("SFX volume", Bus::Sfx)];
+// the window size button steps through these
+const WINDOW_SIZES: [(i32, i32); 4] = [(640, 480), (960, 720), (1280, 960), (1600, 1200)];
+const MIN_ZOOM: i32 = 2;
+const MAX_ZOOM: i32 = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Tab {
+    Video,
+    Audio,
+    Controls,
+    Gameplay,
+}
+
+const TABS: [Tab; 4] = [Tab::Video, Tab::Audio, Tab::Controls, Tab::Gameplay];
+
+impl Tab {
+    fn name(&self) -> &'static str {
+        match self {
+            Tab::Video => "Video",
+            Tab::Audio => "Audio",
+            Tab::Controls => "Controls",
+            Tab::Gameplay => "Gameplay",
+        }
+    }
+}
+
+// everything but the audio and controls, which keep their own files, is saved to the config file
+pub struct SettingsMenu {
+    pub open: bool,
+    tab: Tab,
+    rebinding: Option<Action>,
+    ignore_click: bool,
+    scroll: usize, // first action row shown
+    audio_changed: bool,
+    shake_changed: bool,
+    bloom_changed: bool,
+    errors: Vec<SpellcoderError>, // failed saves, waiting to be shown
+}
+
+impl SettingsMenu {
+    pub fn new() -> Self {
+        SettingsMenu {
+            open: false,
+            tab: Tab::Video,
+            rebinding: None,
+            ignore_click: false,
+            scroll: 0,
+            audio_changed: false,
+            shake_changed: false,
+            bloom_changed: false,
+            errors: Vec::new(),
+        }
+    }
+
+    // whatever went wrong since the last call
+    pub fn take_errors(&mut self) -> Vec<SpellcoderError> {
+        std::mem::take(&mut self.errors)
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.rebinding = None;
+    }
+
+    // runs before drawing so a key press used for rebinding is not also seen by the menu itself
+    pub fn update(&mut self, rl: &mut RaylibHandle, controls: &mut InputMap, audio: &mut AudioManager) {
+        self.ignore_click = false;
+        let Some(action) = self.rebinding else {
+            if controls.is_pressed(rl, Action::Menu) {
+                self.toggle();
+                audio.play_ui(UiSound::Click);
+            }
+            let wheel = rl.get_mouse_wheel_move();
+            if !self.open || self.tab != Tab::Controls {
+                return;
+            }
+            if wheel > 0.0 {
+                self.scroll = self.scroll.saturating_sub(1);
+            } else if wheel < 0.0 {
+                self.scroll += 1;
+            }
+            return;
+        };
+        if let Some(binding) = Binding::poll(rl) {
+            // escape always cancels so the menu key can't lock you out
+            if binding != Binding::Key(KeyboardKey::KEY_ESCAPE) || action == Action::Menu {
+                controls.rebind(action, binding);
+                audio.play_ui(UiSound::Confirm);
+                self.save_controls(controls);
+            }
+            self.rebinding = None;
+            self.ignore_click = true;
+        }
+    }
+
+    pub fn draw(&mut self, d: &mut RaylibDrawHandle, controls: &mut InputMap, audio: &mut AudioManager) {
+        let rows = match self.tab {
+            // only as many rows as fit on screen, the wheel scrolls through the rest
+            // the profile row plus the actions
+            Tab::Controls => 1 + ACTIONS.len().min(((d.get_screen_height() as usize).saturating_sub(270) / 26).max(1)),
+            Tab::Video => 6,
+            Tab::Audio => AUDIO_ROWS.len(),
+            Tab::Gameplay => 3,
+        };
+        let width = 360.0;
+        let height = 92.0 + rows as f32 * 26.0 + 40.0;
+        let x = (d.get_screen_width() as f32 - width) / 2.0;
+        let y = (d.get_screen_height() as f32 - height) / 2.0;
+        ui::panel(d, Rectangle::new(x, y, width, height), "Settings");
+
+        let tab_width = (width - 24.0) / TABS.len() as f32;
+        for (i, tab) in TABS.iter().enumerate() {
+            let rect = Rectangle::new(x + 12.0 + i as f32 * tab_width, y + 36.0, tab_width - 4.0, 22.0);
+            if ui::toggle(d, audio, rect, tab.name(), self.tab == *tab) != (self.tab == *tab) {
+                self.tab = *tab;
+                self.rebinding = None;
+            }
+        }
+
+        let top = y + 68.0;
+        match self.tab {
+            Tab::Video => self.draw_video(d, audio, x, top),
+            Tab::Audio => self.draw_audio(d, audio, x, top),
+            Tab::Controls => self.draw_controls(d, controls, audio, x, top, width, rows),
+            Tab::Gameplay => self.draw_gameplay(d, audio, x, top),
+        }
+
+        let bottom = y + height - 34.0;
+        if self.tab == Tab::Controls && ui::button(d, audio, Rectangle::new(x + 12.0, bottom, 120.0, 24.0), "Reset defaults") {
+            let touch = std::mem::take(&mut controls.touch);
+            *controls = InputMap::for_profile(&config::get().controls_profile);
+            controls.touch = touch;
+            self.save_controls(controls);
+            self.rebinding = None;
+        }
+        if ui::button(d, audio, Rectangle::new(x + width - 92.0, bottom, 80.0, 24.0), "Close") {
+            self.toggle();
+        }
+    }
+
+    fn draw_video(&mut self, d: &mut RaylibDrawHandle, audio: &mut AudioManager, x: f32, top: f32) {
+        let (fullscreen, vsync, show_fps, scale, size, bloom) = {
+            let config = config::get();
+            (config.fullscreen, config.vsync, config.show_fps, config.scale, (config.window_width, config.window_height), config.bloom)
+        };
+        let row = |i: usize| Rectangle::new(x + 160.0, top + i as f32 * 26.0, 188.0, 22.0);
+        let labels = ["Window size", "Fullscreen", "VSync (next start)", "Show FPS", "Zoom", "Bloom"];
+        for (i, label) in labels.iter().enumerate() {
+            d.draw_text(label, x as i32 + 12, (top + i as f32 * 26.0) as i32 + 6, 10, Color::LIGHTGRAY);
+        }
+
+        if ui::button(d, audio, row(0), &format!("{} x {}", size.0, size.1)) {
+            let next = WINDOW_SIZES.iter().position(|s| *s == size).map_or(0, |i| (i + 1) % WINDOW_SIZES.len());
+            let (width, height) = WINDOW_SIZES[next];
+            d.set_window_size(width, height);
+            self.change(|c| {
+                c.window_width = width;
+                c.window_height = height;
+            });
+        }
+        if ui::toggle(d, audio, row(1), on_off(fullscreen), fullscreen) != fullscreen {
+            d.toggle_fullscreen();
+            self.change(|c| c.fullscreen = !fullscreen);
+        }
+        if ui::toggle(d, audio, row(2), on_off(vsync), vsync) != vsync {
+            self.change(|c| c.vsync = !vsync);
+        }
+        if ui::toggle(d, audio, row(3), on_off(show_fps), show_fps) != show_fps {
+            self.change(|c| c.show_fps = !show_fps);
+        }
+        if ui::button(d, audio, row(4), &format!("{}x", scale)) {
+            let next = if scale >= MAX_ZOOM { MIN_ZOOM } else { scale + 1 };
+            self.change(|c| c.scale = next);
+        }
+        let new_bloom = ui::slider(d, audio, row(5), bloom);
+        if new_bloom != bloom {
+            config::set(|c| c.bloom = new_bloom);
+            self.bloom_changed = true;
+        }
+        // like the volume sliders, only saved once it's let go
+        if self.bloom_changed && !d.is_mouse_button_down(MouseButton::MOUSE_BUTTON_LEFT) {
+            self.change(|c| c.bloom = new_bloom);
+            self.bloom_changed = false;
+        }
+    }
+
+    fn draw_audio(&mut self, d: &mut RaylibDrawHandle, audio: &mut AudioManager, x: f32, top: f32) {
+        for (i, (name, bus)) in AUDIO_ROWS.iter().enumerate() {
+            let row_y = top + i as f32 * 26.0;
+            d.draw_text(name, x as i32 + 12, row_y as i32 + 6, 10, Color::LIGHTGRAY);
+            let volume = audio.volume(*bus);
+            let new_volume = ui::slider(d, audio, Rectangle::new(x + 160.0, row_y, 130.0, 22.0), volume);
+            let muted = audio.is_muted(*bus);
+            let new_muted = ui::toggle(d, audio, Rectangle::new(x + 296.0, row_y, 52.0, 22.0), "Mute", muted);
+            if new_volume != volume || new_muted != muted {
+                audio.set_volume(*bus, new_volume);
+                audio.set_muted(*bus, new_muted);
+                self.audio_changed = true;
+            }
+        }
+        // dragging a slider changes it every frame, only write the file once it's let go
+        if self.audio_changed && !d.is_mouse_button_down(MouseButton::MOUSE_BUTTON_LEFT) {
+            if let Err(e) = audio.save_settings(&config::get().save_path(AUDIO_SETTINGS_PATH)) {
+                self.errors.push(e);
+            }
+            self.audio_changed = false;
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn draw_controls(
+        &mut self,
+        d: &mut RaylibDrawHandle,
+        controls: &mut InputMap,
+        audio: &mut AudioManager,
+        x: f32,
+        top: f32,
+        width: f32,
+        rows: usize,
+    ) {
+        let profile = config::get().controls_profile.clone();
+        d.draw_text("Profile", x as i32 + 12, top as i32 + 6, 10, Color::LIGHTGRAY);
+        if ui::button(d, audio, Rectangle::new(x + 160.0, top, 92.0, 22.0), &profile) {
+            let next = PROFILES.iter().position(|p| *p == profile).map_or(0, |i| (i + 1) % PROFILES.len());
+            self.switch_profile(controls, PROFILES[next]);
+        }
+        let export_path = config::get().save_path(PROFILE_EXPORT_PATH);
+        if ui::button(d, audio, Rectangle::new(x + 256.0, top, 44.0, 22.0), "Export") {
+            if let Err(e) = controls.save(&export_path) {
+                self.errors.push(e);
+            }
+        }
+        // into the current profile, over whatever it had
+        if ui::button(d, audio, Rectangle::new(x + 304.0, top, 44.0, 22.0), "Import") {
+            match controls.import(&export_path) {
+                Ok(()) => self.save_controls(controls),
+                Err(e) => self.errors.push(e),
+            }
+        }
+        let (top, rows) = (top + 26.0, rows - 1);
+        self.scroll = self.scroll.min(ACTIONS.len() - rows);
+        if rows < ACTIONS.len() {
+            let range = format!("{}-{} of {}", self.scroll + 1, self.scroll + rows, ACTIONS.len());
+            d.draw_text(&range, (x + width) as i32 - 12 - d.text_width(&range, 10), top as i32 - 54, 10, Color::GRAY);
+        }
+        for (i, action) in ACTIONS.iter().skip(self.scroll).take(rows).enumerate() {
+            let row_y = top + i as f32 * 26.0;
+            d.draw_text(action.name(), x as i32 + 12, row_y as i32 + 6, 10, Color::LIGHTGRAY);
+            let label = if self.rebinding == Some(*action) {
+                "press a key...".to_string()
+            } else {
+                controls.bindings(*action).iter().map(|b| b.name()).collect::<Vec<_>>().join(", ")
+            };
+            // don't start a new rebind on the same click that finished the last one
+            if ui::button(d, audio, Rectangle::new(x + 160.0, row_y, 188.0, 22.0), &label) && !self.ignore_click {
+                self.rebinding = Some(*action);
+            }
+        }
+    }
+
+    fn draw_gameplay(&mut self, d: &mut RaylibDrawHandle, audio: &mut AudioManager, x: f32, top: f32) {
+        let (screen_shake, damage_flash, capture_overlays) = {
+            let config = config::get();
+            (config.screen_shake, config.damage_flash, config.capture_overlays)
+        };
+        d.draw_text("Screen shake", x as i32 + 12, top as i32 + 6, 10, Color::LIGHTGRAY);
+        let shake = ui::slider(d, audio, Rectangle::new(x + 160.0, top, 188.0, 22.0), screen_shake);
+        if shake != screen_shake {
+            config::set(|c| c.screen_shake = shake);
+            self.shake_changed = true;
+        }
+        // like the volume sliders, only saved once it's let go
+        if self.shake_changed && !d.is_mouse_button_down(MouseButton::MOUSE_BUTTON_LEFT) {
+            self.change(|c| c.screen_shake = shake);
+            self.shake_changed = false;
+        }
+        d.draw_text("Damage flash", x as i32 + 12, top as i32 + 32, 10, Color::LIGHTGRAY);
+        let flash_rect = Rectangle::new(x + 160.0, top + 26.0, 188.0, 22.0);
+        if ui::toggle(d, audio, flash_rect, on_off(damage_flash), damage_flash) != damage_flash {
+            self.change(|c| c.damage_flash = !damage_flash);
+        }
+        d.draw_text("Capture HUD", x as i32 + 12, top as i32 + 58, 10, Color::LIGHTGRAY);
+        let capture_rect = Rectangle::new(x + 160.0, top + 52.0, 188.0, 22.0);
+        if ui::toggle(d, audio, capture_rect, on_off(capture_overlays), capture_overlays) != capture_overlays {
+            self.change(|c| c.capture_overlays = !capture_overlays);
+        }
+    }
+
+    // the profile's own rebinds come with it, the current ones are already saved
+    fn switch_profile(&mut self, controls: &mut InputMap, profile: &'static str) {
+        let touch = std::mem::take(&mut controls.touch);
+        *controls = InputMap::load(&config::get().save_path(&input::profile_path(profile)), profile, &mut self.errors);
+        controls.touch = touch;
+        self.change(|c| c.controls_profile = profile.to_string());
+        self.rebinding = None;
+    }
+
+    fn save_controls(&mut self, controls: &InputMap) {
+        let path = config::get().save_path(&input::profile_path(&config::get().controls_profile));
+        if let Err(e) = controls.save(&path) {
+            self.errors.push(e);
+        }
+    }
+
+    // applies and saves a config change, failures show up like the other failed saves
+    fn change(&mut self, apply: impl Fn(&mut Config)) {
+        if let Err(e) = config::change(apply) {
+            self.errors.push(e);
+        }
+    }
+}
+
+fn on_off(on: bool) -> &'static str {
+    if on {
+        "On"
+    } else {
+        "Off"
+    }
+}
@@ -0,0 +1,226 @@
+use std::collections::HashSet;
+
+use rand::Rng;
+
+use crate::digest::Digest;
+use crate::material::{Behavior, MaterialId};
+use raylib::ffi;
+
+use crate::{Pixel, Player, World};
+
+// the world simulates at a fixed rate no matter the frame rate
+pub const STEP_TIME: f32 = 1.0 / 60.0;
+// after a long frame we'd rather fall behind than freeze catching up
+const MAX_STEPS_PER_FRAME: u32 = 4;
+// liquid moves per step, a flood past this finishes over the next steps instead of all at once
+const LIQUID_BUDGET: usize = 1500;
+
+// cellular automaton over the chunks that changed recently. a chunk nothing moved in or next to
+// goes to sleep and costs nothing until something wakes it, see World::wake
+pub struct Simulation {
+    timer: f32,
+    tick: u64,
+}
+
+impl Simulation {
+    pub fn new() -> Self {
+        Simulation { timer: 0.0, tick: 0 }
+    }
+
+    // steps taken since the game started
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    // World::state_hash with how far the simulation has got, two runs that match here have
+    // stepped the same number of times to the same world
+    pub fn hash(&self, world: &World) -> u64 {
+        Digest::default().u64(self.tick).f32(self.timer).u64(world.state_hash()).finish()
+    }
+
+    pub fn update(&mut self, world: &mut World, delta: f32) {
+        self.timer = (self.timer + delta).min(STEP_TIME * MAX_STEPS_PER_FRAME as f32);
+        while self.timer >= STEP_TIME {
+            self.timer -= STEP_TIME;
+            self.step(world);
+        }
+    }
+
+    fn step(&mut self, world: &mut World) {
+        crate::profile_scope!("simulation_step");
+        self.tick += 1;
+        // swap the buffers, whatever got woken last step runs now and this step wakes the next one
+        for chunk in &mut world.chunks {
+            chunk.active = std::mem::take(&mut chunk.wake_next);
+        }
+        let mut active = world.chunks.iter().filter(|c| c.active).map(|c| (c.x, c.y)).collect::<Vec<_>>();
+        // bottom up, so a falling column moves together instead of one pixel per step
+        active.sort_by(|a, b| b.1.cmp(&a.1));
+        // alternate the sweep direction so piles don't lean to one side
+        let left_to_right = self.tick % 2 == 0;
+        let mut moved = HashSet::new();
+        let mut liquid_moves = 0;
+        let mut rng = world.rng.take();
+        for (chunk_x, chunk_y) in active {
+            for y in (chunk_y..chunk_y + 16).rev() {
+                for i in 0..16 {
+                    let x = if left_to_right { chunk_x + i } else { chunk_x + 15 - i };
+                    if moved.contains(&(x, y)) {
+                        continue;
+                    }
+                    let Some(pixel) = world.pixel_at(x, y).copied() else {
+                        continue;
+                    };
+                    if react(world, x, y, pixel.material) {
+                        continue;
+                    }
+                    let material = world.materials.get(pixel.material);
+                    let density = material.density;
+                    let target = match material.behavior {
+                        Behavior::Static => None,
+                        Behavior::Powder => fall_target(world, x, y, density, rng.gen_bool(0.5)),
+                        Behavior::Liquid { .. } if liquid_moves >= LIQUID_BUDGET => {
+                            // out of budget, try again next step
+                            world.wake(x, y);
+                            None
+                        }
+                        Behavior::Liquid { spread } => {
+                            let target = liquid_target(world, x, y, density, spread, rng.gen_bool(0.5));
+                            liquid_moves += target.is_some() as usize;
+                            target
+                        }
+                        Behavior::Gas { fade } => {
+                            if !fade_gas(world, x, y, pixel, fade, &mut rng) {
+                                continue;
+                            }
+                            gas_target(world, x, y, density, &mut rng)
+                        }
+                    };
+                    if let Some((tx, ty)) = target {
+                        swap(world, (x, y), (tx, ty));
+                        moved.insert((tx, ty));
+                    }
+                }
+            }
+        }
+        world.rng.restore(rng);
+    }
+}
+
+// straight down if it can, otherwise down one of the diagonals
+fn fall_target(world: &World, x: i64, y: i64, density: f32, left_first: bool) -> Option<(i64, i64)> {
+    let side = if left_first { -1 } else { 1 };
+    [(0, 1), (side, 1), (-side, 1)]
+        .into_iter()
+        .map(|(dx, dy)| (x + dx, y + dy))
+        .find(|&(tx, ty)| can_enter(world, tx, ty, density))
+}
+
+// falls like powder, otherwise flows a pixel sideways toward the nearest drop within spread.
+// with no drop in reach it's level and stays put, so still water goes to sleep
+fn liquid_target(world: &World, x: i64, y: i64, density: f32, spread: i64, left_first: bool) -> Option<(i64, i64)> {
+    if let Some(target) = fall_target(world, x, y, density, left_first) {
+        return Some(target);
+    }
+    let side = if left_first { -1 } else { 1 };
+    let drop = |direction: i64| {
+        (1..=spread)
+            .map(|d| x + direction * d)
+            .take_while(|&tx| can_enter(world, tx, y, density))
+            .position(|tx| can_enter(world, tx, y + 1, density))
+    };
+    let direction = match (drop(side), drop(-side)) {
+        (Some(a), Some(b)) if b < a => -side,
+        (Some(_), _) => side,
+        (None, Some(_)) => -side,
+        (None, None) => return None,
+    };
+    Some((x + direction, y))
+}
+
+// wanders, mostly upwards. only moves some steps so clouds hang around a bit
+fn gas_target(world: &World, x: i64, y: i64, density: f32, rng: &mut impl Rng) -> Option<(i64, i64)> {
+    if rng.gen_bool(0.5) {
+        return None;
+    }
+    let side = if rng.gen_bool(0.5) { -1 } else { 1 };
+    [(rng.gen_range(-1..=1), -1), (side, 0), (-side, 0)]
+        .into_iter()
+        .map(|(dx, dy)| (x + dx, y + dy))
+        .find(|&(tx, ty)| can_enter(world, tx, ty, density))
+}
+
+// alpha doubles as the gas's lifetime. returns false once it has faded away
+fn fade_gas(world: &mut World, x: i64, y: i64, pixel: Pixel, fade: f32, rng: &mut impl Rng) -> bool {
+    // less than one alpha per step on average, so lose one now and then
+    let loss = fade * STEP_TIME;
+    let loss = loss.floor() as u8 + rng.gen_bool((loss.fract() as f64).clamp(0.0, 1.0)) as u8;
+    if pixel.color.a <= loss {
+        world.remove_pixel(x, y);
+        return false;
+    }
+    if loss > 0 {
+        let color = ffi::Color { a: pixel.color.a - loss, ..pixel.color };
+        world.set_pixel(x, y, pixel.material, color);
+    }
+    true
+}
+
+// heavier things push lighter ones out of the way, as long as the lighter one can move at all
+fn can_enter(world: &World, x: i64, y: i64, density: f32) -> bool {
+    // unloaded chunks count as full, nothing should fall out of the world
+    if world.chunk_at(x, y).is_none() {
+        return false;
+    }
+    let Some(pixel) = world.pixel_at(x, y) else {
+        return true;
+    };
+    let other = world.materials.get(pixel.material);
+    other.behavior != Behavior::Static && other.density < density
+}
+
+// call once a frame
+pub fn apply_contact_effects(world: &World, player: &mut Player) {
+    let start_x = player.position.x.floor() as i64;
+    let start_y = player.position.y.floor() as i64;
+    let end_x = (player.position.x + player.size.x).ceil() as i64;
+    let end_y = (player.position.y + player.size.y).ceil() as i64;
+    for y in start_y..end_y {
+        for x in start_x..end_x {
+            if let Some(effect) = world.contact_effect(x, y) {
+                player.add_status(effect);
+            }
+        }
+    }
+}
+
+fn swap(world: &mut World, from: (i64, i64), to: (i64, i64)) {
+    let Some(moving) = world.remove_pixel(from.0, from.1) else {
+        return;
+    };
+    if let Some(displaced) = world.remove_pixel(to.0, to.1) {
+        world.set_pixel(from.0, from.1, displaced.material, displaced.color);
+    }
+    world.set_pixel(to.0, to.1, moving.material, moving.color);
+}
+
+// returns true if the pixel turned into something else
+fn react(world: &mut World, x: i64, y: i64, material: MaterialId) -> bool {
+    let reactions = &world.materials.get(material).resolved_reactions;
+    if reactions.is_empty() {
+        return false;
+    }
+    for (dx, dy) in [(0, 1), (0, -1), (-1, 0), (1, 0)] {
+        let Some(neighbour) = world.pixel_at(x + dx, y + dy) else {
+            continue;
+        };
+        let Some(&(_, product)) = reactions.iter().find(|(with, _)| *with == neighbour.material) else {
+            continue;
+        };
+        let color = world.materials.get(product).color_at(x, y);
+        world.remove_pixel(x + dx, y + dy);
+        world.set_pixel(x, y, product, color);
+        return true;
+    }
+    false
+}
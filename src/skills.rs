@@ -0,0 +1,206 @@
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::spell::School;
+
+// skill points gained with every level after the first
+const POINTS_PER_LEVEL: u32 = 1;
+
+// what a skill does once unlocked. cost factors multiply, regen bonuses add up
+#[derive(Clone, Copy, Debug)]
+pub enum Bonus {
+    // spells of the school cost this much of what they did
+    Cost(School, f32),
+    // fraction on top of the usual regeneration
+    MpRegen(f32),
+    SpRegen(f32),
+}
+
+pub struct Skill {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    // skill points
+    pub cost: u32,
+    // ids of skills that have to be unlocked first
+    pub requires: &'static [&'static str],
+    pub bonus: Bonus,
+    // column and row on the skill tree screen
+    pub slot: (i32, i32),
+}
+
+// one column per school, the ones further down need the ones above
+pub const SKILLS: &[Skill] = &[
+    Skill {
+        id: "mason",
+        name: "Mason",
+        description: "Shaping spells cost 20% less",
+        cost: 1,
+        requires: &[],
+        bonus: Bonus::Cost(School::Shaping, 0.8),
+        slot: (0, 0),
+    },
+    Skill {
+        id: "architect",
+        name: "Architect",
+        description: "Shaping spells cost another 25% less",
+        cost: 2,
+        requires: &["mason"],
+        bonus: Bonus::Cost(School::Shaping, 0.75),
+        slot: (0, 1),
+    },
+    Skill {
+        id: "demolition",
+        name: "Demolition",
+        description: "Destruction spells cost 15% less",
+        cost: 1,
+        requires: &[],
+        bonus: Bonus::Cost(School::Destruction, 0.85),
+        slot: (1, 0),
+    },
+    Skill {
+        id: "overload",
+        name: "Overload",
+        description: "Destruction spells cost another 15% less",
+        cost: 2,
+        requires: &["demolition"],
+        bonus: Bonus::Cost(School::Destruction, 0.85),
+        slot: (1, 1),
+    },
+    Skill {
+        id: "mender",
+        name: "Mender",
+        description: "Restoration spells cost 20% less",
+        cost: 1,
+        requires: &[],
+        bonus: Bonus::Cost(School::Restoration, 0.8),
+        slot: (2, 0),
+    },
+    Skill {
+        id: "deep_well",
+        name: "Deep Well",
+        description: "Mana regenerates 25% faster",
+        cost: 1,
+        requires: &["mender"],
+        bonus: Bonus::MpRegen(0.25),
+        slot: (2, 1),
+    },
+    Skill {
+        id: "second_wind",
+        name: "Second Wind",
+        description: "Stamina regenerates 30% faster",
+        cost: 1,
+        requires: &["mender"],
+        bonus: Bonus::SpRegen(0.3),
+        slot: (2, 2),
+    },
+    Skill {
+        id: "focus",
+        name: "Focus",
+        description: "Arcane spells cost 15% less",
+        cost: 1,
+        requires: &[],
+        bonus: Bonus::Cost(School::Arcane, 0.85),
+        slot: (3, 0),
+    },
+    Skill {
+        id: "meditation",
+        name: "Meditation",
+        description: "Mana regenerates another 25% faster",
+        cost: 2,
+        requires: &["focus", "deep_well"],
+        bonus: Bonus::MpRegen(0.25),
+        slot: (3, 1),
+    },
+];
+
+pub fn skill(id: &str) -> Option<&'static Skill> {
+    SKILLS.iter().find(|s| s.id == id)
+}
+
+// everything the unlocked skills and worn gear add up to, copied into the player, see
+// Equipment::bonuses
+#[derive(Clone, Copy, Debug)]
+pub struct Bonuses {
+    // by School::index
+    cost: [f32; School::COUNT],
+    pub mp_regen: f32,
+    pub sp_regen: f32,
+    // on top of what the level gives, see Experience::apply
+    pub max_mp: f32,
+    pub move_speed: f32,
+    // on top of the school's cost for Bolt and Shock, see Weather::modify
+    pub lightning_cost: f32,
+}
+
+impl Default for Bonuses {
+    fn default() -> Self {
+        Bonuses { cost: [1.0; School::COUNT], mp_regen: 1.0, sp_regen: 1.0, max_mp: 0.0, move_speed: 1.0, lightning_cost: 1.0 }
+    }
+}
+
+impl Bonuses {
+    pub fn cost_factor(&self, school: School) -> f32 {
+        self.cost[school.index()]
+    }
+
+    // every school at once
+    pub fn scale_costs(&mut self, factor: f32) {
+        for cost in &mut self.cost {
+            *cost *= factor;
+        }
+    }
+}
+
+// which skills the player has, kept in the world save
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SkillTree {
+    pub unlocked: BTreeSet<String>,
+}
+
+impl SkillTree {
+    pub fn has(&self, id: &str) -> bool {
+        self.unlocked.contains(id)
+    }
+
+    pub fn points(&self, level: u32) -> u32 {
+        let spent: u32 = self.unlocked.iter().filter_map(|id| skill(id)).map(|s| s.cost).sum();
+        (level.saturating_sub(1) * POINTS_PER_LEVEL).saturating_sub(spent)
+    }
+
+    // why the skill can't be unlocked yet, if it can't
+    pub fn check(&self, skill: &Skill, level: u32) -> Result<(), String> {
+        if self.has(skill.id) {
+            return Err(format!("{} is already unlocked", skill.name));
+        }
+        if let Some(missing) = skill.requires.iter().find(|id| !self.has(id)) {
+            return Err(format!("{} needs {} first", skill.name, crate::skills::skill(missing).map_or(*missing, |s| s.name)));
+        }
+        if self.points(level) < skill.cost {
+            return Err(format!("{} needs {} skill points", skill.name, skill.cost));
+        }
+        Ok(())
+    }
+
+    pub fn unlock(&mut self, id: &str, level: u32) -> Result<(), String> {
+        let skill = skill(id).ok_or_else(|| format!("no skill called {}", id))?;
+        self.check(skill, level)?;
+        self.unlocked.insert(id.to_string());
+        Ok(())
+    }
+
+    // ids left over from an older version are ignored
+    pub fn bonuses(&self) -> Bonuses {
+        let mut bonuses = Bonuses::default();
+        for skill in self.unlocked.iter().filter_map(|id| skill(id)) {
+            match skill.bonus {
+                Bonus::Cost(school, factor) => bonuses.cost[school.index()] *= factor,
+                Bonus::MpRegen(amount) => bonuses.mp_regen += amount,
+                Bonus::SpRegen(amount) => bonuses.sp_regen += amount,
+            }
+        }
+        bonuses
+    }
+}
@@ -0,0 +1,166 @@
+use raylib::prelude::*;
+
+use crate::events::{EventBus, GameEvent};
+
+// seconds of play from one sunrise to the next
+const DAY_LENGTH: f32 = 360.0;
+// the part of the day after which it's night, until the next sunrise
+const NIGHTFALL: f32 = 0.6;
+// the part of the day dusk and dawn each take to fade the light over
+const TWILIGHT: f32 = 0.04;
+// how much of the sky's light is left at midnight, before the moon's tint
+const NIGHT_LIGHT: f32 = 0.35;
+const MOONLIGHT: Color = Color::new(170, 180, 230, 255);
+const BLOOD_MOONLIGHT: Color = Color::new(230, 90, 90, 255);
+const MANA_MOONLIGHT: Color = Color::new(140, 130, 255, 255);
+
+// one a night, round and round
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MoonPhase {
+    New,
+    WaxingCrescent,
+    FirstQuarter,
+    WaxingGibbous,
+    Full,
+    WaningGibbous,
+    LastQuarter,
+    WaningCrescent,
+}
+
+const PHASES: [MoonPhase; 8] = [
+    MoonPhase::New,
+    MoonPhase::WaxingCrescent,
+    MoonPhase::FirstQuarter,
+    MoonPhase::WaxingGibbous,
+    MoonPhase::Full,
+    MoonPhase::WaningGibbous,
+    MoonPhase::LastQuarter,
+    MoonPhase::WaningCrescent,
+];
+
+// a night that isn't like the others, lasts until sunrise
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NightEvent {
+    // more shades come and they come faster, see DifficultyDirector
+    BloodMoon,
+    // mana comes back faster, see ManaState
+    ManaMoon,
+}
+
+impl MoonPhase {
+    pub fn name(&self) -> &'static str {
+        match self {
+            MoonPhase::New => "new moon",
+            MoonPhase::WaxingCrescent => "waxing crescent",
+            MoonPhase::FirstQuarter => "first quarter",
+            MoonPhase::WaxingGibbous => "waxing gibbous",
+            MoonPhase::Full => "full moon",
+            MoonPhase::WaningGibbous => "waning gibbous",
+            MoonPhase::LastQuarter => "last quarter",
+            MoonPhase::WaningCrescent => "waning crescent",
+        }
+    }
+
+    // how much of the moon is lit, 0 to 1
+    pub fn fullness(&self) -> f32 {
+        let i = PHASES.iter().position(|p| p == self).unwrap_or(0) as f32;
+        1.0 - (i - 4.0).abs() / 4.0
+    }
+
+    // the full moon rises red and the new moon leaves the night to the mana
+    pub fn event(&self) -> Option<NightEvent> {
+        match self {
+            MoonPhase::Full => Some(NightEvent::BloodMoon),
+            MoonPhase::New => Some(NightEvent::ManaMoon),
+            _ => None,
+        }
+    }
+}
+
+impl NightEvent {
+    pub fn announcement(&self) -> &'static str {
+        match self {
+            NightEvent::BloodMoon => "A blood moon rises, the shades are restless",
+            NightEvent::ManaMoon => "The mana moon is out, magic flows freely",
+        }
+    }
+}
+
+// the day and night going round with the time played, and the moon with them. nothing here is
+// saved, it all follows from the time, see Statistics::playtime
+#[derive(Default)]
+pub struct Sky {
+    // whether it was night last update, None before the first
+    night: Option<bool>,
+}
+
+// the day since the world began, counting from 0
+fn day(time: f32) -> u64 {
+    (time / DAY_LENGTH) as u64
+}
+
+// how far into the current day, 0 at sunrise
+fn hour(time: f32) -> f32 {
+    (time / DAY_LENGTH).fract()
+}
+
+pub fn is_night(time: f32) -> bool {
+    hour(time) >= NIGHTFALL
+}
+
+// the moon of the night of the current day, it changes at sunrise
+pub fn moon(time: f32) -> MoonPhase {
+    PHASES[(day(time) % PHASES.len() as u64) as usize]
+}
+
+// what kind of night it is, if it's night
+pub fn night_event(time: f32) -> Option<NightEvent> {
+    if !is_night(time) {
+        return None;
+    }
+    moon(time).event()
+}
+
+// 1 in the day, NIGHT_LIGHT in the night, fading over the twilight in between
+fn daylight(time: f32) -> f32 {
+    let hour = hour(time);
+    let dusk = ((hour - NIGHTFALL) / TWILIGHT).clamp(0.0, 1.0);
+    let dawn = ((1.0 - hour) / TWILIGHT).clamp(0.0, 1.0);
+    1.0 - (1.0 - NIGHT_LIGHT) * dusk.min(dawn)
+}
+
+impl Sky {
+    // publishes Nightfall and Daybreak as they happen. right after loading it says which it is,
+    // so whoever listens doesn't miss a blood moon that was already up
+    pub fn update(&mut self, time: f32, events: &mut EventBus) {
+        let night = is_night(time);
+        if self.night == Some(night) {
+            return;
+        }
+        self.night = Some(night);
+        if night {
+            crate::log_info!("night {} falls under a {}", day(time), moon(time).name());
+            events.publish(GameEvent::Nightfall { phase: moon(time), event: moon(time).event() });
+        } else {
+            events.publish(GameEvent::Daybreak);
+        }
+    }
+
+    // the weather's sky darkened for the night and tinted by the moon
+    pub fn light(&self, time: f32, sky: Color) -> Color {
+        let level = daylight(time);
+        let moonlight = match night_event(time) {
+            Some(NightEvent::BloodMoon) => BLOOD_MOONLIGHT,
+            Some(NightEvent::ManaMoon) => MANA_MOONLIGHT,
+            // a brighter moon lights the night a little more
+            None => MOONLIGHT.fade(0.5 + moon(time).fullness() * 0.5),
+        };
+        // the moon's color only comes in as the daylight goes
+        let night = (1.0 - level) / (1.0 - NIGHT_LIGHT);
+        let channel = |sky: u8, moon: u8| {
+            let moon = 255.0 + (moon as f32 * moonlight.a as f32 / 255.0 - 255.0) * night;
+            (sky as f32 * level * moon / 255.0).clamp(0.0, 255.0) as u8
+        };
+        Color::new(channel(sky.r, moonlight.r), channel(sky.g, moonlight.g), channel(sky.b, moonlight.b), sky.a)
+    }
+}
@@ -0,0 +1,830 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rand::Rng;
+use raylib::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::ecs::{self, Body, Projectile, Pulse, Sprite};
+use crate::effects::{Effect, Effects, FlashKind};
+use crate::electricity;
+use crate::events::GameEvent;
+use crate::lighting::Light;
+use crate::error::SpellcoderError;
+use crate::material::{Behavior, MaterialRegistry};
+use crate::particles::Emitter;
+use crate::skills::Bonuses;
+use crate::status::{StatusEffect, StatusKind};
+use crate::{Player, World};
+
+pub const SPELL_DIR: &str = "spells";
+pub const DEFAULT_CAST_RATE: f32 = 8.0;
+// a rate of 0 would mean no wait at all between repeats, so it never goes below this
+pub const MIN_CAST_RATE: f32 = 0.1;
+// explosions push the player this hard at the center, in world pixels per frame
+const EXPLOSION_KNOCKBACK: f32 = 1.5;
+// empty pixels in the blast that fill with smoke
+const EXPLOSION_SMOKE_CHANCE: f64 = 0.35;
+// a fully charged spell is this many times as strong, and costs about as much more
+pub const MAX_CHARGE_POWER: f32 = 2.5;
+// hp an entity loses at the center of an explosion per point of power
+const EXPLOSION_DAMAGE: f32 = 10.0;
+// seconds a projectile flies before going off by itself
+const PROJECTILE_LIFETIME: f32 = 4.0;
+// world pixels, drawn size and how close it has to get to something with health to hit it
+const PROJECTILE_SIZE: f32 = 1.5;
+const PROJECTILE_HIT_RANGE: f32 = 2.5;
+// world pixels, how far a projectile's glow reaches
+const PROJECTILE_GLOW: f32 = 10.0;
+// the flash an explosion lights its surroundings with, radius on top of the blast's
+const EXPLOSION_LIGHT: Color = Color::new(255, 170, 80, 255);
+const EXPLOSION_GLOW: f32 = 12.0;
+const EXPLOSION_FLASH_TIME: f32 = 0.4;
+// sparks for spells that don't place anything colored
+const CAST_SPARK_COLOR: Color = Color::new(180, 140, 255, 255);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SpellOrigin {
+    Player,
+    Cursor,
+}
+
+// picked by the modifier held while casting, see InputMap::cast_variant
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CastVariant {
+    Normal,
+    Shift,
+    Ctrl,
+}
+
+// what kind of magic a component is, skills make whole schools cheaper
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum School {
+    // placing and removing pixels
+    Shaping,
+    Destruction,
+    Restoration,
+    // everything that carries, repeats or bends other magic
+    Arcane,
+}
+
+impl School {
+    pub const COUNT: usize = 4;
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+}
+
+// every function a spell uses costs mana, see SpellComponent::cost
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum SpellComponent {
+    SetPixel {
+        dx: i64,
+        dy: i64,
+        color: [u8; 4],
+        // name in the material registry
+        #[serde(default = "default_material")]
+        material: String,
+    },
+    Erase { radius: i64 },
+    // damages everything in the radius by power, pixels break once their hardness is used up
+    Break { radius: i64, power: f32 },
+    // like Break but weaker towards the edge, and it throws things around and leaves smoke
+    Explode { radius: i64, power: f32 },
+    Heal { amount: f32 },
+    // fills the empty pixels in the radius with a gas or a liquid, color alpha is how long it lasts
+    Cloud { radius: i64, color: [u8; 4], material: String },
+    Status { kind: StatusKind, duration: f32, magnitude: f32 },
+    // purely visual, from the player to the spell's origin
+    Beam { color: [u8; 4], width: f32, duration: f32 },
+    // electrifies whatever conducts around the origin, see electricity.rs
+    Shock { power: f32 },
+    // also just visual, lightning from the player to the origin
+    Bolt {
+        color: [u8; 4],
+        width: f32,
+        duration: f32,
+        #[serde(default)]
+        branches: u32,
+    },
+    // flies from the player towards the target, the components go off where it hits something.
+    // speed in world pixels per second, gravity pulls it down like a thrown item
+    Projectile {
+        speed: f32,
+        #[serde(default)]
+        gravity: f32,
+        color: [u8; 4],
+        components: Vec<SpellComponent>,
+    },
+    // stays at the origin, setting the components off every interval seconds for duration seconds
+    Linger { duration: f32, interval: f32, components: Vec<SpellComponent> },
+    // the world runs at factor speed for duration real seconds, the caster keeps theirs
+    SlowTime { factor: f32, duration: f32 },
+    // lifts the fog over the unexplored world around the origin for duration seconds
+    Reveal { radius: i64, duration: f32 },
+    // a light that stays at the origin for duration seconds, radius in world pixels
+    Glow { color: [u8; 4], radius: f32, duration: f32 },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Spell {
+    pub name: String,
+    pub origin: SpellOrigin,
+    #[serde(default)]
+    pub cooldown: f32,
+    // seconds of holding cast to reach full power, 0 casts on press like usual
+    #[serde(default)]
+    pub charge_time: f32,
+    pub components: Vec<SpellComponent>,
+    // alternate component sets for modified casts, e.g. "Shift": [...], missing ones use the normal components
+    #[serde(default)]
+    pub variants: HashMap<CastVariant, Vec<SpellComponent>>,
+    // file names in the sfx directory, played on cast and when the spell changes the world
+    #[serde(default)]
+    pub sound: Option<String>,
+    #[serde(default)]
+    pub on_touch_sound: Option<String>,
+    // known from the start, the rest have to be learned, see Spellbook::learn
+    #[serde(default)]
+    pub starter: bool,
+}
+
+fn default_material() -> String {
+    "BLOCK".to_string()
+}
+
+pub struct Spellbook {
+    // the ones the player knows, in the order they were learned
+    pub spells: Vec<Spell>,
+    // every spell in the spell directory with its file name, known or not
+    library: Vec<(String, Spell)>,
+    pub active: usize,
+    pub cast_rate: f32, // max casts per second when holding the cast button
+    cooldowns: Vec<f32>,
+    repeat_timer: f32,
+    charge: Option<f32>, // seconds held so far, while a chargeable spell is being charged
+}
+
+impl SpellComponent {
+    pub fn cost(&self) -> f32 {
+        match self {
+            SpellComponent::SetPixel { .. } => 1.0,
+            SpellComponent::Erase { radius } => 2.0 + (radius * radius) as f32 * 0.5,
+            SpellComponent::Break { radius, power } => 1.0 + (radius * radius) as f32 * 0.3 + power * 0.5,
+            SpellComponent::Explode { radius, power } => 5.0 + (radius * radius) as f32 * 0.4 + power,
+            SpellComponent::Heal { amount } => amount * 0.8,
+            SpellComponent::Cloud { radius, .. } => 3.0 + (radius * radius) as f32 * 0.3,
+            SpellComponent::Status { duration, magnitude, .. } => 2.0 + duration * magnitude.abs() * 0.5,
+            SpellComponent::Shock { power } => 2.0 + power * 2.0,
+            SpellComponent::Beam { .. } => 0.5,
+            SpellComponent::Bolt { branches, .. } => 1.0 + *branches as f32 * 0.25,
+            SpellComponent::Projectile { speed, components, .. } => 1.0 + speed / 100.0 + components.iter().map(|c| c.cost()).sum::<f32>(),
+            // every pulse costs, but paying up front makes it a bit cheaper
+            SpellComponent::SlowTime { factor, duration } => 5.0 + duration * (1.0 - factor.clamp(0.0, 1.0)) * 4.0,
+            SpellComponent::Reveal { radius, duration } => 2.0 + *radius as f32 * 0.3 + duration * 0.2,
+            SpellComponent::Glow { radius, duration, .. } => 1.0 + radius * 0.1 + duration * 0.05,
+            SpellComponent::Linger { duration, interval, components } => {
+                let pulses = (duration / interval.max(0.05)).ceil();
+                components.iter().map(|c| c.cost()).sum::<f32>() * pulses * 0.5
+            }
+        }
+    }
+
+    // nested components count towards the school of the one carrying them
+    pub fn school(&self) -> School {
+        match self {
+            SpellComponent::SetPixel { .. } | SpellComponent::Erase { .. } | SpellComponent::Cloud { .. } => School::Shaping,
+            SpellComponent::Break { .. } | SpellComponent::Explode { .. } | SpellComponent::Shock { .. } | SpellComponent::Bolt { .. } => {
+                School::Destruction
+            }
+            SpellComponent::Heal { .. } | SpellComponent::Status { .. } => School::Restoration,
+            SpellComponent::Beam { .. }
+            | SpellComponent::Projectile { .. }
+            | SpellComponent::Linger { .. }
+            | SpellComponent::SlowTime { .. }
+            | SpellComponent::Reveal { .. }
+            | SpellComponent::Glow { .. } => School::Arcane,
+        }
+    }
+
+    // cheaper in a storm, see Weather::modify
+    pub fn is_lightning(&self) -> bool {
+        matches!(self, SpellComponent::Bolt { .. } | SpellComponent::Shock { .. })
+    }
+
+    // the component as cast with this much charge, 1.0 is uncharged
+    pub fn scaled(&self, power: f32) -> SpellComponent {
+        match self.clone() {
+            SpellComponent::Erase { radius } => SpellComponent::Erase {
+                radius: (radius as f32 * power).round() as i64,
+            },
+            SpellComponent::Break { radius, power: strength } => SpellComponent::Break { radius, power: strength * power },
+            SpellComponent::Explode { radius, power: strength } => SpellComponent::Explode {
+                radius: (radius as f32 * power.sqrt()).round() as i64,
+                power: strength * power,
+            },
+            SpellComponent::Heal { amount } => SpellComponent::Heal { amount: amount * power },
+            SpellComponent::Cloud { radius, color, material } => SpellComponent::Cloud {
+                radius: (radius as f32 * power).round() as i64,
+                color,
+                material,
+            },
+            // longer rather than stronger, haste at 2.5x the speed would be unplayable
+            SpellComponent::Status { kind, duration, magnitude } => SpellComponent::Status {
+                kind,
+                duration: duration * power,
+                magnitude,
+            },
+            SpellComponent::Shock { power: strength } => SpellComponent::Shock { power: strength * power },
+            SpellComponent::Beam { color, width, duration } => SpellComponent::Beam { color, width: width * power, duration },
+            SpellComponent::Bolt { color, width, duration, branches } => SpellComponent::Bolt {
+                color,
+                width: width * power,
+                duration,
+                branches: branches + ((power - 1.0) * 2.0).round() as u32,
+            },
+            SpellComponent::Projectile { speed, gravity, color, components } => SpellComponent::Projectile {
+                speed,
+                gravity,
+                color,
+                components: components.iter().map(|c| c.scaled(power)).collect(),
+            },
+            SpellComponent::Linger { duration, interval, components } => SpellComponent::Linger {
+                duration,
+                interval,
+                components: components.iter().map(|c| c.scaled(power)).collect(),
+            },
+            SpellComponent::SlowTime { factor, duration } => SpellComponent::SlowTime { factor, duration: duration * power },
+            SpellComponent::Reveal { radius, duration } => SpellComponent::Reveal {
+                radius: (radius as f32 * power).round() as i64,
+                duration,
+            },
+            SpellComponent::Glow { color, radius, duration } => SpellComponent::Glow { color, radius: radius * power, duration },
+            component => component,
+        }
+    }
+}
+
+impl CastVariant {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CastVariant::Normal => "Normal",
+            CastVariant::Shift => "Shift",
+            CastVariant::Ctrl => "Ctrl",
+        }
+    }
+
+    // any case, from the console and the command line
+    pub fn parse(name: &str) -> Option<CastVariant> {
+        [CastVariant::Normal, CastVariant::Shift, CastVariant::Ctrl].into_iter().find(|v| v.name().eq_ignore_ascii_case(name))
+    }
+}
+
+impl Spell {
+    // problems that only show up once the spell is cast, for validate-spells
+    pub fn lint(&self, materials: &MaterialRegistry) -> Vec<String> {
+        let mut problems = Vec::new();
+        let sets = std::iter::once(&self.components).chain(self.variants.values());
+        for components in sets {
+            lint_components(components, materials, &mut problems);
+        }
+        if let Some(material) = self.solid_cloud(materials) {
+            problems.push(solid_cloud_problem(material));
+        }
+        if self.cooldown < 0.0 || self.charge_time < 0.0 {
+            problems.push("cooldown and charge_time can't be negative".to_string());
+        }
+        problems
+    }
+
+    // the material of the first Cloud that isn't a gas or a liquid. a cloud of anything else
+    // would hang in the air as a ball of solid pixels
+    pub fn solid_cloud(&self, materials: &MaterialRegistry) -> Option<&str> {
+        std::iter::once(&self.components).chain(self.variants.values()).find_map(|components| solid_cloud(components, materials))
+    }
+
+    pub fn components(&self, variant: CastVariant) -> &[SpellComponent] {
+        self.variants.get(&variant).unwrap_or(&self.components)
+    }
+
+    pub fn has_variant(&self, variant: CastVariant) -> bool {
+        self.variants.contains_key(&variant)
+    }
+
+    pub fn cost(&self, variant: CastVariant) -> f32 {
+        self.cost_at(variant, 1.0)
+    }
+
+    pub fn cost_at(&self, variant: CastVariant, power: f32) -> f32 {
+        self.cost_with(variant, power, &Bonuses::default())
+    }
+
+    // what the caster actually pays, with the skills they have
+    pub fn cost_with(&self, variant: CastVariant, power: f32, bonuses: &Bonuses) -> f32 {
+        self.components(variant)
+            .iter()
+            .map(|c| {
+                let lightning = if c.is_lightning() { bonuses.lightning_cost } else { 1.0 };
+                c.scaled(power).cost() * bonuses.cost_factor(c.school()) * lightning
+            })
+            .sum()
+    }
+
+    pub fn is_chargeable(&self) -> bool {
+        self.charge_time > 0.0
+    }
+}
+
+impl Spellbook {
+    // every *.json in the directory is a spell, sorted by file name so the order is stable.
+    // only starter spells are known to begin with. broken spells are left out and added to errors
+    pub fn load(dir: &str, materials: &MaterialRegistry, errors: &mut Vec<SpellcoderError>) -> Self {
+        let mut library = Vec::new();
+        let paths = spell_files(dir).unwrap_or_else(|e| {
+            errors.push(e);
+            Vec::new()
+        });
+        for path in paths {
+            match load_spell(&path) {
+                Ok(spell) => match spell.solid_cloud(materials) {
+                    Some(material) => errors.push(SpellcoderError::invalid(path.display().to_string(), solid_cloud_problem(material))),
+                    None => library.push((path.file_stem().map(|s| s.to_string_lossy().to_lowercase()).unwrap_or_default(), spell)),
+                },
+                Err(e) => errors.push(e),
+            }
+        }
+        let mut spellbook = Spellbook {
+            spells: Vec::new(),
+            library,
+            active: 0,
+            cast_rate: DEFAULT_CAST_RATE,
+            cooldowns: Vec::new(),
+            repeat_timer: 0.0,
+            charge: None,
+        };
+        spellbook.restore(&[]);
+        spellbook
+    }
+
+    // any spell in the library by spell name or file name, known or not
+    pub fn find(&self, name: &str) -> Option<&Spell> {
+        let name = name.to_lowercase();
+        self.library.iter().find(|(stem, spell)| spell.name.to_lowercase() == name || *stem == name).map(|(_, spell)| spell)
+    }
+
+    // by spell name or file name, from altars, scrolls, quests and the console
+    pub fn learn(&mut self, name: &str) -> Result<String, String> {
+        let Some(spell) = self.find(name).cloned() else {
+            return Err(format!("no spell called {}", name.to_lowercase()));
+        };
+        if self.knows(&spell.name) {
+            return Err(format!("already know {}", spell.name));
+        }
+        let name = spell.name.clone();
+        self.spells.push(spell);
+        self.cooldowns.push(0.0);
+        Ok(name)
+    }
+
+    pub fn knows(&self, name: &str) -> bool {
+        self.spells.iter().any(|s| s.name == name)
+    }
+
+    // names of the spells still to be learned
+    pub fn unknown(&self) -> Vec<&str> {
+        self.library.iter().map(|(_, s)| s.name.as_str()).filter(|name| !self.knows(name)).collect()
+    }
+
+    // for the save, see restore
+    pub fn known(&self) -> Vec<String> {
+        self.spells.iter().map(|s| s.name.clone()).collect()
+    }
+
+    // the starters and the named spells, in that order. names that aren't in the spell
+    // directory any more are dropped
+    pub fn restore(&mut self, known: &[String]) {
+        self.spells = self.library.iter().map(|(_, s)| s).filter(|s| s.starter).cloned().collect();
+        self.cooldowns = vec![0.0; self.spells.len()];
+        for name in known {
+            let _ = self.learn(name);
+        }
+        self.active = 0;
+        self.charge = None;
+    }
+
+    pub fn active_spell(&self) -> Option<&Spell> {
+        self.spells.get(self.active)
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.repeat_timer <= 0.0 && self.cooldowns.get(self.active).is_some_and(|c| *c <= 0.0)
+    }
+
+    pub fn cycle(&mut self, step: i32) {
+        if self.spells.is_empty() {
+            return;
+        }
+        let len = self.spells.len() as i32;
+        self.active = (self.active as i32 + step).rem_euclid(len) as usize;
+        self.charge = None;
+    }
+
+    // slots past the end of the spell list are ignored
+    pub fn select(&mut self, index: usize) {
+        if index < self.spells.len() && index != self.active {
+            self.active = index;
+            self.charge = None;
+        }
+    }
+
+    // call every frame cast is held on a chargeable spell
+    pub fn charge(&mut self, delta: f32) {
+        self.charge = Some(self.charge.unwrap_or(0.0) + delta);
+    }
+
+    pub fn cancel_charge(&mut self) {
+        self.charge = None;
+    }
+
+    // 0 to 1, None when not charging
+    pub fn charge_fraction(&self) -> Option<f32> {
+        let held = self.charge?;
+        let spell = self.active_spell()?;
+        Some((held / spell.charge_time).clamp(0.0, 1.0))
+    }
+
+    // ends the charge, returning the power to cast with
+    pub fn release_charge(&mut self) -> Option<f32> {
+        let fraction = self.charge_fraction();
+        self.charge = None;
+        fraction.map(|f| 1.0 + (MAX_CHARGE_POWER - 1.0) * f)
+    }
+
+    pub fn update(&mut self, delta: f32) {
+        for cooldown in &mut self.cooldowns {
+            *cooldown = (*cooldown - delta).max(0.0);
+        }
+        self.repeat_timer = (self.repeat_timer - delta).max(0.0);
+    }
+
+    // target is the aimed world position in pixels, spells with a cursor origin go there.
+    // power comes from charging, 1.0 otherwise. returns whether the spell touched any pixels
+    pub fn cast(
+        &mut self,
+        world: &mut World,
+        player: &mut Player,
+        effects: &mut Effects,
+        target: Vector2,
+        variant: CastVariant,
+        power: f32,
+    ) -> Result<bool, String> {
+        let Some(spell) = self.spells.get(self.active) else {
+            return Err("no spells loaded".to_string());
+        };
+        if self.cooldowns[self.active] > 0.0 {
+            return Err(format!("{} is on cooldown", spell.name));
+        }
+        let cost = if player.rules.creative { 0.0 } else { spell.cost_with(variant, power, &player.bonuses) };
+        if player.mp < cost {
+            return Err(format!("not enough mana for {} ({:.0} MP)", spell.name, cost));
+        }
+        player.mp -= cost;
+        player.mana.spent(player.mp);
+        self.cooldowns[self.active] = spell.cooldown;
+        self.repeat_timer = 1.0 / self.cast_rate.max(MIN_CAST_RATE);
+        Ok(activate_spell(spell, variant, power, world, player, effects, target))
+    }
+
+    // a scroll's spell, from the whole library so it doesn't have to be known. no mp and no
+    // cooldown. the spell that went off and whether it touched any pixels
+    pub fn cast_scroll(&self, name: &str, world: &mut World, player: &mut Player, effects: &mut Effects, target: Vector2) -> Result<(&Spell, bool), String> {
+        let Some((_, spell)) = self.library.iter().find(|(_, spell)| spell.name == name) else {
+            return Err(format!("no spell called {}", name));
+        };
+        Ok((spell, activate_spell(spell, CastVariant::Normal, 1.0, world, player, effects, target)))
+    }
+}
+
+fn solid_cloud<'a>(components: &'a [SpellComponent], materials: &MaterialRegistry) -> Option<&'a str> {
+    components.iter().find_map(|component| match component {
+        SpellComponent::Cloud { material, .. } => {
+            let id = materials.id(material)?;
+            (!matches!(materials.get(id).behavior, Behavior::Gas { .. } | Behavior::Liquid { .. })).then_some(material.as_str())
+        }
+        SpellComponent::Projectile { components, .. } | SpellComponent::Linger { components, .. } => solid_cloud(components, materials),
+        _ => None,
+    })
+}
+
+fn solid_cloud_problem(material: &str) -> String {
+    format!("Cloud material {} has to be a gas or a liquid", material)
+}
+
+fn lint_components(components: &[SpellComponent], materials: &MaterialRegistry, problems: &mut Vec<String>) {
+    for component in components {
+        match component {
+            SpellComponent::SetPixel { material, .. } | SpellComponent::Cloud { material, .. } if materials.id(material).is_none() => {
+                problems.push(format!("unknown material {}", material));
+            }
+            SpellComponent::Linger { interval, .. } if *interval <= 0.0 => problems.push("Linger needs an interval above 0".to_string()),
+            SpellComponent::Projectile { components, .. } | SpellComponent::Linger { components, .. } => {
+                lint_components(components, materials, problems);
+            }
+            _ => {}
+        }
+    }
+}
+
+pub fn spell_files(dir: &str) -> Result<Vec<PathBuf>, SpellcoderError> {
+    let mut paths = fs::read_dir(dir)
+        .map_err(|e| SpellcoderError::io(dir, e))?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+        .collect::<Vec<_>>();
+    paths.sort();
+    Ok(paths)
+}
+
+pub fn load_spell(path: &Path) -> Result<Spell, SpellcoderError> {
+    let name = path.display().to_string();
+    let text = fs::read_to_string(path).map_err(|e| SpellcoderError::io(&name, e))?;
+    serde_json::from_str::<Spell>(&text).map_err(|e| SpellcoderError::parse(name, e))
+}
+
+// returns true if any component placed or removed a pixel
+pub fn activate_spell(
+    spell: &Spell,
+    variant: CastVariant,
+    power: f32,
+    world: &mut World,
+    player: &mut Player,
+    effects: &mut Effects,
+    target: Vector2,
+) -> bool {
+    let origin = match spell.origin {
+        SpellOrigin::Player => player.center(),
+        SpellOrigin::Cursor => target,
+    };
+    let components = spell.components(variant).iter().map(|c| c.scaled(power)).collect::<Vec<_>>();
+    // sparks take the color of the first pixel the spell places
+    let spark_color = components
+        .iter()
+        .find_map(|c| match c {
+            SpellComponent::SetPixel { color, .. } => Some(Color::new(color[0], color[1], color[2], 255)),
+            _ => None,
+        })
+        .unwrap_or(CAST_SPARK_COLOR);
+    effects.request(Effect::Particles { emitter: Emitter::SpellCast(spark_color), position: origin });
+    apply_components(&spell.name, &components, origin, target, world, player, effects)
+}
+
+// sets the components off at origin, target is where the caster was aiming. spells in flight
+// call this again when they land. returns true if any component placed or removed a pixel
+fn apply_components(
+    spell: &str,
+    components: &[SpellComponent],
+    origin: Vector2,
+    target: Vector2,
+    world: &mut World,
+    player: &mut Player,
+    effects: &mut Effects,
+) -> bool {
+    let ox = origin.x.floor() as i64;
+    let oy = origin.y.floor() as i64;
+    let mut touched = false;
+    for component in components {
+        match component {
+            SpellComponent::SetPixel { dx, dy, color, material } => {
+                let color = ffi::Color {
+                    r: color[0],
+                    g: color[1],
+                    b: color[2],
+                    a: color[3],
+                };
+                let Some(material) = world.materials.id(material) else {
+                    crate::log_warn!("{} uses unknown material {}", spell, material);
+                    continue;
+                };
+                touched |= world.place_pixel(ox + dx, oy + dy, material, color);
+            }
+            SpellComponent::Erase { radius } => {
+                for x in -radius..=*radius {
+                    for y in -radius..=*radius {
+                        if x * x + y * y <= radius * radius {
+                            if world.pixel_at(ox + x, oy + y).is_some_and(|p| world.materials.get(p.material).unbreakable) {
+                                continue;
+                            }
+                            touched |= world.erase_pixel(ox + x, oy + y).is_some();
+                        }
+                    }
+                }
+            }
+            SpellComponent::Break { radius, power } => {
+                for x in -radius..=*radius {
+                    for y in -radius..=*radius {
+                        if x * x + y * y > radius * radius || world.pixel_at(ox + x, oy + y).is_none() {
+                            continue;
+                        }
+                        touched = true;
+                        if let Some(pixel) = world.damage_pixel(ox + x, oy + y, *power) {
+                            world.drop_item(pixel.material, ox + x, oy + y);
+                        }
+                    }
+                }
+            }
+            SpellComponent::Explode { radius, power } => {
+                let light = Light::new(EXPLOSION_LIGHT, *radius as f32 * 2.0 + EXPLOSION_GLOW, 1.0).flickering(0.3);
+                world.lights.flash(origin, light, EXPLOSION_FLASH_TIME);
+                touched |= explode(world, player, effects, origin, *radius, *power);
+            }
+            SpellComponent::Cloud { radius, color, material } => {
+                let Some(material) = world.materials.id(material) else {
+                    crate::log_warn!("{} uses unknown material {}", spell, material);
+                    continue;
+                };
+                let color = ffi::Color {
+                    r: color[0],
+                    g: color[1],
+                    b: color[2],
+                    a: color[3],
+                };
+                for x in -radius..=*radius {
+                    for y in -radius..=*radius {
+                        if x * x + y * y <= radius * radius && world.pixel_at(ox + x, oy + y).is_none() {
+                            world.place_pixel(ox + x, oy + y, material, color);
+                        }
+                    }
+                }
+            }
+            SpellComponent::Heal { amount } => {
+                player.hp = (player.hp + amount).min(player.max_hp);
+                effects.request(Effect::Flash { kind: FlashKind::Heal, strength: 0.3 + amount / 50.0 });
+            }
+            SpellComponent::Status { kind, duration, magnitude } => {
+                player.add_status(StatusEffect::new(*kind, *duration, *magnitude));
+                if *kind == StatusKind::ManaSurge {
+                    effects.request(Effect::Flash { kind: FlashKind::ManaSurge, strength: 0.5 });
+                }
+            }
+            SpellComponent::Shock { power } => touched |= electricity::shock(world, ox, oy, *power),
+            SpellComponent::Beam { color, width, duration } => {
+                let color = Color::new(color[0], color[1], color[2], color[3]);
+                world.lights.flash(origin, Light::new(color, 6.0 + width * 2.0, 0.7), *duration);
+                effects.request(Effect::Beam {
+                    from: player.center(),
+                    to: origin,
+                    color,
+                    width: *width,
+                    duration: *duration,
+                })
+            }
+            SpellComponent::Bolt { color, width, duration, branches } => {
+                let color = Color::new(color[0], color[1], color[2], color[3]);
+                // lightning lights everything up for a moment, flickering as it reshapes itself
+                world.lights.flash(origin, Light::new(color, 14.0 + width * 3.0, 1.0).flickering(0.6), *duration);
+                world.lights.flash(player.center(), Light::new(color, 10.0, 0.6).flickering(0.6), *duration);
+                effects.request(Effect::Bolt {
+                    from: player.center(),
+                    to: origin,
+                    color,
+                    width: *width,
+                    duration: *duration,
+                    branches: *branches,
+                })
+            }
+            SpellComponent::Projectile { speed, gravity, color, components } => {
+                let from = player.center();
+                let direction = (target - from).normalized();
+                let entity = world.ecs.spawn();
+                world.ecs.positions.insert(entity, from);
+                world.ecs.bodies.insert(
+                    entity,
+                    Body { velocity: direction * *speed, gravity: *gravity, bounce: 0.0, collided: false, resting: false },
+                );
+                let color = Color::new(color[0], color[1], color[2], color[3]);
+                world.ecs.sprites.insert(entity, Sprite { color, size: PROJECTILE_SIZE });
+                world.ecs.lights.insert(entity, Light::new(color, PROJECTILE_GLOW, 0.9));
+                world.ecs.lifetimes.insert(entity, PROJECTILE_LIFETIME);
+                world.ecs.projectiles.insert(entity, Projectile { spell: spell.to_string(), components: components.clone() });
+            }
+            SpellComponent::Linger { duration, interval, components } => {
+                let entity = world.ecs.spawn();
+                world.ecs.positions.insert(entity, origin);
+                world.ecs.lifetimes.insert(entity, *duration);
+                world.ecs.pulses.insert(
+                    entity,
+                    Pulse {
+                        spell: spell.to_string(),
+                        components: components.clone(),
+                        interval: interval.max(0.05),
+                        timer: 0.0,
+                    },
+                );
+            }
+            SpellComponent::SlowTime { factor, duration } => {
+                world.events.publish(GameEvent::SlowMotion { factor: *factor, duration: *duration });
+            }
+            SpellComponent::Reveal { radius, duration } => world.explored.reveal(origin, *radius as f32, *duration),
+            SpellComponent::Glow { color, radius, duration } => {
+                let color = Color::new(color[0], color[1], color[2], color[3]);
+                ecs::spawn_light(&mut world.ecs, origin, Light::new(color, *radius, 0.9).flickering(0.1), *duration);
+            }
+        }
+    }
+    touched
+}
+
+// moves spells in flight along: projectiles go off when they hit something or run out of time,
+// lingering spells go off whenever their timer comes round. call before ecs::update despawns them
+pub fn update_entities(world: &mut World, player: &mut Player, effects: &mut Effects, delta: f32) {
+    let mut triggered = Vec::new();
+    for entity in world.ecs.projectiles.entities() {
+        let Some(&position) = world.ecs.positions.get(entity) else {
+            continue;
+        };
+        let landed = world.ecs.bodies.get(entity).is_some_and(|b| b.collided);
+        let expiring = world.ecs.lifetimes.get(entity).is_some_and(|l| *l <= delta);
+        let hit = world.ecs.healths.iter().any(|(other, _)| {
+            world.ecs.positions.get(other).is_some_and(|p| p.distance_to(position) < PROJECTILE_HIT_RANGE)
+        });
+        if !(landed || expiring || hit) {
+            continue;
+        }
+        if let Some(projectile) = world.ecs.projectiles.remove(entity) {
+            triggered.push((projectile.spell, projectile.components, position));
+        }
+        world.ecs.despawn(entity);
+    }
+    for (entity, pulse) in world.ecs.pulses.iter_mut() {
+        pulse.timer -= delta;
+        if pulse.timer > 0.0 {
+            continue;
+        }
+        pulse.timer += pulse.interval;
+        if let Some(&position) = world.ecs.positions.get(entity) {
+            triggered.push((pulse.spell.clone(), pulse.components.clone(), position));
+        }
+    }
+    for (spell, components, position) in triggered {
+        apply_components(&spell, &components, position, position, world, player, effects);
+    }
+}
+
+// returns whether any pixel was hit
+fn explode(world: &mut World, player: &mut Player, effects: &mut Effects, origin: Vector2, radius: i64, power: f32) -> bool {
+    let (ox, oy) = (origin.x.floor() as i64, origin.y.floor() as i64);
+    let radius_f = radius.max(1) as f32;
+    let smoke = world.materials.named("SMOKE");
+    let mut touched = false;
+    for x in -radius..=radius {
+        for y in -radius..=radius {
+            let distance = ((x * x + y * y) as f32).sqrt();
+            if distance > radius_f {
+                continue;
+            }
+            let (px, py) = (ox + x, oy + y);
+            // full power in the middle, nothing at the edge
+            let falloff = 1.0 - distance / radius_f;
+            if world.pixel_at(px, py).is_some() {
+                touched = true;
+                let Some(pixel) = world.damage_pixel(px, py, power * falloff) else {
+                    continue;
+                };
+                world.drop_item(pixel.material, px, py);
+            }
+            if world.pixel_at(px, py).is_none() && world.rng.world.gen_bool(EXPLOSION_SMOKE_CHANCE) {
+                let color = world.materials.get(smoke).color_at(px, py);
+                world.set_pixel(px, py, smoke, color);
+            }
+        }
+    }
+
+    // everything in reach gets thrown away from the center
+    let reach = radius_f * 2.0;
+    let away = player.center() - origin;
+    let distance = away.length();
+    if distance < reach {
+        let direction = if distance > 0.0 { away / distance } else { Vector2::new(0.0, -1.0) };
+        player.knock(direction * EXPLOSION_KNOCKBACK * power.sqrt() * (1.0 - distance / reach));
+    }
+    for entity in world.ecs.positions.entities() {
+        let Some(&position) = world.ecs.positions.get(entity) else {
+            continue;
+        };
+        let away = position - origin;
+        let distance = away.length();
+        if distance >= reach || distance <= 0.0 {
+            continue;
+        }
+        let falloff = 1.0 - distance / reach;
+        if let Some(body) = world.ecs.bodies.get_mut(entity) {
+            body.velocity += away / distance * 60.0 * falloff;
+        }
+        ecs::damage(world, entity, power * falloff * EXPLOSION_DAMAGE, Some(origin));
+    }
+    effects.request(Effect::Particles { emitter: Emitter::SpellCast(Color::new(255, 170, 60, 255)), position: origin });
+    touched
+}
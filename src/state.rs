@@ -0,0 +1,741 @@
+use raylib::prelude::*;
+
+use crate::game::Game;
+use crate::chest;
+use crate::consumables;
+use crate::crafting::{Category, CATEGORIES};
+use crate::equipment;
+use crate::hud::Severity;
+use crate::input::Action;
+use crate::inventory;
+use crate::loot::Rarity;
+use crate::material::MaterialId;
+use crate::merchant;
+use crate::skills::{self, SKILLS};
+use crate::{ui, TextWidth};
+
+// which screen the game is on. each frame the main loop calls handle_input, update and draw on
+// the current state, and each of them returns the state to carry on with. a new screen is a new
+// variant here, the main loop doesn't change
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameState {
+    // title screen over the running world
+    Menu,
+    // the save's statistics, from the title screen
+    Stats,
+    Playing,
+    // settings open, the world frozen
+    Paused,
+    // the skill tree, also with the world frozen
+    Skills,
+    // the whole inventory, world frozen. held is the slot picked up to move somewhere else
+    Inventory { held: Option<usize> },
+    // recipes from recipes.json a category at a time, world frozen
+    Crafting { category: Category },
+    // a ruin's chest next to the inventory, by its id, see Chests
+    Chest { id: i64 },
+    // buying from and selling to the merchant, world frozen
+    Trading,
+    // world frozen, the mouse paints the material
+    Editor { material: MaterialId },
+    // the death sequence plays out, back to Playing once it has respawned the player. in
+    // hardcore worlds it never does and the save is gone
+    Dead,
+}
+
+impl GameState {
+    pub fn handle_input(self, game: &mut Game, rl: &mut RaylibHandle) -> GameState {
+        match self {
+            GameState::Menu => {
+                game.ignore_input();
+                if rl.is_key_pressed(KeyboardKey::KEY_ENTER) {
+                    game.start();
+                    return GameState::Playing;
+                }
+            }
+            GameState::Playing => {
+                if game.controls.is_pressed(rl, Action::Console) {
+                    game.console.toggle();
+                }
+                if game.console.open {
+                    game.ignore_input();
+                    if let Some(command) = game.console.update(rl) {
+                        game.console.run(command, &mut game.player, &mut game.world, &mut game.spellbook, &mut game.audio, &mut game.debug, game.aim);
+                    }
+                    return self;
+                }
+                if game.controls.is_pressed(rl, Action::Menu) {
+                    game.settings.toggle();
+                    return GameState::Paused;
+                }
+                if game.controls.is_pressed(rl, Action::Editor) {
+                    return GameState::Editor { material: game.world.materials.named("STONE") };
+                }
+                if game.controls.is_pressed(rl, Action::Skills) {
+                    return GameState::Skills;
+                }
+                if game.controls.is_pressed(rl, Action::Inventory) {
+                    return GameState::Inventory { held: None };
+                }
+                if game.controls.is_pressed(rl, Action::Craft) {
+                    return GameState::Crafting { category: Category::Material };
+                }
+                if game.controls.is_pressed(rl, Action::Hotbar) {
+                    game.player.inventory.select_next();
+                }
+                if game.controls.is_pressed(rl, Action::UseItem) {
+                    game.use_item();
+                }
+                if game.controls.is_pressed(rl, Action::Interact) {
+                    if let Some(id) = game.open_chest() {
+                        return GameState::Chest { id };
+                    }
+                    if game.talk_to_merchant() {
+                        return GameState::Trading;
+                    }
+                    game.use_altar();
+                }
+            }
+            GameState::Skills => {
+                game.ignore_input();
+                if game.controls.is_pressed(rl, Action::Skills) || game.controls.is_pressed(rl, Action::Menu) {
+                    return GameState::Playing;
+                }
+            }
+            GameState::Inventory { .. } => {
+                game.ignore_input();
+                if game.controls.is_pressed(rl, Action::Inventory) || game.controls.is_pressed(rl, Action::Menu) {
+                    return GameState::Playing;
+                }
+            }
+            GameState::Crafting { .. } => {
+                game.ignore_input();
+                if game.controls.is_pressed(rl, Action::Craft) || game.controls.is_pressed(rl, Action::Menu) {
+                    return GameState::Playing;
+                }
+            }
+            GameState::Chest { .. } | GameState::Trading => {
+                game.ignore_input();
+                if game.controls.is_pressed(rl, Action::Interact) || game.controls.is_pressed(rl, Action::Menu) {
+                    return GameState::Playing;
+                }
+            }
+            GameState::Paused => {
+                game.ignore_input();
+                game.settings.update(rl, &mut game.controls, &mut game.audio);
+                for e in game.settings.take_errors() {
+                    game.hud.notify(&e.to_string(), 4.0, Severity::Danger);
+                }
+            }
+            GameState::Editor { material } => {
+                game.ignore_input();
+                if game.controls.is_pressed(rl, Action::Editor) || game.controls.is_pressed(rl, Action::Menu) {
+                    return GameState::Playing;
+                }
+                game.update_aim(rl);
+                // AIR can't be painted, the wheel skips over it
+                let count = game.world.materials.count() as i32 - 1;
+                let wheel = rl.get_mouse_wheel_move();
+                let material = if wheel != 0.0 && count > 0 {
+                    let step = if wheel > 0.0 { -1 } else { 1 };
+                    MaterialId(((material.0 as i32 - 1 + step).rem_euclid(count) + 1) as u16)
+                } else {
+                    material
+                };
+                let (x, y) = (game.aim.x.floor() as i64, game.aim.y.floor() as i64);
+                if rl.is_mouse_button_down(MouseButton::MOUSE_BUTTON_LEFT) {
+                    let color = game.world.materials.get(material).color_at(x, y);
+                    game.world.place_pixel(x, y, material, color);
+                } else if rl.is_mouse_button_down(MouseButton::MOUSE_BUTTON_RIGHT) {
+                    game.world.erase_pixel(x, y);
+                }
+                return GameState::Editor { material };
+            }
+            GameState::Stats => {
+                game.ignore_input();
+                if game.controls.is_pressed(rl, Action::Menu) {
+                    return GameState::Menu;
+                }
+            }
+            GameState::Dead => game.ignore_input(),
+        }
+        self
+    }
+
+    // the world runs on the game clock, which is paused along with it. walking and casting run
+    // on the clock's player_delta so slow motion slows everything down but the player, while the
+    // debug time controls hold and scale the player too
+    pub fn update(self, game: &mut Game, rl: &mut RaylibHandle) -> GameState {
+        game.clock.set_paused(matches!(self, GameState::Paused | GameState::Skills | GameState::Inventory { .. } | GameState::Crafting { .. } | GameState::Chest { .. } | GameState::Trading | GameState::Editor { .. }));
+        game.update_capture(rl);
+        game.update_audio(rl, game.clock.real_delta());
+        let next = self.update_systems(game, rl, game.clock.delta(), game.clock.real_delta());
+        game.run_background();
+        game.dispatch_events();
+        next
+    }
+
+    fn update_systems(self, game: &mut Game, rl: &mut RaylibHandle, delta: f32, real_delta: f32) -> GameState {
+        match self {
+            GameState::Menu | GameState::Stats => {
+                game.move_player(rl, delta, false);
+                game.update_world(rl, delta);
+            }
+            GameState::Playing => {
+                let controls_on = !game.console.open;
+                let player_delta = game.clock.player_delta();
+                game.move_player(rl, player_delta, controls_on);
+                game.update_aim(rl);
+                if controls_on {
+                    game.cast_spells(rl, player_delta);
+                    game.place_pixels(rl, player_delta);
+                }
+                game.update_world(rl, delta);
+                game.stats.update(game.player.center(), real_delta);
+                game.discover();
+                game.quests.update(game.player.center());
+                if let Some(run) = &mut game.daily {
+                    run.update(game.player.center(), delta);
+                }
+                if !game.death.is_alive() {
+                    return GameState::Dead;
+                }
+            }
+            GameState::Paused | GameState::Skills | GameState::Inventory { .. } | GameState::Crafting { .. } | GameState::Chest { .. } | GameState::Trading | GameState::Editor { .. } => {}
+            GameState::Dead => {
+                let was_gone = game.death.is_gone();
+                game.move_player(rl, delta, false);
+                game.update_world(rl, delta);
+                if game.death.is_gone() && !was_gone {
+                    game.delete_save();
+                }
+                if game.death.is_alive() {
+                    return GameState::Playing;
+                }
+            }
+        }
+        self
+    }
+
+    // ui buttons only know they were clicked while they are drawn, so drawing can change the state too
+    pub fn draw(self, game: &mut Game, d: &mut RaylibDrawHandle, thread: &RaylibThread) -> GameState {
+        match self {
+            GameState::Menu => {
+                game.draw(d, thread, false);
+                let (width, height) = (240.0, 300.0);
+                // room for the recovery panel under it
+                let below = if game.recovery.is_some() { 108.0 } else { 0.0 };
+                let x = (d.get_screen_width() as f32 - width) / 2.0;
+                let y = (d.get_screen_height() as f32 - height - below) / 2.0;
+                ui::panel(d, Rectangle::new(x, y, width, height), "Spellcoder");
+                let mut rules = game.world.rules;
+                if game.new_world {
+                    // only a new world gets to pick, after that they're part of the save
+                    let label = format!("Difficulty: {}", rules.difficulty.name());
+                    if ui::button(d, &mut game.audio, Rectangle::new(x + 20.0, y + 46.0, width - 40.0, 24.0), &label) {
+                        rules.difficulty = rules.difficulty.next();
+                    }
+                    rules.hardcore = ui::toggle(d, &mut game.audio, Rectangle::new(x + 20.0, y + 80.0, width - 40.0, 24.0), "Hardcore", rules.hardcore);
+                    rules.creative = ui::toggle(d, &mut game.audio, Rectangle::new(x + 20.0, y + 112.0, width - 40.0, 24.0), "Creative", rules.creative);
+                    let label = format!("Scaling: {}", rules.scaling.name());
+                    if ui::button(d, &mut game.audio, Rectangle::new(x + 20.0, y + 144.0, width - 40.0, 24.0), &label) {
+                        rules.scaling = rules.scaling.next();
+                    }
+                    game.set_rules(rules);
+                } else {
+                    d.draw_text(&rules.describe(), x as i32 + 20, y as i32 + 52, 10, Color::LIGHTGRAY);
+                    if let Some(run) = &game.daily {
+                        d.draw_text(&format!("Daily challenge {}", run.date), x as i32 + 20, y as i32 + 68, 10, Color::new(230, 200, 60, 255));
+                    }
+                    if game.difficulty.boss_defeated && ui::button(d, &mut game.audio, Rectangle::new(x + 20.0, y + 144.0, width - 40.0, 28.0), "New Game+") {
+                        game.new_game_plus();
+                        return GameState::Playing;
+                    }
+                }
+                if ui::button(d, &mut game.audio, Rectangle::new(x + 20.0, y + 182.0, width - 40.0, 28.0), "Play") {
+                    game.start();
+                    return GameState::Playing;
+                }
+                if ui::button(d, &mut game.audio, Rectangle::new(x + 20.0, y + 220.0, width - 40.0, 28.0), "Statistics") {
+                    return GameState::Stats;
+                }
+                if ui::button(d, &mut game.audio, Rectangle::new(x + 20.0, y + 258.0, width - 40.0, 28.0), "Quit") {
+                    game.quit = true;
+                }
+                if game.recovery.is_some() {
+                    let y = y + height + 8.0;
+                    ui::panel(d, Rectangle::new(x, y, width, 100.0), "Recovery");
+                    d.draw_text("The last session crashed.", x as i32 + 20, y as i32 + 40, 10, Color::LIGHTGRAY);
+                    let half = (width - 48.0) / 2.0;
+                    if ui::button(d, &mut game.audio, Rectangle::new(x + 20.0, y + 60.0, half, 28.0), "Recover") {
+                        game.recover();
+                    }
+                    if ui::button(d, &mut game.audio, Rectangle::new(x + 28.0 + half, y + 60.0, half, 28.0), "Discard") {
+                        game.discard_recovery();
+                    }
+                }
+            }
+            GameState::Stats => {
+                game.draw(d, thread, false);
+                let rows = game.stats.rows();
+                let (width, height) = (280.0, 100.0 + rows.len() as f32 * 16.0);
+                let x = (d.get_screen_width() as f32 - width) / 2.0;
+                let y = (d.get_screen_height() as f32 - height) / 2.0;
+                ui::panel(d, Rectangle::new(x, y, width, height), "Statistics");
+                for (i, (name, value)) in rows.iter().enumerate() {
+                    let row_y = y as i32 + 44 + i as i32 * 16;
+                    d.draw_text(name, x as i32 + 20, row_y, 10, Color::LIGHTGRAY);
+                    d.draw_text(value, (x + width) as i32 - 20 - d.text_width(value, 10), row_y, 10, Color::WHITE);
+                }
+                if ui::button(d, &mut game.audio, Rectangle::new(x + 20.0, y + height - 44.0, width - 40.0, 28.0), "Back") {
+                    return GameState::Menu;
+                }
+            }
+            GameState::Playing => {
+                game.draw(d, thread, true);
+                if game.console.open {
+                    game.console.draw(d);
+                }
+            }
+            GameState::Paused => {
+                game.draw(d, thread, false);
+                game.settings.draw(d, &mut game.controls, &mut game.audio);
+                if !game.settings.open {
+                    return GameState::Playing;
+                }
+            }
+            GameState::Skills => {
+                game.draw(d, thread, false);
+                if let Some(next) = draw_skill_tree(game, d) {
+                    return next;
+                }
+            }
+            GameState::Inventory { held } => {
+                game.draw(d, thread, false);
+                return draw_inventory(game, d, held);
+            }
+            GameState::Crafting { category } => {
+                game.draw(d, thread, false);
+                return draw_crafting(game, d, category);
+            }
+            GameState::Chest { id } => {
+                game.draw(d, thread, false);
+                if let Some(next) = draw_chest(game, d, id) {
+                    return next;
+                }
+            }
+            GameState::Trading => {
+                game.draw(d, thread, false);
+                if let Some(next) = draw_trading(game, d) {
+                    return next;
+                }
+            }
+            GameState::Editor { material } => {
+                game.draw(d, thread, true);
+                let name = &game.world.materials.get(material).name;
+                let text = format!("EDITOR - {} (wheel to change, left paints, right erases, F2 to leave)", name);
+                d.draw_rectangle(0, d.get_screen_height() - 24, d.get_screen_width(), 24, Color::new(0, 0, 0, 180));
+                d.draw_text(&text, 8, d.get_screen_height() - 17, 10, Color::WHITE);
+            }
+            GameState::Dead => {
+                game.draw(d, thread, false);
+                if game.death.is_gone() {
+                    let (width, height) = (240.0, 100.0);
+                    let x = (d.get_screen_width() as f32 - width) / 2.0;
+                    let y = d.get_screen_height() as f32 / 2.0 + 30.0;
+                    ui::panel(d, Rectangle::new(x, y, width, height), "Hardcore");
+                    d.draw_text("This world is lost.", x as i32 + 20, y as i32 + 40, 10, Color::LIGHTGRAY);
+                    if ui::button(d, &mut game.audio, Rectangle::new(x + 20.0, y + 60.0, width - 40.0, 28.0), "Quit") {
+                        game.quit = true;
+                    }
+                }
+            }
+        }
+        self
+    }
+}
+
+// one column per school, prerequisites joined by lines. clicking a skill that can be unlocked
+// unlocks it, hovering one says what it does and what's missing
+fn draw_skill_tree(game: &mut Game, d: &mut RaylibDrawHandle) -> Option<GameState> {
+    let (node_w, node_h, gap_x, gap_y) = (110.0, 28.0, 16.0, 24.0);
+    let columns = SKILLS.iter().map(|s| s.slot.0).max().unwrap_or(0) + 1;
+    let rows = SKILLS.iter().map(|s| s.slot.1).max().unwrap_or(0) + 1;
+    let width = 40.0 + columns as f32 * (node_w + gap_x) - gap_x;
+    let height = 150.0 + rows as f32 * (node_h + gap_y) - gap_y;
+    let x = (d.get_screen_width() as f32 - width) / 2.0;
+    let y = (d.get_screen_height() as f32 - height) / 2.0;
+    ui::panel(d, Rectangle::new(x, y, width, height), "Skills");
+    let level = game.experience.level;
+    let points = format!("Level {}, {} skill points", level, game.skills.points(level));
+    d.draw_text(&points, (x + width) as i32 - 20 - d.text_width(&points, 10), y as i32 + 14, 10, Color::LIGHTGRAY);
+    let rect = |slot: (i32, i32)| {
+        Rectangle::new(x + 20.0 + slot.0 as f32 * (node_w + gap_x), y + 44.0 + slot.1 as f32 * (node_h + gap_y), node_w, node_h)
+    };
+    for skill in SKILLS {
+        for required in skill.requires.iter().filter_map(|id| skills::skill(id)) {
+            let (from, to) = (rect(required.slot), rect(skill.slot));
+            let color = if game.skills.has(required.id) { Color::new(220, 200, 120, 255) } else { Color::new(80, 80, 110, 255) };
+            d.draw_line_ex(
+                Vector2::new(from.x + from.width / 2.0, from.y + from.height),
+                Vector2::new(to.x + to.width / 2.0, to.y),
+                2.0,
+                color,
+            );
+        }
+    }
+    let mut hovered = None;
+    let mut clicked = None;
+    for skill in SKILLS {
+        let node = rect(skill.slot);
+        let label = format!("{} ({})", skill.name, skill.cost);
+        if ui::button(d, &mut game.audio, node, &label) {
+            clicked = Some(skill.id);
+        }
+        let outline = if game.skills.has(skill.id) {
+            Color::new(220, 200, 120, 255)
+        } else if game.skills.check(skill, level).is_ok() {
+            Color::new(120, 200, 120, 255)
+        } else {
+            Color::new(60, 60, 80, 255)
+        };
+        d.draw_rectangle_lines_ex(node, 2.0, outline);
+        if node.check_collision_point_rec(d.get_mouse_position()) {
+            hovered = Some(skill);
+        }
+    }
+    if let Some(skill) = hovered {
+        let text_y = (y + height) as i32 - 84;
+        d.draw_text(skill.description, x as i32 + 20, text_y, 10, Color::WHITE);
+        if let Err(reason) = game.skills.check(skill, level) {
+            d.draw_text(&reason, x as i32 + 20, text_y + 16, 10, Color::GRAY);
+        }
+    }
+    if let Some(id) = clicked {
+        game.unlock_skill(id);
+    }
+    if ui::button(d, &mut game.audio, Rectangle::new(x + 20.0, y + height - 44.0, width - 40.0, 28.0), "Back") {
+        return Some(GameState::Playing);
+    }
+    None
+}
+
+// every slot in rows, the hotbar on top, what's worn underneath. clicking a stack picks it up,
+// clicking another slot puts it there, see Inventory::move_stack. right clicking gear wears it
+// and clicking a worn slot takes it off
+fn draw_inventory(game: &mut Game, d: &mut RaylibDrawHandle, held: Option<usize>) -> GameState {
+    let (slot, gap) = (30.0, 4.0);
+    let columns = inventory::HOTBAR_SLOTS;
+    let rows = inventory::SLOTS.div_ceil(columns);
+    let width = 40.0 + columns as f32 * (slot + gap) - gap;
+    // a little extra under the hotbar row to set it apart, and a labelled row for the gear
+    let gear_y = 44.0 + rows as f32 * (slot + gap) + 8.0 + 16.0;
+    let height = 90.0 + rows as f32 * (slot + gap) + 8.0 + 16.0 + slot + 14.0;
+    let x = (d.get_screen_width() as f32 - width) / 2.0;
+    let y = (d.get_screen_height() as f32 - height) / 2.0;
+    ui::panel(d, Rectangle::new(x, y, width, height), "Inventory");
+    let purse = format!("{} {}s", game.player.coins, merchant::CURRENCY);
+    d.draw_text(&purse, (x + width) as i32 - 20 - d.text_width(&purse, 10), y as i32 + 14, 10, Color::new(230, 200, 90, 255));
+    let mouse = d.get_mouse_position();
+    let mut held = held;
+    let mut hovered = None;
+    for i in 0..inventory::SLOTS {
+        let (column, row) = (i % columns, i / columns);
+        let below_hotbar = if row > 0 { 8.0 } else { 0.0 };
+        let rect = Rectangle::new(x + 20.0 + column as f32 * (slot + gap), y + 44.0 + row as f32 * (slot + gap) + below_hotbar, slot, slot);
+        let outline = if held == Some(i) {
+            Color::new(220, 200, 120, 255)
+        } else if i == game.player.inventory.selected {
+            Color::new(120, 200, 120, 255)
+        } else {
+            Color::new(60, 60, 80, 255)
+        };
+        d.draw_rectangle_rec(rect, Color::new(20, 20, 30, 220));
+        d.draw_rectangle_lines_ex(rect, 2.0, outline);
+        if let Some(stack) = &game.player.inventory.slots()[i] {
+            let short: String = stack.name.chars().take(4).collect();
+            d.draw_text(&short, rect.x as i32 + 3, rect.y as i32 + 3, 10, Color::WHITE);
+            let count = stack.count.to_string();
+            d.draw_text(&count, (rect.x + rect.width) as i32 - 3 - d.text_width(&count, 10), (rect.y + rect.height) as i32 - 12, 10, Color::LIGHTGRAY);
+        }
+        if rect.check_collision_point_rec(mouse) {
+            hovered = Some(i);
+        }
+    }
+    let mut hovered_gear = None;
+    for (i, gear_slot) in equipment::SLOTS.iter().enumerate() {
+        let rect = Rectangle::new(x + 20.0 + i as f32 * (slot + gap) * 2.0, y + gear_y, slot * 2.0 + gap, slot);
+        d.draw_text(gear_slot.name(), rect.x as i32, rect.y as i32 - 12, 10, Color::GRAY);
+        d.draw_rectangle_rec(rect, Color::new(20, 20, 30, 220));
+        d.draw_rectangle_lines_ex(rect, 2.0, Color::new(60, 60, 80, 255));
+        if let Some(item) = game.player.equipment.worn.get(gear_slot) {
+            let short: String = item.chars().take(10).collect();
+            d.draw_text(&short, rect.x as i32 + 3, rect.y as i32 + 3, 10, Color::WHITE);
+        }
+        if rect.check_collision_point_rec(mouse) {
+            hovered_gear = Some(*gear_slot);
+        }
+    }
+    if let Some(stack) = hovered.and_then(|i| game.player.inventory.slots()[i].as_ref()) {
+        ui::tooltips(d, &item_tooltips(game, &stack.name, stack.count));
+    }
+    if let Some(gear) = hovered_gear.and_then(|s| game.player.equipment.get(s)) {
+        ui::tooltips(d, &item_tooltips(game, gear.item, 1));
+    }
+    if d.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_RIGHT) {
+        if let Some(name) = hovered.and_then(|i| game.player.inventory.slots()[i].as_ref()).map(|s| s.name.clone()) {
+            game.equip(&name);
+            held = None;
+        }
+    }
+    if d.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) {
+        if let Some(gear_slot) = hovered_gear {
+            game.unequip(gear_slot);
+        }
+        if let Some(i) = hovered {
+            match held {
+                Some(from) => {
+                    game.player.inventory.move_stack(from, i);
+                    held = None;
+                }
+                None if game.player.inventory.slots()[i].is_some() => held = Some(i),
+                None => {}
+            }
+        }
+    }
+    if ui::button(d, &mut game.audio, Rectangle::new(x + 20.0, y + height - 44.0, width - 40.0, 28.0), "Back") {
+        return GameState::Playing;
+    }
+    GameState::Inventory { held }
+}
+
+// a tab per category and a row per recipe in it. clicking one crafts it, hovering says what
+// it takes and what's still missing
+fn draw_crafting(game: &mut Game, d: &mut RaylibDrawHandle, category: Category) -> GameState {
+    let (row_h, gap, tab_h) = (22.0, 4.0, 20.0);
+    let most = CATEGORIES.iter().map(|c| game.recipes.recipes.iter().filter(|r| r.category == *c).count()).max().unwrap_or(0);
+    let width = 360.0;
+    let height = 136.0 + tab_h + most.max(1) as f32 * (row_h + gap);
+    let x = (d.get_screen_width() as f32 - width) / 2.0;
+    let y = (d.get_screen_height() as f32 - height) / 2.0;
+    ui::panel(d, Rectangle::new(x, y, width, height), "Crafting");
+    let mut next = category;
+    let tab_w = (width - 40.0) / CATEGORIES.len() as f32;
+    for (i, tab) in CATEGORIES.iter().enumerate() {
+        let rect = Rectangle::new(x + 20.0 + i as f32 * tab_w, y + 40.0, tab_w - gap, tab_h);
+        if ui::button(d, &mut game.audio, rect, tab.name()) {
+            next = *tab;
+        }
+        if *tab == category {
+            d.draw_rectangle_lines_ex(rect, 2.0, Color::new(220, 200, 120, 255));
+        }
+    }
+    let top = y + 48.0 + tab_h;
+    let recipes: Vec<_> = game.recipes.recipes.iter().filter(|r| r.category == category).collect();
+    if recipes.is_empty() {
+        d.draw_text("Nothing to craft", x as i32 + 20, top as i32 + 4, 10, Color::GRAY);
+    }
+    let mut hovered = None;
+    let mut clicked = None;
+    for (i, recipe) in recipes.iter().enumerate() {
+        let rect = Rectangle::new(x + 20.0, top + i as f32 * (row_h + gap), width - 40.0, row_h);
+        let label = format!("{} x{}", recipe.name, recipe.output.count);
+        if ui::button(d, &mut game.audio, rect, &label) {
+            clicked = Some(recipe.id.clone());
+        }
+        let outline = if game.recipes.check(recipe, &game.player.inventory).is_ok() {
+            Color::new(120, 200, 120, 255)
+        } else {
+            Color::new(60, 60, 80, 255)
+        };
+        d.draw_rectangle_lines_ex(rect, 2.0, outline);
+        if rect.check_collision_point_rec(d.get_mouse_position()) {
+            hovered = Some(*recipe);
+        }
+    }
+    if let Some(recipe) = hovered {
+        let text_y = (y + height) as i32 - 78;
+        d.draw_text(&format!("Takes {}", recipe.describe_inputs()), x as i32 + 20, text_y, 10, Color::WHITE);
+        if let Err(reason) = game.recipes.check(recipe, &game.player.inventory) {
+            d.draw_text(&reason, x as i32 + 20, text_y + 16, 10, Color::GRAY);
+        }
+    }
+    if let Some(id) = clicked {
+        game.craft(&id);
+    }
+    if ui::button(d, &mut game.audio, Rectangle::new(x + 20.0, y + height - 44.0, width - 40.0, 28.0), "Back") {
+        return GameState::Playing;
+    }
+    GameState::Crafting { category: next }
+}
+
+// the chest's slots over the inventory's. clicking a stack moves it to the other side, as much
+// of it as fits
+fn draw_chest(game: &mut Game, d: &mut RaylibDrawHandle, id: i64) -> Option<GameState> {
+    let (slot, gap) = (30.0, 4.0);
+    let columns = inventory::HOTBAR_SLOTS;
+    let chest_rows = chest::CHEST_SLOTS.div_ceil(columns);
+    let rows = inventory::SLOTS.div_ceil(columns);
+    let width = 40.0 + columns as f32 * (slot + gap) - gap;
+    let inventory_y = 44.0 + chest_rows as f32 * (slot + gap) + 20.0;
+    let height = inventory_y + rows as f32 * (slot + gap) + 60.0;
+    let x = (d.get_screen_width() as f32 - width) / 2.0;
+    let y = (d.get_screen_height() as f32 - height) / 2.0;
+    ui::panel(d, Rectangle::new(x, y, width, height), "Chest");
+    d.draw_text("Inventory", x as i32 + 20, (y + inventory_y) as i32 - 14, 10, Color::GRAY);
+    let mouse = d.get_mouse_position();
+    let mut hovered = None;
+    let chest = game.chests.get(id, game.world.seed, &game.world.loot);
+    let grids = [(true, &chest.contents, 44.0), (false, &game.player.inventory, inventory_y)];
+    for (from_chest, inventory, top) in grids {
+        for (i, stack) in inventory.slots().iter().enumerate() {
+            let (column, row) = (i % columns, i / columns);
+            let rect = Rectangle::new(x + 20.0 + column as f32 * (slot + gap), y + top + row as f32 * (slot + gap), slot, slot);
+            d.draw_rectangle_rec(rect, Color::new(20, 20, 30, 220));
+            d.draw_rectangle_lines_ex(rect, 2.0, Color::new(60, 60, 80, 255));
+            if let Some(stack) = stack {
+                let short: String = stack.name.chars().take(4).collect();
+                d.draw_text(&short, rect.x as i32 + 3, rect.y as i32 + 3, 10, Color::WHITE);
+                let count = stack.count.to_string();
+                d.draw_text(&count, (rect.x + rect.width) as i32 - 3 - d.text_width(&count, 10), (rect.y + rect.height) as i32 - 12, 10, Color::LIGHTGRAY);
+                if rect.check_collision_point_rec(mouse) {
+                    hovered = Some((from_chest, i, stack.name.clone(), stack.count));
+                }
+            }
+        }
+    }
+    if let Some((_, _, name, count)) = &hovered {
+        ui::tooltips(d, &item_tooltips(game, name, *count));
+    }
+    if d.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) {
+        if let Some((from_chest, i, _, _)) = hovered {
+            game.transfer(id, from_chest, i);
+        }
+    }
+    if ui::button(d, &mut game.audio, Rectangle::new(x + 20.0, y + height - 44.0, width - 40.0, 28.0), "Back") {
+        return Some(GameState::Playing);
+    }
+    None
+}
+
+// the merchant's stock on the left and what they'd buy from the inventory on the right, a click
+// buys or sells one
+fn draw_trading(game: &mut Game, d: &mut RaylibDrawHandle) -> Option<GameState> {
+    let (row_h, gap) = (20.0, 3.0);
+    let mut selling: Vec<(String, u32)> = Vec::new();
+    for stack in game.player.inventory.slots().iter().flatten() {
+        if game.merchant.offer_for(&stack.name).is_some() && !selling.iter().any(|(name, _)| *name == stack.name) {
+            selling.push((stack.name.clone(), game.player.inventory.count(&stack.name)));
+        }
+    }
+    let rows = game.merchant.stock.len().max(selling.len()).max(1);
+    let width = 520.0;
+    let column = (width - 60.0) / 2.0;
+    let height = 124.0 + rows as f32 * (row_h + gap);
+    let x = (d.get_screen_width() as f32 - width) / 2.0;
+    let y = (d.get_screen_height() as f32 - height) / 2.0;
+    ui::panel(d, Rectangle::new(x, y, width, height), "Merchant");
+    let purse = format!("{} {}s", game.player.coins, merchant::CURRENCY);
+    d.draw_text(&purse, (x + width) as i32 - 20 - d.text_width(&purse, 20), y as i32 + 8, 20, Color::new(230, 200, 90, 255));
+    let top = y + 56.0;
+    d.draw_text("For sale", x as i32 + 20, top as i32 - 14, 10, Color::GRAY);
+    d.draw_text("They buy", (x + 40.0 + column) as i32, top as i32 - 14, 10, Color::GRAY);
+    if game.merchant.stock.is_empty() {
+        d.draw_text("Sold out, come back later", x as i32 + 20, top as i32 + 4, 10, Color::GRAY);
+    }
+    if selling.is_empty() {
+        d.draw_text("Nothing they want", (x + 40.0 + column) as i32, top as i32 + 4, 10, Color::GRAY);
+    }
+    let mouse = d.get_mouse_position();
+    let mut hovered = None;
+    let mut bought = None;
+    for (i, listing) in game.merchant.stock.iter().enumerate() {
+        let rect = Rectangle::new(x + 20.0, top + i as f32 * (row_h + gap), column, row_h);
+        let label = format!("{} x{} - {}", listing.item, listing.count, listing.price);
+        if ui::button(d, &mut game.audio, rect, &label) {
+            bought = Some(i);
+        }
+        let outline = if game.player.coins >= listing.price { Color::new(120, 200, 120, 255) } else { Color::new(60, 60, 80, 255) };
+        d.draw_rectangle_lines_ex(rect, 2.0, outline);
+        if rect.check_collision_point_rec(mouse) {
+            hovered = Some((listing.item.clone(), 1));
+        }
+    }
+    let mut sold = None;
+    for (i, (item, count)) in selling.iter().enumerate() {
+        let rect = Rectangle::new(x + 40.0 + column, top + i as f32 * (row_h + gap), column, row_h);
+        let price = game.merchant.offer_for(item).unwrap_or(0);
+        if ui::button(d, &mut game.audio, rect, &format!("{} x{} - {}", item, count, price)) {
+            sold = Some(item.clone());
+        }
+        if rect.check_collision_point_rec(mouse) {
+            hovered = Some((item.clone(), *count));
+        }
+    }
+    if let Some((name, count)) = &hovered {
+        ui::tooltips(d, &item_tooltips(game, name, *count));
+    }
+    if let Some(i) = bought {
+        game.buy(i);
+    }
+    if let Some(item) = sold {
+        game.sell(&item);
+    }
+    if ui::button(d, &mut game.audio, Rectangle::new(x + 20.0, y + height - 44.0, width - 40.0, 28.0), "Back") {
+        return Some(GameState::Playing);
+    }
+    None
+}
+
+// what the item is and does, and for gear a second tooltip with what's worn in its slot. the
+// first one says what would change swapping the two
+fn item_tooltips(game: &Game, name: &str, count: u32) -> Vec<ui::Tooltip> {
+    let rarity = game.world.loot.rarity(name);
+    let title_color = if rarity == Rarity::Common { Color::WHITE } else { rarity.color() };
+    let mut tip = ui::Tooltip::new(name, title_color);
+    if count > 1 {
+        tip.line(&format!("{} carried", count), Color::GRAY);
+    }
+    let mut tips = Vec::new();
+    if let Some(gear) = equipment::gear(name) {
+        let worn = game.player.equipment.get(gear.slot);
+        let wearing = worn.is_some_and(|w| w.item == name);
+        tip.line(&format!("{}{}", gear.slot.name(), if wearing { ", worn" } else { ", right click to wear" }), Color::GRAY);
+        for modifier in gear.modifiers {
+            tip.line(&modifier.describe(), Color::WHITE);
+        }
+        if !wearing {
+            let changes = gear.stats().differences(&worn.map(|w| w.stats()).unwrap_or_default());
+            if !changes.is_empty() {
+                tip.line(&format!("Instead of {}:", worn.map_or("nothing", |w| w.item)), Color::GRAY);
+            }
+            for (text, better) in changes {
+                tip.line(&text, if better { Color::new(120, 220, 120, 255) } else { Color::new(230, 110, 110, 255) });
+            }
+            if let Some(worn) = worn {
+                let mut current = ui::Tooltip::new(&format!("Worn: {}", worn.item), Color::LIGHTGRAY);
+                for modifier in worn.modifiers {
+                    current.line(&modifier.describe(), Color::WHITE);
+                }
+                tips.push(current);
+            }
+        }
+    }
+    if let Some(consumable) = consumables::consumable(name) {
+        tip.line(&consumable.effect.describe(), Color::WHITE);
+        tip.line(&format!("{} to use from the hotbar", game.controls.prompt(Action::UseItem)), Color::GRAY);
+    }
+    if let Some(material) = game.world.materials.placed_by(name) {
+        let material = game.world.materials.get(material);
+        tip.line(&format!("Builds {} with hardness {:.0}, see build mode", material.name.to_lowercase(), material.hardness), Color::WHITE);
+    }
+    if let Some(price) = game.merchant.offer_for(name) {
+        tip.line(&format!("The merchant pays {} {}s", price, merchant::CURRENCY), Color::GRAY);
+    }
+    let uses: Vec<_> = game.recipes.recipes.iter().filter(|r| r.inputs.iter().any(|i| i.item == name)).map(|r| r.name.as_str()).collect();
+    if !uses.is_empty() {
+        tip.line(&format!("Used to craft {}", uses.join(", ")), Color::GRAY);
+    }
+    tips.insert(0, tip);
+    tips
+}
@@ -0,0 +1,79 @@
+use std::collections::BTreeMap;
+
+use raylib::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::events::{GameEvent, Subscriber};
+
+// world pixels in one frame, anything further was a teleport or a respawn and isn't walking
+const MAX_STEP: f32 = 20.0;
+
+// everything counted over the life of a save, stored in it, see WorldSave
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Statistics {
+    // world pixels
+    pub distance: f32,
+    pub pixels_placed: u64,
+    pub pixels_destroyed: u64,
+    // by spell name, sorted so the save file doesn't reshuffle every time
+    pub spells_cast: BTreeMap<String, u64>,
+    pub damage_dealt: f32,
+    pub damage_taken: f32,
+    pub deaths: u64,
+    // real seconds spent playing, the menus don't count
+    pub playtime: f32,
+    #[serde(skip)]
+    last_position: Option<Vector2>,
+}
+
+impl Statistics {
+    // call every frame the player is in control, with where they are now
+    pub fn update(&mut self, position: Vector2, delta: f32) {
+        self.playtime += delta;
+        if let Some(last) = self.last_position {
+            let step = last.distance_to(position);
+            if step < MAX_STEP {
+                self.distance += step;
+            }
+        }
+        self.last_position = Some(position);
+    }
+
+    pub fn spells_total(&self) -> u64 {
+        self.spells_cast.values().sum()
+    }
+
+    // name and value for every line of the stats screen
+    pub fn rows(&self) -> Vec<(String, String)> {
+        let seconds = self.playtime as u64;
+        let mut rows = vec![
+            ("Playtime".to_string(), format!("{}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)),
+            ("Distance traveled".to_string(), format!("{:.0} px", self.distance)),
+            ("Pixels placed".to_string(), self.pixels_placed.to_string()),
+            ("Pixels destroyed".to_string(), self.pixels_destroyed.to_string()),
+            ("Damage dealt".to_string(), format!("{:.0}", self.damage_dealt)),
+            ("Damage taken".to_string(), format!("{:.0}", self.damage_taken)),
+            ("Deaths".to_string(), self.deaths.to_string()),
+            ("Spells cast".to_string(), self.spells_total().to_string()),
+        ];
+        let mut spells: Vec<_> = self.spells_cast.iter().collect();
+        spells.sort_by(|a, b| b.1.cmp(a.1));
+        rows.extend(spells.into_iter().map(|(name, count)| (format!("  {}", name), count.to_string())));
+        rows
+    }
+}
+
+impl Subscriber for Statistics {
+    fn on_event(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::DamageDealt { amount, entity: None, .. } => self.damage_taken += amount,
+            GameEvent::DamageDealt { amount, .. } => self.damage_dealt += amount,
+            GameEvent::PixelChanged { after: Some(_), .. } => self.pixels_placed += 1,
+            GameEvent::PixelChanged { before: Some(_), after: None, .. } => self.pixels_destroyed += 1,
+            GameEvent::SpellCast { name, .. } => *self.spells_cast.entry(name.clone()).or_default() += 1,
+            GameEvent::EntityDied { entity: None, .. } => self.deaths += 1,
+            _ => {}
+        }
+    }
+}
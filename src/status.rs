@@ -0,0 +1,147 @@
+use raylib::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StatusKind {
+    Regen,
+    Poison,
+    Haste,
+    Slow,
+    Burning,
+    ManaSurge,
+    Vigor,
+}
+
+impl StatusKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            StatusKind::Regen => "Regeneration",
+            StatusKind::Poison => "Poison",
+            StatusKind::Haste => "Haste",
+            StatusKind::Slow => "Slow",
+            StatusKind::Burning => "Burning",
+            StatusKind::ManaSurge => "Mana surge",
+            StatusKind::Vigor => "Vigor",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            StatusKind::Regen => "Restores HP over time",
+            StatusKind::Poison => "Drains HP over time",
+            StatusKind::Haste => "Move faster",
+            StatusKind::Slow => "Move slower",
+            StatusKind::Burning => "Drains HP and SP over time",
+            StatusKind::ManaSurge => "Restores MP faster",
+            StatusKind::Vigor => "Restores SP over time",
+        }
+    }
+
+    // letter drawn on the icon until we have proper sprites
+    pub fn glyph(&self) -> &'static str {
+        match self {
+            StatusKind::Regen => "R",
+            StatusKind::Poison => "P",
+            StatusKind::Haste => "H",
+            StatusKind::Slow => "S",
+            StatusKind::Burning => "B",
+            StatusKind::ManaSurge => "M",
+            StatusKind::Vigor => "V",
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            StatusKind::Regen => Color::new(40, 170, 60, 255),
+            StatusKind::Poison => Color::new(110, 160, 20, 255),
+            StatusKind::Haste => Color::new(230, 200, 40, 255),
+            StatusKind::Slow => Color::new(90, 110, 150, 255),
+            StatusKind::Burning => Color::new(220, 90, 20, 255),
+            StatusKind::ManaSurge => Color::new(60, 100, 230, 255),
+            StatusKind::Vigor => Color::new(230, 150, 40, 255),
+        }
+    }
+
+    pub fn is_debuff(&self) -> bool {
+        matches!(self, StatusKind::Poison | StatusKind::Slow | StatusKind::Burning)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    pub duration: f32,
+    pub remaining: f32,
+    pub magnitude: f32, // hp/mp per second for over-time effects, speed factor for haste/slow
+}
+
+impl StatusEffect {
+    pub fn new(kind: StatusKind, duration: f32, magnitude: f32) -> Self {
+        StatusEffect {
+            kind,
+            duration,
+            remaining: duration,
+            magnitude,
+        }
+    }
+
+    pub fn fraction_left(&self) -> f32 {
+        if self.duration <= 0.0 {
+            return 0.0;
+        }
+        (self.remaining / self.duration).clamp(0.0, 1.0)
+    }
+}
+
+// what a set of effects did to whoever has them over one update, hp/mp/sp gained, negative when lost
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StatusTick {
+    pub hp: f32,
+    pub mp: f32,
+    pub sp: f32,
+}
+
+// reapplying an effect refreshes it instead of stacking a second icon
+pub fn add(effects: &mut Vec<StatusEffect>, effect: StatusEffect) {
+    match effects.iter_mut().find(|e| e.kind == effect.kind) {
+        Some(existing) => {
+            existing.duration = existing.duration.max(effect.duration);
+            existing.remaining = existing.remaining.max(effect.remaining);
+            existing.magnitude = existing.magnitude.max(effect.magnitude);
+        }
+        None => effects.push(effect),
+    }
+}
+
+// counts the effects down, dropping the finished ones, shared by the player and every entity with statuses
+pub fn tick(effects: &mut Vec<StatusEffect>, delta: f32) -> StatusTick {
+    let mut result = StatusTick::default();
+    for effect in effects.iter_mut() {
+        effect.remaining -= delta;
+        match effect.kind {
+            StatusKind::Regen => result.hp += effect.magnitude * delta,
+            StatusKind::Poison => result.hp -= effect.magnitude * delta,
+            StatusKind::Burning => {
+                result.hp -= effect.magnitude * delta;
+                result.sp -= effect.magnitude * delta;
+            }
+            StatusKind::ManaSurge => result.mp += effect.magnitude * delta,
+            StatusKind::Vigor => result.sp += effect.magnitude * delta,
+            StatusKind::Haste | StatusKind::Slow => {}
+        }
+    }
+    effects.retain(|e| e.remaining > 0.0);
+    result
+}
+
+pub fn speed_multiplier(effects: &[StatusEffect]) -> f32 {
+    let mut mult = 1.0;
+    for effect in effects {
+        match effect.kind {
+            StatusKind::Haste => mult *= 1.0 + effect.magnitude,
+            StatusKind::Slow => mult /= 1.0 + effect.magnitude,
+            _ => {}
+        }
+    }
+    mult
+}
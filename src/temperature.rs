@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+
+use rand::Rng;
+
+use raylib::prelude::*;
+
+use crate::material::MaterialId;
+use crate::{biome_at, Biome, Player, World};
+
+// temperature changes slowly, no need to work it out every frame
+const TICK_TIME: f32 = 0.25;
+// fraction of the way to its target a chunk moves each tick
+const RELAX: f32 = 0.1;
+// fraction of the difference to each neighbouring chunk that flows across each tick
+const DIFFUSION: f32 = 0.05;
+// chance per tick that a pixel past one of its limits actually changes
+const TRANSITION_CHANCE: f64 = 0.05;
+// flammable pixels catch fire above this, scaled by their flammability
+const IGNITION_TEMPERATURE: f32 = 150.0;
+// outside this range the player starts to suffer
+const COMFORT_MIN: f32 = -10.0;
+const COMFORT_MAX: f32 = 50.0;
+// hp and sp lost per second per degree past the comfortable range
+const EXPOSURE_DRAIN: f32 = 0.05;
+
+impl Biome {
+    // degrees a chunk settles at with nothing hot or cold in it
+    pub fn temperature(&self) -> f32 {
+        match self {
+            Biome::Meadow => 15.0,
+            Biome::Crystal => -15.0,
+            Biome::Underground => 25.0,
+        }
+    }
+}
+
+// the chunk's starting temperature, before anything inside it has had a say
+pub fn base_temperature(seed: u64, chunk_x: i64, chunk_y: i64) -> f32 {
+    biome_at(seed, Vector2::new(chunk_x as f32 + 8.0, chunk_y as f32 + 8.0)).temperature()
+}
+
+// one coarse temperature per chunk. each drifts towards its biome plus whatever hot or cold
+// material is inside, leaks into its neighbours, and changes the pixels that can't stand it
+pub struct Temperature {
+    timer: f32,
+}
+
+impl Temperature {
+    pub fn new() -> Self {
+        Temperature { timer: 0.0 }
+    }
+
+    // hazards scales the exposure damage, see DifficultyDirector::hazards
+    pub fn update(&mut self, world: &mut World, player: &mut Player, hazards: f32, delta: f32) {
+        self.timer += delta;
+        while self.timer >= TICK_TIME {
+            self.timer -= TICK_TIME;
+            tick(world);
+        }
+        expose(world, player, hazards, delta);
+    }
+}
+
+fn tick(world: &mut World) {
+    let index = world.chunks.iter().enumerate().map(|(i, c)| ((c.x, c.y), i)).collect::<HashMap<_, _>>();
+    let mut next = Vec::with_capacity(world.chunks.len());
+    for chunk in &world.chunks {
+        let heat = chunk.pixels.iter().flatten().map(|p| world.materials.get(p.material).heat).sum::<f32>();
+        let target = base_temperature(world.seed, chunk.x, chunk.y) + heat;
+        let mut temperature = chunk.temperature + (target - chunk.temperature) * RELAX;
+        // read from the old values so the order chunks are visited in doesn't matter
+        for (dx, dy) in [(-16, 0), (16, 0), (0, -16), (0, 16)] {
+            if let Some(&i) = index.get(&(chunk.x + dx, chunk.y + dy)) {
+                temperature += (world.chunks[i].temperature - chunk.temperature) * DIFFUSION;
+            }
+        }
+        next.push(temperature);
+    }
+    for (chunk, temperature) in world.chunks.iter_mut().zip(next) {
+        chunk.temperature = temperature;
+    }
+
+    let fire = world.materials.named("FIRE");
+    let mut rng = world.rng.take();
+    let mut changes = Vec::new();
+    for chunk in &world.chunks {
+        for pixel in chunk.pixels.iter().flatten() {
+            let Some(becomes) = transition(world, pixel.material, chunk.temperature, fire) else {
+                continue;
+            };
+            if rng.gen_bool(TRANSITION_CHANCE) {
+                changes.push((chunk.x + pixel.x as i64, chunk.y + pixel.y as i64, becomes));
+            }
+        }
+    }
+    world.rng.restore(rng);
+    for (x, y, becomes) in changes {
+        let color = world.materials.get(becomes).color_at(x, y);
+        world.set_pixel(x, y, becomes, color);
+    }
+}
+
+// what the material turns into at this temperature, if anything
+fn transition(world: &World, material: MaterialId, temperature: f32, fire: MaterialId) -> Option<MaterialId> {
+    let material = world.materials.get(material);
+    let limit = material.resolved_transitions.iter().find(|(below, above, _)| {
+        below.map_or(false, |b| temperature < b) || above.map_or(false, |a| temperature > a)
+    });
+    if let Some(&(_, _, becomes)) = limit {
+        return Some(becomes);
+    }
+    // the more flammable, the cooler it catches
+    if material.flammability > 0.0 && temperature * material.flammability > IGNITION_TEMPERATURE {
+        return Some(fire);
+    }
+    None
+}
+
+// slowly wears the player down when it's too hot or too cold where they stand
+fn expose(world: &World, player: &mut Player, hazards: f32, delta: f32) {
+    let center = player.center();
+    let Some(chunk) = world.chunk_at(center.x.floor() as i64, center.y.floor() as i64) else {
+        return;
+    };
+    let excess = (COMFORT_MIN - chunk.temperature).max(chunk.temperature - COMFORT_MAX);
+    if excess > 0.0 {
+        let drain = excess * EXPOSURE_DRAIN * player.rules.difficulty.damage_taken() * hazards * delta;
+        player.hp = (player.hp - drain).max(0.0);
+        player.sp = (player.sp - drain).max(0.0);
+    }
+}
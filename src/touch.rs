@@ -0,0 +1,126 @@
+use raylib::prelude::*;
+
+use crate::input::Action;
+use crate::TextWidth;
+
+const STICK_RADIUS: f32 = 50.0;
+const CAST_RADIUS: f32 = 32.0;
+const JUMP_RADIUS: f32 = 26.0;
+// a touch starting this far from where the mouse was means a finger, mice glide there first
+const TOUCH_JUMP_DISTANCE: f32 = 24.0;
+
+#[derive(Default)]
+pub struct TouchControls {
+    pub active: bool,
+    stick: Vector2, // -1..1 on both axes
+    cast_down: bool,
+    cast_down_before: bool,
+    jump_down: bool,
+    jump_down_before: bool,
+    aim: Option<Vector2>,
+    last_mouse: Vector2,
+    was_touching: bool,
+}
+
+struct Layout {
+    stick_center: Vector2,
+    cast_center: Vector2,
+    jump_center: Vector2,
+}
+
+fn layout(rl: &RaylibHandle) -> Layout {
+    let w = rl.get_screen_width() as f32;
+    let h = rl.get_screen_height() as f32;
+    Layout {
+        stick_center: Vector2::new(90.0, h - 110.0),
+        cast_center: Vector2::new(w - 70.0, h - 110.0),
+        jump_center: Vector2::new(w - 150.0, h - 70.0),
+    }
+}
+
+impl TouchControls {
+    pub fn update(&mut self, rl: &RaylibHandle) {
+        let count = rl.get_touch_point_count() as usize;
+        let mouse = rl.get_mouse_position();
+
+        // raylib also reports the mouse as a touch on desktop, so only switch on touches that appear
+        // somewhere new and switch back off as soon as the cursor hovers around
+        if count > 0 && !self.was_touching && mouse.distance_to(self.last_mouse) > TOUCH_JUMP_DISTANCE {
+            self.active = true;
+        } else if count == 0 && mouse.distance_to(self.last_mouse) > 0.0 {
+            self.active = false;
+        }
+        self.was_touching = count > 0;
+        self.last_mouse = mouse;
+
+        self.cast_down_before = self.cast_down;
+        self.jump_down_before = self.jump_down;
+        self.stick = Vector2::zero();
+        self.cast_down = false;
+        self.jump_down = false;
+        self.aim = None;
+        if !self.active {
+            return;
+        }
+
+        let layout = layout(rl);
+        for i in 0..count {
+            let pos = rl.get_touch_position(i as u32);
+            if pos.distance_to(layout.stick_center) < STICK_RADIUS * 1.5 {
+                let offset = (pos - layout.stick_center) / STICK_RADIUS;
+                self.stick = if offset.length() > 1.0 { offset.normalized() } else { offset };
+            } else if pos.distance_to(layout.cast_center) < CAST_RADIUS {
+                self.cast_down = true;
+            } else if pos.distance_to(layout.jump_center) < JUMP_RADIUS {
+                self.jump_down = true;
+            } else if self.aim.is_none() {
+                self.aim = Some(pos);
+            }
+        }
+    }
+
+    // screen position to aim at, None keeps aiming where the last touch was
+    pub fn aim(&self) -> Option<Vector2> {
+        self.aim
+    }
+
+    pub fn strength(&self, action: Action) -> f32 {
+        if !self.active {
+            return 0.0;
+        }
+        match action {
+            Action::MoveLeft => (-self.stick.x).max(0.0),
+            Action::MoveRight => self.stick.x.max(0.0),
+            Action::MoveDown => self.stick.y.max(0.0),
+            Action::Jump => self.jump_down as i32 as f32,
+            Action::Cast => self.cast_down as i32 as f32,
+            _ => 0.0,
+        }
+    }
+
+    pub fn is_pressed(&self, action: Action) -> bool {
+        match action {
+            Action::Jump => self.jump_down && !self.jump_down_before,
+            Action::Cast => self.cast_down && !self.cast_down_before,
+            _ => false,
+        }
+    }
+
+    pub fn draw(&self, d: &mut RaylibDrawHandle) {
+        if !self.active {
+            return;
+        }
+        let layout = layout(d);
+        let faint = Color::new(255, 255, 255, 50);
+        d.draw_circle_v(layout.stick_center, STICK_RADIUS, faint);
+        d.draw_circle_v(layout.stick_center + self.stick * STICK_RADIUS, STICK_RADIUS * 0.4, Color::new(255, 255, 255, 120));
+
+        let cast_color = if self.cast_down { Color::new(160, 120, 255, 200) } else { Color::new(160, 120, 255, 90) };
+        d.draw_circle_v(layout.cast_center, CAST_RADIUS, cast_color);
+        d.draw_text("CAST", layout.cast_center.x as i32 - d.text_width("CAST", 10) / 2, layout.cast_center.y as i32 - 5, 10, Color::WHITE);
+
+        let jump_color = if self.jump_down { Color::new(255, 255, 255, 160) } else { faint };
+        d.draw_circle_v(layout.jump_center, JUMP_RADIUS, jump_color);
+        d.draw_text("JUMP", layout.jump_center.x as i32 - d.text_width("JUMP", 10) / 2, layout.jump_center.y as i32 - 5, 10, Color::WHITE);
+    }
+}
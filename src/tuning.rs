@@ -0,0 +1,170 @@
+use std::fs;
+use std::path::Path;
+use std::sync::{OnceLock, RwLock, RwLockReadGuard};
+use std::time::{Duration, Instant, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::SpellcoderError;
+
+// next to materials.json, it's part of the game rather than the player's settings
+pub const TUNING_PATH: &str = "tuning.toml";
+// how often the file's modification time is looked at
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+// numbers that decide how the game feels. read from tuning.toml every time they're used and
+// reloaded whenever the file changes, so they can be adjusted while playing, see TuningWatcher
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Tuning {
+    pub player: PlayerTuning,
+    pub leveling: LevelingTuning,
+    pub hud: HudTuning,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlayerTuning {
+    // world pixels per frame, see Game::move_player
+    pub walk_speed: f32,
+    pub jump_speed: f32,
+    pub gravity: f32,
+    // per second before difficulty
+    pub mp_regen: f32,
+    pub sp_regen: f32,
+    // see RegenModel
+    pub mp_regen_delay: f32,
+    pub exhausted_regen: f32,
+    pub exhaustion_ends: f32,
+}
+
+impl Default for PlayerTuning {
+    fn default() -> Self {
+        PlayerTuning {
+            walk_speed: 1.0,
+            jump_speed: 3.2,
+            gravity: 9.81,
+            mp_regen: 2.0,
+            sp_regen: 5.0,
+            mp_regen_delay: 1.0,
+            exhausted_regen: 0.4,
+            exhaustion_ends: 0.25,
+        }
+    }
+}
+
+// see experience.rs
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LevelingTuning {
+    pub kill_xp: u32,
+    // the first visit to a biome
+    pub discovery_xp: u32,
+    // xp from level 1 to 2, every level after needs xp_growth times the one before
+    pub xp_base: f32,
+    pub xp_growth: f32,
+    // max stats gained by level n are per_level * (n - 1) ^ stat_curve, 1 for a straight line
+    pub hp_per_level: f32,
+    pub mp_per_level: f32,
+    pub sp_per_level: f32,
+    pub stat_curve: f32,
+}
+
+impl Default for LevelingTuning {
+    fn default() -> Self {
+        LevelingTuning {
+            kill_xp: 10,
+            discovery_xp: 25,
+            xp_base: 50.0,
+            xp_growth: 1.4,
+            hp_per_level: 10.0,
+            mp_per_level: 8.0,
+            sp_per_level: 5.0,
+            stat_curve: 1.0,
+        }
+    }
+}
+
+// screen pixels
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HudTuning {
+    pub bar_x: i32,
+    // from the bottom of the screen to the last bar
+    pub bar_bottom: i32,
+    pub bar_width: i32,
+    pub bar_height: i32,
+    pub bar_spacing: i32,
+}
+
+impl Default for HudTuning {
+    fn default() -> Self {
+        HudTuning { bar_x: 10, bar_bottom: 10, bar_width: 150, bar_height: 12, bar_spacing: 16 }
+    }
+}
+
+impl Tuning {
+    // a missing file is fine and gives the defaults
+    pub fn load(path: &str) -> Result<Tuning, SpellcoderError> {
+        if !Path::new(path).exists() {
+            return Ok(Tuning::default());
+        }
+        let text = fs::read_to_string(path).map_err(|e| SpellcoderError::io(path, e))?;
+        toml::from_str(&text).map_err(|e| SpellcoderError::invalid(path, e.to_string()))
+    }
+}
+
+fn lock() -> &'static RwLock<Tuning> {
+    static TUNING: OnceLock<RwLock<Tuning>> = OnceLock::new();
+    TUNING.get_or_init(|| RwLock::new(Tuning::default()))
+}
+
+// the values as they are right now. don't hold on to it, the watcher needs to write
+pub fn get() -> RwLockReadGuard<'static, Tuning> {
+    lock().read().unwrap_or_else(|e| e.into_inner())
+}
+
+pub fn set(tuning: Tuning) {
+    *lock().write().unwrap_or_else(|e| e.into_inner()) = tuning;
+}
+
+// looks at the file a couple of times a second and loads it again when it has changed.
+// a file that doesn't parse leaves the last good values in place
+pub struct TuningWatcher {
+    path: String,
+    modified: Option<SystemTime>,
+    last_poll: Instant,
+}
+
+impl TuningWatcher {
+    pub fn new(path: &str) -> Self {
+        TuningWatcher { path: path.to_string(), modified: modified(path), last_poll: Instant::now() }
+    }
+
+    // loads the file now, whether it changed or not
+    pub fn load(&mut self) -> Result<(), SpellcoderError> {
+        self.modified = modified(&self.path);
+        set(Tuning::load(&self.path)?);
+        Ok(())
+    }
+
+    // None when nothing changed, otherwise whether reloading worked
+    pub fn poll(&mut self) -> Option<Result<(), SpellcoderError>> {
+        if self.last_poll.elapsed() < POLL_INTERVAL {
+            return None;
+        }
+        self.last_poll = Instant::now();
+        if modified(&self.path) == self.modified {
+            return None;
+        }
+        Some(self.load())
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+fn modified(path: &str) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
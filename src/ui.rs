@@ -0,0 +1,137 @@
+use raylib::prelude::*;
+
+use crate::audio::{AudioManager, UiSound};
+use crate::TextWidth;
+
+// immediate mode widgets, call them while drawing and act on the return value.
+// they make their own hover and click sounds
+
+pub fn panel(d: &mut RaylibDrawHandle, rect: Rectangle, title: &str) {
+    d.draw_rectangle_rec(rect, Color::new(15, 15, 25, 230));
+    d.draw_rectangle_lines_ex(rect, 2.0, Color::new(90, 90, 140, 255));
+    d.draw_text(title, rect.x as i32 + 10, rect.y as i32 + 8, 20, Color::WHITE);
+}
+
+pub fn button(d: &mut RaylibDrawHandle, audio: &mut AudioManager, rect: Rectangle, label: &str) -> bool {
+    let hovered = rect.check_collision_point_rec(d.get_mouse_position());
+    if hovered {
+        audio.ui_hover(rect);
+    }
+    let background = if hovered {
+        Color::new(70, 70, 110, 255)
+    } else {
+        Color::new(40, 40, 60, 255)
+    };
+    d.draw_rectangle_rec(rect, background);
+    d.draw_rectangle_lines_ex(rect, 1.0, Color::new(120, 120, 170, 255));
+    let text_x = rect.x as i32 + (rect.width as i32 - d.text_width(label, 10)) / 2;
+    d.draw_text(label, text_x, rect.y as i32 + (rect.height as i32 - 10) / 2, 10, Color::WHITE);
+    let clicked = hovered && d.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT);
+    if clicked {
+        audio.play_ui(UiSound::Click);
+    }
+    clicked
+}
+
+// horizontal slider for a 0 to 1 value, click or drag along it to change it
+pub fn slider(d: &mut RaylibDrawHandle, audio: &mut AudioManager, rect: Rectangle, value: f32) -> f32 {
+    let mouse = d.get_mouse_position();
+    let hovered = rect.check_collision_point_rec(mouse);
+    if hovered {
+        audio.ui_hover(rect);
+    }
+    let value = if hovered && d.is_mouse_button_down(MouseButton::MOUSE_BUTTON_LEFT) {
+        ((mouse.x - rect.x) / rect.width).clamp(0.0, 1.0)
+    } else {
+        value
+    };
+    d.draw_rectangle_rec(rect, Color::new(40, 40, 60, 255));
+    d.draw_rectangle_rec(Rectangle::new(rect.x, rect.y, rect.width * value, rect.height), Color::new(90, 90, 160, 255));
+    d.draw_rectangle_lines_ex(rect, 1.0, Color::new(120, 120, 170, 255));
+    let label = format!("{:.0}%", value * 100.0);
+    let text_x = rect.x as i32 + (rect.width as i32 - d.text_width(&label, 10)) / 2;
+    d.draw_text(&label, text_x, rect.y as i32 + (rect.height as i32 - 10) / 2, 10, Color::WHITE);
+    value
+}
+
+// the text of a tooltip wraps to this many pixels
+const TOOLTIP_WIDTH: i32 = 180;
+const TOOLTIP_PADDING: i32 = 6;
+const LINE_HEIGHT: i32 = 12;
+
+// a box of text following the mouse, the title over wrapped lines each in their own color
+pub struct Tooltip {
+    pub title: String,
+    pub color: Color,
+    pub lines: Vec<(String, Color)>,
+}
+
+impl Tooltip {
+    pub fn new(title: &str, color: Color) -> Self {
+        Tooltip { title: title.to_string(), color, lines: Vec::new() }
+    }
+
+    pub fn line(&mut self, text: &str, color: Color) {
+        self.lines.push((text.to_string(), color));
+    }
+
+    fn wrapped(&self, d: &impl TextWidth) -> Vec<(String, Color)> {
+        self.lines.iter().flat_map(|(text, color)| wrap(d, text, TOOLTIP_WIDTH - 2 * TOOLTIP_PADDING, 10).into_iter().map(|l| (l, *color))).collect()
+    }
+}
+
+// breaks text into lines no wider than width at size, between words. a word longer than the
+// width gets a line to itself
+pub fn wrap(d: &impl TextWidth, text: &str, width: i32, size: i32) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        let candidate = if line.is_empty() { word.to_string() } else { format!("{} {}", line, word) };
+        if d.text_width(&candidate, size) > width && !line.is_empty() {
+            lines.push(std::mem::replace(&mut line, word.to_string()));
+        } else {
+            line = candidate;
+        }
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+// tooltips side by side next to the mouse, the first one closest. moved over to the other side
+// of the mouse if they'd go off screen
+pub fn tooltips(d: &mut RaylibDrawHandle, tips: &[Tooltip]) {
+    if tips.is_empty() {
+        return;
+    }
+    let mouse = d.get_mouse_position();
+    let width = TOOLTIP_WIDTH + 4;
+    let total = tips.len() as i32 * width;
+    let mut x = mouse.x as i32 + 14;
+    if x + total > d.get_screen_width() {
+        x = mouse.x as i32 - 14 - total;
+    }
+    for tip in tips {
+        let lines = tip.wrapped(d);
+        let height = 2 * TOOLTIP_PADDING + 14 + lines.len() as i32 * LINE_HEIGHT;
+        let y = (mouse.y as i32 + 14).min(d.get_screen_height() - height).max(0);
+        let rect = Rectangle::new(x as f32, y as f32, TOOLTIP_WIDTH as f32, height as f32);
+        d.draw_rectangle_rec(rect, Color::new(10, 10, 18, 240));
+        d.draw_rectangle_lines_ex(rect, 1.0, tip.color);
+        d.draw_text(&tip.title, x + TOOLTIP_PADDING, y + TOOLTIP_PADDING, 10, tip.color);
+        for (i, (text, color)) in lines.iter().enumerate() {
+            d.draw_text(text, x + TOOLTIP_PADDING, y + TOOLTIP_PADDING + 14 + i as i32 * LINE_HEIGHT, 10, *color);
+        }
+        x += width;
+    }
+}
+
+// a button that flips a setting, returns the new state
+pub fn toggle(d: &mut RaylibDrawHandle, audio: &mut AudioManager, rect: Rectangle, label: &str, on: bool) -> bool {
+    let clicked = button(d, audio, rect, label);
+    if on {
+        d.draw_rectangle_lines_ex(rect, 2.0, Color::new(220, 120, 120, 255));
+    }
+    on != clicked
+}
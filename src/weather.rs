@@ -0,0 +1,305 @@
+use rand::Rng;
+use raylib::prelude::*;
+
+use crate::effects::{Effect, Effects};
+use crate::lighting::Light;
+use crate::particles::Emitter;
+use crate::rng::StreamRng;
+use crate::skills::Bonuses;
+use crate::world::DEPTH_CHUNKS;
+use crate::{Biome, World};
+
+// seconds to fade the old weather out, and the new one in
+const WEATHER_FADE: f32 = 4.0;
+// world pixels per second at the strongest gust
+const MAX_WIND: f32 = 25.0;
+// particles start this far above the view so they are already moving when they show up
+const SPAWN_MARGIN: f32 = 8.0;
+// seconds between lightning strikes in a storm
+const STRIKE_MIN_TIME: f32 = 3.0;
+const STRIKE_MAX_TIME: f32 = 9.0;
+const STRIKE_COLOR: Color = Color::new(210, 225, 255, 255);
+// world pixels the flash of a strike lights up around where it lands
+const STRIKE_GLOW: f32 = 60.0;
+// lightning spells cost this much of what they do during a storm, see Weather::modify
+const STORM_LIGHTNING_COST: f32 = 0.6;
+// mp regeneration on top during a mana surge
+const SURGE_MP_REGEN: f32 = 0.5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WeatherKind {
+    Clear,
+    Rain,
+    // heavier rain with lightning, only ever comes out of rain
+    Storm,
+    Snow,
+    Ash,
+    ManaSurge,
+}
+
+impl WeatherKind {
+    fn emitter(&self) -> Option<Emitter> {
+        match self {
+            WeatherKind::Clear => None,
+            WeatherKind::Rain | WeatherKind::Storm => Some(Emitter::Rain),
+            WeatherKind::Snow => Some(Emitter::Snow),
+            WeatherKind::Ash => Some(Emitter::Ash),
+            WeatherKind::ManaSurge => Some(Emitter::Mana),
+        }
+    }
+
+    // particles per second per 100 world pixels of view width at full intensity
+    fn rate(&self) -> f32 {
+        match self {
+            WeatherKind::Clear => 0.0,
+            WeatherKind::Rain => 90.0,
+            WeatherKind::Storm => 170.0,
+            WeatherKind::Snow => 25.0,
+            WeatherKind::Ash => 12.0,
+            WeatherKind::ManaSurge => 10.0,
+        }
+    }
+
+    // water pixels per second per 100 world pixels of view width that collect in open holes
+    fn water(&self) -> f32 {
+        match self {
+            WeatherKind::Rain => 1.5,
+            WeatherKind::Storm => 4.0,
+            _ => 0.0,
+        }
+    }
+
+    // seconds it lasts before the next one is rolled
+    fn duration(&self) -> (f32, f32) {
+        match self {
+            WeatherKind::Storm => (25.0, 60.0),
+            WeatherKind::ManaSurge => (20.0, 45.0),
+            WeatherKind::Rain => (40.0, 100.0),
+            WeatherKind::Clear | WeatherKind::Snow | WeatherKind::Ash => (40.0, 120.0),
+        }
+    }
+
+    // what it can turn into next, with weights. the biome has the last word, see Biome::weathers
+    fn transitions(&self) -> &'static [(WeatherKind, f32)] {
+        match self {
+            WeatherKind::Clear => &[
+                (WeatherKind::Clear, 2.0),
+                (WeatherKind::Rain, 2.0),
+                (WeatherKind::Snow, 2.0),
+                (WeatherKind::Ash, 1.0),
+                (WeatherKind::ManaSurge, 0.5),
+            ],
+            WeatherKind::Rain => &[(WeatherKind::Clear, 2.0), (WeatherKind::Rain, 1.0), (WeatherKind::Storm, 1.5)],
+            WeatherKind::Storm => &[(WeatherKind::Rain, 2.0), (WeatherKind::Clear, 1.0)],
+            WeatherKind::Snow => &[(WeatherKind::Clear, 2.0), (WeatherKind::Snow, 1.0), (WeatherKind::ManaSurge, 0.5)],
+            WeatherKind::Ash => &[(WeatherKind::Clear, 2.0), (WeatherKind::Ash, 1.0)],
+            WeatherKind::ManaSurge => &[(WeatherKind::Clear, 1.0)],
+        }
+    }
+
+    // the sky's light at full intensity, see LightSources::sky
+    fn sky(&self) -> Color {
+        match self {
+            WeatherKind::Clear => Color::WHITE,
+            WeatherKind::Rain => Color::new(200, 205, 215, 255),
+            WeatherKind::Storm => Color::new(140, 145, 165, 255),
+            WeatherKind::Snow => Color::new(235, 240, 250, 255),
+            WeatherKind::Ash => Color::new(200, 180, 165, 255),
+            WeatherKind::ManaSurge => Color::new(215, 190, 255, 255),
+        }
+    }
+
+    // the ambient bed it drowns the biome's out with, see AMBIENT_DIR
+    fn ambience(&self) -> Option<&'static str> {
+        match self {
+            WeatherKind::Rain => Some("rain"),
+            WeatherKind::Storm => Some("storm"),
+            WeatherKind::ManaSurge => Some("surge"),
+            _ => None,
+        }
+    }
+
+    // said when it starts, for the weathers that change how things play
+    pub fn announcement(&self) -> Option<&'static str> {
+        match self {
+            WeatherKind::Storm => Some("A storm rolls in, lightning spells cost less"),
+            WeatherKind::ManaSurge => Some("Mana surges through the air"),
+            _ => None,
+        }
+    }
+}
+
+impl Biome {
+    // what the sky can do here, with weights
+    fn weathers(&self) -> &'static [(WeatherKind, f32)] {
+        match self {
+            Biome::Meadow => &[(WeatherKind::Clear, 3.0), (WeatherKind::Rain, 2.0), (WeatherKind::Storm, 1.0)],
+            Biome::Crystal => &[(WeatherKind::Clear, 2.0), (WeatherKind::Snow, 3.0), (WeatherKind::ManaSurge, 1.0)],
+            // the caves drop ash from whatever burns further down
+            Biome::Underground => &[(WeatherKind::Clear, 3.0), (WeatherKind::Ash, 1.0), (WeatherKind::ManaSurge, 0.5)],
+        }
+    }
+}
+
+// a state machine over WeatherKind, each one rolling what comes after it from its transitions
+// once its time is up. the current weather fades out before the next one fades in, so it never
+// switches mid-shower
+pub struct Weather {
+    pub kind: WeatherKind,
+    next: WeatherKind,
+    intensity: f32, // 0 to 1
+    remaining: f32,
+    spawn_timer: f32,
+    water_timer: f32,
+    strike_timer: f32,
+    time: f32,
+    rng: StreamRng,
+}
+
+impl Weather {
+    // rng is the weather stream, see RngService
+    pub fn new(rng: StreamRng) -> Self {
+        Weather {
+            kind: WeatherKind::Clear,
+            next: WeatherKind::Clear,
+            intensity: 0.0,
+            remaining: WeatherKind::Clear.duration().0,
+            spawn_timer: 0.0,
+            water_timer: 0.0,
+            strike_timer: STRIKE_MAX_TIME,
+            time: 0.0,
+            rng,
+        }
+    }
+
+    pub fn wind(&self) -> f32 {
+        // two slow waves so gusts don't repeat on an obvious beat
+        ((self.time * 0.05).sin() * 0.7 + (self.time * 0.23).sin() * 0.3) * MAX_WIND
+    }
+
+    // the sky's light with the weather faded in, see LightSources::sky
+    pub fn sky(&self) -> Color {
+        let sky = self.kind.sky();
+        let fade = |channel: u8| (255.0 - (255.0 - channel as f32) * self.intensity) as u8;
+        Color::new(fade(sky.r), fade(sky.g), fade(sky.b), 255)
+    }
+
+    // the ambient bed to play instead of the biome's, once the weather is properly going
+    pub fn ambience(&self) -> Option<&'static str> {
+        self.kind.ambience().filter(|_| self.intensity >= 0.5)
+    }
+
+    // what the weather does for the player, on top of their skills and gear
+    pub fn modify(&self, bonuses: &mut Bonuses) {
+        match self.kind {
+            WeatherKind::Storm => bonuses.lightning_cost *= 1.0 - (1.0 - STORM_LIGHTNING_COST) * self.intensity,
+            WeatherKind::ManaSurge => bonuses.mp_regen += SURGE_MP_REGEN * self.intensity,
+            _ => {}
+        }
+    }
+
+    // view is the visible area in world pixels, center where the player is. returns the new
+    // weather when one has just started fading in
+    pub fn update(&mut self, world: &mut World, center: Vector2, view: Rectangle, effects: &mut Effects, delta: f32) -> Option<WeatherKind> {
+        self.time += delta;
+        self.remaining -= delta;
+        let allowed = world.biome_at(center).weathers();
+        // walking into a biome where the current weather can't happen ends it early
+        if self.remaining <= 0.0 || !allowed.iter().any(|(kind, _)| *kind == self.next) {
+            self.next = roll(self.next.transitions(), allowed, &mut self.rng);
+            let (min, max) = self.next.duration();
+            self.remaining = self.rng.gen_range(min..max);
+        }
+        let mut started = None;
+        if self.next != self.kind {
+            self.intensity -= delta / WEATHER_FADE;
+            if self.intensity <= 0.0 {
+                self.intensity = 0.0;
+                self.kind = self.next;
+                started = Some(self.kind);
+                crate::log_debug!("the weather turns to {:?}", self.kind);
+            }
+        } else {
+            self.intensity = (self.intensity + delta / WEATHER_FADE).min(1.0);
+        }
+        world.lights.sky = self.sky();
+
+        let wind = self.wind();
+        effects.particles.wind = wind;
+        self.collect_water(world, view, delta);
+        self.strike(world, view, effects, delta);
+        let Some(emitter) = self.kind.emitter() else {
+            return started;
+        };
+        self.spawn_timer += self.kind.rate() * view.width / 100.0 * self.intensity * delta;
+        // widen the strip upwind so drifting particles still cover the whole view
+        let (left, right) = if wind > 0.0 {
+            (view.x - wind, view.x + view.width)
+        } else {
+            (view.x, view.x + view.width - wind)
+        };
+        while self.spawn_timer >= 1.0 {
+            self.spawn_timer -= 1.0;
+            let position = Vector2::new(self.rng.gen_range(left..right), view.y - self.rng.gen_range(0.0..SPAWN_MARGIN));
+            effects.request(Effect::Particles { emitter, position });
+        }
+        started
+    }
+
+    // rain fills whatever has been dug into the surface, a pixel of water at a time. the top
+    // row of the world is open to the sky everywhere, anything empty there catches it
+    fn collect_water(&mut self, world: &mut World, view: Rectangle, delta: f32) {
+        let Some(water) = world.materials.id("WATER") else {
+            return;
+        };
+        self.water_timer += self.kind.water() * view.width / 100.0 * self.intensity * delta;
+        while self.water_timer >= 1.0 {
+            self.water_timer -= 1.0;
+            // which pixel it lands on is the world's business, not the weather's
+            let x = world.rng.world.gen_range(view.x..view.x + view.width).floor() as i64;
+            if world.chunk_at(x, 0).is_some() && world.pixel_at(x, 0).is_none() {
+                let color = world.materials.get(water).color_at(x, 0);
+                world.set_pixel(x, 0, water, color);
+            }
+        }
+    }
+
+    // every few seconds of a storm, lightning comes down somewhere in view and lights it all up
+    fn strike(&mut self, world: &mut World, view: Rectangle, effects: &mut Effects, delta: f32) {
+        if self.kind != WeatherKind::Storm || self.intensity < 0.5 {
+            return;
+        }
+        self.strike_timer -= delta;
+        if self.strike_timer > 0.0 {
+            return;
+        }
+        self.strike_timer = self.rng.gen_range(STRIKE_MIN_TIME..STRIKE_MAX_TIME);
+        let x = self.rng.gen_range(view.x..view.x + view.width);
+        // down to whatever it hits first, the surface unless something's been dug out there
+        let ground = (0..DEPTH_CHUNKS * 16).find(|y| world.is_solid(x.floor() as i64, *y)).unwrap_or(0) as f32;
+        let to = Vector2::new(x, ground);
+        effects.request(Effect::Bolt { from: Vector2::new(x, view.y), to, color: STRIKE_COLOR, width: 1.0, duration: 0.3, branches: 3 });
+        world.lights.flash(to, Light::new(STRIKE_COLOR, STRIKE_GLOW, 1.0).flickering(0.5), 0.4);
+    }
+}
+
+// picks one of choices by weight times how likely the biome makes it. nothing the biome allows
+// means clear skies
+fn roll(choices: &[(WeatherKind, f32)], allowed: &[(WeatherKind, f32)], rng: &mut StreamRng) -> WeatherKind {
+    let weights: Vec<(WeatherKind, f32)> = choices
+        .iter()
+        .filter_map(|(kind, weight)| allowed.iter().find(|(a, _)| a == kind).map(|(_, biome)| (*kind, weight * biome)))
+        .collect();
+    let total = weights.iter().map(|(_, weight)| weight).sum::<f32>();
+    if total <= 0.0 {
+        return WeatherKind::Clear;
+    }
+    let mut pick = rng.gen_range(0.0..total);
+    for (kind, weight) in &weights {
+        if pick < *weight {
+            return *kind;
+        }
+        pick -= weight;
+    }
+    WeatherKind::Clear
+}
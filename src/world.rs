@@ -0,0 +1,624 @@
+use std::fmt::{self, Debug};
+
+use rand::Rng;
+use raylib::prelude::*;
+use worldgen::noise::perlin::PerlinNoise;
+
+use crate::digest::Digest;
+use crate::ecs::Ecs;
+use crate::electricity::Discharge;
+use crate::error::SpellcoderError;
+use crate::events::{EventBus, GameEvent};
+use crate::exploration::Exploration;
+use crate::lighting::LightSources;
+use crate::loot::LootTables;
+use crate::material::{MaterialId, MaterialRegistry};
+use crate::rng::RngService;
+use crate::rules::WorldRules;
+use crate::season::Season;
+use crate::status::StatusEffect;
+use crate::{item, temperature};
+
+// biomes alternate in bands this many world pixels wide, below UNDERGROUND_DEPTH it's all underground
+const BIOME_WIDTH: i64 = 256;
+const UNDERGROUND_DEPTH: f32 = 64.0;
+// the top layer of the world is grass over dirt (or crystal), stone below
+const TOPSOIL_DEPTH: i64 = 4;
+// the bottom of the world is bedrock, nothing gets through it
+const BEDROCK_DEPTH: i64 = 62;
+// chunks from the surface down to below the bedrock
+pub const DEPTH_CHUNKS: i64 = 4;
+// mana ore is scattered through this many rows of stone under crystal fields
+const MANA_ORE_DEPTH: i64 = 8;
+const MANA_ORE_CHANCE: f32 = 0.04;
+// every New Game+ leaves this much of the ore there was
+const ORE_PER_PRESTIGE: f32 = 0.75;
+// a wavy metal vein runs through the stone around each of these depths
+const METAL_VEIN_DEPTHS: [i64; 2] = [30, 48];
+const METAL_VEIN_AMPLITUDE: f32 = 5.0;
+// one altar in every stretch of the surface this wide, somewhere in it
+const ALTAR_SPACING: i64 = 96;
+const ALTAR_WIDTH: i64 = 3;
+const ALTAR_HEIGHT: i64 = 2;
+// one ruin buried in every stretch this wide, a brick room with a chest in the middle of its
+// floor. the top of the room is somewhere between the depths
+const RUIN_SPACING: i64 = 128;
+const RUIN_WIDTH: i64 = 14;
+const RUIN_HEIGHT: i64 = 8;
+const RUIN_DEPTHS: (i64, i64) = (16, 44);
+const CHEST_SIZE: i64 = 2;
+// solid pixels get this much darker for every open pixel around them, down in the caves more
+// than on the surface, and never darker than SHADE_FLOOR. see World::bake_shading
+const EDGE_SHADE: f32 = 0.04;
+const CAVE_EDGE_SHADE: f32 = 0.09;
+const SHADE_FLOOR: f32 = 0.45;
+// contact statuses are refreshed every frame so they end shortly after leaving
+const CONTACT_EFFECT_TIME: f32 = 0.75;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Biome {
+    Meadow,
+    Crystal,
+    Underground,
+}
+
+// a ruin's top left corner in world pixels
+#[derive(Clone, Copy, Debug)]
+pub struct Ruin {
+    pub left: i64,
+    pub top: i64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum RuinCell {
+    Wall,
+    Hollow,
+    Chest,
+}
+
+#[derive(Clone, Copy)]
+pub struct Pixel {
+    pub x: u8, // first nibble for x, second nibble for z
+    pub y: u8,
+    pub material: MaterialId,
+    pub color: ffi::Color,
+    pub damage: f32, // breaks once it reaches the material's hardness
+}
+
+#[derive(Clone)]
+pub struct Chunk {
+    pub pixels: Vec<Vec<Pixel>>,
+    pub x: i64,
+    pub y: i64,
+    // simulated this step, and woken for the next one by anything changing in or next to it
+    pub active: bool,
+    pub wake_next: bool,
+    pub temperature: f32, // degrees, one value for the whole chunk
+    // how bright each pixel is drawn by [x][y], 255 for as it is. baked again whenever the chunk
+    // or the edge of a neighbour changes, see World::bake_shading
+    pub shading: [[u8; 16]; 16],
+    pub shaded: bool,
+}
+
+pub struct World {
+    pub chunks: Vec<Chunk>,
+    pub noise: PerlinNoise,
+    pub seed: u64,
+    pub materials: MaterialRegistry,
+    pub ecs: Ecs,
+    pub discharges: Vec<Discharge>,
+    pub events: EventBus,
+    pub rng: RngService,
+    pub rules: WorldRules,
+    pub loot: LootTables,
+    pub lights: LightSources,
+    // what the player has seen of it, see WorldSave::explored
+    pub explored: Exploration,
+    // what new ground is generated and everything is colored for, see Game::update_world
+    pub season: Season,
+    // pixels set or removed since the debug overlay last looked, only kept while it's watching
+    pub changed: Option<Vec<(i64, i64)>>,
+}
+
+impl Biome {
+    // also the mood name music tracks are picked by
+    pub fn name(&self) -> &'static str {
+        match self {
+            Biome::Meadow => "meadow",
+            Biome::Crystal => "crystal",
+            Biome::Underground => "underground",
+        }
+    }
+}
+
+impl Debug for Pixel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pixel")
+            .field("color", &self.color)
+            .field("x", &self.x)
+            .field("y", &self.y)
+            .finish()
+    }
+}
+
+impl Pixel {
+    fn compare_by_y(&self, other: &Self) -> std::cmp::Ordering {
+        self.y.cmp(&other.y)
+    }
+}
+
+impl Chunk {
+    pub fn new(x: i64, y: i64) -> Chunk {
+        let mut pixels = Vec::with_capacity(16) as Vec<Vec<Pixel>>;
+        for x in 0..16 as usize {
+            pixels.push(Vec::with_capacity(16) as Vec<Pixel>);
+        }
+        let chunk = Chunk {
+            pixels,
+            x,
+            y,
+            active: false,
+            wake_next: true,
+            temperature: 0.0,
+            shading: [[255; 16]; 16],
+            shaded: false,
+        };
+        // for x in 0..16 as u8 {
+        //     for y in 0..=65535 as u16 {
+        //         for z in 0..16 as u8 {
+        //             chunk.add_voxel(Voxel{material: VoxelMaterial::AIR, color: prelude::Color::WHITE.into(), visible_faces: [true; 6]}, x, y, z);
+        //         }
+        //     }
+        // }
+        chunk
+    }
+
+    // pure cpu work, nothing here needs a window, see cli.rs. prestige is from WorldRules, the
+    // season tints the colors and covers the grass in snow in winter
+    pub fn generate(chunk_x: i64, chunk_y: i64, noise: &PerlinNoise, seed: u64, prestige: u32, season: Season, materials: &MaterialRegistry) -> Self {
+        let mut chunk = Chunk::new(chunk_x * 16, chunk_y * 16);
+        chunk.temperature = temperature::base_temperature(seed, chunk.x, chunk.y);
+        let stone = materials.named("STONE");
+        let bedrock = materials.named("BEDROCK");
+        let grass = materials.named("GRASS");
+        let dirt = materials.named("DIRT");
+        let crystal = materials.named("CRYSTAL");
+        let mana_ore = materials.named("MANA_ORE");
+        let metal = materials.named("METAL");
+        // older material files don't have them, then there are no altars or ruins
+        let altar = materials.id("ALTAR");
+        let ruin_materials = materials.id("BRICK").zip(materials.id("CHEST"));
+        let snow = materials.id("SNOW").filter(|_| season.snow_cover());
+        let ore_chance = MANA_ORE_CHANCE * ORE_PER_PRESTIGE.powi(prestige as i32);
+        for x in 0..16 {
+            let biome = biome_at(seed, Vector2::new((chunk.x + x) as f32, 0.0));
+            let ruin = ruin_at(seed, chunk.x + x);
+            for y in 0..16 {
+                let (world_x, world_y) = (chunk.x + x, chunk.y + y);
+                let cell = ruin.and_then(|r| r.cell(world_x, world_y));
+                let material = if world_y >= BEDROCK_DEPTH {
+                    bedrock
+                } else if let (Some(cell), Some((brick, chest))) = (cell, ruin_materials) {
+                    match cell {
+                        RuinCell::Wall => brick,
+                        RuinCell::Chest => chest,
+                        RuinCell::Hollow => continue,
+                    }
+                } else if let Some(altar) = altar.filter(|_| world_y < ALTAR_HEIGHT && altar_at(seed, world_x).is_some()) {
+                    altar
+                } else if world_y < TOPSOIL_DEPTH {
+                    match biome {
+                        Biome::Crystal => crystal,
+                        _ if world_y == 0 => snow.unwrap_or(grass),
+                        _ => dirt,
+                    }
+                } else if biome == Biome::Crystal && world_y < TOPSOIL_DEPTH + MANA_ORE_DEPTH && ore_roll(seed, world_x, world_y, ore_chance) {
+                    mana_ore
+                } else if in_metal_vein(seed, world_x, world_y) {
+                    metal
+                } else {
+                    stone
+                };
+                chunk.add_pixel(
+                    Pixel {
+                        color: season.tint(&materials.get(material).name, materials.get(material).color_at(world_x, world_y)),
+                        material,
+                        damage: 0.0,
+                        x: x as u8,
+                        y: y as u8
+                    }
+                );
+                // println!("{}", noise.generate((chunk_x * 16 + x) as f64 / 32.0, (chunk_z * 16 + z) as f64 / 32.0, seed));
+            }
+        }
+        
+        chunk
+    }
+    
+    pub fn add_pixel(&mut self, pixel: Pixel) {
+        let x = pixel.x as usize;
+        let y = pixel.y as usize;
+        self.pixels[x].push(pixel);
+        self.pixels[x].sort_by(|a, b| a.compare_by_y(&b));
+    }
+
+    pub fn get_pixel(&self, x: usize, y: usize) -> Result<&Pixel, usize> {
+        match self.pixels[x].binary_search_by(|a| (a.y).cmp(&(y as u8))) {
+            Ok(i) => Ok(&self.pixels[x][i]),
+            Err(i) => Err(i)
+        }
+    }
+
+    // replaces whatever is at the pixel's position, keeps the column sorted
+    pub fn set_pixel(&mut self, pixel: Pixel) {
+        let column = &mut self.pixels[pixel.x as usize];
+        match column.binary_search_by(|a| a.y.cmp(&pixel.y)) {
+            Ok(i) => column[i] = pixel,
+            Err(i) => column.insert(i, pixel),
+        }
+    }
+
+    pub fn get_pixel_mut(&mut self, x: usize, y: usize) -> Option<&mut Pixel> {
+        let i = self.pixels[x].binary_search_by(|a| a.y.cmp(&(y as u8))).ok()?;
+        Some(&mut self.pixels[x][i])
+    }
+
+    pub fn remove_pixel(&mut self, x: usize, y: usize) -> Option<Pixel> {
+        let i = self.pixels[x].binary_search_by(|a| a.y.cmp(&(y as u8))).ok()?;
+        Some(self.pixels[x].remove(i))
+    }
+}
+
+impl World {
+    pub fn new(materials: MaterialRegistry, seed: u64) -> Self {
+        let noise = PerlinNoise::new();
+        World {
+            chunks: Vec::new() as Vec<Chunk>,
+            noise,
+            seed,
+            rng: RngService::new(seed),
+            rules: WorldRules::default(),
+            materials,
+            ecs: Ecs::default(),
+            discharges: Vec::new(),
+            events: EventBus::default(),
+            loot: LootTables::default(),
+            lights: LightSources::default(),
+            explored: Exploration::default(),
+            season: Season::default(),
+            changed: None,
+        }
+    }
+
+    // throws away everything but the materials for a different world, see Game::new_game_plus
+    pub fn reset(&mut self, seed: u64, rules: WorldRules) {
+        self.chunks.clear();
+        self.seed = seed;
+        self.rng = RngService::new(seed);
+        self.rules = rules;
+        self.ecs = Ecs::default();
+        self.discharges.clear();
+        self.lights.clear();
+        self.explored.clear();
+        self.events = EventBus::default();
+    }
+
+    pub fn generate_chunk(&mut self, chunk_x: i64, chunk_z: i64) {
+        crate::profile_scope!("generate_chunk");
+        self.chunks.push(Chunk::generate(chunk_x, chunk_z, &self.noise, self.seed, self.rules.prestige, self.season, &self.materials));
+        // the neighbours' edges were shaded as if there was nothing here
+        for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+            if let Some(chunk) = self.chunk_at_mut((chunk_x + dx) * 16, (chunk_z + dy) * 16) {
+                chunk.shaded = false;
+            }
+        }
+        self.events.publish(GameEvent::ChunkGenerated { chunk_x, chunk_y: chunk_z });
+    }
+
+    pub fn regenerate_chunk(&mut self, chunk_x: i64, chunk_y: i64) -> Result<(), SpellcoderError> {
+        let Some(i) = self.chunks.iter().position(|c| c.x == chunk_x * 16 && c.y == chunk_y * 16) else {
+            return Err(SpellcoderError::Worldgen(format!("no chunk loaded at {}, {}", chunk_x, chunk_y)));
+        };
+        self.chunks[i] = Chunk::generate(chunk_x, chunk_y, &self.noise, self.seed, self.rules.prestige, self.season, &self.materials);
+        self.events.publish(GameEvent::ChunkGenerated { chunk_x, chunk_y });
+        Ok(())
+    }
+
+    // x and y are world pixel coordinates
+    pub fn chunk_at_mut(&mut self, x: i64, y: i64) -> Option<&mut Chunk> {
+        let chunk_x = x.div_euclid(16) * 16;
+        let chunk_y = y.div_euclid(16) * 16;
+        self.chunks.iter_mut().find(|c| c.x == chunk_x && c.y == chunk_y)
+    }
+
+    pub fn chunk_at(&self, x: i64, y: i64) -> Option<&Chunk> {
+        let chunk_x = x.div_euclid(16) * 16;
+        let chunk_y = y.div_euclid(16) * 16;
+        self.chunks.iter().find(|c| c.x == chunk_x && c.y == chunk_y)
+    }
+
+    pub fn set_pixel(&mut self, x: i64, y: i64, material: MaterialId, color: ffi::Color) -> bool {
+        let Some(chunk) = self.chunk_at_mut(x, y) else {
+            return false;
+        };
+        chunk.set_pixel(Pixel {
+            x: x.rem_euclid(16) as u8,
+            y: y.rem_euclid(16) as u8,
+            material,
+            color,
+            damage: 0.0,
+        });
+        self.wake(x, y);
+        if let Some(changed) = &mut self.changed {
+            changed.push((x, y));
+        }
+        true
+    }
+
+    // chips away at the pixel, returns it once it breaks
+    pub fn damage_pixel(&mut self, x: i64, y: i64, amount: f32) -> Option<Pixel> {
+        let material = self.materials.get(self.pixel_at(x, y)?.material);
+        if material.unbreakable {
+            return None;
+        }
+        let hardness = material.hardness;
+        let chunk = self.chunk_at_mut(x, y)?;
+        let pixel = chunk.get_pixel_mut(x.rem_euclid(16) as usize, y.rem_euclid(16) as usize)?;
+        pixel.damage += amount;
+        // creative worlds break anything breakable in one go
+        if pixel.damage < hardness && !self.rules.creative {
+            return None;
+        }
+        self.erase_pixel(x, y)
+    }
+
+    // rolls the material's drop and loot table, x and y are where the pixel was
+    pub fn drop_item(&mut self, material: MaterialId, x: i64, y: i64) {
+        let position = Vector2::new(x as f32 + 0.5, y as f32 + 0.5);
+        if let Some(table) = self.materials.get(material).loot.clone() {
+            self.drop_loot(&table, position);
+        }
+        let Some(drop) = &self.materials.get(material).drop else {
+            return;
+        };
+        if self.rng.loot.gen::<f32>() >= drop.chance {
+            return;
+        }
+        let color = self.materials.get(material).color_at(x, y).into();
+        let velocity = Vector2::new(self.rng.loot.gen_range(-10.0..10.0), -20.0);
+        item::spawn_item(&mut self.ecs, &drop.item, position, velocity, color);
+    }
+
+    // rolls the table and throws what comes up out of position, one pickup per item
+    pub fn drop_loot(&mut self, table: &str, position: Vector2) {
+        for loot in self.loot.roll(table, &mut self.rng.loot) {
+            for _ in 0..loot.count {
+                let velocity = Vector2::new(self.rng.loot.gen_range(-15.0..15.0), self.rng.loot.gen_range(-35.0..-20.0));
+                item::spawn_loot(&mut self.ecs, &loot, position, velocity);
+            }
+        }
+    }
+
+    pub fn remove_pixel(&mut self, x: i64, y: i64) -> Option<Pixel> {
+        let chunk = self.chunk_at_mut(x, y)?;
+        let pixel = chunk.remove_pixel(x.rem_euclid(16) as usize, y.rem_euclid(16) as usize)?;
+        self.wake(x, y);
+        if let Some(changed) = &mut self.changed {
+            changed.push((x, y));
+        }
+        Some(pixel)
+    }
+
+    // set_pixel and remove_pixel for deliberate changes, spells and the editor, that the rest of
+    // the game hears about through a PixelChanged event
+    pub fn place_pixel(&mut self, x: i64, y: i64, material: MaterialId, color: ffi::Color) -> bool {
+        let before = self.pixel_at(x, y).map(|p| p.color.into());
+        if !self.set_pixel(x, y, material, color) {
+            return false;
+        }
+        self.events.publish(GameEvent::PixelChanged { x, y, before, after: Some(material) });
+        true
+    }
+
+    pub fn erase_pixel(&mut self, x: i64, y: i64) -> Option<Pixel> {
+        let pixel = self.remove_pixel(x, y)?;
+        self.events.publish(GameEvent::PixelChanged { x, y, before: Some(pixel.color.into()), after: None });
+        Some(pixel)
+    }
+
+    // a change at x, y can let things in this chunk or the neighbouring ones move again, and
+    // changes the shading around it
+    pub fn wake(&mut self, x: i64, y: i64) {
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                if let Some(chunk) = self.chunk_at_mut(x + dx, y + dy) {
+                    chunk.wake_next = true;
+                    chunk.shaded = false;
+                }
+            }
+        }
+    }
+
+    // position in world pixels
+    pub fn biome_at(&self, position: Vector2) -> Biome {
+        biome_at(self.seed, position)
+    }
+
+    pub fn pixel_at(&self, x: i64, y: i64) -> Option<&Pixel> {
+        let chunk = self.chunk_at(x, y)?;
+        chunk.get_pixel(x.rem_euclid(16) as usize, y.rem_euclid(16) as usize).ok()
+    }
+
+    pub fn is_solid(&self, x: i64, y: i64) -> bool {
+        self.pixel_at(x, y).map_or(false, |p| self.materials.get(p.material).is_solid())
+    }
+
+    // the status whatever is at x, y puts on anything touching it, the player and entities alike
+    pub fn contact_effect(&self, x: i64, y: i64) -> Option<StatusEffect> {
+        let contact = self.pixel_at(x, y).and_then(|p| self.materials.get(p.material).contact)?;
+        Some(StatusEffect::new(contact.status, CONTACT_EFFECT_TIME, contact.magnitude))
+    }
+
+    // what the material looks like at x, y this season
+    pub fn color_at(&self, material: MaterialId, x: i64, y: i64) -> ffi::Color {
+        let material = self.materials.get(material);
+        self.season.tint(&material.name, material.color_at(x, y))
+    }
+
+    // recolors everything already loaded for the season, new chunks get it as they're generated
+    pub fn set_season(&mut self, season: Season) {
+        if season == self.season {
+            return;
+        }
+        self.season = season;
+        for chunk in &mut self.chunks {
+            for pixel in chunk.pixels.iter_mut().flatten() {
+                let material = self.materials.get(pixel.material);
+                if Season::tints(&material.name) {
+                    pixel.color = season.tint(&material.name, material.color_at(chunk.x + pixel.x as i64, chunk.y + pixel.y as i64));
+                }
+            }
+        }
+    }
+
+    // a copy of the chunks and entities to try things out on, see dryrun.rs. events, lights and
+    // what's been explored start out empty
+    pub fn scratch(&self) -> World {
+        let mut world = World::new(self.materials.clone(), self.seed);
+        world.chunks = self.chunks.clone();
+        world.ecs = self.ecs.clone();
+        world.rng = self.rng.clone();
+        world.rules = self.rules;
+        world.loot = self.loot.clone();
+        world.season = self.season;
+        world
+    }
+
+    // a digest of the chunks, entities and random streams that comes out the same for the same
+    // state on any machine, whatever order the chunks were loaded in. for checking that a replay
+    // or a refactor still plays out the same, see Simulation::hash
+    pub fn state_hash(&self) -> u64 {
+        let mut digest = Digest::default();
+        digest.u64(self.seed);
+        self.rng.digest(&mut digest);
+        let mut chunks = self.chunks.iter().collect::<Vec<_>>();
+        chunks.sort_by_key(|c| (c.x, c.y));
+        for chunk in chunks {
+            digest.i64(chunk.x).i64(chunk.y).f32(chunk.temperature);
+            for pixel in chunk.pixels.iter().flatten() {
+                let c = pixel.color;
+                digest.u8(pixel.x).u8(pixel.y).u16(pixel.material.0).bytes(&[c.r, c.g, c.b, c.a]).f32(pixel.damage);
+            }
+        }
+        self.ecs.digest(&mut digest);
+        digest.finish()
+    }
+
+    // darkens solid pixels along the open space next to them, the flat colors get some depth.
+    // only the chunks that changed since they were last baked
+    pub fn bake_shading(&mut self) {
+        for i in 0..self.chunks.len() {
+            if self.chunks[i].shaded {
+                continue;
+            }
+            let shading = self.shade(&self.chunks[i]);
+            self.chunks[i].shading = shading;
+            self.chunks[i].shaded = true;
+        }
+    }
+
+    fn shade(&self, chunk: &Chunk) -> [[u8; 16]; 16] {
+        // inside the chunk without looking it up again
+        let solid = |x: i64, y: i64| {
+            let (local_x, local_y) = (x - chunk.x, y - chunk.y);
+            let pixel = if (0..16).contains(&local_x) && (0..16).contains(&local_y) {
+                chunk.get_pixel(local_x as usize, local_y as usize).ok()
+            } else {
+                self.pixel_at(x, y)
+            };
+            pixel.is_some_and(|p| self.materials.get(p.material).is_solid())
+        };
+        let mut shading = [[255; 16]; 16];
+        for pixel in chunk.pixels.iter().flatten() {
+            if !self.materials.get(pixel.material).is_solid() {
+                continue;
+            }
+            let (x, y) = (chunk.x + pixel.x as i64, chunk.y + pixel.y as i64);
+            let open = (-1..=1).flat_map(|dy| (-1..=1).map(move |dx| (dx, dy))).filter(|&(dx, dy)| !solid(x + dx, y + dy)).count();
+            let edge = if y < TOPSOIL_DEPTH { EDGE_SHADE } else { CAVE_EDGE_SHADE };
+            let level = (1.0 - open as f32 * edge).max(SHADE_FLOOR);
+            shading[pixel.x as usize][pixel.y as usize] = (level * 255.0) as u8;
+        }
+        shading
+    }
+}
+
+// the same pixel of the same world always rolls the same
+fn ore_roll(seed: u64, x: i64, y: i64, chance: f32) -> bool {
+    let hash = ((x as u64).wrapping_mul(0x9E37_79B9) ^ (y as u64).wrapping_mul(0x85EB_CA6B) ^ seed).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    ((hash >> 40) as f32 / (1u64 << 24) as f32) < chance
+}
+
+// each vein is two pixels thick and waves up and down along x
+fn in_metal_vein(seed: u64, x: i64, y: i64) -> bool {
+    METAL_VEIN_DEPTHS.iter().enumerate().any(|(i, depth)| {
+        let phase = ((seed >> (i * 8)) & 0xff) as f32;
+        let center = depth + ((x as f32 / 9.0 + phase).sin() * METAL_VEIN_AMPLITUDE) as i64;
+        y == center || y == center + 1
+    })
+}
+
+// the left edge of the altar column x is part of, if any. it also names the altar, see
+// Game::use_altar
+pub fn altar_at(seed: u64, x: i64) -> Option<i64> {
+    let stretch = x.div_euclid(ALTAR_SPACING);
+    let hash = ((stretch as u64).wrapping_mul(0xC2B2_AE3D) ^ seed).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    let left = stretch * ALTAR_SPACING + (hash >> 40) as i64 % (ALTAR_SPACING - ALTAR_WIDTH);
+    (left..left + ALTAR_WIDTH).contains(&x).then_some(left)
+}
+
+// the ruin under column x, if there is one
+pub fn ruin_at(seed: u64, x: i64) -> Option<Ruin> {
+    let stretch = x.div_euclid(RUIN_SPACING);
+    let hash = ((stretch as u64).wrapping_mul(0x27D4_EB2F) ^ seed.rotate_left(17)).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    let left = stretch * RUIN_SPACING + (hash >> 40) as i64 % (RUIN_SPACING - RUIN_WIDTH);
+    let top = RUIN_DEPTHS.0 + ((hash >> 16) & 0xffff) as i64 % (RUIN_DEPTHS.1 - RUIN_DEPTHS.0);
+    (left..left + RUIN_WIDTH).contains(&x).then_some(Ruin { left, top })
+}
+
+// the chest x, y is part of, named by the left edge of its ruin like altars are, see Chests
+pub fn chest_at(seed: u64, x: i64, y: i64) -> Option<i64> {
+    ruin_at(seed, x).filter(|r| r.cell(x, y) == Some(RuinCell::Chest)).map(|r| r.left)
+}
+
+impl Ruin {
+    fn cell(&self, x: i64, y: i64) -> Option<RuinCell> {
+        let (dx, dy) = (x - self.left, y - self.top);
+        if !(0..RUIN_WIDTH).contains(&dx) || !(0..RUIN_HEIGHT).contains(&dy) {
+            return None;
+        }
+        if dx == 0 || dx == RUIN_WIDTH - 1 || dy == 0 || dy == RUIN_HEIGHT - 1 {
+            return Some(RuinCell::Wall);
+        }
+        let middle = RUIN_WIDTH / 2 - CHEST_SIZE / 2;
+        if (middle..middle + CHEST_SIZE).contains(&dx) && dy >= RUIN_HEIGHT - 1 - CHEST_SIZE {
+            return Some(RuinCell::Chest);
+        }
+        Some(RuinCell::Hollow)
+    }
+}
+
+// free function so chunk generation can use it before there is a World
+pub fn biome_at(seed: u64, position: Vector2) -> Biome {
+    if position.y > UNDERGROUND_DEPTH {
+        return Biome::Underground;
+    }
+    // scramble the band index with the seed so every world lays its bands out differently
+    let band = (position.x.floor() as i64).div_euclid(BIOME_WIDTH) as u64;
+    let hash = (band ^ seed).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    if (hash >> 32) % 2 == 0 {
+        Biome::Meadow
+    } else {
+        Biome::Crystal
+    }
+}
@@ -0,0 +1,19 @@
+use spellcoder::material::{MaterialRegistry, MATERIALS_PATH};
+use spellcoder::scenario::{self, SCENARIO_DIR};
+use spellcoder::spell::{Spellbook, SPELL_DIR};
+
+// every scenario in scenarios/ against the game's own materials and spells, like `spellcoder scenario`
+#[test]
+fn scenarios_pass() {
+    let materials = MaterialRegistry::load(MATERIALS_PATH).expect("materials.json should load");
+    let mut errors = Vec::new();
+    let spellbook = Spellbook::load(SPELL_DIR, &materials, &mut errors);
+    assert!(errors.is_empty(), "spells failed to load: {}", errors.iter().map(|e| e.to_string()).collect::<Vec<_>>().join(", "));
+    let paths = scenario::scenario_files(SCENARIO_DIR).expect("scenarios/ should be readable");
+    assert!(!paths.is_empty(), "no scenarios in {}", SCENARIO_DIR);
+    for path in paths {
+        let scenario = scenario::load(&path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+        let outcome = scenario.run(materials.clone(), &spellbook);
+        assert!(outcome.passed(), "{} failed: {}", outcome.name, outcome.failures.join(", "));
+    }
+}